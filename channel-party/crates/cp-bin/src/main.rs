@@ -4,48 +4,97 @@
 //! control over ordering. See DESIGN §10.
 
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use cp_core::{Core, Registry};
 
+/// How often a server tails the change log for writes committed by other processes (the shell).
+const RELAY_EVERY: Duration = Duration::from_millis(250);
+
+/// How long a tombstone stays restorable before trash-retention purges it. `CP_TRASH_RETENTION_DAYS`,
+/// default 30.
+fn trash_retention() -> anyhow::Result<Duration> {
+    let days: u64 = match std::env::var("CP_TRASH_RETENTION_DAYS") {
+        Ok(v) => v.parse()?,
+        Err(_) => 30,
+    };
+    Ok(Duration::from_secs(days * 24 * 60 * 60))
+}
+
+/// How long the durable change log keeps rows (after compaction) for consumers to resume from.
+/// `CP_CHANGE_LOG_RETENTION_DAYS`, default 7.
+fn change_log_retention() -> anyhow::Result<Duration> {
+    let days: u64 = match std::env::var("CP_CHANGE_LOG_RETENTION_DAYS") {
+        Ok(v) => v.parse()?,
+        Err(_) => 7,
+    };
+    Ok(Duration::from_secs(days * 24 * 60 * 60))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    // The Discord bridge (#10) is built only when configured (a token is set), so a tokenless dev/CI boot
+    // runs no Discord component. With it, the namespace's channel kinds share its client (for the shell's
+    // `discord-compatible resync`).
+    let discord = cp_discord::BridgeConfig::from_env().map(cp_discord::bridge);
+    let discord_channels = match &discord {
+        Some(bridge) => bridge.channels(),
+        None => cp_discord::channels(),
+    };
+
     let mut builder = Registry::builder()
         .item(cp_basic::item())
         .channel(cp_basic::channel())
         .channel(cp_space::channel())
-        .channels(cp_discord::channels())
+        .channels(discord_channels)
         .items(cp_discord::items())
         .channel(cp_canvas::channel())
         .item(cp_canvas::text_box())
         .runtime(cp_canvas::spatial_index()) // WriteScope::Derived
+        .runtime(cp_core::trash::retention(trash_retention()?)) // WriteScope::Primary
+        .runtime(cp_core::change_log::retention(change_log_retention()?)) // WriteScope::Derived
+        .runtime(cp_core::auth::session_reaper()) // WriteScope::Derived
         .migrations(cp_discord::MIGRATIONS)
         .migrations(cp_canvas::MIGRATIONS);
 
-    // The Discord bridge (#10) registers its Primary ingestor only when configured (a token is set), so
-    // a tokenless dev/CI boot runs no Discord component. The Derived semantic index (§7c) is deferred.
-    if let Some(config) = cp_discord::BridgeConfig::from_env() {
-        builder = builder.runtime(cp_discord::bridge(config).sync());
+    // The bridge's Primary ingestor. The Derived semantic index (§7c) is deferred.
+    if let Some(bridge) = &discord {
+        builder = builder.runtime(bridge.sync());
     }
     let registry = builder.build();
 
-    // `sqlite:channel-party.db` (created if missing) or `sqlite::memory:`; override with CP_DB.
+    // `sqlite:channel-party.db` (created if missing), `sqlite::memory:`, or `postgres://…` (#37);
+    // override with CP_DB.
     let db_url = std::env::var("CP_DB").unwrap_or_else(|_| "sqlite:channel-party.db".to_owned());
     let core = Core::open(&db_url, registry.clone()).await?;
 
     // `channel-party shell` opens the gated debug REPL against the same DB, then exits (§8). Seed or
-    // inspect here. A concurrent server on the same DB sees these writes on its next read (direct-read
-    // `contents` like basic/space hits sqlite), but NOT live: the event bus is per-process, so SSE and
-    // derived indexes (canvas's R-tree) only reflect writes from the server's own process — or, for a
-    // shell-seeded DB, at the server's next boot via each component's backfill.
-    if std::env::args().nth(1).as_deref() == Some("shell") {
-        return cp_core::debug::run(&core).await;
+    // inspect here. Every write lands in the durable change log, and a concurrent server on the same DB
+    // relays it onto its own (per-process) event bus within `RELAY_EVERY` — so SSE clients and derived
+    // indexes (canvas's R-tree) reflect shell writes live.
+    // `--script <file>` / `--exec <cmd>` run it non-interactively, stopping (non-zero exit) at the first
+    // failing command; `--write` is then the only way to write, `--json` prints structured replies.
+    //
+    // `channel-party export` / `import` write or read a portable JSONL archive of the store (or a
+    // subtree), then exit.
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("shell") => {
+            let args = cp_core::debug::ShellArgs::parse(args).map_err(anyhow::Error::msg)?;
+            return cp_core::debug::run(&core, &args).await;
+        }
+        Some("export") => return cp_core::archive::run_export(&core, args).await,
+        Some("import") => return cp_core::archive::run_import(&core, args).await,
+        _ => {}
     }
 
-    // Hold the handle for the process lifetime: dropping it aborts the supervised components.
+    // Hold the handles for the process lifetime: dropping them stops the relay and aborts the
+    // supervised components.
+    let _relay = core.spawn_relay(RELAY_EVERY);
     let _runtime = core.spawn_runtime();
 
     let addr: SocketAddr = std::env::var("CP_BIND")
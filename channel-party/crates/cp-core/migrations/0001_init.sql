@@ -1,6 +1,7 @@
 -- channel-party core schema: three tables (two super-types) plus the fixed `users` substrate.
--- See DESIGN §3. Idempotent (CREATE ... IF NOT EXISTS): the scaffold re-runs this on every boot,
--- so there is no migration-tracking table yet.
+-- See DESIGN §3. Applied once and recorded in the `_migrations` ledger (`cp-core::migrate`). It stays
+-- idempotent (CREATE ... IF NOT EXISTS) so databases created before the ledger adopt it cleanly; later
+-- schema changes are new, append-only migrations, never edits to this file.
 
 CREATE TABLE IF NOT EXISTS users (
     id            TEXT PRIMARY KEY,                                     -- ULID
@@ -0,0 +1,15 @@
+-- Envelope revision history. Every overwrite of a channel/item (payload update, reparent, upsert
+-- update, revert, delete) first appends the envelope's prior state here, so edits are auditable and
+-- revertible. Append-only: rows are never updated. No FK to the envelope tables — history outlives a
+-- deleted envelope. `rev` is 1-based per envelope; `actor` is `cp_model::Actor`'s display form.
+
+CREATE TABLE envelope_revisions (
+    envelope_id TEXT NOT NULL,                                         -- channel or item ULID
+    super_type  TEXT NOT NULL,                                         -- 'channel' | 'item'
+    rev         INTEGER NOT NULL,
+    payload     TEXT NOT NULL,                                         -- the prior payload
+    container   TEXT,                                                  -- the prior container
+    actor       TEXT NOT NULL,                                         -- who made the superseding change
+    recorded_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (envelope_id, rev)
+);
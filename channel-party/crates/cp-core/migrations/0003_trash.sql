@@ -0,0 +1,13 @@
+-- Soft delete. `delete_*` now tombstones instead of removing rows: `trash_root` is NULL for a live
+-- envelope, the envelope's own id when it was trashed directly (then `deleted_at` records when), or a
+-- trashed ancestor's id when that ancestor's tombstone hides it. Every read filters
+-- `trash_root IS NULL`; restore clears exactly the rows a tombstone set; purge (by hand or by the
+-- trash-retention component after the configured age) is the old hard delete.
+
+ALTER TABLE channels ADD COLUMN trash_root TEXT;
+ALTER TABLE channels ADD COLUMN deleted_at TEXT;
+ALTER TABLE items ADD COLUMN trash_root TEXT;
+ALTER TABLE items ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX channels_trash_root ON channels (trash_root) WHERE trash_root IS NOT NULL;
+CREATE INDEX items_trash_root ON items (trash_root) WHERE trash_root IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Optimistic concurrency. `rev` starts at 1 and every overwrite of the envelope's payload or container
+-- (set-payload, reparent, upsert update, revert) bumps it by one inside the writing transaction. The
+-- conditional setters compare it against the caller's expected value; HTTP exposes it as the ETag.
+-- Trashing and restoring leave it alone — the envelope's content does not change.
+
+ALTER TABLE channels ADD COLUMN rev INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE items ADD COLUMN rev INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,14 @@
+-- Sort-key substrate (DESIGN §6). One row per envelope whose kind's `index()` declared a `sort_key`,
+-- written by the write path in the envelope's transaction and rewritten with it. `children` ordered by
+-- `SortKeyAsc`/`SortKeyDesc` reads it, keyset-paginating over `(sort_key, id)`; envelopes without a row
+-- sort as the empty key. Like `search_index`, rows are keyed by `(super_type, envelope_id)` and
+-- orphaned by FK-cascaded purges, which the lookup by live id renders invisible.
+
+CREATE TABLE sort_index (
+    super_type  TEXT NOT NULL,
+    envelope_id TEXT NOT NULL,
+    sort_key    TEXT NOT NULL,
+    PRIMARY KEY (super_type, envelope_id)
+);
+
+CREATE INDEX sort_index_key ON sort_index (sort_key, envelope_id);
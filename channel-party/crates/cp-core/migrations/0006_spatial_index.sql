@@ -0,0 +1,18 @@
+-- Spatial substrate (DESIGN §6). The write path projects a kind's `IndexEntry::coord` (plus an optional
+-- `extent`, making it a rectangle) into an R-tree in the envelope's transaction, and `within_bbox` reads
+-- it. R-trees key on integers, so `spatial_key` maps each envelope to the `rid` its box is stored under.
+-- Rows are keyed by `(super_type, envelope_id)` and orphaned by FK-cascaded purges exactly like
+-- `search_index`; reads join back to the live envelope tables, so orphans are invisible.
+
+CREATE TABLE spatial_key (
+    rid         INTEGER PRIMARY KEY AUTOINCREMENT,
+    super_type  TEXT NOT NULL,
+    envelope_id TEXT NOT NULL,
+    UNIQUE (super_type, envelope_id)
+);
+
+CREATE VIRTUAL TABLE spatial_index USING rtree(
+    rid,                                  -- = spatial_key.rid
+    min_x, max_x,                         -- [x, x + w]
+    min_y, max_y                          -- [y, y + h]
+);
@@ -0,0 +1,30 @@
+-- Durable change log (an outbox, DESIGN §7). The write path appends one row per `ChangeEvent` in the
+-- mutation's own transaction, so the log holds exactly the committed changes in commit order, and the
+-- in-memory bus is only the fast path. `seq` is AUTOINCREMENT so it is never reused, even after
+-- retention empties the table: consumers resume strictly after the last `seq` they handled.
+--
+-- Compaction drops rows superseded by a later row for the same envelope (a resumer still learns every
+-- envelope's latest change); retention drops rows older than an age and raises `horizon` to the highest
+-- `seq` it dropped. A consumer whose offset is below the horizon may have missed changes and resyncs.
+
+CREATE TABLE change_log (
+    seq         INTEGER PRIMARY KEY AUTOINCREMENT,
+    op          TEXT NOT NULL,                -- 'created' | 'updated' | 'deleted'
+    super_type  TEXT NOT NULL,                -- 'channel' | 'item'
+    envelope_id TEXT NOT NULL,
+    type_id     TEXT NOT NULL,
+    container   TEXT,
+    at          TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX change_log_envelope ON change_log (super_type, envelope_id, seq);
+CREATE INDEX change_log_at ON change_log (at);
+
+CREATE TABLE change_log_horizon (
+    id      INTEGER PRIMARY KEY CHECK (id = 1),
+    horizon INTEGER NOT NULL
+);
+INSERT INTO change_log_horizon (id, horizon) VALUES (1, 0);
+
+-- Each runtime component's persisted position in the log: the last `seq` it finished handling.
+ALTER TABLE runtime_component_state ADD COLUMN log_offset INTEGER NOT NULL DEFAULT 0;
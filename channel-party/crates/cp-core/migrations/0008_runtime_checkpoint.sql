@@ -0,0 +1,10 @@
+-- Each runtime component's opaque checkpoint (DESIGN §7), next to `runtime_component_state`. A component
+-- records its own progress here (`RuntimeCtx::checkpoint`) — typically how far a backfill got — so a
+-- restart resumes it instead of rebuilding from scratch. Core never interprets `value` (JSON text); it
+-- only deletes the row when a reset is due (a `version()` bump, or changes missed past the log horizon).
+
+CREATE TABLE runtime_component_checkpoint (
+    name       TEXT PRIMARY KEY,
+    value      TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
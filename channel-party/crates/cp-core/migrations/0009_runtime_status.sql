@@ -0,0 +1,13 @@
+-- Supervision status per runtime component (DESIGN §7), so an operator can see which component is
+-- crash-looping and why — from the shell (`show runtimes`) or the admin API, in any process on the DB.
+-- The supervisor writes these on each transition; `restarts` counts from the supervising process's boot.
+--
+-- `control` is an operator request queue of one ('restart' | 'reset'): the shell or the admin API sets it,
+-- and the supervising process (possibly another one) takes it within a poll.
+
+ALTER TABLE runtime_component_state ADD COLUMN state TEXT NOT NULL DEFAULT 'stopped';
+ALTER TABLE runtime_component_state ADD COLUMN restarts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE runtime_component_state ADD COLUMN last_error TEXT;
+ALTER TABLE runtime_component_state ADD COLUMN last_healthy_at TEXT;
+ALTER TABLE runtime_component_state ADD COLUMN updated_at TEXT;
+ALTER TABLE runtime_component_state ADD COLUMN control TEXT;
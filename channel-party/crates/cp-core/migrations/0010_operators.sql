@@ -0,0 +1,4 @@
+-- Operators (DESIGN §8): native users allowed on the admin HTTP surface, which mirrors the debug shell's
+-- operational commands. Granted only from the shell (`set-operator`), whose access is the filesystem's.
+
+ALTER TABLE users ADD COLUMN operator INTEGER NOT NULL DEFAULT 0;
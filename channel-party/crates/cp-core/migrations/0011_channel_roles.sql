@@ -0,0 +1,12 @@
+-- Channel roles (`design/permissions.md`): the named grants a native user holds on a channel — the
+-- built-in owner / admin / moderator / member, or a kind's custom role. A grant reaches every channel
+-- below its channel; `RolePermission` decides against them.
+
+CREATE TABLE IF NOT EXISTS channel_roles (
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role       TEXT NOT NULL,
+    PRIMARY KEY (channel_id, user_id, role)
+);
+
+CREATE INDEX IF NOT EXISTS channel_roles_user ON channel_roles (user_id);
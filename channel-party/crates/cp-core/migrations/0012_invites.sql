@@ -0,0 +1,20 @@
+-- Invite codes (`design/auth.md`): the only way to self-register a native user. A code is handed out once
+-- and only its SHA-256 is kept, like a session token. `uses_left` counts down on each registration and
+-- the row goes when it reaches zero; `expires_at` is `db::timestamp` text (`2024-05-01T12:00:00.000Z`),
+-- like `created_at`. The channels an invite names are joined on registration, through each kind's
+-- `Membership`.
+
+CREATE TABLE IF NOT EXISTS invites (
+    id         TEXT PRIMARY KEY,
+    code_hash  TEXT NOT NULL UNIQUE,
+    created_by TEXT REFERENCES users (id) ON DELETE SET NULL,
+    uses_left  INTEGER NOT NULL,
+    expires_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS invite_channels (
+    invite_id  TEXT NOT NULL REFERENCES invites (id) ON DELETE CASCADE,
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    PRIMARY KEY (invite_id, channel_id)
+);
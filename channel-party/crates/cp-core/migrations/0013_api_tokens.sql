@@ -0,0 +1,17 @@
+-- Personal access tokens (`design/auth.md`): long-lived credentials for scripts and bots, sent as
+-- `Authorization: Bearer`. Only the SHA-256 of a token is kept, like `sessions.token_hash`. `scopes` is
+-- the JSON array of scope strings (`read`, `post:<channel-id>`, …) the token is limited to; `expires_at`
+-- is `db::timestamp` text like `created_at`, or NULL for a token that does not expire.
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id           TEXT PRIMARY KEY,
+    token_hash   TEXT NOT NULL UNIQUE,
+    user_id      TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name         TEXT NOT NULL,
+    scopes       TEXT NOT NULL,
+    created_at   TEXT NOT NULL,
+    expires_at   TEXT,
+    last_used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS api_tokens_user ON api_tokens (user_id);
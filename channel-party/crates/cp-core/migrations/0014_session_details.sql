@@ -0,0 +1,13 @@
+-- Session management (`design/auth.md`): a session gets a public `id` (what the owner lists and revokes
+-- by — never the token hash), a `last_seen_at` stamped as it slides, and the user agent and IP it was
+-- opened from. Sessions opened before this migration get a random id and were last seen when created.
+
+ALTER TABLE sessions ADD COLUMN id TEXT;
+ALTER TABLE sessions ADD COLUMN last_seen_at TEXT;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip TEXT;
+
+UPDATE sessions SET id = lower(hex(randomblob(16))), last_seen_at = created_at;
+
+CREATE UNIQUE INDEX IF NOT EXISTS sessions_id ON sessions (id);
+CREATE INDEX IF NOT EXISTS sessions_expires ON sessions (expires_at);
@@ -0,0 +1,14 @@
+-- The failed-login ledger (`design/auth.md`, `crate::throttle`): one row per handle and per client
+-- address that has recently failed a password login. `kind` is `handle` or `ip`. `blocked_until` is when
+-- the next attempt may be checked — a growing delay, then a lockout; `locked_at` records when the
+-- latest lockout began (NULL if it never reached one). Timestamps are `db::timestamp` text.
+
+CREATE TABLE IF NOT EXISTS login_failures (
+    kind           TEXT NOT NULL,
+    subject        TEXT NOT NULL,
+    failures       INTEGER NOT NULL,
+    last_failed_at TEXT NOT NULL,
+    blocked_until  TEXT NOT NULL,
+    locked_at      TEXT,
+    PRIMARY KEY (kind, subject)
+);
@@ -0,0 +1,28 @@
+-- TOTP second factor (`design/auth.md`, `crate::totp`). `user_totp` holds a user's RFC 6238 secret
+-- (base32; it must be readable to check a code) — `enabled_at` is NULL until the user confirms
+-- enrollment with a first code, and `last_step` is the time step of the last code accepted, so no code
+-- is accepted twice. Recovery codes are single-use and kept only as SHA-256. A `pending_logins` row is
+-- the short-lived token a correct password earns a 2FA user, exchanged with a code for a session.
+
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id    TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    secret     TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    enabled_at TEXT,
+    last_step  INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS totp_recovery_codes (
+    code_hash TEXT PRIMARY KEY,
+    user_id   TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    used_at   TEXT
+);
+
+CREATE INDEX IF NOT EXISTS totp_recovery_codes_user ON totp_recovery_codes (user_id);
+
+CREATE TABLE IF NOT EXISTS pending_logins (
+    token_hash TEXT PRIMARY KEY,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at TEXT NOT NULL,
+    attempts   INTEGER NOT NULL DEFAULT 0
+);
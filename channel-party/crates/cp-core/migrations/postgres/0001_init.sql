@@ -0,0 +1,148 @@
+-- channel-party core schema for Postgres (DESIGN §3). The same tables and meaning as the sqlite
+-- migrations 0001–0010, written as one step because a Postgres database always starts at this schema;
+-- later changes are new, append-only steps here and in the sqlite list alike. The comments on the sqlite
+-- files describe each table; this file notes only where the dialects part:
+--
+--  * timestamps are the same UTC text (`2024-05-01T12:00:00.000Z`; sessions use `YYYY-MM-DD HH:MM:SS`),
+--    so they compare and sort as strings exactly as on sqlite;
+--  * AUTOINCREMENT keys are identity columns, BLOB is BYTEA, REAL is DOUBLE PRECISION;
+--  * the FTS5 `search_index` is a plain table with `pg_trgm` GIN indexes (substring matching, like the
+--    trigram tokenizer), and the R-tree `spatial_index` a plain table with a GiST box index.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE TABLE users (
+    id            TEXT PRIMARY KEY,
+    handle        TEXT NOT NULL UNIQUE,
+    password_hash TEXT,
+    created_at    TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"'),
+    operator      INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE channels (
+    id         TEXT PRIMARY KEY,
+    type_id    TEXT NOT NULL,
+    container  TEXT REFERENCES channels (id) ON DELETE CASCADE,
+    payload    TEXT NOT NULL,
+    trash_root TEXT,
+    deleted_at TEXT,
+    rev        INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX channels_container ON channels (container);
+CREATE INDEX channels_trash_root ON channels (trash_root) WHERE trash_root IS NOT NULL;
+
+CREATE TABLE items (
+    id           TEXT PRIMARY KEY,
+    type_id      TEXT NOT NULL,
+    container    TEXT REFERENCES channels (id) ON DELETE CASCADE,
+    external_key TEXT,
+    payload      TEXT NOT NULL,
+    trash_root   TEXT,
+    deleted_at   TEXT,
+    rev          INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX items_container ON items (container);
+CREATE UNIQUE INDEX items_external_key ON items (external_key) WHERE external_key IS NOT NULL;
+CREATE INDEX items_trash_root ON items (trash_root) WHERE trash_root IS NOT NULL;
+
+CREATE TABLE user_external_links (
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    item_id TEXT NOT NULL UNIQUE REFERENCES items (id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, item_id)
+);
+
+CREATE TABLE channel_members (
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (channel_id, user_id)
+);
+
+CREATE TABLE search_index (
+    name        TEXT,
+    text        TEXT,
+    envelope_id TEXT NOT NULL,
+    super_type  TEXT NOT NULL
+);
+CREATE INDEX search_index_envelope ON search_index (envelope_id, super_type);
+CREATE INDEX search_index_name ON search_index USING gin (name gin_trgm_ops);
+CREATE INDEX search_index_text ON search_index USING gin (text gin_trgm_ops);
+
+CREATE TABLE sort_index (
+    super_type  TEXT NOT NULL,
+    envelope_id TEXT NOT NULL,
+    sort_key    TEXT NOT NULL,
+    PRIMARY KEY (super_type, envelope_id)
+);
+CREATE INDEX sort_index_key ON sort_index (sort_key, envelope_id);
+
+CREATE TABLE spatial_key (
+    rid         BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    super_type  TEXT NOT NULL,
+    envelope_id TEXT NOT NULL,
+    UNIQUE (super_type, envelope_id)
+);
+
+CREATE TABLE spatial_index (
+    rid   BIGINT PRIMARY KEY,
+    min_x DOUBLE PRECISION NOT NULL,
+    max_x DOUBLE PRECISION NOT NULL,
+    min_y DOUBLE PRECISION NOT NULL,
+    max_y DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX spatial_index_box ON spatial_index
+    USING gist (box(point(min_x, min_y), point(max_x, max_y)));
+
+CREATE TABLE envelope_revisions (
+    envelope_id TEXT NOT NULL,
+    super_type  TEXT NOT NULL,
+    rev         INTEGER NOT NULL,
+    payload     TEXT NOT NULL,
+    container   TEXT,
+    actor       TEXT NOT NULL,
+    recorded_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"'),
+    PRIMARY KEY (envelope_id, rev)
+);
+
+CREATE TABLE change_log (
+    seq         BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    op          TEXT NOT NULL,
+    super_type  TEXT NOT NULL,
+    envelope_id TEXT NOT NULL,
+    type_id     TEXT NOT NULL,
+    container   TEXT,
+    at          TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')
+);
+CREATE INDEX change_log_envelope ON change_log (super_type, envelope_id, seq);
+CREATE INDEX change_log_at ON change_log (at);
+
+CREATE TABLE change_log_horizon (
+    id      INTEGER PRIMARY KEY CHECK (id = 1),
+    horizon BIGINT NOT NULL
+);
+INSERT INTO change_log_horizon (id, horizon) VALUES (1, 0);
+
+CREATE TABLE runtime_component_state (
+    name            TEXT PRIMARY KEY,
+    version         BIGINT NOT NULL,
+    log_offset      BIGINT NOT NULL DEFAULT 0,
+    state           TEXT NOT NULL DEFAULT 'stopped',
+    restarts        BIGINT NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    last_healthy_at TEXT,
+    updated_at      TEXT,
+    control         TEXT
+);
+
+CREATE TABLE runtime_component_checkpoint (
+    name       TEXT PRIMARY KEY,
+    value      TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')
+);
+
+CREATE TABLE sessions (
+    token_hash TEXT PRIMARY KEY,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    expires_at TEXT NOT NULL
+);
+CREATE INDEX sessions_user ON sessions (user_id);
@@ -0,0 +1,10 @@
+-- Channel roles (`design/permissions.md`), as in the sqlite step of the same name.
+
+CREATE TABLE channel_roles (
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role       TEXT NOT NULL,
+    PRIMARY KEY (channel_id, user_id, role)
+);
+
+CREATE INDEX channel_roles_user ON channel_roles (user_id);
@@ -0,0 +1,16 @@
+-- Invite codes (`design/auth.md`), as in the sqlite step of the same name.
+
+CREATE TABLE invites (
+    id         TEXT PRIMARY KEY,
+    code_hash  TEXT NOT NULL UNIQUE,
+    created_by TEXT REFERENCES users (id) ON DELETE SET NULL,
+    uses_left  INTEGER NOT NULL,
+    expires_at TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE invite_channels (
+    invite_id  TEXT NOT NULL REFERENCES invites (id) ON DELETE CASCADE,
+    channel_id TEXT NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+    PRIMARY KEY (invite_id, channel_id)
+);
@@ -0,0 +1,14 @@
+-- Personal access tokens (`design/auth.md`), as in the sqlite step of the same name.
+
+CREATE TABLE api_tokens (
+    id           TEXT PRIMARY KEY,
+    token_hash   TEXT NOT NULL UNIQUE,
+    user_id      TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name         TEXT NOT NULL,
+    scopes       TEXT NOT NULL,
+    created_at   TEXT NOT NULL,
+    expires_at   TEXT,
+    last_used_at TEXT
+);
+
+CREATE INDEX api_tokens_user ON api_tokens (user_id);
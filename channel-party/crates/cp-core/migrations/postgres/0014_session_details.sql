@@ -0,0 +1,11 @@
+-- Session management (`design/auth.md`), as in the sqlite step of the same name.
+
+ALTER TABLE sessions ADD COLUMN id TEXT;
+ALTER TABLE sessions ADD COLUMN last_seen_at TEXT;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip TEXT;
+
+UPDATE sessions SET id = md5(random()::text || token_hash), last_seen_at = created_at;
+
+CREATE UNIQUE INDEX sessions_id ON sessions (id);
+CREATE INDEX sessions_expires ON sessions (expires_at);
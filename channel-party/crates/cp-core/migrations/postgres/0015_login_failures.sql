@@ -0,0 +1,11 @@
+-- The failed-login ledger (`design/auth.md`), as in the sqlite step of the same name.
+
+CREATE TABLE login_failures (
+    kind           TEXT NOT NULL,
+    subject        TEXT NOT NULL,
+    failures       BIGINT NOT NULL,
+    last_failed_at TEXT NOT NULL,
+    blocked_until  TEXT NOT NULL,
+    locked_at      TEXT,
+    PRIMARY KEY (kind, subject)
+);
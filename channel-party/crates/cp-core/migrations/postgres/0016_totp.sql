@@ -0,0 +1,24 @@
+-- TOTP second factor (`design/auth.md`), as in the sqlite step of the same name.
+
+CREATE TABLE user_totp (
+    user_id    TEXT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    secret     TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    enabled_at TEXT,
+    last_step  BIGINT
+);
+
+CREATE TABLE totp_recovery_codes (
+    code_hash TEXT PRIMARY KEY,
+    user_id   TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    used_at   TEXT
+);
+
+CREATE INDEX totp_recovery_codes_user ON totp_recovery_codes (user_id);
+
+CREATE TABLE pending_logins (
+    token_hash TEXT PRIMARY KEY,
+    user_id    TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    expires_at TEXT NOT NULL,
+    attempts   BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,924 @@
+//! Portable export/import of a store or a subtree (`channel-party export` / `import`, DESIGN §3/§10,
+//! `TODO.md` #36). An archive is self-describing JSONL: a `header` record, then `user`, `channel`,
+//! `item`, `member`, `role` and `link` records, then `kind_row` dumps of the kind-owned tables (§6). It carries
+//! live state only — no revision history, tombstones, sessions or change log.
+//!
+//! Export walks the tree with `StoreCtx::descendants`. Import replays it through the `WriteCtx` write
+//! path as one `Batch` whose creates carry the archived ULIDs, so every envelope is re-validated and
+//! re-indexed by its kind and evented like any other write. Conflicts (a taken id, handle or
+//! `external_key`, an unregistered type, a payload the kind rejects) are all found before anything is
+//! written; any conflict refuses the whole import. Kind-owned rows are opaque to core, so they are
+//! restored last with `INSERT OR IGNORE` — a row colliding with an existing one, or naming a table its
+//! owner does not create here, is skipped and counted, not a conflict — and only exported for a whole
+//! store (core cannot tell which rows a subtree owns; a `Derived` table is rebuilt from the imported
+//! envelopes anyway). Users and envelopes commit together, so a failed batch leaves no users behind.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+
+use cp_model::{
+    Actor, Backend, Channel, ChannelId, Error, Filter, Item, ItemId, Json, Mutation, NewChannel,
+    NewItem, Node, Result, Role, StoreCtx, UserId, WriteCtx,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::any::AnyRow;
+use sqlx::{AnyPool, Column, Row, TypeInfo, ValueRef};
+
+use crate::store::Store;
+use crate::Core;
+
+/// The `format` every archive header names.
+pub const FORMAT: &str = "channel-party-archive";
+
+/// The archive version this build writes (and the newest it reads).
+pub const VERSION: u32 = 2;
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// One JSONL line of an archive, tagged by `record`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum Record {
+    Header(Header),
+    User(ArchivedUser),
+    Channel(Channel),
+    Item(Item),
+    /// A `channel_members` edge.
+    Member {
+        channel: ChannelId,
+        user: UserId,
+    },
+    /// A `channel_roles` grant (§18), since version 2.
+    Role {
+        channel: ChannelId,
+        user: UserId,
+        role: String,
+    },
+    /// A `linked-users` edge (§19).
+    Link {
+        user: UserId,
+        item: ItemId,
+    },
+    /// One row of a kind-owned table, column name → value (a BLOB is `{"blob": "<hex>"}`).
+    KindRow {
+        owner: String,
+        table: String,
+        row: serde_json::Map<String, Json>,
+    },
+}
+
+/// The first record: what the archive is and what it holds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub format: String,
+    pub version: u32,
+    pub exported_at_ms: u64,
+    /// The subtree root, or `None` for a whole store.
+    pub root: Option<ChannelId>,
+    pub password_hashes: bool,
+    /// The kind-owned tables dumped (none for a subtree).
+    pub kind_tables: Vec<KindTable>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KindTable {
+    pub owner: String,
+    pub table: String,
+}
+
+/// A native user as archived. The password hash is only present when the export asked for it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedUser {
+    pub id: UserId,
+    pub handle: String,
+    pub operator: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Export only this channel and its subtree.
+    pub root: Option<ChannelId>,
+    /// Include users' argon2 password hashes (off by default: an archive is easy to leak).
+    pub password_hashes: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    /// Where the archive's top-level channels land: under this channel, or at the root.
+    pub into: Option<ChannelId>,
+}
+
+/// One reason an import was refused.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// What it concerns, e.g. `channel 01H…` or `user @alice`.
+    pub record: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.record, self.reason)
+    }
+}
+
+/// What an import did — or, with `conflicts`, why it did nothing.
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    pub users: usize,
+    /// Archived users that already existed here with the same id and handle, reused as-is.
+    pub users_existing: usize,
+    pub channels: usize,
+    pub items: usize,
+    pub members: usize,
+    pub roles: usize,
+    pub links: usize,
+    pub kind_rows: usize,
+    /// Kind-owned rows skipped: they collided with an existing row, or their table is not registered.
+    pub kind_rows_skipped: usize,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "imported {} users ({} already present), {} channels, {} items, {} memberships, {} roles, \
+             {} links, {} kind rows ({} skipped)",
+            self.users,
+            self.users_existing,
+            self.channels,
+            self.items,
+            self.members,
+            self.roles,
+            self.links,
+            self.kind_rows,
+            self.kind_rows_skipped
+        )
+    }
+}
+
+// --- export ---------------------------------------------------------------------------------------
+
+/// Export the live store (or `options.root`'s subtree) as archive records, header first.
+pub async fn export(store: &Store, options: &ExportOptions) -> Result<Vec<Record>> {
+    let pool = store.pool();
+    let mut nodes = Vec::new();
+    match options.root {
+        Some(root) => {
+            let channel = store.get_channel(root).await?.ok_or(Error::NotFound)?;
+            nodes.push(Node::Channel(channel));
+            nodes.extend(store.descendants(root, Filter::default(), None).await?);
+        }
+        None => {
+            for id in live_ids(pool, "channels").await? {
+                let Some(channel) = store.get_channel(id.parse().map_err(bad_id)?).await? else {
+                    continue;
+                };
+                let root = channel.id;
+                nodes.push(Node::Channel(channel));
+                nodes.extend(store.descendants(root, Filter::default(), None).await?);
+            }
+            // Items outside any channel are no channel's descendants.
+            for id in live_ids(pool, "items").await? {
+                if let Some(item) = store.get_item(id.parse().map_err(bad_id)?).await? {
+                    nodes.push(Node::Item(item));
+                }
+            }
+        }
+    }
+    let (mut channels, mut items) = (Vec::new(), Vec::new());
+    for node in nodes {
+        match node {
+            Node::Channel(c) => channels.push(c),
+            Node::Item(i) => items.push(i),
+        }
+    }
+
+    let (mut members, mut roles) = (Vec::new(), Vec::new());
+    for channel in &channels {
+        for user in store.members(channel.id).await? {
+            members.push((channel.id, user));
+        }
+        for (user, role) in store.role_grants(channel.id).await? {
+            roles.push((channel.id, user, role));
+        }
+    }
+    let mut links = Vec::new();
+    for item in &items {
+        if let Some(user) = crate::links::user_for_item(pool, item.id).await? {
+            links.push((user.id, item.id));
+        }
+    }
+
+    // A whole store carries every user; a subtree only those its memberships, roles and links mention.
+    let mentioned: HashSet<UserId> = members
+        .iter()
+        .map(|(_, u)| *u)
+        .chain(roles.iter().map(|(_, u, _)| *u))
+        .chain(links.iter().map(|(u, _)| *u))
+        .collect();
+    let users: Vec<ArchivedUser> = load_users(pool)
+        .await?
+        .into_iter()
+        .filter(|u| options.root.is_none() || mentioned.contains(&u.id))
+        .map(|mut u| {
+            if !options.password_hashes {
+                u.password_hash = None;
+            }
+            u
+        })
+        .collect();
+
+    let kind_tables = match options.root {
+        Some(_) => Vec::new(),
+        None => kind_tables(store),
+    };
+    let mut records = vec![Record::Header(Header {
+        format: FORMAT.to_owned(),
+        version: VERSION,
+        exported_at_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+        root: options.root,
+        password_hashes: options.password_hashes,
+        kind_tables: kind_tables.clone(),
+    })];
+    records.extend(users.into_iter().map(Record::User));
+    records.extend(channels.into_iter().map(Record::Channel));
+    records.extend(items.into_iter().map(Record::Item));
+    records.extend(
+        members
+            .into_iter()
+            .map(|(channel, user)| Record::Member { channel, user }),
+    );
+    records.extend(roles.into_iter().map(|(channel, user, role)| Record::Role {
+        channel,
+        user,
+        role: role.to_string(),
+    }));
+    records.extend(
+        links
+            .into_iter()
+            .map(|(user, item)| Record::Link { user, item }),
+    );
+    for KindTable { owner, table } in kind_tables {
+        let rows = sqlx::query(&format!("SELECT * FROM \"{table}\""))
+            .fetch_all(pool)
+            .await
+            .map_err(db)?;
+        for row in &rows {
+            records.push(Record::KindRow {
+                owner: owner.clone(),
+                table: table.clone(),
+                row: row_to_json(row)?,
+            });
+        }
+    }
+    Ok(records)
+}
+
+/// Render records as JSONL, one per line.
+pub fn to_jsonl(records: &[Record]) -> Result<String> {
+    let mut out = String::new();
+    for record in records {
+        out.push_str(&serde_json::to_string(record).map_err(|e| Error::Other(e.to_string()))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Parse JSONL into records, checking the header names a format and version this build reads.
+pub fn from_jsonl(text: &str) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(line)
+            .map_err(|e| Error::Validation(format!("archive line {}: {e}", n + 1)))?;
+        records.push(record);
+    }
+    match records.first() {
+        Some(Record::Header(h)) if h.format == FORMAT && h.version <= VERSION => Ok(records),
+        Some(Record::Header(h)) => Err(Error::Validation(format!(
+            "unsupported archive: format `{}` version {} (this build reads `{FORMAT}` up to {VERSION})",
+            h.format, h.version
+        ))),
+        _ => Err(Error::Validation(
+            "not a channel-party archive: the first record must be a header".to_owned(),
+        )),
+    }
+}
+
+/// Top-level live ids of `channels` / `items` (no container).
+async fn live_ids(pool: &AnyPool, table: &str) -> Result<Vec<String>> {
+    sqlx::query_scalar(&format!(
+        "SELECT id FROM {table} WHERE container IS NULL AND trash_root IS NULL ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(db)
+}
+
+async fn load_users(pool: &AnyPool) -> Result<Vec<ArchivedUser>> {
+    let rows = sqlx::query("SELECT id, handle, operator, password_hash FROM users ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(db)?;
+    rows.iter()
+        .map(|r| {
+            Ok(ArchivedUser {
+                id: r
+                    .try_get::<String, _>("id")
+                    .map_err(db)?
+                    .parse()
+                    .map_err(bad_id)?,
+                handle: r.try_get("handle").map_err(db)?,
+                operator: r.try_get::<i64, _>("operator").map_err(db)? != 0,
+                password_hash: r.try_get("password_hash").map_err(db)?,
+            })
+        })
+        .collect()
+}
+
+/// The tables each registered kind migration set creates on this backend, read off its
+/// `CREATE [VIRTUAL] TABLE` statements (a kind declares its tables nowhere else). A virtual table's
+/// shadow tables are not named there, so they are rebuilt by inserting into the virtual table.
+fn kind_tables(store: &Store) -> Vec<KindTable> {
+    let backend = Backend::of(store.pool());
+    let mut tables = Vec::new();
+    for set in store.registry().migrations() {
+        for step in set.steps_for(backend) {
+            for table in created_tables(step.sql) {
+                let entry = KindTable {
+                    owner: set.owner.to_owned(),
+                    table,
+                };
+                if !tables.contains(&entry) {
+                    tables.push(entry);
+                }
+            }
+        }
+    }
+    tables
+}
+
+fn created_tables(sql: &str) -> Vec<String> {
+    let code: String = sql
+        .lines()
+        .map(|l| l.split("--").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let words: Vec<String> = code
+        .replace('(', " ( ")
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect();
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let upper = |j: usize| words.get(j).map(|w| w.to_ascii_uppercase());
+        if upper(i).as_deref() == Some("CREATE") {
+            let mut j = i + 1;
+            if upper(j).as_deref() == Some("VIRTUAL") {
+                j += 1;
+            }
+            if upper(j).as_deref() == Some("TABLE") {
+                j += 1;
+                if upper(j).as_deref() == Some("IF") {
+                    j += 3; // IF NOT EXISTS
+                }
+                if let Some(name) = words.get(j) {
+                    tables.push(name.trim_matches(['"', '`', '[', ']']).to_owned());
+                }
+                i = j;
+            }
+        }
+        i += 1;
+    }
+    tables
+}
+
+/// A row of an opaque table as JSON, by each value's type.
+fn row_to_json(row: &AnyRow) -> Result<serde_json::Map<String, Json>> {
+    let mut out = serde_json::Map::new();
+    for (i, column) in row.columns().iter().enumerate() {
+        let raw = row.try_get_raw(i).map_err(db)?;
+        let value = if raw.is_null() {
+            Json::Null
+        } else {
+            match raw.type_info().name() {
+                "BOOLEAN" => Json::from(row.try_get::<bool, _>(i).map_err(db)?),
+                "SMALLINT" | "INTEGER" | "BIGINT" => {
+                    Json::from(row.try_get::<i64, _>(i).map_err(db)?)
+                }
+                "REAL" | "DOUBLE" => Json::from(row.try_get::<f64, _>(i).map_err(db)?),
+                "BLOB" => {
+                    serde_json::json!({ "blob": hex(&row.try_get::<Vec<u8>, _>(i).map_err(db)?) })
+                }
+                _ => Json::from(row.try_get::<String, _>(i).map_err(db)?),
+            }
+        };
+        out.insert(column.name().to_owned(), value);
+    }
+    Ok(out)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn bad_id<E>(_: E) -> Error {
+    Error::Other("invalid id in the store".to_owned())
+}
+
+// --- import ---------------------------------------------------------------------------------------
+
+/// Import archive records into `store`, preserving every id. Refuses (writing nothing) when
+/// `report.conflicts` is non-empty; otherwise writes users, envelopes, memberships and roles in one
+/// transaction, then links and kind-owned rows.
+pub async fn import(
+    store: &Store,
+    records: Vec<Record>,
+    options: &ImportOptions,
+) -> Result<ImportReport> {
+    let pool = store.pool();
+    let (mut users, mut channels, mut items) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut members, mut roles, mut links, mut kind_rows) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for record in records {
+        match record {
+            Record::Header(_) => {}
+            Record::User(u) => users.push(u),
+            Record::Channel(c) => channels.push(c),
+            Record::Item(i) => items.push(i),
+            Record::Member { channel, user } => members.push((channel, user)),
+            Record::Role {
+                channel,
+                user,
+                role,
+            } => roles.push((channel, user, role)),
+            Record::Link { user, item } => links.push((user, item)),
+            Record::KindRow { owner, table, row } => kind_rows.push((owner, table, row)),
+        }
+    }
+    if let Some(into) = options.into {
+        store.get_channel(into).await?.ok_or(Error::NotFound)?;
+    }
+
+    let mut report = ImportReport::default();
+    let conflict = |record: String, reason: &str| Conflict {
+        record,
+        reason: reason.to_owned(),
+    };
+
+    // Users: the same id + handle already here is the same person; anything else taken is a conflict.
+    let mut new_users = Vec::new();
+    let mut known_users: HashSet<UserId> = HashSet::new();
+    for user in users {
+        let existing = sqlx::query("SELECT id, handle FROM users WHERE id = $1 OR handle = $2")
+            .bind(user.id.to_string())
+            .bind(&user.handle)
+            .fetch_all(pool)
+            .await
+            .map_err(db)?;
+        let same = |r: &AnyRow| {
+            r.try_get::<String, _>("id").ok() == Some(user.id.to_string())
+                && r.try_get::<String, _>("handle").ok().as_deref() == Some(user.handle.as_str())
+        };
+        match existing.as_slice() {
+            [] => new_users.push(user.clone()),
+            [r] if same(r) => report.users_existing += 1,
+            _ => report.conflicts.push(conflict(
+                format!("user @{}", user.handle),
+                "its id or handle is already taken by another user",
+            )),
+        }
+        known_users.insert(user.id);
+    }
+
+    // Envelopes: registered type, a payload the kind accepts, and an id (and external key) not in use.
+    let channel_ids: HashSet<ChannelId> = channels.iter().map(|c| c.id).collect();
+    let item_ids: HashSet<ItemId> = items.iter().map(|i| i.id).collect();
+    for channel in &channels {
+        let record = format!("channel {}", channel.id);
+        match store.registry().channel(&channel.type_id) {
+            None => report.conflicts.push(conflict(
+                record.clone(),
+                &format!("type `{}` is not registered here", channel.type_id),
+            )),
+            Some(kind) => {
+                if let Err(e) = kind.validate(&channel.payload) {
+                    report
+                        .conflicts
+                        .push(conflict(record.clone(), &format!("rejected: {e}")));
+                }
+            }
+        }
+        if exists(pool, "channels", &channel.id.to_string()).await? {
+            report
+                .conflicts
+                .push(conflict(record, "a channel with this id already exists"));
+        }
+    }
+    for item in &items {
+        let record = format!("item {}", item.id);
+        match store.registry().item(&item.type_id) {
+            None => report.conflicts.push(conflict(
+                record.clone(),
+                &format!("type `{}` is not registered here", item.type_id),
+            )),
+            Some(kind) => {
+                if let Err(e) = kind.validate(&item.payload) {
+                    report
+                        .conflicts
+                        .push(conflict(record.clone(), &format!("rejected: {e}")));
+                }
+            }
+        }
+        if item.container.is_some_and(|c| !channel_ids.contains(&c)) {
+            report.conflicts.push(conflict(
+                record.clone(),
+                "its container is not in the archive",
+            ));
+        }
+        if exists(pool, "items", &item.id.to_string()).await? {
+            report.conflicts.push(conflict(
+                record.clone(),
+                "an item with this id already exists",
+            ));
+        }
+        if let Some(key) = &item.external_key {
+            let taken: Option<String> =
+                sqlx::query_scalar("SELECT id FROM items WHERE external_key = $1")
+                    .bind(key)
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(db)?;
+            if taken.is_some() {
+                report.conflicts.push(conflict(
+                    record,
+                    &format!("external key `{key}` is already in use"),
+                ));
+            }
+        }
+    }
+    for (channel, user) in &members {
+        let record = format!("membership {channel} / {user}");
+        if !channel_ids.contains(channel) {
+            report
+                .conflicts
+                .push(conflict(record, "its channel is not in the archive"));
+        } else if !known_users.contains(user) && !exists(pool, "users", &user.to_string()).await? {
+            report
+                .conflicts
+                .push(conflict(record, "its user is unknown"));
+        }
+    }
+    let mut parsed_roles = Vec::with_capacity(roles.len());
+    for (channel, user, role) in roles {
+        let record = format!("role {role} on {channel} / {user}");
+        let parsed = match role.parse::<Role>() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                report.conflicts.push(conflict(record, &e.to_string()));
+                continue;
+            }
+        };
+        if !channel_ids.contains(&channel) {
+            report
+                .conflicts
+                .push(conflict(record, "its channel is not in the archive"));
+        } else if !known_users.contains(&user) && !exists(pool, "users", &user.to_string()).await? {
+            report
+                .conflicts
+                .push(conflict(record, "its user is unknown"));
+        }
+        parsed_roles.push((channel, user, parsed));
+    }
+    for (user, item) in &links {
+        let record = format!("link {user} / {item}");
+        if !item_ids.contains(item) {
+            report
+                .conflicts
+                .push(conflict(record, "its item is not in the archive"));
+        } else if !known_users.contains(user) && !exists(pool, "users", &user.to_string()).await? {
+            report
+                .conflicts
+                .push(conflict(record, "its user is unknown"));
+        }
+    }
+    if !report.conflicts.is_empty() {
+        report.users_existing = 0;
+        return Ok(report);
+    }
+    let restorable = restorable_rows(store, kind_rows, &mut report).await?;
+
+    // Parents before children: a channel is queued once its container is (or is outside the archive,
+    // making it a top-level channel, re-homed under `into`).
+    let mut batch = cp_model::Batch::new();
+    let mut placed: HashSet<ChannelId> = HashSet::new();
+    let mut pending = channels;
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<Channel>, Vec<Channel>) = pending.into_iter().partition(|c| {
+            c.container
+                .is_none_or(|p| placed.contains(&p) || !channel_ids.contains(&p))
+        });
+        if ready.is_empty() {
+            return Err(Error::Validation(
+                "the archive's channels form a containment cycle".to_owned(),
+            ));
+        }
+        for c in ready {
+            let container = match c.container {
+                Some(p) if channel_ids.contains(&p) => Some(p),
+                _ => options.into,
+            };
+            placed.insert(c.id);
+            batch.push(Mutation::CreateChannel {
+                id: c.id,
+                spec: NewChannel {
+                    type_id: c.type_id,
+                    container,
+                    payload: c.payload,
+                },
+            });
+        }
+        pending = rest;
+    }
+    report.channels = placed.len();
+    report.items = items.len();
+    for i in items {
+        batch.push(Mutation::CreateItem {
+            id: i.id,
+            spec: NewItem {
+                type_id: i.type_id,
+                container: i.container,
+                external_key: i.external_key,
+                payload: i.payload,
+            },
+        });
+    }
+    report.members = members.len();
+    for (channel, user) in members {
+        batch.add_member(channel, user);
+    }
+    report.roles = parsed_roles.len();
+    for (channel, user, role) in parsed_roles {
+        batch.grant_role(channel, user, role);
+    }
+    store.apply_with_users(&new_users, batch).await?;
+    report.users = new_users.len();
+
+    for (user, item) in &links {
+        crate::links::link(pool, *user, *item).await?;
+    }
+    report.links = links.len();
+
+    // Kind-owned rows, opaque to core: restored where this build registers the table, never overwriting.
+    let backend = Backend::of(pool);
+    for (table, row) in restorable {
+        let inserted = match backend {
+            Backend::Sqlite => insert_sqlite_row(pool, &table, &row).await?,
+            Backend::Postgres => insert_postgres_row(pool, &table, row).await?,
+        };
+        if inserted == 0 {
+            report.kind_rows_skipped += 1;
+        } else {
+            report.kind_rows += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// The kind rows an import may write: only into a table its named owner's migrations create here (never
+/// a core table, whatever the archive says), and only with that table's real columns, since both are
+/// spliced into the SQL. Anything else is counted as skipped.
+async fn restorable_rows(
+    store: &Store,
+    rows: Vec<(String, String, serde_json::Map<String, Json>)>,
+    report: &mut ImportReport,
+) -> Result<Vec<(String, serde_json::Map<String, Json>)>> {
+    let registered = kind_tables(store);
+    let mut columns: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut restorable = Vec::new();
+    for (owner, table, row) in rows {
+        let entry = KindTable { owner, table };
+        if !registered.contains(&entry) {
+            report.kind_rows_skipped += 1;
+            continue;
+        }
+        let table = entry.table;
+        if !columns.contains_key(&table) {
+            let found = table_columns(store.pool(), &table).await?;
+            columns.insert(table.clone(), found);
+        }
+        if row.is_empty() || !row.keys().all(|c| columns[&table].contains(c)) {
+            report.kind_rows_skipped += 1;
+            continue;
+        }
+        restorable.push((table, row));
+    }
+    Ok(restorable)
+}
+
+/// The column names of `table` as this backend has it.
+async fn table_columns(pool: &AnyPool, table: &str) -> Result<HashSet<String>> {
+    let names: Vec<String> = sqlx::query_scalar(match Backend::of(pool) {
+        Backend::Sqlite => "SELECT name FROM pragma_table_info($1)",
+        Backend::Postgres => {
+            "SELECT column_name::text FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1"
+        }
+    })
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    Ok(names.into_iter().collect())
+}
+
+/// Insert an exported row unless its key is taken, binding each value by its JSON type. `INSERT OR
+/// IGNORE`, not an upsert clause: sqlite's virtual tables (an R-tree, FTS) reject `ON CONFLICT`.
+async fn insert_sqlite_row(
+    pool: &AnyPool,
+    table: &str,
+    row: &serde_json::Map<String, Json>,
+) -> Result<u64> {
+    let columns: Vec<String> = row.keys().map(|c| format!("\"{c}\"")).collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|n| format!("${n}")).collect();
+    let sql = format!(
+        "INSERT OR IGNORE INTO \"{table}\" ({}) VALUES ({})",
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for value in row.values() {
+        query = match value {
+            Json::Null => query.bind(None::<String>),
+            Json::Bool(b) => query.bind(*b),
+            Json::Number(n) => match n.as_i64() {
+                Some(i) => query.bind(i),
+                None => query.bind(n.as_f64()),
+            },
+            Json::String(s) => query.bind(s.clone()),
+            Json::Object(o) => match o.get("blob").and_then(Json::as_str).and_then(unhex) {
+                Some(bytes) => query.bind(bytes),
+                None => query.bind(value.to_string()),
+            },
+            Json::Array(_) => query.bind(value.to_string()),
+        };
+    }
+    Ok(query.execute(pool).await.map_err(db)?.rows_affected())
+}
+
+/// Insert an exported row unless its key is taken. Postgres types every bind, so the row goes in as one
+/// JSON object that `json_populate_record` converts column by column against the table's own types; a
+/// blob becomes `bytea`'s `\x…` text form on the way.
+async fn insert_postgres_row(
+    pool: &AnyPool,
+    table: &str,
+    mut row: serde_json::Map<String, Json>,
+) -> Result<u64> {
+    for value in row.values_mut() {
+        if let Some(hex) = value.get("blob").and_then(Json::as_str) {
+            *value = Json::from(format!("\\x{hex}"));
+        }
+    }
+    let sql = format!(
+        "INSERT INTO \"{table}\" SELECT * FROM json_populate_record(NULL::\"{table}\", $1::json) \
+         ON CONFLICT DO NOTHING"
+    );
+    let done = sqlx::query(&sql)
+        .bind(Json::Object(row).to_string())
+        .execute(pool)
+        .await
+        .map_err(db)?;
+    Ok(done.rows_affected())
+}
+
+/// Whether `id` is in `table` at all, trashed or not (a tombstone still holds its id).
+async fn exists(pool: &AnyPool, table: &str, id: &str) -> Result<bool> {
+    let n: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE id = $1"))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(db)?;
+    Ok(n > 0)
+}
+
+// --- CLI ------------------------------------------------------------------------------------------
+
+/// `channel-party export [--root <channel-id>] [--with-password-hashes] [--out <file>]`: write an
+/// archive to the file, or stdout.
+pub async fn run_export(core: &Core, args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let mut options = ExportOptions::default();
+    let mut out: Option<PathBuf> = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => {
+                let id = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--root needs a channel id"))?;
+                options.root = Some(
+                    id.parse()
+                        .map_err(|_| anyhow::anyhow!("invalid channel id `{id}`"))?,
+                );
+            }
+            "--with-password-hashes" => options.password_hashes = true,
+            "--out" => {
+                out = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow::anyhow!("--out needs a file"))?
+                        .into(),
+                )
+            }
+            other => anyhow::bail!(
+                "unknown export argument `{other}`; usage: channel-party export [--root <channel-id>] \
+                 [--with-password-hashes] [--out <file>]"
+            ),
+        }
+    }
+    let store = core.store();
+    let records = export(&store, &options).await.map_err(|e| match e {
+        Error::NotFound => anyhow::anyhow!("no live channel is the export root"),
+        e => e.into(),
+    })?;
+    let text = to_jsonl(&records)?;
+    match out {
+        Some(path) => tokio::fs::write(&path, text).await?,
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// `channel-party import <file|-> [--into <channel-id>]`: import an archive, printing what it did, or
+/// every conflict and a non-zero exit (nothing written).
+pub async fn run_import(core: &Core, args: impl IntoIterator<Item = String>) -> anyhow::Result<()> {
+    let mut options = ImportOptions::default();
+    let mut source: Option<String> = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--into" => {
+                let id = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--into needs a channel id"))?;
+                options.into = Some(
+                    id.parse()
+                        .map_err(|_| anyhow::anyhow!("invalid channel id `{id}`"))?,
+                );
+            }
+            s if source.is_none() && (s == "-" || !s.starts_with("--")) => {
+                source = Some(s.to_owned())
+            }
+            other => anyhow::bail!(
+                "unexpected import argument `{other}`; usage: channel-party import <file|-> \
+                 [--into <channel-id>]"
+            ),
+        }
+    }
+    let text = match source.as_deref() {
+        Some("-") => {
+            use tokio::io::AsyncReadExt;
+            let mut text = String::new();
+            tokio::io::stdin().read_to_string(&mut text).await?;
+            text
+        }
+        Some(path) => tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("reading {path}: {e}"))?,
+        None => anyhow::bail!("usage: channel-party import <file|-> [--into <channel-id>]"),
+    };
+    // Imported writes are the operator's, in the revision history, as the shell's are.
+    let store = core.store().acting_as(Actor::Operator);
+    let report = import(&store, from_jsonl(&text)?, &options).await?;
+    if !report.conflicts.is_empty() {
+        for c in &report.conflicts {
+            eprintln!("conflict: {c}");
+        }
+        anyhow::bail!(
+            "{} conflict(s); nothing was imported",
+            report.conflicts.len()
+        );
+    }
+    println!("{report}");
+    Ok(())
+}
@@ -3,21 +3,45 @@
 //! argon2id PHC strings in `users.password_hash`; a login mints an opaque random token handed to the
 //! browser in a cookie, of which only the SHA-256 is stored (a DB leak exposes no live token). The
 //! HTTP/cookie layer lives in `cp-frontend`; this module is the store logic both it and the debug
-//! shell call. Accounts are *provisioned* (shell `set-password`) — there is no public registration.
+//! shell call. Accounts are *provisioned* (shell `set-password`) or self-registered with an invite code
+//! (`crate::invites`, #41); either way the handle policy below applies, and a self-chosen password must
+//! meet the password policy. Sessions (#43) record where they were opened, slide their expiry on use,
+//! can be listed and revoked by their owner, and are reaped once expired by [`SessionReaper`]. The login
+//! route reaches [`authenticate`] through `crate::throttle` (#44), which rate-limits failed attempts; a
+//! user with a second factor (`crate::totp`, #45) then needs a code before a session is created.
+
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
 
 use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
 use argon2::Argon2;
-use cp_model::{Error, Result, User, UserId};
+use async_trait::async_trait;
+use cp_model::{Error, Interests, Result, RuntimeComponent, RuntimeCtx, User, UserId, WriteScope};
 use rand_core::{OsRng, RngCore};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
-use sqlx::sqlite::SqliteRow;
-use sqlx::{Row, SqlitePool};
+use sqlx::any::AnyRow;
+use sqlx::{Any, AnyPool, Row};
+
+/// How long a login lasts without use. Each use renews it (see [`resolve_session`]).
+const SESSION_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How stale `last_seen_at` may get before a use renews the session, so a busy session is not written
+/// on every request.
+const RENEW_AFTER: Duration = Duration::from_secs(60);
+
+/// Handle length bounds, in characters.
+const HANDLE_LEN: std::ops::RangeInclusive<usize> = 2..=32;
+
+/// Password length bounds, in characters. The upper bound caps the cost of hashing a hostile input.
+const PASSWORD_LEN: std::ops::RangeInclusive<usize> = 8..=256;
 
 fn db(e: sqlx::Error) -> Error {
     Error::Other(e.to_string())
 }
 
-fn hash_password(password: &str) -> Result<String> {
+/// A PHC string for `password`: Argon2 with a fresh salt.
+pub(crate) fn hash_password(password: &str) -> Result<String> {
     let salt = SaltString::generate(&mut OsRng);
     Argon2::default()
         .hash_password(password.as_bytes(), &salt)
@@ -32,6 +56,12 @@ fn verify_password(password: &str, phc: &str) -> bool {
         .is_ok()
 }
 
+/// A hash no password is checked against in earnest, verified when there is no real one to check.
+fn decoy_hash() -> &'static str {
+    static DECOY: OnceLock<String> = OnceLock::new();
+    DECOY.get_or_init(|| hash_password(&random_token()).unwrap_or_default())
+}
+
 fn hex(bytes: &[u8]) -> String {
     use std::fmt::Write;
     bytes
@@ -42,7 +72,7 @@ fn hex(bytes: &[u8]) -> String {
         })
 }
 
-fn sha256_hex(bytes: &[u8]) -> String {
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
     hex(&hasher.finalize())
@@ -50,13 +80,13 @@ fn sha256_hex(bytes: &[u8]) -> String {
 
 /// A fresh 256-bit opaque token (hex), from the OS CSPRNG. This is the cookie value; the DB keys on its
 /// hash, never this.
-fn random_token() -> String {
+pub(crate) fn random_token() -> String {
     let mut bytes = [0u8; 32];
     OsRng.fill_bytes(&mut bytes);
     hex(&bytes)
 }
 
-fn user_from_row(row: &SqliteRow) -> Result<User> {
+fn user_from_row(row: &AnyRow) -> Result<User> {
     Ok(User {
         id: row
             .try_get::<String, _>("id")
@@ -67,26 +97,122 @@ fn user_from_row(row: &SqliteRow) -> Result<User> {
     })
 }
 
+/// The handle policy: 2–32 lowercase ASCII letters, digits, `_`, `-` and `.`, starting with a letter
+/// or digit. `Validation` otherwise.
+pub fn validate_handle(handle: &str) -> Result<()> {
+    let len = handle.chars().count();
+    if !HANDLE_LEN.contains(&len) {
+        return Err(Error::Validation(format!(
+            "a handle must be {} to {} characters",
+            HANDLE_LEN.start(),
+            HANDLE_LEN.end()
+        )));
+    }
+    let allowed = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.".contains(c);
+    if !handle.chars().all(allowed) {
+        return Err(Error::Validation(
+            "a handle may only contain lowercase letters, digits, `_`, `-` and `.`".to_owned(),
+        ));
+    }
+    if !handle.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(Error::Validation(
+            "a handle must start with a letter or digit".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// The password policy for a password a user chooses: 8–256 characters, and not the handle itself.
+/// `Validation` otherwise. Operator-set passwords (`set_password`) are the operator's call.
+pub fn validate_password(handle: &str, password: &str) -> Result<()> {
+    let len = password.chars().count();
+    if !PASSWORD_LEN.contains(&len) {
+        return Err(Error::Validation(format!(
+            "a password must be {} to {} characters",
+            PASSWORD_LEN.start(),
+            PASSWORD_LEN.end()
+        )));
+    }
+    if password.eq_ignore_ascii_case(handle) {
+        return Err(Error::Validation(
+            "a password must not be the handle".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a user with this handle exists.
+pub async fn handle_taken(pool: &AnyPool, handle: &str) -> Result<bool> {
+    let found: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE handle = $1")
+        .bind(handle)
+        .fetch_optional(pool)
+        .await
+        .map_err(db)?;
+    Ok(found.is_some())
+}
+
 /// Insert a native user (no password yet — inert until `set_password`). The provisioning primitive
 /// behind the shell's `create-user`; users are the fixed substrate, minted here rather than through the
-/// envelope API (§2/§8). Errors (e.g. a duplicate handle) surface as `Other`.
-pub async fn provision_user(pool: &SqlitePool, handle: &str) -> Result<UserId> {
+/// envelope API (§2/§8). The handle must meet [`validate_handle`] and be free (`Validation`, decided by
+/// the insert itself, so two callers cannot both take it); other errors surface as `Other`.
+pub async fn provision_user(pool: &AnyPool, handle: &str) -> Result<UserId> {
+    insert_user(pool, handle, None).await
+}
+
+/// [`provision_user`] on any executor, with an optional PHC `password_hash` ([`hash_password`]) — how
+/// invite registration creates the user and its password in the write that spends the invite.
+pub(crate) async fn insert_user<'e>(
+    executor: impl sqlx::Executor<'e, Database = Any>,
+    handle: &str,
+    password_hash: Option<&str>,
+) -> Result<UserId> {
+    validate_handle(handle)?;
     let id = UserId::generate();
-    sqlx::query("INSERT INTO users (id, handle) VALUES (?, ?)")
+    sqlx::query("INSERT INTO users (id, handle, password_hash) VALUES ($1, $2, $3)")
         .bind(id.to_string())
         .bind(handle)
-        .execute(pool)
+        .bind(password_hash)
+        .execute(executor)
         .await
-        .map_err(db)?;
+        .map_err(|e| match &e {
+            sqlx::Error::Database(d) if d.is_unique_violation() => {
+                Error::Validation(format!("handle `{handle}` is taken"))
+            }
+            _ => db(e),
+        })?;
     Ok(id)
 }
 
-/// Set (or replace) a user's password. `NotFound` if no user has that handle. The provisioning path
-/// (debug shell `set-password`) — there is no public registration. §17.
-pub async fn set_password(pool: &SqlitePool, handle: &str, password: &str) -> Result<()> {
+/// Set (or replace) a user's password, logging them out everywhere ([`revoke_all_sessions`]).
+/// `NotFound` if no user has that handle. The provisioning path (debug shell `set-password`, and invite
+/// registration after [`validate_password`]). §17.
+pub async fn set_password(pool: &AnyPool, handle: &str, password: &str) -> Result<()> {
     let hash = hash_password(password)?;
-    let affected = sqlx::query("UPDATE users SET password_hash = ? WHERE handle = ?")
-        .bind(hash)
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let user: Option<String> =
+        sqlx::query_scalar("UPDATE users SET password_hash = $1 WHERE handle = $2 RETURNING id")
+            .bind(hash)
+            .bind(handle)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db)?;
+    let Some(user) = user else {
+        return Err(Error::NotFound);
+    };
+    sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    tx.commit().await.map_err(db)?;
+    Ok(())
+}
+
+/// Grant or revoke operator rights (the admin HTTP surface, §8). `NotFound` if no user has that handle.
+/// Shell-only, like the rest of provisioning.
+pub async fn set_operator(pool: &AnyPool, handle: &str, operator: bool) -> Result<()> {
+    let affected = sqlx::query("UPDATE users SET operator = $1 WHERE handle = $2")
+        .bind(i64::from(operator))
         .bind(handle)
         .execute(pool)
         .await
@@ -98,19 +224,43 @@ pub async fn set_password(pool: &SqlitePool, handle: &str, password: &str) -> Re
     Ok(())
 }
 
+/// Whether a user holds operator rights. An unknown user does not.
+pub async fn is_operator(pool: &AnyPool, user_id: UserId) -> Result<bool> {
+    let operator: Option<i64> = sqlx::query_scalar("SELECT operator FROM users WHERE id = $1")
+        .bind(user_id.to_string())
+        .fetch_optional(pool)
+        .await
+        .map_err(db)?;
+    Ok(operator.is_some_and(|o| o != 0))
+}
+
+/// Look a user up by id. `None` if there is no such user.
+pub async fn user(pool: &AnyPool, user_id: UserId) -> Result<Option<User>> {
+    let row = sqlx::query("SELECT id, handle FROM users WHERE id = $1")
+        .bind(user_id.to_string())
+        .fetch_optional(pool)
+        .await
+        .map_err(db)?;
+    row.as_ref().map(user_from_row).transpose()
+}
+
 /// Verify credentials, returning the `User` on success. `None` for an unknown handle, a wrong password,
 /// or a user with no password set (a bare `create-user` account is inert until `set-password`).
-pub async fn authenticate(pool: &SqlitePool, handle: &str, password: &str) -> Result<Option<User>> {
-    let row = sqlx::query("SELECT id, handle, password_hash FROM users WHERE handle = ?")
+pub async fn authenticate(pool: &AnyPool, handle: &str, password: &str) -> Result<Option<User>> {
+    let row = sqlx::query("SELECT id, handle, password_hash FROM users WHERE handle = $1")
         .bind(handle)
         .fetch_optional(pool)
         .await
         .map_err(db)?;
-    let Some(row) = row else {
-        return Ok(None);
+    let hash = match &row {
+        Some(row) => row
+            .try_get::<Option<String>, _>("password_hash")
+            .map_err(db)?,
+        None => None,
     };
-    let hash: Option<String> = row.try_get("password_hash").map_err(db)?;
-    let Some(hash) = hash else {
+    let (Some(row), Some(hash)) = (row, hash) else {
+        // Spend a verify anyway, so a missing handle takes as long to refuse as a wrong password.
+        verify_password(password, decoy_hash());
         return Ok(None);
     };
     if verify_password(password, &hash) {
@@ -120,40 +270,221 @@ pub async fn authenticate(pool: &SqlitePool, handle: &str, password: &str) -> Re
     }
 }
 
+/// Where a session was opened from, as the HTTP layer saw it. Recorded for the owner's session list;
+/// never used to decide anything.
+#[derive(Clone, Debug, Default)]
+pub struct SessionOrigin {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// A live session, as its owner lists it. The token is never stored, so it is not here; `id` is what a
+/// session is revoked by.
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub expires_at: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether this is the session the listing was asked for with.
+    pub current: bool,
+}
+
 /// Mint a session for a user, returning the plaintext token to set as the cookie. §17.
-pub async fn create_session(pool: &SqlitePool, user_id: UserId) -> Result<String> {
+pub async fn create_session(
+    pool: &AnyPool,
+    user_id: UserId,
+    origin: &SessionOrigin,
+) -> Result<String> {
     let token = random_token();
+    let now = SystemTime::now();
     sqlx::query(
-        "INSERT INTO sessions (token_hash, user_id, expires_at) \
-         VALUES (?, ?, datetime('now', '+30 days'))",
+        "INSERT INTO sessions \
+         (id, token_hash, user_id, created_at, last_seen_at, expires_at, user_agent, ip) \
+         VALUES ($1, $2, $3, $4, $4, $5, $6, $7)",
     )
+    .bind(ulid::Ulid::new().to_string())
     .bind(sha256_hex(token.as_bytes()))
     .bind(user_id.to_string())
+    .bind(crate::db::session_time(now))
+    .bind(crate::db::session_time(now + SESSION_TTL))
+    .bind(origin.user_agent.as_deref())
+    .bind(origin.ip.as_deref())
     .execute(pool)
     .await
     .map_err(db)?;
     Ok(token)
 }
 
-/// Resolve a cookie token to its user, or `None` if the session is unknown or expired. §17.
-pub async fn resolve_session(pool: &SqlitePool, token: &str) -> Result<Option<User>> {
+/// Resolve a cookie token to its user, or `None` if the session is unknown or expired. §17. Expiry
+/// slides: a session seen more than [`RENEW_AFTER`] ago is stamped seen now and given a fresh
+/// [`SESSION_TTL`], so a session in use never lapses and an idle one does.
+pub async fn resolve_session(pool: &AnyPool, token: &str) -> Result<Option<User>> {
+    let hash = sha256_hex(token.as_bytes());
+    let now = SystemTime::now();
     let row = sqlx::query(
-        "SELECT u.id, u.handle FROM sessions s JOIN users u ON u.id = s.user_id \
-         WHERE s.token_hash = ? AND s.expires_at > datetime('now')",
+        "SELECT u.id, u.handle, s.last_seen_at FROM sessions s JOIN users u ON u.id = s.user_id \
+         WHERE s.token_hash = $1 AND s.expires_at > $2",
     )
-    .bind(sha256_hex(token.as_bytes()))
+    .bind(&hash)
+    .bind(crate::db::session_time(now))
     .fetch_optional(pool)
     .await
     .map_err(db)?;
-    row.as_ref().map(user_from_row).transpose()
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let last_seen: Option<String> = row.try_get("last_seen_at").map_err(db)?;
+    let stale = crate::db::session_time(now.checked_sub(RENEW_AFTER).unwrap_or(now));
+    if last_seen.is_none_or(|seen| seen <= stale) {
+        sqlx::query("UPDATE sessions SET last_seen_at = $1, expires_at = $2 WHERE token_hash = $3")
+            .bind(crate::db::session_time(now))
+            .bind(crate::db::session_time(now + SESSION_TTL))
+            .bind(&hash)
+            .execute(pool)
+            .await
+            .map_err(db)?;
+    }
+    user_from_row(&row).map(Some)
+}
+
+/// A user's live sessions, most recently seen first. `current` marks the one `token` opened, if given.
+pub async fn list_sessions(
+    pool: &AnyPool,
+    user_id: UserId,
+    token: Option<&str>,
+) -> Result<Vec<Session>> {
+    let current = token.map(|t| sha256_hex(t.as_bytes()));
+    let rows = sqlx::query(
+        "SELECT id, token_hash, created_at, last_seen_at, expires_at, user_agent, ip FROM sessions \
+         WHERE user_id = $1 AND expires_at > $2 ORDER BY last_seen_at DESC, id",
+    )
+    .bind(user_id.to_string())
+    .bind(crate::db::session_time(SystemTime::now()))
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter()
+        .map(|row| {
+            let hash: String = row.try_get("token_hash").map_err(db)?;
+            let created_at: String = row.try_get("created_at").map_err(db)?;
+            Ok(Session {
+                id: row.try_get("id").map_err(db)?,
+                last_seen_at: row
+                    .try_get::<Option<String>, _>("last_seen_at")
+                    .map_err(db)?
+                    .unwrap_or_else(|| created_at.clone()),
+                created_at,
+                expires_at: row.try_get("expires_at").map_err(db)?,
+                user_agent: row.try_get("user_agent").map_err(db)?,
+                ip: row.try_get("ip").map_err(db)?,
+                current: current.as_deref() == Some(hash.as_str()),
+            })
+        })
+        .collect()
+}
+
+/// Revoke one of a user's sessions by id. `NotFound` if the user has no session with that id — someone
+/// else's session included.
+pub async fn revoke_session(pool: &AnyPool, user_id: UserId, id: &str) -> Result<()> {
+    let affected = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id.to_string())
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    if affected == 0 {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+/// Log a user out everywhere: revoke every session they hold, returning how many. [`set_password`] does
+/// this too, so a changed password shuts out whoever held the old one.
+pub async fn revoke_all_sessions(pool: &AnyPool, user_id: UserId) -> Result<u64> {
+    let affected = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user_id.to_string())
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(affected)
+}
+
+/// Delete every expired session, returning how many. The [`session_reaper`] runs this on a schedule;
+/// an expired row is already dead to [`resolve_session`], so this is housekeeping, not security.
+pub async fn purge_expired_sessions(pool: &AnyPool) -> Result<u64> {
+    let affected = sqlx::query("DELETE FROM sessions WHERE expires_at <= $1")
+        .bind(crate::db::session_time(SystemTime::now()))
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(affected)
 }
 
 /// Revoke a session (logout). A no-op if the token is unknown. §17.
-pub async fn delete_session(pool: &SqlitePool, token: &str) -> Result<()> {
-    sqlx::query("DELETE FROM sessions WHERE token_hash = ?")
+pub async fn delete_session(pool: &AnyPool, token: &str) -> Result<()> {
+    sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
         .bind(sha256_hex(token.as_bytes()))
         .execute(pool)
         .await
         .map_err(db)?;
     Ok(())
 }
+
+/// The session reaper's `name()`.
+pub const REAPER: &str = "session-reaper";
+
+/// How often the reaper sweeps (and once at start).
+const REAP_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// The built-in `RuntimeComponent` that deletes expired sessions on a schedule (§7), and with them stale
+/// rows of the failed-login ledger (`crate::throttle`, #44) and expired pending logins (`crate::totp`,
+/// #45). Build with [`session_reaper`]. It touches only those core tables, never an envelope.
+pub struct SessionReaper;
+
+/// The session-reaper component.
+pub fn session_reaper() -> SessionReaper {
+    SessionReaper
+}
+
+#[async_trait]
+impl RuntimeComponent for SessionReaper {
+    fn name(&self) -> &str {
+        REAPER
+    }
+
+    fn interests(&self) -> Interests {
+        Interests {
+            schedule_secs: Some(REAP_EVERY.as_secs()),
+            types: Vec::new(),
+        }
+    }
+
+    fn writes(&self) -> WriteScope {
+        WriteScope::Derived
+    }
+
+    async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
+        // The first scheduler tick fires immediately, so a boot sweeps before the first interval.
+        while cx.next_event().await.is_some() {
+            let reaped = purge_expired_sessions(cx.type_owned_db()).await?;
+            if reaped > 0 {
+                tracing::info!(reaped, "session-reaper: deleted expired sessions");
+            }
+            let forgotten = crate::throttle::purge_stale(cx.type_owned_db()).await?;
+            if forgotten > 0 {
+                tracing::info!(forgotten, "session-reaper: forgot stale login failures");
+            }
+            let pending = crate::totp::purge_expired_pending(cx.type_owned_db()).await?;
+            if pending > 0 {
+                tracing::info!(pending, "session-reaper: deleted expired pending logins");
+            }
+        }
+        Ok(())
+    }
+}
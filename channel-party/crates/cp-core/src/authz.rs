@@ -1,10 +1,23 @@
 //! Authorization dispatch (DESIGN §18, `design/permissions.md`). One generic resolver, mirroring
 //! `contents::dispatch`: it resolves the channel's kind and asks its `Permission` capability. Core holds
 //! no policy — the answer is the kind's. Deny-by-default: a kind with no `Permission` authorizes no one.
+//!
+//! Reads are gated by [`ViewScope`]: an envelope is visible when every channel on its container chain
+//! grants `View` to the caller, who may be anonymous.
 
-use cp_model::{Action, Channel, Result, StoreCtx, UserId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
+use async_trait::async_trait;
+use cp_model::{
+    Action, Channel, ChannelId, Cursor, Filter, Item, ItemId, Node, NodePage, Order, Page, Rect,
+    Result, Role, StoreCtx, UserId,
+};
+
+use crate::events::{ChangeEvent, EnvelopeRef};
 use crate::registry::Registry;
+use crate::store::Store;
+use crate::tokens::Scopes;
 
 /// May `user` perform `action` on `channel`? `Ok(false)` when the channel's kind is unknown or declares
 /// no `Permission` capability (deny-by-default, §18); otherwise the kind's own decision.
@@ -23,3 +36,233 @@ pub async fn authorize(
         None => Ok(false),
     }
 }
+
+/// May a visitor with no session perform `action` on `channel`? The same dispatch as [`authorize`],
+/// through `Permission::authorize_anonymous`.
+pub async fn authorize_anonymous(
+    registry: &Registry,
+    store: &dyn StoreCtx,
+    channel: &Channel,
+    action: Action,
+) -> Result<bool> {
+    match registry
+        .channel(&channel.type_id)
+        .and_then(|kind| kind.permission())
+    {
+        Some(policy) => policy.authorize_anonymous(store, channel, action).await,
+        None => Ok(false),
+    }
+}
+
+/// The store as one reader sees it: `viewer` is the signed-in user, or `None` for an anonymous
+/// visitor. A channel is visible when it and each of its ancestors grant `View`; an item when its
+/// container is visible (an item with no container has no channel governing it, and is visible).
+/// Decisions are cached per channel for the scope's lifetime, so build one per request. A reader
+/// signed in with an API token is further narrowed by its [`Scopes`] ([`ViewScope::limited`]).
+///
+/// As a [`StoreCtx`] it is what `contents` runs against for that reader: point reads of a hidden
+/// envelope are `None`, and the listing primitives drop hidden nodes from each page. A page may then
+/// hold fewer than `limit` nodes; its cursor still continues where the unfiltered page stopped.
+pub struct ViewScope {
+    store: Arc<Store>,
+    viewer: Option<UserId>,
+    scopes: Scopes,
+    seen: Mutex<HashMap<ChannelId, bool>>,
+}
+
+impl ViewScope {
+    pub fn new(store: Arc<Store>, viewer: Option<UserId>) -> Self {
+        Self {
+            store,
+            viewer,
+            scopes: Scopes::Full,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Narrow the viewer to a token's scopes: a channel outside its `read` scopes (and not on the way
+    /// down to one) is judged as an anonymous visitor would see it.
+    pub fn limited(mut self, scopes: Scopes) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Whether the viewer may see `channel`. The walk goes up to the root (or the first ancestor
+    /// already decided), then decides top-down so a hidden ancestor short-circuits its subtree.
+    pub async fn channel(&self, channel: &Channel) -> Result<bool> {
+        if let Some(known) = self.known(channel.id) {
+            return Ok(known);
+        }
+        let mut chain = vec![channel.clone()];
+        let mut visible = true;
+        while let Some(parent) = chain.last().and_then(|c| c.container) {
+            if let Some(known) = self.known(parent) {
+                visible = known;
+                break;
+            }
+            match StoreCtx::get_channel(&*self.store, parent).await? {
+                Some(p) => chain.push(p),
+                None => {
+                    visible = false; // an ancestor in the trash hides what is under it
+                    break;
+                }
+            }
+        }
+        for ch in chain.iter().rev() {
+            visible = visible && self.grants_view(ch).await?;
+            self.seen.lock().expect("view cache").insert(ch.id, visible);
+        }
+        Ok(visible)
+    }
+
+    /// Whether the viewer may see what sits in `container` (`None`: at the top level, ungoverned).
+    pub async fn container(&self, container: Option<ChannelId>) -> Result<bool> {
+        let Some(id) = container else {
+            return Ok(true);
+        };
+        if let Some(known) = self.known(id) {
+            return Ok(known);
+        }
+        match StoreCtx::get_channel(&*self.store, id).await? {
+            Some(ch) => self.channel(&ch).await,
+            None => Ok(false),
+        }
+    }
+
+    pub async fn item(&self, item: &Item) -> Result<bool> {
+        self.container(item.container).await
+    }
+
+    pub async fn node(&self, node: &Node) -> Result<bool> {
+        match node {
+            Node::Channel(ch) => self.channel(ch).await,
+            Node::Item(item) => self.item(item).await,
+        }
+    }
+
+    /// Whether a change event concerns something the viewer may see. A live channel is judged as
+    /// itself; anything else — an item, or a channel already deleted — by the container it was in.
+    pub async fn event(&self, event: &ChangeEvent) -> Result<bool> {
+        if let EnvelopeRef::Channel(id) = event.target {
+            if let Some(ch) = StoreCtx::get_channel(&*self.store, id).await? {
+                return self.channel(&ch).await;
+            }
+        }
+        self.container(event.container).await
+    }
+
+    /// `nodes` without the ones the viewer may not see, order kept.
+    pub async fn filter(&self, nodes: Vec<Node>) -> Result<Vec<Node>> {
+        let mut visible = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            if self.node(&node).await? {
+                visible.push(node);
+            }
+        }
+        Ok(visible)
+    }
+
+    async fn filter_page(&self, page: NodePage) -> Result<NodePage> {
+        Ok(NodePage {
+            nodes: self.filter(page.nodes).await?,
+            next: page.next,
+        })
+    }
+
+    fn known(&self, id: ChannelId) -> Option<bool> {
+        self.seen.lock().expect("view cache").get(&id).copied()
+    }
+
+    /// The channel's own `View` decision, ignoring its ancestors.
+    async fn grants_view(&self, channel: &Channel) -> Result<bool> {
+        let registry = self.store.registry();
+        let viewer = match self.viewer {
+            Some(user) if !self.scopes.is_full() => self
+                .scopes
+                .reaches(self.store.pool(), Action::View, channel.id)
+                .await?
+                .then_some(user),
+            viewer => viewer,
+        };
+        match viewer {
+            Some(user) => authorize(registry, &*self.store, channel, user, Action::View).await,
+            None => authorize_anonymous(registry, &*self.store, channel, Action::View).await,
+        }
+    }
+}
+
+#[async_trait]
+impl StoreCtx for ViewScope {
+    async fn children(
+        &self,
+        container: ChannelId,
+        filter: Filter,
+        page: Page,
+        order: Order,
+    ) -> Result<NodePage> {
+        let page = self.store.children(container, filter, page, order).await?;
+        self.filter_page(page).await
+    }
+
+    async fn descendants(
+        &self,
+        root: ChannelId,
+        filter: Filter,
+        depth: Option<u32>,
+    ) -> Result<Vec<Node>> {
+        let nodes = self.store.descendants(root, filter, depth).await?;
+        self.filter(nodes).await
+    }
+
+    async fn seek_time(&self, container: ChannelId, timestamp_ms: u64) -> Result<Cursor> {
+        self.store.seek_time(container, timestamp_ms).await
+    }
+
+    async fn search(
+        &self,
+        scope: ChannelId,
+        text: &str,
+        filter: Filter,
+        page: Page,
+    ) -> Result<NodePage> {
+        let page = self.store.search(scope, text, filter, page).await?;
+        self.filter_page(page).await
+    }
+
+    async fn within_bbox(
+        &self,
+        scope: ChannelId,
+        rect: Rect,
+        filter: Filter,
+        page: Page,
+    ) -> Result<NodePage> {
+        let page = self.store.within_bbox(scope, rect, filter, page).await?;
+        self.filter_page(page).await
+    }
+
+    async fn get_channel(&self, id: ChannelId) -> Result<Option<Channel>> {
+        match StoreCtx::get_channel(&*self.store, id).await? {
+            Some(ch) if self.channel(&ch).await? => Ok(Some(ch)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn get_item(&self, id: ItemId) -> Result<Option<Item>> {
+        match StoreCtx::get_item(&*self.store, id).await? {
+            Some(item) if self.item(&item).await? => Ok(Some(item)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn is_member(&self, channel: ChannelId, user: UserId) -> Result<bool> {
+        self.store.is_member(channel, user).await
+    }
+
+    async fn roles(&self, channel: ChannelId, user: UserId) -> Result<Vec<Role>> {
+        self.store.roles(channel, user).await
+    }
+
+    fn type_owned_db(&self) -> &sqlx::AnyPool {
+        self.store.type_owned_db()
+    }
+}
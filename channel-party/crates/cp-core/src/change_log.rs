@@ -0,0 +1,355 @@
+//! The durable change log: an outbox the write path appends every `ChangeEvent` to inside the
+//! mutation's own transaction, giving each a `seq`. The in-memory `EventBus` stays the fast path; the
+//! log is what lets a consumer resume after a restart or a lag without a full rescan. [`follow`] merges
+//! the two — replay from the log after an offset, then the bus, falling back to the log whenever the
+//! bus skips ahead. SSE resumes from `Last-Event-ID`, runtime components from a persisted per-component
+//! offset. [`compact`]/[`expire`] and the [`retention`] component keep the table bounded. The bus is
+//! per-process, so a server also runs the [`relay`]: it tails the log and republishes changes other
+//! processes on the same database committed (e.g. `channel-party shell`). §7/§9.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_model::{
+    ChangeEvent, ChangeOp, EnvelopeRef, Error, Interests, Result, RuntimeComponent, RuntimeCtx,
+    TypeId, WriteScope,
+};
+use sqlx::any::AnyRow;
+use sqlx::{AnyConnection, AnyPool, Row};
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
+
+use crate::events::EventBus;
+
+/// The retention component's `name()`.
+pub const NAME: &str = "change-log-retention";
+
+/// How often the retention sweep runs (and once at start), capped by the retention age.
+const SWEEP_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// Rows read per log query while a [`Follow`] catches up.
+const PAGE: u32 = 256;
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// Append `event` to the log in the caller's transaction and stamp it with its `seq`. §7.
+pub(crate) async fn append(tx: &mut AnyConnection, event: &mut ChangeEvent) -> Result<()> {
+    let (super_type, id) = match event.target {
+        EnvelopeRef::Channel(id) => ("channel", id.to_string()),
+        EnvelopeRef::Item(id) => ("item", id.to_string()),
+    };
+    let op = match event.op {
+        ChangeOp::Created => "created",
+        ChangeOp::Updated => "updated",
+        ChangeOp::Deleted => "deleted",
+    };
+    event.seq = sqlx::query_scalar(
+        "INSERT INTO change_log (op, super_type, envelope_id, type_id, container) \
+         VALUES ($1, $2, $3, $4, $5) RETURNING seq",
+    )
+    .bind(op)
+    .bind(super_type)
+    .bind(id)
+    .bind(event.type_id.as_str())
+    .bind(event.container.map(|c| c.to_string()))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db)?;
+    Ok(())
+}
+
+fn row_to_event(row: &AnyRow) -> Result<ChangeEvent> {
+    let id: String = row.try_get("envelope_id").map_err(db)?;
+    let target = match row.try_get::<String, _>("super_type").map_err(db)?.as_str() {
+        "channel" => EnvelopeRef::Channel(
+            id.parse()
+                .map_err(|_| Error::Other(format!("invalid channel id in change_log: {id}")))?,
+        ),
+        _ => EnvelopeRef::Item(
+            id.parse()
+                .map_err(|_| Error::Other(format!("invalid item id in change_log: {id}")))?,
+        ),
+    };
+    let op = match row.try_get::<String, _>("op").map_err(db)?.as_str() {
+        "created" => ChangeOp::Created,
+        "updated" => ChangeOp::Updated,
+        _ => ChangeOp::Deleted,
+    };
+    let container: Option<String> = row.try_get("container").map_err(db)?;
+    let container = container
+        .map(|c| {
+            c.parse()
+                .map_err(|_| Error::Other(format!("invalid container in change_log: {c}")))
+        })
+        .transpose()?;
+    Ok(ChangeEvent {
+        seq: row.try_get("seq").map_err(db)?,
+        op,
+        target,
+        type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
+        container,
+    })
+}
+
+/// Up to `limit` logged events with `seq > after`, in order. §7.
+pub async fn read(pool: &AnyPool, after: i64, limit: u32) -> Result<Vec<ChangeEvent>> {
+    let rows = sqlx::query(
+        "SELECT seq, op, super_type, envelope_id, type_id, container FROM change_log \
+         WHERE seq > $1 ORDER BY seq ASC LIMIT $2",
+    )
+    .bind(after)
+    .bind(i64::from(limit))
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter().map(row_to_event).collect()
+}
+
+/// The highest `seq` ever assigned (0 for an empty log). Survives retention emptying the table: compaction
+/// keeps each envelope's latest row, and expiry raises the horizon to the last row it drops.
+pub async fn head(pool: &AnyPool) -> Result<i64> {
+    let seq: Option<i64> = sqlx::query_scalar(
+        "SELECT MAX(h) FROM (SELECT MAX(seq) AS h FROM change_log \
+         UNION ALL SELECT horizon FROM change_log_horizon WHERE id = 1) m",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(db)?;
+    Ok(seq.unwrap_or(0))
+}
+
+/// The highest `seq` retention has dropped. A consumer resuming from below it may have missed changes.
+pub async fn horizon(pool: &AnyPool) -> Result<i64> {
+    sqlx::query_scalar("SELECT horizon FROM change_log_horizon WHERE id = 1")
+        .fetch_one(pool)
+        .await
+        .map_err(db)
+}
+
+/// Drop every row superseded by a later row for the same envelope; returns how many. Loses no state —
+/// a resumer still sees each envelope's latest change — so the horizon is left alone.
+pub async fn compact(pool: &AnyPool) -> Result<u64> {
+    let done = sqlx::query(
+        "DELETE FROM change_log WHERE seq < (SELECT MAX(l.seq) FROM change_log l \
+         WHERE l.super_type = change_log.super_type AND l.envelope_id = change_log.envelope_id)",
+    )
+    .execute(pool)
+    .await
+    .map_err(db)?;
+    Ok(done.rows_affected())
+}
+
+/// Drop every row logged more than `max_age` ago and raise the horizon past them; returns how many.
+pub async fn expire(pool: &AnyPool, max_age: Duration) -> Result<u64> {
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let last: Option<i64> = sqlx::query_scalar("SELECT MAX(seq) FROM change_log WHERE at <= $1")
+        .bind(crate::db::ago(max_age))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db)?;
+    let Some(last) = last else {
+        return Ok(0);
+    };
+    let done = sqlx::query("DELETE FROM change_log WHERE seq <= $1")
+        .bind(last)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    sqlx::query(
+        "UPDATE change_log_horizon SET horizon = CASE WHEN horizon < $1 THEN $1 ELSE horizon END \
+         WHERE id = 1",
+    )
+    .bind(last)
+    .execute(&mut *tx)
+    .await
+    .map_err(db)?;
+    tx.commit().await.map_err(db)?;
+    Ok(done.rows_affected())
+}
+
+/// A resumable view of the change stream: every committed event after an offset, exactly once and in
+/// `seq` order, drawn from the log until caught up and from the bus after. Build with [`follow`].
+pub struct Follow {
+    pool: AnyPool,
+    rx: broadcast::Receiver<ChangeEvent>,
+    offset: i64,
+    backlog: VecDeque<ChangeEvent>,
+    live: bool,
+    missed: bool,
+}
+
+/// Follow the change stream after `after` (`None` = from now on). Subscribes to the bus before reading
+/// the log, so nothing committed from here on can fall between the two. §7/§9.
+pub async fn follow(pool: &AnyPool, bus: &EventBus, after: Option<i64>) -> Result<Follow> {
+    let rx = bus.subscribe();
+    let head = head(pool).await?;
+    let (offset, live, missed) = match after {
+        None => (head, true, false),
+        // An offset from the future means the log was replaced under the consumer: start over.
+        Some(after) if after > head => (head, true, true),
+        Some(after) => (after, false, after < horizon(pool).await?),
+    };
+    Ok(Follow {
+        pool: pool.clone(),
+        rx,
+        offset,
+        backlog: VecDeque::new(),
+        live,
+        missed,
+    })
+}
+
+impl Follow {
+    /// The `seq` of the last event handed out (or the starting offset).
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Whether the requested offset was behind the retention horizon (or ahead of the log), so events
+    /// may be missing between it and what follows: the consumer should resync from current state.
+    pub fn missed(&self) -> bool {
+        self.missed
+    }
+
+    /// The next event, or `None` once the bus is closed. Cancel-safe: state only changes after each
+    /// await completes, so an abandoned call loses nothing.
+    pub async fn next(&mut self) -> Result<Option<ChangeEvent>> {
+        loop {
+            if let Some(event) = self.backlog.pop_front() {
+                self.offset = event.seq;
+                return Ok(Some(event));
+            }
+            if !self.live {
+                let page = read(&self.pool, self.offset, PAGE).await?;
+                if page.is_empty() {
+                    self.live = true;
+                } else {
+                    self.backlog.extend(page);
+                }
+                continue;
+            }
+            match self.rx.recv().await {
+                Ok(event) if event.seq <= self.offset => {} // already handed out from the log
+                Ok(event) if event.seq == self.offset + 1 => {
+                    self.offset = event.seq;
+                    return Ok(Some(event));
+                }
+                // The bus skipped ahead (a concurrent commit published out of order) or overflowed:
+                // the log has everything in between.
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => self.live = false,
+                Err(broadcast::error::RecvError::Closed) => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Keeps the relay task alive; dropping it stops the relay.
+pub struct RelayHandle {
+    task: AbortHandle,
+    bus: EventBus,
+}
+
+impl Drop for RelayHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.bus.set_relaying(false);
+    }
+}
+
+/// Tail the log every `every` and publish, on this process's bus, each change another process
+/// committed. This process's own commits are claimed by the write path and skipped, so a local
+/// subscriber still sees each of them once. §7.
+pub fn relay(pool: AnyPool, bus: EventBus, every: Duration) -> RelayHandle {
+    // Claiming starts before the starting point is read, so no local commit after it goes unclaimed.
+    bus.set_relaying(true);
+    let task = tokio::spawn({
+        let bus = bus.clone();
+        async move {
+            let mut upto = None;
+            let mut tick = tokio::time::interval(every);
+            loop {
+                tick.tick().await;
+                match pass(&pool, &bus, upto).await {
+                    Ok(seq) => upto = Some(seq),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "change-log relay: tailing the log failed")
+                    }
+                }
+            }
+        }
+    });
+    RelayHandle {
+        task: task.abort_handle(),
+        bus,
+    }
+}
+
+/// One relay pass: publish every foreign change after `upto` (the head, on the first pass), returning
+/// the new high-water mark.
+async fn pass(pool: &AnyPool, bus: &EventBus, upto: Option<i64>) -> Result<i64> {
+    let head = head(pool).await?;
+    let Some(mut upto) = upto else {
+        return Ok(head);
+    };
+    while upto < head {
+        let page = read(pool, upto, PAGE).await?;
+        let Some(last) = page.last().map(|e| e.seq) else {
+            break; // the rest was compacted or expired away
+        };
+        for event in page {
+            if !bus.take_claim(event.seq) {
+                bus.publish(event);
+            }
+        }
+        upto = last;
+    }
+    Ok(head)
+}
+
+/// Compacts the log and expires rows older than `max_age`. Build with [`retention`].
+pub struct LogRetention {
+    max_age: Duration,
+}
+
+/// The change-log retention component for a given maximum row age.
+pub fn retention(max_age: Duration) -> LogRetention {
+    LogRetention { max_age }
+}
+
+#[async_trait]
+impl RuntimeComponent for LogRetention {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn interests(&self) -> Interests {
+        Interests {
+            schedule_secs: Some(SWEEP_EVERY.min(self.max_age).as_secs().max(1)),
+            types: Vec::new(),
+        }
+    }
+
+    fn writes(&self) -> WriteScope {
+        // Touches only core's log table, never an envelope.
+        WriteScope::Derived
+    }
+
+    async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
+        // The first scheduler tick fires immediately, so a boot sweeps before the first interval.
+        while cx.next_event().await.is_some() {
+            let compacted = compact(cx.type_owned_db()).await?;
+            let expired = expire(cx.type_owned_db(), self.max_age).await?;
+            if compacted + expired > 0 {
+                tracing::info!(
+                    compacted,
+                    expired,
+                    "change-log-retention: swept the change log"
+                );
+            }
+        }
+        Ok(())
+    }
+}
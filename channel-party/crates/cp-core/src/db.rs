@@ -0,0 +1,113 @@
+//! Opening the database and the few things the two backends do differently at runtime. Core runs one
+//! code path through sqlx's `Any` driver (see `cp_model::db`); what cannot be written once lives here or
+//! branches on [`Backend`] where it is used (the FTS and spatial reads in `store`, kind tables in
+//! `archive`). Timestamps are computed here rather than by SQL date functions, which differ per backend.
+//! §3/§10.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cp_model::Backend;
+use sqlx::any::{AnyConnectOptions, AnyPoolOptions};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Any, AnyPool, ConnectOptions, Transaction};
+
+/// The Postgres advisory-lock key write transactions serialize on (see [`begin_write`]).
+const WRITE_LOCK: i64 = 0x0063_7077_7269_7465; // "cpwrite"
+
+/// Numbers the `sqlite::memory:` databases this process opens, so each `connect` gets its own.
+static MEMORY_DB: AtomicU32 = AtomicU32::new(0);
+
+/// Connect to `url` — `sqlite:<path>` (created if missing), `sqlite::memory:`, or `postgres://…`.
+pub async fn connect(url: &str) -> anyhow::Result<AnyPool> {
+    sqlx::any::install_default_drivers();
+    let backend = Backend::from_url(url).ok_or_else(|| {
+        anyhow::anyhow!("unsupported database URL `{url}`: use sqlite: or postgres://")
+    })?;
+    let url = match backend {
+        // `Any` re-parses the URL for every connection, so sqlite's options go into it: a `:memory:`
+        // database gets one shared-cache name, so every connection in the pool sees the same database,
+        // and a file is created if missing. Foreign keys are on by default — the `container` FK and its
+        // `ON DELETE CASCADE` (and the channel_members FKs) need it. §3.
+        Backend::Sqlite
+            if url.trim_start_matches("sqlite:").trim_start_matches("//") == ":memory:" =>
+        {
+            let n = MEMORY_DB.fetch_add(1, Ordering::Relaxed);
+            format!("sqlite:file:cp-memory-{n}?mode=memory&cache=shared")
+        }
+        Backend::Sqlite => SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .to_url_lossy()
+            .to_string(),
+        Backend::Postgres => url.to_owned(),
+    };
+    let opts = AnyConnectOptions::from_str(&url)?;
+    Ok(AnyPoolOptions::new().connect_with(opts).await?)
+}
+
+/// Open a write transaction holding the database's single write lock until it ends. On sqlite that is
+/// `BEGIN IMMEDIATE`: most steps read (a tombstone check, an upsert lookup) before they write, and a
+/// deferred transaction upgrading from a read lock can deadlock against a concurrent writer, which
+/// sqlite reports as `database is locked` at once. Postgres takes a transaction-scoped advisory lock
+/// instead, so writers commit one at a time there too: a revision check cannot race the update after
+/// it, and change-log `seq`s become visible in commit order, which `change_log::follow` relies on.
+pub async fn begin_write(pool: &AnyPool) -> Result<Transaction<'static, Any>, sqlx::Error> {
+    match Backend::of(pool) {
+        Backend::Sqlite => pool.begin_with("BEGIN IMMEDIATE").await,
+        Backend::Postgres => {
+            let mut tx = pool.begin().await?;
+            sqlx::query("SELECT 1 FROM pg_advisory_xact_lock($1)")
+                .bind(WRITE_LOCK)
+                .execute(&mut *tx)
+                .await?;
+            Ok(tx)
+        }
+    }
+}
+
+/// `at` as the UTC timestamp text every table stores (`2024-05-01T12:00:00.000Z`), which sorts
+/// chronologically as a string.
+pub fn timestamp(at: SystemTime) -> String {
+    let (date, (h, m, s), ms) = civil(at);
+    format!("{date}T{h:02}:{m:02}:{s:02}.{ms:03}Z")
+}
+
+/// [`timestamp`] of now.
+pub fn now() -> String {
+    timestamp(SystemTime::now())
+}
+
+/// [`timestamp`] of `age` ago — the cutoff an age-based sweep compares against.
+pub fn ago(age: Duration) -> String {
+    timestamp(SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH))
+}
+
+/// `at` as `2024-05-01 12:00:00`, the coarser form `sessions` has stored since it was created.
+pub fn session_time(at: SystemTime) -> String {
+    let (date, (h, m, s), _) = civil(at);
+    format!("{date} {h:02}:{m:02}:{s:02}")
+}
+
+/// Split a UTC instant into `YYYY-MM-DD`, `(h, m, s)` and milliseconds (days-to-civil, proleptic
+/// Gregorian).
+fn civil(at: SystemTime) -> (String, (u64, u64, u64), u32) {
+    let since = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        (rem / 3600, rem % 3600 / 60, rem % 60),
+        since.subsec_millis(),
+    )
+}
@@ -2,18 +2,26 @@
 //! explicitly-gated write surface that is off by default, per-session, and never persisted. Reads are
 //! direct DB queries (an honest view of what is actually stored); writes route through core's mutation
 //! API + the kind's `validate`, never raw SQL — the one exception is `create-user`, a bootstrap for the
-//! fixed `users` substrate until auth (`TODO.md` #17). See DESIGN §8.
+//! fixed `users` substrate until auth (`TODO.md` #17). Kinds contribute further commands, invoked as
+//! `<namespace> <name> <args…>` and gated by their declared `DebugAccess`. Besides the interactive REPL
+//! the shell runs non-interactively (`--script` / `--exec`), stopping at the first failing command;
+//! every command has a `--json` form, and `$last` names the id the session most recently created. See
+//! DESIGN §8.
 
+use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use cp_model::{
-    Channel, ChannelId, DebugAccess, Item, ItemId, Json, Membership, NewChannel, NewItem, TypeId,
-    UserId, WriteCtx,
+    Actor, Channel, ChannelId, DebugAccess, EnvelopeRef, Item, ItemId, Json, Membership,
+    NewChannel, NewItem, Patch, Role, TypeId, UserId, WriteCtx,
 };
-use sqlx::sqlite::SqliteRow;
+use serde_json::json;
+use sqlx::any::AnyRow;
 use sqlx::Row;
 
 use crate::registry::Registry;
+use crate::runtime::Control;
 use crate::store::Store;
 use crate::Core;
 
@@ -24,19 +32,80 @@ pub enum Mode {
     Write,
 }
 
+/// One command's result: the text the REPL prints and the structured form `--json` prints. §8.
+#[derive(Clone, Debug)]
+pub struct Reply {
+    pub text: String,
+    pub json: Json,
+    /// The id the command created, which `$last` names from then on.
+    created: Option<String>,
+}
+
+impl Reply {
+    fn new(text: impl Into<String>, json: Json) -> Self {
+        Self {
+            text: text.into(),
+            json,
+            created: None,
+        }
+    }
+
+    fn created(mut self, id: impl ToString) -> Self {
+        self.created = Some(id.to_string());
+        self
+    }
+
+    /// What to print: the JSON on one line, or the human text (empty for a blank/comment line).
+    pub fn render(&self, json: bool) -> String {
+        if json {
+            self.json.to_string()
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// The first failing line of a script, which stops it (the process then exits non-zero). §8.
+#[derive(Debug)]
+pub struct ScriptError {
+    /// 1-based, counting blank and comment lines (for `--exec`, the command's position).
+    pub line: usize,
+    pub command: String,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: `{}`: {}",
+            self.line, self.command, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
 /// A read-only-by-default REPL over the store with a gated write surface. §8.
 pub struct DebugShell {
     registry: Registry,
     store: Arc<Store>,
     mode: Mode,
+    /// Set for scripts: the `--write` flag alone decides the mode, so a script cannot escalate itself.
+    mode_locked: bool,
+    /// The id most recently created this session, substituted for `$last`.
+    last: Option<String>,
 }
 
 impl DebugShell {
     pub fn new(registry: Registry, store: Arc<Store>) -> Self {
         Self {
             registry,
-            store,
+            // Shell writes are attributed to the operator in the revision history.
+            store: Arc::new(store.acting_as(Actor::Operator)),
             mode: Mode::ReadOnly,
+            mode_locked: false,
+            last: None,
         }
     }
 
@@ -56,6 +125,17 @@ impl DebugShell {
         self.mode = Mode::ReadOnly;
     }
 
+    /// Refuse `enable-write-mode` / `disable-write-mode` from here on: the mode is whatever the caller
+    /// set. Scripts run locked, so writing takes the explicit `--write` flag. §8.
+    pub fn lock_mode(&mut self) {
+        self.mode_locked = true;
+    }
+
+    /// The id most recently created this session (`create-channel`, `create-item`, `create-user`).
+    pub fn last(&self) -> Option<&str> {
+        self.last.as_deref()
+    }
+
     /// Whether a command of the given access may run under the current mode. Every mutating command
     /// refuses until write mode is on. §8.
     pub fn permits(&self, access: DebugAccess) -> bool {
@@ -72,31 +152,80 @@ impl DebugShell {
     /// Parse and run one input line, returning the text to print. Never panics on bad input; errors
     /// (including the write-mode gate) come back as a message.
     pub async fn eval(&mut self, line: &str) -> String {
-        match self.run(line).await {
-            Ok(out) => out,
+        match self.exec(line).await {
+            Ok(reply) => reply.text,
             Err(msg) => msg,
         }
     }
 
-    async fn run(&mut self, line: &str) -> Result<String, String> {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            return Ok(String::new());
+    /// Run one input line with `$last` substituted, keeping the result structured. A blank or comment
+    /// line is an empty reply.
+    pub async fn exec(&mut self, line: &str) -> Result<Reply, String> {
+        let line = if line.contains("$last") {
+            let last = self
+                .last
+                .as_deref()
+                .ok_or("`$last` is unset: nothing has been created this session")?;
+            line.replace("$last", last)
+        } else {
+            line.to_owned()
+        };
+        let reply = self.run(&line).await?;
+        if let Some(id) = &reply.created {
+            self.last = Some(id.clone());
+        }
+        Ok(reply)
+    }
+
+    /// Run lines in order, handing each reply to `emit`, and stop at the first that fails. Blank and
+    /// comment lines are skipped. Returns how many commands ran. §8.
+    pub async fn run_script<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = &'a str>,
+        mut emit: impl FnMut(&Reply),
+    ) -> Result<usize, ScriptError> {
+        let mut ran = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            if is_blank(line) {
+                continue;
+            }
+            let reply = self.exec(line).await.map_err(|message| ScriptError {
+                line: i + 1,
+                command: line.trim().to_owned(),
+                message,
+            })?;
+            emit(&reply);
+            ran += 1;
+        }
+        Ok(ran)
+    }
+
+    async fn run(&mut self, line: &str) -> Result<Reply, String> {
+        if is_blank(line) {
+            return Ok(Reply::new("", Json::Null));
         }
-        let (cmd, rest) = split_first(line);
+        let (cmd, rest) = split_first(line.trim());
         match cmd {
-            "help" => Ok(help_text()),
+            "help" => {
+                let text = help_text(&self.registry);
+                Ok(Reply::new(text.clone(), json!({ "help": text })))
+            }
             "enable-write-mode" => {
-                self.mode = Mode::Write;
-                Ok("write mode ON — mutations enabled for this session".to_owned())
+                self.set_mode(Mode::Write)?;
+                Ok(Reply::new(
+                    "write mode ON — mutations enabled for this session",
+                    json!({ "mode": "write" }),
+                ))
             }
             "disable-write-mode" => {
-                self.mode = Mode::ReadOnly;
-                Ok("write mode OFF".to_owned())
+                self.set_mode(Mode::ReadOnly)?;
+                Ok(Reply::new("write mode OFF", json!({ "mode": "read-only" })))
             }
             "show" => self.cmd_show(rest).await,
             "inspect" => self.cmd_inspect(rest.trim()).await,
             "members" => self.cmd_members(rest.trim()).await,
+            "roles" => self.cmd_roles(rest.trim()).await,
+            "history" => self.cmd_history(rest.trim()).await,
             "create-user" => {
                 self.require_write()?;
                 self.cmd_create_user(rest.trim()).await
@@ -117,10 +246,26 @@ impl DebugShell {
                 self.require_write()?;
                 self.cmd_set_payload(rest).await
             }
+            "patch" => {
+                self.require_write()?;
+                self.cmd_patch(rest).await
+            }
             "delete" => {
                 self.require_write()?;
                 self.cmd_delete(rest.trim()).await
             }
+            "revert" => {
+                self.require_write()?;
+                self.cmd_revert(rest).await
+            }
+            "restore" => {
+                self.require_write()?;
+                self.cmd_restore(rest.trim()).await
+            }
+            "purge" => {
+                self.require_write()?;
+                self.cmd_purge(rest.trim()).await
+            }
             "reparent" => {
                 self.require_write()?;
                 self.cmd_reparent(rest).await
@@ -133,6 +278,54 @@ impl DebugShell {
                 self.require_write()?;
                 self.cmd_add_user(rest, false).await
             }
+            "grant-role" => {
+                self.require_write()?;
+                self.cmd_role(rest, true).await
+            }
+            "revoke-role" => {
+                self.require_write()?;
+                self.cmd_role(rest, false).await
+            }
+            "restart" => {
+                self.require_write()?;
+                self.cmd_control(rest.trim(), Control::Restart).await
+            }
+            "reset" => {
+                self.require_write()?;
+                self.cmd_control(rest.trim(), Control::Reset).await
+            }
+            "set-operator" => {
+                self.require_write()?;
+                self.cmd_set_operator(rest).await
+            }
+            "create-invite" => {
+                self.require_write()?;
+                self.cmd_create_invite(rest).await
+            }
+            "revoke-invite" => {
+                self.require_write()?;
+                self.cmd_revoke_invite(rest.trim()).await
+            }
+            "create-token" => {
+                self.require_write()?;
+                self.cmd_create_token(rest).await
+            }
+            "revoke-token" => {
+                self.require_write()?;
+                self.cmd_revoke_token(rest.trim()).await
+            }
+            "revoke-sessions" => {
+                self.require_write()?;
+                self.cmd_revoke_sessions(rest.trim()).await
+            }
+            "reset-totp" => {
+                self.require_write()?;
+                self.cmd_reset_totp(rest.trim()).await
+            }
+            "clear-lockout" => {
+                self.require_write()?;
+                self.cmd_clear_lockout(rest.trim()).await
+            }
             "link-user" => {
                 self.require_write()?;
                 self.cmd_link(rest, true).await
@@ -141,8 +334,58 @@ impl DebugShell {
                 self.require_write()?;
                 self.cmd_link(rest, false).await
             }
-            other => Err(format!("unknown command `{other}` — try `help`")),
+            other => self.cmd_kind(other, rest).await,
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) -> Result<(), String> {
+        if self.mode_locked {
+            return Err(
+                "the mode is fixed for a script; pass --write to channel-party shell instead"
+                    .to_owned(),
+            );
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// A kind-contributed command, `<namespace> <name> <args…>` (§8): gated by its declared access, then
+    /// run through the kind's hook with the shell's operator-attributed write surface.
+    async fn cmd_kind(&self, namespace: &str, rest: &str) -> Result<Reply, String> {
+        let commands: Vec<_> = self
+            .registry
+            .debug_commands()
+            .into_iter()
+            .filter(|c| c.namespace == namespace)
+            .collect();
+        if commands.is_empty() {
+            return Err(format!("unknown command `{namespace}` — try `help`"));
+        }
+        let (name, args) = split_first(rest.trim());
+        if name.is_empty() {
+            let names: Vec<_> = commands.iter().map(|c| c.command.name.as_str()).collect();
+            return Err(format!("usage: {namespace} <{}> …", names.join(" | ")));
+        }
+        let mut matching = commands.iter().filter(|c| c.command.name == name);
+        let Some(command) = matching.next() else {
+            return Err(format!("unknown command `{namespace} {name}` — try `help`"));
+        };
+        if let Some(other) = matching.next() {
+            return Err(format!(
+                "`{namespace} {name}` is contributed by both {} and {}",
+                command.type_id(),
+                other.type_id()
+            ));
+        }
+        if command.command.access == DebugAccess::Write {
+            self.require_write()?;
         }
+        let args: Vec<String> = args.split_whitespace().map(str::to_owned).collect();
+        let output = command
+            .run(self.store.as_ref(), &args)
+            .await
+            .map_err(core_err)?;
+        Ok(Reply::new(output.clone(), json!({ "output": output })))
     }
 
     fn require_write(&self) -> Result<(), String> {
@@ -155,29 +398,39 @@ impl DebugShell {
 
     // --- reads (direct DB queries, §8) ------------------------------------------------------------
 
-    async fn cmd_show(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_show(&self, rest: &str) -> Result<Reply, String> {
         let (sub, arg) = split_first(rest.trim());
         match sub {
             "channels" => self.show_channels().await,
             "items" => self.show_items(arg.trim()).await,
             "users" => self.show_users().await,
             "links" => self.show_links(arg.trim()).await,
+            "migrations" => self.show_migrations().await,
+            "trash" => self.show_trash().await,
+            "runtimes" => self.show_runtimes().await,
+            "invites" => self.show_invites().await,
+            "tokens" => self.show_tokens(arg.trim()).await,
+            "sessions" => self.show_sessions(arg.trim()).await,
+            "lockouts" => self.show_lockouts().await,
             "" => Err(
-                "usage: show <channels | items <channel-id> | users | links <handle>>".to_owned(),
+                "usage: show <channels | items <channel-id> | users | links <handle> | migrations \
+                 | trash | runtimes | invites | tokens <handle> | sessions <handle> | lockouts>"
+                    .to_owned(),
             ),
             _ => Err(format!("unknown `show {sub}` — try `help`")),
         }
     }
 
-    async fn show_channels(&self) -> Result<String, String> {
-        let rows = sqlx::query("SELECT id, type_id, container, payload FROM channels ORDER BY id")
-            .fetch_all(self.store.pool())
-            .await
-            .map_err(sql_err)?;
-        if rows.is_empty() {
-            return Ok("(no channels)".to_owned());
-        }
+    async fn show_channels(&self) -> Result<Reply, String> {
+        let rows = sqlx::query(
+            "SELECT id, type_id, container, payload, rev FROM channels WHERE trash_root IS NULL \
+             ORDER BY id",
+        )
+        .fetch_all(self.store.pool())
+        .await
+        .map_err(sql_err)?;
         let mut out = Vec::new();
+        let mut values = Vec::new();
         for row in &rows {
             let ch = channel_from_row(row)?;
             let container = ch
@@ -188,28 +441,32 @@ impl DebugShell {
                 .channel(&ch.type_id)
                 .and_then(|k| k.debug_summary(&ch));
             let mut line = format!("{}  type={}  container={container}", ch.id, ch.type_id);
-            if let Some(s) = summary {
+            if let Some(s) = &summary {
                 line.push_str(&format!("  — {s}"));
             }
             out.push(line);
+            values.push(json!({
+                "id": ch.id,
+                "type_id": ch.type_id,
+                "container": ch.container,
+                "summary": summary,
+            }));
         }
-        Ok(out.join("\n"))
+        Ok(listing(out, values, "(no channels)"))
     }
 
-    async fn show_items(&self, cid: &str) -> Result<String, String> {
+    async fn show_items(&self, cid: &str) -> Result<Reply, String> {
         let container = parse_channel_id(cid)?;
         let rows = sqlx::query(
-            "SELECT id, type_id, container, external_key, payload FROM items \
-             WHERE container = ? ORDER BY id",
+            "SELECT id, type_id, container, external_key, payload, rev FROM items \
+             WHERE container = $1 AND trash_root IS NULL ORDER BY id",
         )
         .bind(container.to_string())
         .fetch_all(self.store.pool())
         .await
         .map_err(sql_err)?;
-        if rows.is_empty() {
-            return Ok("(no items)".to_owned());
-        }
         let mut out = Vec::new();
+        let mut values = Vec::new();
         for row in &rows {
             let item = item_from_row(row)?;
             let summary = self
@@ -217,29 +474,40 @@ impl DebugShell {
                 .item(&item.type_id)
                 .and_then(|k| k.debug_summary(&item));
             let mut line = format!("{}  type={}", item.id, item.type_id);
-            if let Some(s) = summary {
+            if let Some(s) = &summary {
                 line.push_str(&format!("  — {s}"));
             }
             out.push(line);
+            values.push(json!({
+                "id": item.id,
+                "type_id": item.type_id,
+                "external_key": item.external_key,
+                "summary": summary,
+            }));
         }
-        Ok(out.join("\n"))
+        Ok(listing(out, values, "(no items)"))
     }
 
-    async fn show_users(&self) -> Result<String, String> {
-        let rows = sqlx::query("SELECT id, handle FROM users ORDER BY id")
+    async fn show_users(&self) -> Result<Reply, String> {
+        let rows = sqlx::query("SELECT id, handle, operator FROM users ORDER BY id")
             .fetch_all(self.store.pool())
             .await
             .map_err(sql_err)?;
-        if rows.is_empty() {
-            return Ok("(no users)".to_owned());
+        let mut out = Vec::new();
+        let mut values = Vec::new();
+        for r in &rows {
+            let (id, handle) = (str_col(r, "id")?, str_col(r, "handle")?);
+            let operator = r.try_get::<i64, _>("operator").map_err(sql_err)? != 0;
+            out.push(format!(
+                "{id}  @{handle}{}",
+                if operator { "  operator" } else { "" }
+            ));
+            values.push(json!({ "id": id, "handle": handle, "operator": operator }));
         }
-        rows.iter()
-            .map(|r| Ok(format!("{}  @{}", str_col(r, "id")?, str_col(r, "handle")?)))
-            .collect::<Result<Vec<_>, String>>()
-            .map(|v| v.join("\n"))
+        Ok(listing(out, values, "(no users)"))
     }
 
-    async fn show_links(&self, handle: &str) -> Result<String, String> {
+    async fn show_links(&self, handle: &str) -> Result<Reply, String> {
         if handle.is_empty() {
             return Err("usage: show links <handle>".to_owned());
         }
@@ -247,17 +515,240 @@ impl DebugShell {
         let items = crate::links::linked_items(self.store.pool(), user)
             .await
             .map_err(core_err)?;
-        if items.is_empty() {
-            return Ok("(no links)".to_owned());
+        Ok(listing(
+            items
+                .iter()
+                .map(|i| format!("{}  type={}", i.id, i.type_id))
+                .collect(),
+            items
+                .iter()
+                .map(|i| json!({ "id": i.id, "type_id": i.type_id }))
+                .collect(),
+            "(no links)",
+        ))
+    }
+
+    async fn show_invites(&self) -> Result<Reply, String> {
+        let invites = crate::invites::list(self.store.pool(), None)
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            invites
+                .iter()
+                .map(|i| {
+                    let mut line = format!(
+                        "{}  uses_left={}  expires={}",
+                        i.id,
+                        i.uses_left,
+                        i.expires_at.as_deref().unwrap_or("never")
+                    );
+                    if !i.channels.is_empty() {
+                        let channels: Vec<String> =
+                            i.channels.iter().map(ToString::to_string).collect();
+                        line.push_str(&format!("  joins={}", channels.join(",")));
+                    }
+                    line
+                })
+                .collect(),
+            invites
+                .iter()
+                .map(|i| serde_json::to_value(i).unwrap_or(Json::Null))
+                .collect(),
+            "(no open invites)",
+        ))
+    }
+
+    async fn show_tokens(&self, handle: &str) -> Result<Reply, String> {
+        if handle.is_empty() {
+            return Err("usage: show tokens <handle>".to_owned());
+        }
+        let user = self.user_id_by_handle(handle).await?;
+        let tokens = crate::tokens::list(self.store.pool(), user)
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            tokens
+                .iter()
+                .map(|t| {
+                    let scopes: Vec<String> = t.scopes.iter().map(ToString::to_string).collect();
+                    format!(
+                        "{}  {}  scopes={}  expires={}  last_used={}",
+                        t.id,
+                        t.name,
+                        scopes.join(","),
+                        t.expires_at.as_deref().unwrap_or("never"),
+                        t.last_used_at.as_deref().unwrap_or("never")
+                    )
+                })
+                .collect(),
+            tokens
+                .iter()
+                .map(|t| serde_json::to_value(t).unwrap_or(Json::Null))
+                .collect(),
+            "(no API tokens)",
+        ))
+    }
+
+    async fn show_sessions(&self, handle: &str) -> Result<Reply, String> {
+        if handle.is_empty() {
+            return Err("usage: show sessions <handle>".to_owned());
+        }
+        let user = self.user_id_by_handle(handle).await?;
+        let sessions = crate::auth::list_sessions(self.store.pool(), user, None)
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            sessions
+                .iter()
+                .map(|s| {
+                    format!(
+                        "{}  last_seen={}  expires={}  ip={}  agent={}",
+                        s.id,
+                        s.last_seen_at,
+                        s.expires_at,
+                        s.ip.as_deref().unwrap_or("-"),
+                        s.user_agent.as_deref().unwrap_or("-")
+                    )
+                })
+                .collect(),
+            sessions
+                .iter()
+                .map(|s| serde_json::to_value(s).unwrap_or(Json::Null))
+                .collect(),
+            "(no live sessions)",
+        ))
+    }
+
+    async fn show_lockouts(&self) -> Result<Reply, String> {
+        let lockouts = crate::throttle::lockouts(self.store.pool())
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            lockouts
+                .iter()
+                .map(|l| {
+                    format!(
+                        "{}  failures={}  locked={}  until={}",
+                        l.subject, l.failures, l.locked_at, l.until
+                    )
+                })
+                .collect(),
+            lockouts
+                .iter()
+                .map(|l| serde_json::to_value(l).unwrap_or(Json::Null))
+                .collect(),
+            "(no lockouts)",
+        ))
+    }
+
+    async fn show_migrations(&self) -> Result<Reply, String> {
+        let applied = crate::migrate::applied(self.store.pool())
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            applied
+                .iter()
+                .map(|m| {
+                    format!(
+                        "{}/{}  checksum={}  applied={}",
+                        m.owner,
+                        m.name,
+                        &m.checksum[..m.checksum.len().min(12)],
+                        m.applied_at
+                    )
+                })
+                .collect(),
+            applied
+                .iter()
+                .map(|m| {
+                    json!({
+                        "owner": m.owner,
+                        "name": m.name,
+                        "checksum": m.checksum,
+                        "applied_at": m.applied_at,
+                    })
+                })
+                .collect(),
+            "(no migrations)",
+        ))
+    }
+
+    async fn show_trash(&self) -> Result<Reply, String> {
+        // Directly-trashed envelopes only; what each one hides comes back (or goes) with it.
+        let rows = sqlx::query(
+            "SELECT 'channel' AS super_type, id, type_id, deleted_at FROM channels \
+               WHERE trash_root = id \
+             UNION ALL SELECT 'item', id, type_id, deleted_at FROM items WHERE trash_root = id \
+             ORDER BY deleted_at, id",
+        )
+        .fetch_all(self.store.pool())
+        .await
+        .map_err(sql_err)?;
+        let mut out = Vec::new();
+        let mut values = Vec::new();
+        for r in &rows {
+            let (super_type, id) = (str_col(r, "super_type")?, str_col(r, "id")?);
+            let (type_id, deleted_at) = (str_col(r, "type_id")?, str_col(r, "deleted_at")?);
+            out.push(format!(
+                "{super_type} {id}  type={type_id}  deleted={deleted_at}"
+            ));
+            values.push(json!({
+                "super_type": super_type,
+                "id": id,
+                "type_id": type_id,
+                "deleted_at": deleted_at,
+            }));
         }
-        Ok(items
-            .iter()
-            .map(|i| format!("{}  type={}", i.id, i.type_id))
-            .collect::<Vec<_>>()
-            .join("\n"))
+        Ok(listing(out, values, "(trash is empty)"))
+    }
+
+    async fn show_runtimes(&self) -> Result<Reply, String> {
+        // As recorded by whichever process supervises them (normally the server), not just this one.
+        let statuses = crate::runtime::statuses(self.store.pool())
+            .await
+            .map_err(core_err)?;
+        Ok(listing(
+            statuses
+                .iter()
+                .map(|s| {
+                    let mut line = format!(
+                        "{}  {}  v{}  restarts={}  healthy={}  since={}",
+                        s.name,
+                        s.state.as_str(),
+                        s.version,
+                        s.restarts,
+                        s.last_healthy_at.as_deref().unwrap_or("never"),
+                        s.updated_at.as_deref().unwrap_or("-"),
+                    );
+                    if let Some(pending) = s.pending {
+                        line.push_str(&format!("  pending={}", pending.as_str()));
+                    }
+                    if let Some(error) = &s.last_error {
+                        line.push_str(&format!("\n    last error: {error}"));
+                    }
+                    line
+                })
+                .collect(),
+            statuses
+                .iter()
+                .map(|s| {
+                    json!({
+                        "name": s.name,
+                        "state": s.state.as_str(),
+                        "version": s.version,
+                        "restarts": s.restarts,
+                        "last_error": s.last_error,
+                        "last_healthy_at": s.last_healthy_at,
+                        "updated_at": s.updated_at,
+                        "pending": s.pending.map(Control::as_str),
+                    })
+                })
+                .collect(),
+            "(no runtime components have run)",
+        ))
     }
 
-    async fn cmd_inspect(&self, id: &str) -> Result<String, String> {
+    async fn cmd_inspect(&self, id: &str) -> Result<Reply, String> {
         if id.is_empty() {
             return Err("usage: inspect <id>".to_owned());
         }
@@ -270,14 +761,26 @@ impl DebugShell {
                 let container = ch
                     .container
                     .map_or_else(|| "(root)".to_owned(), |c| c.to_string());
-                return Ok(format_envelope(
-                    "channel",
-                    &ch.id.to_string(),
-                    &ch.type_id,
-                    &container,
-                    None,
-                    summary,
-                    &ch.payload,
+                let value = json!({
+                    "super_type": "channel",
+                    "id": ch.id,
+                    "type_id": ch.type_id,
+                    "container": ch.container,
+                    "summary": summary,
+                    "payload": ch.payload,
+                    "rev": ch.rev,
+                });
+                return Ok(Reply::new(
+                    format_envelope(
+                        "channel",
+                        &ch.id.to_string(),
+                        &ch.type_id,
+                        &container,
+                        None,
+                        summary,
+                        &ch.payload,
+                    ),
+                    value,
                 ));
             }
         }
@@ -290,39 +793,151 @@ impl DebugShell {
                 let container = item
                     .container
                     .map_or_else(|| "(none)".to_owned(), |c| c.to_string());
-                return Ok(format_envelope(
-                    "item",
-                    &item.id.to_string(),
-                    &item.type_id,
-                    &container,
-                    item.external_key.as_deref(),
-                    summary,
-                    &item.payload,
+                let value = json!({
+                    "super_type": "item",
+                    "id": item.id,
+                    "type_id": item.type_id,
+                    "container": item.container,
+                    "external_key": item.external_key,
+                    "summary": summary,
+                    "payload": item.payload,
+                    "rev": item.rev,
+                });
+                return Ok(Reply::new(
+                    format_envelope(
+                        "item",
+                        &item.id.to_string(),
+                        &item.type_id,
+                        &container,
+                        item.external_key.as_deref(),
+                        summary,
+                        &item.payload,
+                    ),
+                    value,
                 ));
             }
         }
         Err(format!("no channel or item with id `{id}`"))
     }
 
-    async fn cmd_members(&self, cid: &str) -> Result<String, String> {
+    async fn cmd_members(&self, cid: &str) -> Result<Reply, String> {
         let (ch, membership) = self.resolve_membership(cid).await?;
         let users = membership
             .members(self.store.as_ref(), &ch)
             .await
             .map_err(core_err)?;
-        if users.is_empty() {
-            return Ok("(no members)".to_owned());
+        Ok(listing(
+            users.iter().map(ToString::to_string).collect(),
+            users.iter().map(|u| json!(u)).collect(),
+            "(no members)",
+        ))
+    }
+
+    async fn cmd_roles(&self, cid: &str) -> Result<Reply, String> {
+        if cid.is_empty() {
+            return Err("usage: roles <channel-id>".to_owned());
+        }
+        let channel_id = parse_channel_id(cid)?;
+        self.store
+            .get_channel(channel_id)
+            .await
+            .map_err(core_err)?
+            .ok_or_else(|| format!("no channel with id `{cid}`"))?;
+        let grants = self.store.role_grants(channel_id).await.map_err(core_err)?;
+        Ok(listing(
+            grants
+                .iter()
+                .map(|(user, role)| format!("{user}  {role}"))
+                .collect(),
+            grants
+                .iter()
+                .map(|(user, role)| json!({ "user": user, "role": role.as_str() }))
+                .collect(),
+            "(no roles granted here)",
+        ))
+    }
+
+    async fn cmd_history(&self, id: &str) -> Result<Reply, String> {
+        if id.is_empty() {
+            return Err("usage: history <id>".to_owned());
+        }
+        let target = self.resolve_revisioned(id).await?;
+        let revisions = self.store.revisions(target).await.map_err(core_err)?;
+        Ok(listing(
+            revisions
+                .iter()
+                .map(|r| {
+                    let container = r
+                        .container
+                        .map_or_else(|| "(none)".to_owned(), |c| c.to_string());
+                    format!(
+                        "rev {}  {}  by {}  container={}  payload={}",
+                        r.rev, r.recorded_at, r.actor, container, r.payload
+                    )
+                })
+                .collect(),
+            revisions.iter().map(|r| json!(r)).collect(),
+            "(no revisions)",
+        ))
+    }
+
+    /// Which envelope an id names, for the history commands: a live channel/item, or else one that
+    /// only survives in the revision history (deleted).
+    async fn resolve_revisioned(&self, id: &str) -> Result<EnvelopeRef, String> {
+        let (Ok(cid), Ok(iid)) = (id.parse::<ChannelId>(), id.parse::<ItemId>()) else {
+            return Err(format!("invalid id `{id}`"));
+        };
+        if self
+            .store
+            .get_channel(cid)
+            .await
+            .map_err(core_err)?
+            .is_some()
+        {
+            return Ok(EnvelopeRef::Channel(cid));
+        }
+        if self.store.get_item(iid).await.map_err(core_err)?.is_some() {
+            return Ok(EnvelopeRef::Item(iid));
         }
-        Ok(users
-            .iter()
-            .map(ToString::to_string)
-            .collect::<Vec<_>>()
-            .join("\n"))
+        for target in [EnvelopeRef::Channel(cid), EnvelopeRef::Item(iid)] {
+            if !self
+                .store
+                .revisions(target)
+                .await
+                .map_err(core_err)?
+                .is_empty()
+            {
+                return Ok(target);
+            }
+        }
+        Err(format!("no channel or item with id `{id}`"))
     }
 
     // --- writes (through the mutation API, §8) ----------------------------------------------------
 
-    async fn cmd_create_channel(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_revert(&self, rest: &str) -> Result<Reply, String> {
+        let (id, rev) = split_first(rest.trim());
+        let Ok(rev) = rev.trim().parse::<i64>() else {
+            return Err("usage: revert <id> <rev>".to_owned());
+        };
+        let target = self.resolve_revisioned(id).await?;
+        self.store.revert(target, rev).await.map_err(|e| match e {
+            cp_model::Error::NotFound => format!("no revision {rev} of live envelope `{id}`"),
+            e => core_err(e),
+        })?;
+        let super_type = match target {
+            EnvelopeRef::Channel(_) => "channel",
+            EnvelopeRef::Item(_) => "item",
+        };
+        let mut value = effect("reverted", super_type, id);
+        value["rev"] = json!(rev);
+        Ok(Reply::new(
+            format!("reverted {id} to revision {rev}"),
+            value,
+        ))
+    }
+
+    async fn cmd_create_channel(&self, rest: &str) -> Result<Reply, String> {
         let (type_id, payload) = split_first(rest.trim());
         if type_id.is_empty() {
             return Err("usage: create-channel <type_id> <json-payload>".to_owned());
@@ -336,10 +951,14 @@ impl DebugShell {
             })
             .await
             .map_err(core_err)?;
-        Ok(format!("created channel {id}"))
+        Ok(Reply::new(
+            format!("created channel {id}"),
+            effect("created", "channel", id),
+        )
+        .created(id))
     }
 
-    async fn cmd_create_item(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_create_item(&self, rest: &str) -> Result<Reply, String> {
         let (cid, tail) = split_first(rest.trim());
         let (type_id, payload) = split_first(tail);
         if cid.is_empty() || type_id.is_empty() {
@@ -355,10 +974,10 @@ impl DebugShell {
             })
             .await
             .map_err(core_err)?;
-        Ok(format!("created item {id}"))
+        Ok(Reply::new(format!("created item {id}"), effect("created", "item", id)).created(id))
     }
 
-    async fn cmd_set_payload(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_set_payload(&self, rest: &str) -> Result<Reply, String> {
         let (id, payload) = split_first(rest.trim());
         if id.is_empty() {
             return Err("usage: set-payload <id> <json-payload>".to_owned());
@@ -376,7 +995,10 @@ impl DebugShell {
                     .set_channel_payload(cid, payload)
                     .await
                     .map_err(core_err)?;
-                return Ok(format!("updated channel {cid}"));
+                return Ok(Reply::new(
+                    format!("updated channel {cid}"),
+                    effect("updated", "channel", cid),
+                ));
             }
         }
         if let Ok(iid) = id.parse::<ItemId>() {
@@ -385,13 +1007,62 @@ impl DebugShell {
                     .set_item_payload(iid, payload)
                     .await
                     .map_err(core_err)?;
-                return Ok(format!("updated item {iid}"));
+                return Ok(Reply::new(
+                    format!("updated item {iid}"),
+                    effect("updated", "item", iid),
+                ));
             }
         }
         Err(format!("no channel or item with id `{id}`"))
     }
 
-    async fn cmd_delete(&self, id: &str) -> Result<String, String> {
+    async fn cmd_patch(&self, rest: &str) -> Result<Reply, String> {
+        // One field at a time without retyping the payload: an object is a merge-patch, an array a
+        // JSON-patch (a merge-patch that is an array would just replace everything — that's set-payload).
+        let (id, doc) = split_first(rest.trim());
+        if id.is_empty() {
+            return Err("usage: patch <id> <merge-patch object | json-patch array>".to_owned());
+        }
+        let doc = parse_json(doc)?;
+        let patch = if doc.is_array() {
+            Patch::Json(doc)
+        } else {
+            Patch::Merge(doc)
+        };
+        if let Ok(cid) = id.parse::<ChannelId>() {
+            if self
+                .store
+                .get_channel(cid)
+                .await
+                .map_err(core_err)?
+                .is_some()
+            {
+                self.store
+                    .patch_channel_payload(cid, patch)
+                    .await
+                    .map_err(core_err)?;
+                return Ok(Reply::new(
+                    format!("patched channel {cid}"),
+                    effect("patched", "channel", cid),
+                ));
+            }
+        }
+        if let Ok(iid) = id.parse::<ItemId>() {
+            if self.store.get_item(iid).await.map_err(core_err)?.is_some() {
+                self.store
+                    .patch_item_payload(iid, patch)
+                    .await
+                    .map_err(core_err)?;
+                return Ok(Reply::new(
+                    format!("patched item {iid}"),
+                    effect("patched", "item", iid),
+                ));
+            }
+        }
+        Err(format!("no channel or item with id `{id}`"))
+    }
+
+    async fn cmd_delete(&self, id: &str) -> Result<Reply, String> {
         if let Ok(cid) = id.parse::<ChannelId>() {
             if self
                 .store
@@ -401,19 +1072,90 @@ impl DebugShell {
                 .is_some()
             {
                 self.store.delete_channel(cid).await.map_err(core_err)?;
-                return Ok(format!("deleted channel {cid} (and its subtree)"));
+                return Ok(Reply::new(
+                    format!(
+                        "deleted channel {cid} (and its subtree) to the trash; `restore {cid}` undoes it"
+                    ),
+                    effect("deleted", "channel", cid),
+                ));
             }
         }
         if let Ok(iid) = id.parse::<ItemId>() {
             if self.store.get_item(iid).await.map_err(core_err)?.is_some() {
                 self.store.delete_item(iid).await.map_err(core_err)?;
-                return Ok(format!("deleted item {iid}"));
+                return Ok(Reply::new(
+                    format!("deleted item {iid} to the trash; `restore {iid}` undoes it"),
+                    effect("deleted", "item", iid),
+                ));
             }
         }
         Err(format!("no channel or item with id `{id}`"))
     }
 
-    async fn cmd_reparent(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_restore(&self, id: &str) -> Result<Reply, String> {
+        match self.stored_super_type(id).await? {
+            "channel" => {
+                let cid = parse_channel_id(id)?;
+                self.store.restore_channel(cid).await.map_err(core_err)?;
+                Ok(Reply::new(
+                    format!("restored channel {cid}"),
+                    effect("restored", "channel", cid),
+                ))
+            }
+            _ => {
+                let iid = parse_item_id(id)?;
+                self.store.restore_item(iid).await.map_err(core_err)?;
+                Ok(Reply::new(
+                    format!("restored item {iid}"),
+                    effect("restored", "item", iid),
+                ))
+            }
+        }
+    }
+
+    async fn cmd_purge(&self, id: &str) -> Result<Reply, String> {
+        match self.stored_super_type(id).await? {
+            "channel" => {
+                let cid = parse_channel_id(id)?;
+                self.store.purge_channel(cid).await.map_err(core_err)?;
+                Ok(Reply::new(
+                    format!("purged channel {cid} (and its subtree) permanently"),
+                    effect("purged", "channel", cid),
+                ))
+            }
+            _ => {
+                let iid = parse_item_id(id)?;
+                self.store.purge_item(iid).await.map_err(core_err)?;
+                Ok(Reply::new(
+                    format!("purged item {iid} permanently"),
+                    effect("purged", "item", iid),
+                ))
+            }
+        }
+    }
+
+    /// Which table holds `id`, trashed or not — `restore`/`purge` act on tombstones, which the
+    /// store's point reads hide.
+    async fn stored_super_type(&self, id: &str) -> Result<&'static str, String> {
+        if id.is_empty() {
+            return Err("usage: restore|purge <id>".to_owned());
+        }
+        let row = sqlx::query(
+            "SELECT 'channel' AS super_type FROM channels WHERE id = $1 \
+             UNION ALL SELECT 'item' FROM items WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(self.store.pool())
+        .await
+        .map_err(sql_err)?;
+        match row {
+            Some(r) if str_col(&r, "super_type")? == "channel" => Ok("channel"),
+            Some(_) => Ok("item"),
+            None => Err(format!("no channel or item with id `{id}`")),
+        }
+    }
+
+    async fn cmd_reparent(&self, rest: &str) -> Result<Reply, String> {
         // Move a channel/item under a new container (or `root` for none). The only way to build a
         // hierarchy interactively — e.g. put `basic` rooms inside a `space` so its search finds them.
         let (id, target) = split_first(rest.trim());
@@ -439,7 +1181,12 @@ impl DebugShell {
                     .reparent_channel(cid, container)
                     .await
                     .map_err(core_err)?;
-                return Ok(format!("reparented channel {cid} -> {dest}"));
+                let mut value = effect("reparented", "channel", cid);
+                value["container"] = json!(container);
+                return Ok(Reply::new(
+                    format!("reparented channel {cid} -> {dest}"),
+                    value,
+                ));
             }
         }
         if let Ok(iid) = id.parse::<ItemId>() {
@@ -448,13 +1195,18 @@ impl DebugShell {
                     .reparent_item(iid, container)
                     .await
                     .map_err(core_err)?;
-                return Ok(format!("reparented item {iid} -> {dest}"));
+                let mut value = effect("reparented", "item", iid);
+                value["container"] = json!(container);
+                return Ok(Reply::new(
+                    format!("reparented item {iid} -> {dest}"),
+                    value,
+                ));
             }
         }
         Err(format!("no channel or item with id `{id}`"))
     }
 
-    async fn cmd_set_password(&self, rest: &str) -> Result<String, String> {
+    async fn cmd_set_password(&self, rest: &str) -> Result<Reply, String> {
         // Provision login for a native user (accounts are shell-provisioned; there is no public
         // registration — §17). The password is the remainder of the line.
         let (handle, password) = split_first(rest.trim());
@@ -464,10 +1216,197 @@ impl DebugShell {
         crate::auth::set_password(self.store.pool(), handle, password.trim())
             .await
             .map_err(core_err)?;
-        Ok(format!("password set for @{handle}"))
+        Ok(Reply::new(
+            format!("password set for @{handle}"),
+            json!({ "action": "password-set", "handle": handle }),
+        ))
+    }
+
+    async fn cmd_control(&self, name: &str, control: Control) -> Result<Reply, String> {
+        if name.is_empty() {
+            return Err(format!("usage: {} <component>", control.as_str()));
+        }
+        // Queued in the DB: the supervising process (normally the server) takes it within a poll.
+        match crate::runtime::request(self.store.pool(), name, control).await {
+            Ok(()) => Ok(Reply::new(
+                format!("{} requested for {name}", control.as_str()),
+                json!({ "action": "requested", "component": name, "pending": control.as_str() }),
+            )),
+            Err(cp_model::Error::NotFound) => Err(format!(
+                "no runtime component `{name}` — see `show runtimes`"
+            )),
+            Err(e) => Err(core_err(e)),
+        }
+    }
+
+    async fn cmd_set_operator(&self, rest: &str) -> Result<Reply, String> {
+        let (handle, flag) = split_first(rest.trim());
+        let operator = match flag.trim() {
+            "on" => true,
+            "off" => false,
+            _ => return Err("usage: set-operator <handle> <on|off>".to_owned()),
+        };
+        crate::auth::set_operator(self.store.pool(), handle, operator)
+            .await
+            .map_err(core_err)?;
+        Ok(Reply::new(
+            format!(
+                "@{handle} is {}an operator",
+                if operator { "" } else { "no longer " }
+            ),
+            json!({ "action": "set-operator", "handle": handle, "operator": operator }),
+        ))
+    }
+
+    async fn cmd_create_invite(&self, rest: &str) -> Result<Reply, String> {
+        // Mint a self-registration code (#41). The code is printed once; only its hash is kept.
+        let mut args = rest.split_whitespace();
+        let usage = "usage: create-invite <uses> [<channel-id>…]";
+        let uses = args
+            .next()
+            .ok_or(usage)?
+            .parse::<u32>()
+            .map_err(|_| usage.to_owned())?;
+        let channels = args.map(parse_channel_id).collect::<Result<Vec<_>, _>>()?;
+        let spec = crate::invites::NewInvite {
+            uses,
+            channels,
+            ..Default::default()
+        };
+        let (invite, code) = crate::invites::mint(&self.store, None, spec)
+            .await
+            .map_err(core_err)?;
+        Ok(Reply::new(
+            format!(
+                "created invite {} ({} uses, expires {}): code {code}",
+                invite.id,
+                invite.uses_left,
+                invite.expires_at.as_deref().unwrap_or("never")
+            ),
+            json!({ "action": "created", "invite": invite, "code": code }),
+        ))
+    }
+
+    async fn cmd_revoke_invite(&self, id: &str) -> Result<Reply, String> {
+        if id.is_empty() {
+            return Err("usage: revoke-invite <invite-id>".to_owned());
+        }
+        crate::invites::revoke(self.store.pool(), id)
+            .await
+            .map_err(|e| match e {
+                cp_model::Error::NotFound => format!("no invite with id `{id}`"),
+                e => core_err(e),
+            })?;
+        Ok(Reply::new(
+            format!("revoked invite {id}"),
+            json!({ "action": "revoked", "invite": id }),
+        ))
     }
 
-    async fn cmd_create_user(&self, handle: &str) -> Result<String, String> {
+    async fn cmd_create_token(&self, rest: &str) -> Result<Reply, String> {
+        // Mint an API token for a user (#42). The secret is printed once; only its hash is kept.
+        let mut args = rest.split_whitespace();
+        let usage = "usage: create-token <handle> <name> <scope>[,<scope>…] [<days>]";
+        let (Some(handle), Some(name), Some(scopes)) = (args.next(), args.next(), args.next())
+        else {
+            return Err(usage.to_owned());
+        };
+        let ttl = match args.next() {
+            Some(days) => match days.parse::<u64>() {
+                Ok(days) if days > 0 => Some(std::time::Duration::from_secs(days * 24 * 60 * 60)),
+                _ => return Err(usage.to_owned()),
+            },
+            None => None,
+        };
+        let scopes = scopes
+            .split(',')
+            .map(|s| s.parse::<crate::tokens::Scope>())
+            .collect::<cp_model::Result<Vec<_>>>()
+            .map_err(core_err)?;
+        let user = self.user_id_by_handle(handle).await?;
+        let (token, secret) = crate::tokens::mint(self.store.pool(), user, name, scopes, ttl)
+            .await
+            .map_err(core_err)?;
+        Ok(Reply::new(
+            format!(
+                "created token {} for @{handle} (expires {}): {secret}",
+                token.id,
+                token.expires_at.as_deref().unwrap_or("never")
+            ),
+            json!({ "action": "created", "token": token, "secret": secret }),
+        ))
+    }
+
+    async fn cmd_revoke_token(&self, id: &str) -> Result<Reply, String> {
+        if id.is_empty() {
+            return Err("usage: revoke-token <token-id>".to_owned());
+        }
+        crate::tokens::revoke(self.store.pool(), id)
+            .await
+            .map_err(|e| match e {
+                cp_model::Error::NotFound => format!("no token with id `{id}`"),
+                e => core_err(e),
+            })?;
+        Ok(Reply::new(
+            format!("revoked token {id}"),
+            json!({ "action": "revoked", "token": id }),
+        ))
+    }
+
+    async fn cmd_revoke_sessions(&self, handle: &str) -> Result<Reply, String> {
+        // Log a user out everywhere (#43). API tokens are separate credentials: `revoke-token`.
+        if handle.is_empty() {
+            return Err("usage: revoke-sessions <handle>".to_owned());
+        }
+        let user = self.user_id_by_handle(handle).await?;
+        let revoked = crate::auth::revoke_all_sessions(self.store.pool(), user)
+            .await
+            .map_err(core_err)?;
+        Ok(Reply::new(
+            format!("revoked {revoked} session(s) for @{handle}"),
+            json!({ "action": "revoked-sessions", "handle": handle, "sessions": revoked }),
+        ))
+    }
+
+    async fn cmd_reset_totp(&self, handle: &str) -> Result<Reply, String> {
+        // For a user who lost both their authenticator and their recovery codes (#45). Their password
+        // alone signs them in again until they re-enroll.
+        if handle.is_empty() {
+            return Err("usage: reset-totp <handle>".to_owned());
+        }
+        let user = self.user_id_by_handle(handle).await?;
+        if !crate::totp::reset(self.store.pool(), user)
+            .await
+            .map_err(core_err)?
+        {
+            return Err(format!("@{handle} has no second factor"));
+        }
+        Ok(Reply::new(
+            format!("reset the second factor for @{handle}"),
+            json!({ "action": "reset-totp", "handle": handle }),
+        ))
+    }
+
+    async fn cmd_clear_lockout(&self, rest: &str) -> Result<Reply, String> {
+        // Forget a handle's or an address's failed logins (#44), lifting a delay or a lockout alike.
+        let subject = match rest.split_once(char::is_whitespace) {
+            Some(("handle", handle)) => crate::throttle::Subject::Handle(handle.trim().to_owned()),
+            Some(("ip", ip)) => crate::throttle::Subject::Ip(ip.trim().to_owned()),
+            _ => return Err("usage: clear-lockout <handle <handle> | ip <address>>".to_owned()),
+        };
+        crate::throttle::clear(self.store.pool(), &subject)
+            .await
+            .map_err(|e| match e {
+                cp_model::Error::NotFound => format!("no failed logins on record for {subject}"),
+                e => core_err(e),
+            })?;
+        Ok(Reply::new(
+            format!("cleared {subject}"),
+            json!({ "action": "cleared-lockout", "subject": subject }),
+        ))
+    }
+
+    async fn cmd_create_user(&self, handle: &str) -> Result<Reply, String> {
         if handle.is_empty() || handle.contains(char::is_whitespace) {
             return Err("usage: create-user <handle>".to_owned());
         }
@@ -476,10 +1415,12 @@ impl DebugShell {
         let id = crate::auth::provision_user(self.store.pool(), handle)
             .await
             .map_err(core_err)?;
-        Ok(format!("created user {id} (@{handle})"))
+        let mut value = effect("created", "user", id);
+        value["handle"] = json!(handle);
+        Ok(Reply::new(format!("created user {id} (@{handle})"), value).created(id))
     }
 
-    async fn cmd_add_user(&self, rest: &str, add: bool) -> Result<String, String> {
+    async fn cmd_add_user(&self, rest: &str, add: bool) -> Result<Reply, String> {
         let (cid, uid) = split_first(rest.trim());
         if cid.is_empty() || uid.trim().is_empty() {
             return Err(format!(
@@ -496,17 +1437,59 @@ impl DebugShell {
         let cx: &dyn WriteCtx = self.store.as_ref();
         if add {
             membership.add_user(cx, &ch, user).await.map_err(core_err)?;
-            Ok(format!("added user {user} to channel {}", ch.id))
+            Ok(Reply::new(
+                format!("added user {user} to channel {}", ch.id),
+                json!({ "action": "added", "channel": ch.id, "user": user }),
+            ))
         } else {
             membership
                 .remove_user(cx, &ch, user)
                 .await
                 .map_err(core_err)?;
-            Ok(format!("removed user {user} from channel {}", ch.id))
+            Ok(Reply::new(
+                format!("removed user {user} from channel {}", ch.id),
+                json!({ "action": "removed", "channel": ch.id, "user": user }),
+            ))
+        }
+    }
+
+    async fn cmd_role(&self, rest: &str, grant: bool) -> Result<Reply, String> {
+        // The `channel_roles` substrate (§18): a grant reaches every channel below this one.
+        let verb = if grant { "grant-role" } else { "revoke-role" };
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let [cid, handle, role] = args[..] else {
+            return Err(format!("usage: {verb} <channel-id> <handle> <role>"));
+        };
+        let channel_id = parse_channel_id(cid)?;
+        self.store
+            .get_channel(channel_id)
+            .await
+            .map_err(core_err)?
+            .ok_or_else(|| format!("no channel with id `{cid}`"))?;
+        let user = self.user_id_by_handle(handle).await?;
+        let role: Role = role.parse().map_err(core_err)?;
+        if grant {
+            self.store
+                .grant_role(channel_id, user, role.clone())
+                .await
+                .map_err(core_err)?;
+            Ok(Reply::new(
+                format!("granted {role} on channel {channel_id} to @{handle}"),
+                json!({ "action": "granted", "channel": channel_id, "handle": handle, "role": role.as_str() }),
+            ))
+        } else {
+            self.store
+                .revoke_role(channel_id, user, role.clone())
+                .await
+                .map_err(core_err)?;
+            Ok(Reply::new(
+                format!("revoked {role} on channel {channel_id} from @{handle}"),
+                json!({ "action": "revoked", "channel": channel_id, "handle": handle, "role": role.as_str() }),
+            ))
         }
     }
 
-    async fn cmd_link(&self, rest: &str, add: bool) -> Result<String, String> {
+    async fn cmd_link(&self, rest: &str, add: bool) -> Result<Reply, String> {
         // Operator-provisioned `linked-users` (§2/§19): link a native user to an external cached-user
         // item. Pre-OAuth this is an operator-trusted assertion — there is no self-service HTTP write.
         let (handle, item) = split_first(rest.trim());
@@ -525,18 +1508,24 @@ impl DebugShell {
             crate::links::link(self.store.pool(), user, item_id)
                 .await
                 .map_err(core_err)?;
-            Ok(format!("linked @{handle} -> item {item_id}"))
+            Ok(Reply::new(
+                format!("linked @{handle} -> item {item_id}"),
+                json!({ "action": "linked", "handle": handle, "item": item_id }),
+            ))
         } else {
             crate::links::unlink(self.store.pool(), user, item_id)
                 .await
                 .map_err(core_err)?;
-            Ok(format!("unlinked @{handle} -> item {item_id}"))
+            Ok(Reply::new(
+                format!("unlinked @{handle} -> item {item_id}"),
+                json!({ "action": "unlinked", "handle": handle, "item": item_id }),
+            ))
         }
     }
 
     /// Resolve a user handle to its id, or a "no user" refusal.
     async fn user_id_by_handle(&self, handle: &str) -> Result<UserId, String> {
-        let row = sqlx::query("SELECT id FROM users WHERE handle = ?")
+        let row = sqlx::query("SELECT id FROM users WHERE handle = $1")
             .bind(handle)
             .fetch_optional(self.store.pool())
             .await
@@ -568,12 +1557,113 @@ impl DebugShell {
     }
 }
 
-/// Run the interactive REPL against a core, reading stdin until EOF. Prompts + banner go to stderr so
-/// stdout carries only command output (scriptable: `printf '…' | channel-party shell`). §8.
-pub async fn run(core: &Core) -> anyhow::Result<()> {
-    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+/// How `channel-party shell` was invoked: `[--write] [--json] [--script <file|-> | --exec <command>…]`.
+/// §8.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShellArgs {
+    /// Start in write mode. For a script this is the only way to write: its mode is locked.
+    pub write: bool,
+    /// Print each reply as one line of JSON.
+    pub json: bool,
+    pub input: ShellInput,
+}
+
+/// Where the shell's commands come from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ShellInput {
+    /// The REPL on stdin.
+    #[default]
+    Interactive,
+    /// A script file, one command per line (`-` reads stdin).
+    Script(PathBuf),
+    /// Each `--exec` argument is one command, run in order.
+    Exec(Vec<String>),
+}
+
+impl ShellArgs {
+    /// Parse the arguments after `shell`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--write" => parsed.write = true,
+                "--json" => parsed.json = true,
+                "--script" => {
+                    let path = args
+                        .next()
+                        .ok_or("--script needs a file (or `-` for stdin)")?;
+                    if parsed.input != ShellInput::Interactive {
+                        return Err("give one --script, or --exec commands, not both".to_owned());
+                    }
+                    parsed.input = ShellInput::Script(path.into());
+                }
+                "--exec" => {
+                    let command = args.next().ok_or("--exec needs a command")?;
+                    match &mut parsed.input {
+                        ShellInput::Exec(commands) => commands.push(command),
+                        ShellInput::Interactive => parsed.input = ShellInput::Exec(vec![command]),
+                        ShellInput::Script(_) => {
+                            return Err("give one --script, or --exec commands, not both".to_owned())
+                        }
+                    }
+                }
+                other => {
+                    return Err(format!(
+                        "unknown shell argument `{other}`; usage: channel-party shell [--write] \
+                         [--json] [--script <file|-> | --exec <command>…]"
+                    ))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
 
+/// Run the shell against a core as `args` says: the interactive REPL, or a script / `--exec` list that
+/// stops at the first failing command with an error (so the process exits non-zero). Replies go to
+/// stdout; the REPL's prompts + banner go to stderr, so stdout carries only command output. §8.
+pub async fn run(core: &Core, args: &ShellArgs) -> anyhow::Result<()> {
     let mut shell = DebugShell::new(core.registry().clone(), core.store());
+    if args.write {
+        shell.enable_write_mode();
+    }
+    let script;
+    let lines: Vec<&str> = match &args.input {
+        ShellInput::Interactive => return repl(shell, args.json).await,
+        ShellInput::Script(path) => {
+            script = if path.as_os_str() == "-" {
+                use tokio::io::AsyncReadExt;
+                let mut script = String::new();
+                tokio::io::stdin().read_to_string(&mut script).await?;
+                script
+            } else {
+                tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("reading {}: {e}", path.display()))?
+            };
+            script.lines().collect()
+        }
+        ShellInput::Exec(commands) => commands.iter().map(String::as_str).collect(),
+    };
+    shell.lock_mode();
+    let json = args.json;
+    shell
+        .run_script(lines, |reply| {
+            let out = reply.render(json);
+            if !out.is_empty() {
+                println!("{out}");
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+/// The interactive REPL, reading stdin until EOF. A failing command prints its error and the session
+/// goes on.
+async fn repl(mut shell: DebugShell, json: bool) -> anyhow::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
     let mut lines = BufReader::new(tokio::io::stdin()).lines();
     let mut err = tokio::io::stderr();
     err.write_all(b"channel-party debug shell. `help` for commands; Ctrl-D to exit.\n")
@@ -581,7 +1671,12 @@ pub async fn run(core: &Core) -> anyhow::Result<()> {
     err.write_all(shell.prompt().as_bytes()).await?;
     err.flush().await?;
     while let Some(line) = lines.next_line().await? {
-        let out = shell.eval(&line).await;
+        let out = match shell.exec(&line).await {
+            Ok(reply) if json && reply.json.is_null() => String::new(),
+            Ok(reply) => reply.render(json),
+            Err(msg) if json => json!({ "error": msg }).to_string(),
+            Err(msg) => msg,
+        };
         if !out.is_empty() {
             println!("{out}");
         }
@@ -592,34 +1687,75 @@ pub async fn run(core: &Core) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn help_text() -> String {
-    // Built-in commands only. Kind-contributed `debug_commands()` (§8) list/execute once a kind ships
-    // one (e.g. canvas `move-box`, #11); that needs a registry enumerator + a kind execution hook,
-    // deferred until then.
-    [
+fn help_text(registry: &Registry) -> String {
+    let mut lines: Vec<String> = [
         "reads (always available):",
         "  show channels                      list every channel",
         "  show items <channel-id>            list a channel's items",
         "  show users                         list native users",
         "  show links <handle>                list a user's linked external items (#19)",
+        "  show migrations                    list applied migrations, per owner (#7)",
+        "  show trash                         list trashed channels/items (#25)",
+        "  show runtimes                      list runtime components: state, restarts, last error",
+        "  show invites                       list open invite codes (#41)",
+        "  show tokens <handle>               list a user's API tokens (#42)",
+        "  show sessions <handle>             list a user's live sessions (#43)",
+        "  show lockouts                      list handles and addresses locked out of login (#44)",
         "  inspect <id>                       dump one envelope (channel or item)",
         "  members <channel-id>               list a channel's members",
+        "  roles <channel-id>                 list the roles granted on a channel itself",
+        "  history <id>                       list a channel/item's revisions (prior states)",
         "mode:",
         "  enable-write-mode / disable-write-mode",
         "writes (require write mode):",
         "  create-channel <type_id> <json>",
         "  create-item <channel-id> <type_id> <json>",
         "  set-payload <id> <json>            replace a channel/item payload",
-        "  delete <id>                        delete a channel (subtree) or item",
+        "  patch <id> <json>                  merge-patch (object) or JSON-patch (array) a payload",
+        "  delete <id>                        move a channel (subtree) or item to the trash",
+        "  restore <id>                       bring a trashed channel/item back (#25)",
+        "  purge <id>                         permanently delete a channel (subtree) or item (#25)",
         "  reparent <id> <container-id|root>  move a channel/item under a new container",
+        "  revert <id> <rev>                  restore a channel/item to a revision's payload + container",
         "  create-user <handle>               bootstrap a native user",
-        "  set-password <handle> <password>   set a user's login password (#17)",
+        "  set-password <handle> <password>   set a user's login password (#17); signs them out everywhere",
         "  add-user-to-channel <channel-id> <user-id>",
         "  remove-user-from-channel <channel-id> <user-id>",
+        "  grant-role <channel-id> <handle> <role>   owner|admin|moderator|member|<custom>; inherited below",
+        "  revoke-role <channel-id> <handle> <role>",
+        "  set-operator <handle> <on|off>     grant/revoke the admin HTTP surface",
+        "  restart <component>                restart a runtime component (taken by the server)",
+        "  reset <component>                  restart it with reset_requested(), no version() bump",
+        "  create-invite <uses> [<channel-id>…]  mint a self-registration code; its users join the channels",
+        "  revoke-invite <invite-id>          withdraw an invite code",
+        "  create-token <handle> <name> <scope>[,<scope>…] [<days>]  mint an API token; scopes are",
+        "                                     read|post|manage, optionally :<channel-id>",
+        "  revoke-token <token-id>            revoke an API token",
+        "  revoke-sessions <handle>           log a user out everywhere",
+        "  reset-totp <handle>                remove a user's second factor and recovery codes (#45)",
+        "  clear-lockout <handle <handle> | ip <address>>  forget failed logins, lifting a lockout",
         "  link-user <handle> <item-id>       link a user to an external cached-user item (#19)",
         "  unlink-user <handle> <item-id>     remove that link",
     ]
-    .join("\n")
+    .into_iter()
+    .map(str::to_owned)
+    .collect();
+    // Kind-contributed commands (§8), from whatever kinds this binary registered.
+    let kind_commands = registry.debug_commands();
+    if !kind_commands.is_empty() {
+        lines.push("kind commands ([write] requires write mode):".to_owned());
+        for c in &kind_commands {
+            let access = match c.command.access {
+                DebugAccess::Read => "",
+                DebugAccess::Write => "  [write]",
+            };
+            lines.push(format!(
+                "  {} {} {}{access}",
+                c.namespace, c.command.name, c.command.help
+            ));
+        }
+    }
+    lines.join("\n")
 }
 
 fn format_envelope(
@@ -643,6 +1779,26 @@ fn format_envelope(
     out
 }
 
+/// A list command's reply: one line (and one JSON element) per row, or `empty` for none.
+fn listing(lines: Vec<String>, values: Vec<Json>, empty: &str) -> Reply {
+    let text = if lines.is_empty() {
+        empty.to_owned()
+    } else {
+        lines.join("\n")
+    };
+    Reply::new(text, Json::Array(values))
+}
+
+/// The `--json` form of a write: what was done to which envelope (or user).
+fn effect(action: &str, super_type: &str, id: impl fmt::Display) -> Json {
+    json!({ "action": action, "super_type": super_type, "id": id.to_string() })
+}
+
+fn is_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
 /// Split a line into its first whitespace-delimited word and the trimmed remainder (which may itself
 /// contain whitespace, e.g. a JSON payload).
 fn split_first(s: &str) -> (&str, &str) {
@@ -673,25 +1829,30 @@ fn parse_channel_id(s: &str) -> Result<ChannelId, String> {
     s.parse().map_err(|_| format!("invalid channel id `{s}`"))
 }
 
+fn parse_item_id(s: &str) -> Result<ItemId, String> {
+    s.parse().map_err(|_| format!("invalid item id `{s}`"))
+}
+
 fn parse_user_id(s: &str) -> Result<UserId, String> {
     s.parse().map_err(|_| format!("invalid user id `{s}`"))
 }
 
-fn str_col(row: &SqliteRow, col: &str) -> Result<String, String> {
+fn str_col(row: &AnyRow, col: &str) -> Result<String, String> {
     row.try_get::<String, _>(col).map_err(sql_err)
 }
 
-fn channel_from_row(row: &SqliteRow) -> Result<Channel, String> {
+fn channel_from_row(row: &AnyRow) -> Result<Channel, String> {
     let container: Option<String> = row.try_get("container").map_err(sql_err)?;
     Ok(Channel {
         id: parse_channel_id(&str_col(row, "id")?)?,
         type_id: TypeId::new(str_col(row, "type_id")?),
         container: container.as_deref().map(parse_channel_id).transpose()?,
         payload: parse_json(&str_col(row, "payload")?)?,
+        rev: row.try_get("rev").map_err(sql_err)?,
     })
 }
 
-fn item_from_row(row: &SqliteRow) -> Result<Item, String> {
+fn item_from_row(row: &AnyRow) -> Result<Item, String> {
     let container: Option<String> = row.try_get("container").map_err(sql_err)?;
     Ok(Item {
         id: str_col(row, "id")?
@@ -701,5 +1862,6 @@ fn item_from_row(row: &SqliteRow) -> Result<Item, String> {
         container: container.as_deref().map(parse_channel_id).transpose()?,
         external_key: row.try_get("external_key").map_err(sql_err)?,
         payload: parse_json(&str_col(row, "payload")?)?,
+        rev: row.try_get("rev").map_err(sql_err)?,
     })
 }
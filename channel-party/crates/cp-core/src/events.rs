@@ -2,6 +2,14 @@
 //! derived indexers and the frontend SSE consume the stream — independent tasks, so a slow
 //! pipeline never blocks the write. The event *types* live in `cp-model` (so a kind's runtime
 //! component can consume them without depending on `cp-core`); the bus is the mechanism. §7/§9.
+//!
+//! The bus is per-process. Changes committed by another process on the same database reach it through
+//! the change-log relay (`change_log::relay`), which needs to tell those apart from this process's own
+//! commits: while a relay runs, the write path *claims* each `seq` before committing it.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use tokio::sync::broadcast;
 
@@ -12,12 +20,19 @@ pub use cp_model::{ChangeEvent, ChangeOp, EnvelopeRef};
 #[derive(Clone)]
 pub struct EventBus {
     tx: broadcast::Sender<ChangeEvent>,
+    relaying: Arc<AtomicBool>,
+    /// Local `seq`s the relay has not yet passed; only tracked while a relay runs.
+    claimed: Arc<Mutex<BTreeSet<i64>>>,
 }
 
 impl EventBus {
     pub fn new() -> Self {
         let (tx, _rx) = broadcast::channel(1024);
-        Self { tx }
+        Self {
+            tx,
+            relaying: Arc::new(AtomicBool::new(false)),
+            claimed: Arc::new(Mutex::new(BTreeSet::new())),
+        }
     }
 
     /// Emit a change event; dropped if there are no subscribers. §7.
@@ -29,6 +44,39 @@ impl EventBus {
     pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
         self.tx.subscribe()
     }
+
+    /// Mark `seqs` as this process's own, before the unit that logged them commits, so the relay never
+    /// republishes them. A no-op unless a relay is running.
+    pub(crate) fn claim(&self, seqs: impl IntoIterator<Item = i64>) {
+        if self.relaying.load(Ordering::SeqCst) {
+            self.claimed.lock().expect("claims lock").extend(seqs);
+        }
+    }
+
+    /// Withdraw claims for a unit that failed to commit: its `seq`s were rolled back and may be reused
+    /// by another process.
+    pub(crate) fn unclaim(&self, seqs: impl IntoIterator<Item = i64>) {
+        let mut claimed = self.claimed.lock().expect("claims lock");
+        for seq in seqs {
+            claimed.remove(&seq);
+        }
+    }
+
+    /// Whether `seq` was claimed locally, forgetting it and every claim below it: the relay reads the
+    /// log in order, so it never asks about those again.
+    pub(crate) fn take_claim(&self, seq: i64) -> bool {
+        let mut claimed = self.claimed.lock().expect("claims lock");
+        let local = claimed.contains(&seq);
+        *claimed = claimed.split_off(&(seq + 1));
+        local
+    }
+
+    pub(crate) fn set_relaying(&self, on: bool) {
+        self.relaying.store(on, Ordering::SeqCst);
+        if !on {
+            self.claimed.lock().expect("claims lock").clear();
+        }
+    }
 }
 
 impl Default for EventBus {
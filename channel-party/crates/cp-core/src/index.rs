@@ -1,8 +1,9 @@
 //! Index substrates. `index(payload) -> IndexEntry` (DESIGN §6) is written transactionally with the
 //! envelope by the write path. This implements the **FTS5** substrate (`search_index`, trigram) for
-//! `name`/`text`, which `StoreCtx::search` queries. The other two `IndexEntry` fields have no consumer
-//! yet: `sort_key` (expression index) and `coord` (R-tree) wait on `canvas` (`TODO.md` #11) and are
-//! ignored here rather than built speculatively. See `design/index-search.md`.
+//! `name`/`text`, which `StoreCtx::search` queries, the **sort-key** substrate (`sort_index`), which
+//! `children` reads for `Order::SortKeyAsc`/`SortKeyDesc`, and the **R-tree** (`spatial_index`, keyed
+//! through `spatial_key`) for `coord`/`extent`, which `StoreCtx::within_bbox` queries. See
+//! `design/index-search.md`.
 
 use cp_model::{IndexEntry, Result};
 
@@ -21,42 +22,98 @@ fn db(e: sqlx::Error) -> cp_model::Error {
     cp_model::Error::Other(e.to_string())
 }
 
-/// Write a kind's inline projection into `search_index`, in the caller's transaction. §6. FTS5 has no
-/// unique constraint, so this is delete-then-insert keyed by envelope; an entry with neither `name` nor
-/// `text` inserts no row (nothing to search). `sort_key`/`coord` are #11's substrates, ignored here.
+/// Write a kind's inline projection into `search_index` and `sort_index`, in the caller's transaction.
+/// §6. FTS5 has no unique constraint, so this is delete-then-insert keyed by envelope; an entry with
+/// neither `name` nor `text` inserts no FTS row (nothing to search), one without `sort_key` no sort
+/// row (it sorts as the empty key), and one without `coord` no box (`extent` alone means nothing).
 pub async fn upsert(
-    tx: &mut sqlx::SqliteConnection,
+    tx: &mut sqlx::AnyConnection,
     target: EnvelopeRef,
     entry: &IndexEntry,
 ) -> Result<()> {
     delete(tx, target).await?;
-    if entry.name.is_none() && entry.text.is_none() {
-        return Ok(());
-    }
     let (super_type, id) = key(target);
-    sqlx::query(
-        "INSERT INTO search_index (name, text, envelope_id, super_type) VALUES (?, ?, ?, ?)",
-    )
-    .bind(entry.name.as_deref())
-    .bind(entry.text.as_deref())
-    .bind(id)
-    .bind(super_type)
-    .execute(&mut *tx)
-    .await
-    .map_err(db)?;
+    if entry.name.is_some() || entry.text.is_some() {
+        sqlx::query(
+            "INSERT INTO search_index (name, text, envelope_id, super_type) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(entry.name.as_deref())
+        .bind(entry.text.as_deref())
+        .bind(&id)
+        .bind(super_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    }
+    if let Some(sort_key) = &entry.sort_key {
+        sqlx::query(
+            "INSERT INTO sort_index (super_type, envelope_id, sort_key) VALUES ($1, $2, $3)",
+        )
+        .bind(super_type)
+        .bind(&id)
+        .bind(sort_key)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    }
+    if let Some((x, y)) = entry.coord {
+        let (w, h) = entry.extent.unwrap_or((0.0, 0.0));
+        let rid: i64 = sqlx::query_scalar(
+            "INSERT INTO spatial_key (super_type, envelope_id) VALUES ($1, $2) RETURNING rid",
+        )
+        .bind(super_type)
+        .bind(&id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db)?;
+        // A negative extent is a rectangle drawn the other way; the R-tree wants min <= max.
+        sqlx::query(
+            "INSERT INTO spatial_index (rid, min_x, max_x, min_y, max_y) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(rid)
+        .bind(x.min(x + w))
+        .bind(x.max(x + w))
+        .bind(y.min(y + h))
+        .bind(y.max(y + h))
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    }
     Ok(())
 }
 
-/// Remove an envelope's `search_index` row (on delete/before re-upsert), in the caller's transaction.
-/// Only the directly-targeted envelope is purged; FK-cascaded children orphan their rows, which the
-/// INNER JOIN in `search` renders invisible (see the design note). §6.
-pub async fn delete(tx: &mut sqlx::SqliteConnection, target: EnvelopeRef) -> Result<()> {
+/// Remove an envelope's `search_index`, `sort_index` and spatial rows (on delete/before re-upsert), in the
+/// caller's transaction. Only the directly-targeted envelope is purged; FK-cascaded children orphan
+/// their rows, which `search`'s INNER JOIN and `children`' lookup by live id render invisible (see the
+/// design note). §6.
+pub async fn delete(tx: &mut sqlx::AnyConnection, target: EnvelopeRef) -> Result<()> {
     let (super_type, id) = key(target);
-    sqlx::query("DELETE FROM search_index WHERE envelope_id = ? AND super_type = ?")
-        .bind(id)
+    sqlx::query("DELETE FROM search_index WHERE envelope_id = $1 AND super_type = $2")
+        .bind(&id)
         .bind(super_type)
         .execute(&mut *tx)
         .await
         .map_err(db)?;
+    sqlx::query("DELETE FROM sort_index WHERE envelope_id = $1 AND super_type = $2")
+        .bind(&id)
+        .bind(super_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    let rid: Option<i64> = sqlx::query_scalar(
+        "DELETE FROM spatial_key WHERE envelope_id = $1 AND super_type = $2 RETURNING rid",
+    )
+    .bind(&id)
+    .bind(super_type)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db)?;
+    if let Some(rid) = rid {
+        sqlx::query("DELETE FROM spatial_index WHERE rid = $1")
+            .bind(rid)
+            .execute(&mut *tx)
+            .await
+            .map_err(db)?;
+    }
     Ok(())
 }
@@ -0,0 +1,291 @@
+//! Invite codes + self-registration (DESIGN §2, `design/auth.md`, `TODO.md` #41). An operator or a
+//! channel admin mints a code good for N registrations, optionally naming channels its users join;
+//! [`register`] spends one use and creates the user with their password in one write, then joins the
+//! channels through each kind's `Membership`. Like a session token, the code is shown once and
+//! only its SHA-256 is stored. Who may mint is the caller's decision (the shell, or the HTTP layer's
+//! `Manage` check); this module is the store logic. Sibling to `auth`.
+
+use std::time::{Duration, SystemTime};
+
+use cp_model::{Actor, ChannelId, Error, Result, User, UserId};
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+use sqlx::any::AnyRow;
+use sqlx::{Any, AnyPool, Row};
+
+use crate::auth;
+use crate::Store;
+
+/// How long an invite lasts when its minter does not say.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The longest lifetime an invite may be given; for longer, mint one that never expires.
+pub const MAX_TTL: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// What to mint: how many registrations the code is good for, how long it lives (`None` = forever),
+/// and the channels its users join.
+#[derive(Clone, Debug)]
+pub struct NewInvite {
+    pub uses: u32,
+    pub ttl: Option<Duration>,
+    pub channels: Vec<ChannelId>,
+}
+
+impl Default for NewInvite {
+    fn default() -> Self {
+        Self {
+            uses: 1,
+            ttl: Some(DEFAULT_TTL),
+            channels: Vec::new(),
+        }
+    }
+}
+
+/// A live invite, as listed. The code itself is never stored, so it is not here.
+#[derive(Clone, Debug, Serialize)]
+pub struct Invite {
+    pub id: String,
+    pub created_by: Option<UserId>,
+    pub uses_left: i64,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+    pub channels: Vec<ChannelId>,
+}
+
+/// A fresh 128-bit invite code (hex), from the OS CSPRNG.
+fn random_code() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn invite_from_row(row: &AnyRow, channels: Vec<ChannelId>) -> Result<Invite> {
+    let created_by: Option<String> = row.try_get("created_by").map_err(db)?;
+    Ok(Invite {
+        id: row.try_get("id").map_err(db)?,
+        created_by: created_by
+            .map(|u| u.parse())
+            .transpose()
+            .map_err(|_| Error::Other("invalid user id".to_owned()))?,
+        uses_left: row.try_get("uses_left").map_err(db)?,
+        expires_at: row.try_get("expires_at").map_err(db)?,
+        created_at: row.try_get("created_at").map_err(db)?,
+        channels,
+    })
+}
+
+async fn channels_of<'e>(
+    executor: impl sqlx::Executor<'e, Database = Any>,
+    invite: &str,
+) -> Result<Vec<ChannelId>> {
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT channel_id FROM invite_channels WHERE invite_id = $1 ORDER BY channel_id",
+    )
+    .bind(invite)
+    .fetch_all(executor)
+    .await
+    .map_err(db)?;
+    ids.iter()
+        .map(|c| c.parse())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| Error::Other("invalid channel id".to_owned()))
+}
+
+/// Mint an invite, returning it and its plaintext code (shown once). `Validation` for zero uses, a
+/// lifetime over [`MAX_TTL`] or a channel whose kind does not accept users; `NotFound` for a channel
+/// that does not exist.
+pub async fn mint(
+    store: &Store,
+    created_by: Option<UserId>,
+    spec: NewInvite,
+) -> Result<(Invite, String)> {
+    if spec.uses == 0 {
+        return Err(Error::Validation(
+            "an invite needs at least one use".to_owned(),
+        ));
+    }
+    let expires_at = match spec.ttl {
+        None => None,
+        Some(ttl) => match SystemTime::now().checked_add(ttl) {
+            Some(at) if ttl <= MAX_TTL => Some(crate::db::timestamp(at)),
+            _ => {
+                return Err(Error::Validation(format!(
+                    "an invite lasts at most {} days",
+                    MAX_TTL.as_secs() / 86_400
+                )))
+            }
+        },
+    };
+    for &cid in &spec.channels {
+        let ch = store.get_channel(cid).await?.ok_or(Error::NotFound)?;
+        let accepts = store
+            .registry()
+            .channel(&ch.type_id)
+            .is_some_and(|kind| kind.membership().is_some());
+        if !accepts {
+            return Err(Error::Validation(format!(
+                "channel-type `{}` does not accept users",
+                ch.type_id
+            )));
+        }
+    }
+    let code = random_code();
+    let id = ulid::Ulid::new().to_string();
+    let pool = store.pool();
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    sqlx::query(
+        "INSERT INTO invites (id, code_hash, created_by, uses_left, expires_at, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(&id)
+    .bind(auth::sha256_hex(code.as_bytes()))
+    .bind(created_by.map(|u| u.to_string()))
+    .bind(i64::from(spec.uses))
+    .bind(expires_at)
+    .bind(crate::db::now())
+    .execute(&mut *tx)
+    .await
+    .map_err(db)?;
+    for cid in &spec.channels {
+        sqlx::query(
+            "INSERT INTO invite_channels (invite_id, channel_id) VALUES ($1, $2) \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(&id)
+        .bind(cid.to_string())
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    }
+    tx.commit().await.map_err(db)?;
+    let invite = get(pool, &id).await?.ok_or(Error::NotFound)?;
+    Ok((invite, code))
+}
+
+/// One invite by id, or `None`.
+pub async fn get(pool: &AnyPool, id: &str) -> Result<Option<Invite>> {
+    let row = sqlx::query(
+        "SELECT id, created_by, uses_left, expires_at, created_at FROM invites WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db)?;
+    match row {
+        Some(row) => Ok(Some(invite_from_row(&row, channels_of(pool, id).await?)?)),
+        None => Ok(None),
+    }
+}
+
+/// The invites still open (unexpired, uses left), oldest first — every one, or only `created_by`'s.
+pub async fn list(pool: &AnyPool, created_by: Option<UserId>) -> Result<Vec<Invite>> {
+    let mut sql = "SELECT id, created_by, uses_left, expires_at, created_at FROM invites \
+                   WHERE uses_left > 0 AND (expires_at IS NULL OR expires_at > $1)"
+        .to_owned();
+    if created_by.is_some() {
+        sql.push_str(" AND created_by = $2");
+    }
+    sql.push_str(" ORDER BY id");
+    let mut query = sqlx::query(&sql).bind(crate::db::now());
+    if let Some(user) = created_by {
+        query = query.bind(user.to_string());
+    }
+    let rows = query.fetch_all(pool).await.map_err(db)?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let id: String = row.try_get("id").map_err(db)?;
+        out.push(invite_from_row(row, channels_of(pool, &id).await?)?);
+    }
+    Ok(out)
+}
+
+/// Withdraw an invite. `NotFound` if there is no such invite.
+pub async fn revoke(pool: &AnyPool, id: &str) -> Result<()> {
+    let affected = sqlx::query("DELETE FROM invites WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    if affected == 0 {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+/// The one answer for a code that is unknown, spent or expired, so a caller cannot tell them apart.
+fn bad_code() -> Error {
+    Error::Validation("invalid invite code".to_owned())
+}
+
+/// Self-register: check the handle and password against the `auth` policies, then in one write spend a
+/// use of `code` and create the user with that password, deleting the invite with its last use. Then
+/// join the invite's channels. `Validation` for a policy failure, a bad code or a taken handle — in that
+/// order, so only a valid code learns whether a handle is taken; if the write fails, nothing is spent
+/// and no user exists. The joins come after: the account is made by then, so a channel that has since
+/// been deleted is skipped and one whose kind refuses the user is logged, never failing the call.
+pub async fn register(store: &Store, code: &str, handle: &str, password: &str) -> Result<User> {
+    auth::validate_handle(handle)?;
+    auth::validate_password(handle, password)?;
+    let hash = auth::hash_password(password)?;
+    let pool = store.pool();
+
+    let code_hash = auth::sha256_hex(code.as_bytes());
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let invite: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM invites \
+         WHERE code_hash = $1 AND uses_left > 0 AND (expires_at IS NULL OR expires_at > $2)",
+    )
+    .bind(&code_hash)
+    .bind(crate::db::now())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db)?;
+    let Some(invite) = invite else {
+        return Err(bad_code());
+    };
+    let user_id = auth::insert_user(&mut *tx, handle, Some(&hash)).await?;
+    let channels = channels_of(&mut *tx, &invite).await?;
+    sqlx::query("UPDATE invites SET uses_left = uses_left - 1 WHERE id = $1")
+        .bind(&invite)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    sqlx::query("DELETE FROM invites WHERE id = $1 AND uses_left <= 0")
+        .bind(&invite)
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    tx.commit().await.map_err(db)?;
+
+    let writer = store.acting_as(Actor::User(user_id));
+    for cid in channels {
+        if let Err(e) = join(store, &writer, cid, user_id).await {
+            tracing::warn!(%cid, user = %user_id, error = %e, "register: could not join an invite channel");
+        }
+    }
+    Ok(User {
+        id: user_id,
+        handle: handle.to_owned(),
+    })
+}
+
+/// Add a newly registered user to one of their invite's channels through its kind's `Membership`.
+/// A channel that is gone, or whose kind no longer accepts users, is skipped.
+async fn join(store: &Store, writer: &Store, cid: ChannelId, user: UserId) -> Result<()> {
+    let Some(ch) = writer.get_channel(cid).await? else {
+        return Ok(());
+    };
+    match store
+        .registry()
+        .channel(&ch.type_id)
+        .and_then(|kind| kind.membership())
+    {
+        Some(membership) => membership.add_user(writer, &ch, user).await,
+        None => Ok(()),
+    }
+}
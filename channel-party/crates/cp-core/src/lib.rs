@@ -7,32 +7,38 @@
 //! wired in exactly one place, the composition root (`cp-bin`). Store primitives and several
 //! mechanisms are stubbed in the scaffold; DESIGN §5/§6/§7/§8 fill them in.
 
+pub mod archive;
 pub mod auth;
 pub mod authz;
+pub mod change_log;
 pub mod contents;
+pub mod db;
 pub mod debug;
 pub mod events;
 pub mod index;
+pub mod invites;
 pub mod links;
 pub mod migrate;
 pub mod registry;
 pub mod runtime;
 pub mod store;
+pub mod throttle;
+pub mod tokens;
+pub mod totp;
+pub mod trash;
 
-use std::str::FromStr;
 use std::sync::Arc;
 
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use sqlx::SqlitePool;
+use sqlx::AnyPool;
 
 pub use cp_model::{Migration, Migrations};
 pub use events::{ChangeEvent, ChangeOp, EnvelopeRef, EventBus};
-pub use registry::{Registry, RegistryBuilder};
+pub use registry::{KindCommand, Registry, RegistryBuilder};
 pub use store::Store;
 
 /// The running core: the store handle, the registry, and the event bus. §10.
 pub struct Core {
-    pool: SqlitePool,
+    pool: AnyPool,
     registry: Registry,
     store: Arc<Store>,
     events: EventBus,
@@ -40,14 +46,10 @@ pub struct Core {
 
 impl Core {
     /// Open the store at `db_url` (a sqlite URL, e.g. `sqlite:channel-party.db` or
-    /// `sqlite::memory:`), run the core + kind migrations, and return a ready Core. §3/§10.
+    /// `sqlite::memory:`, or a `postgres://` one), run the core + kind migrations, and return a ready
+    /// Core. §3/§10.
     pub async fn open(db_url: &str, registry: Registry) -> anyhow::Result<Self> {
-        // `foreign_keys(true)` makes the `container` FK + `ON DELETE CASCADE` (and the
-        // channel_members FKs) actually enforce — the write path relies on it. §3.
-        let opts = SqliteConnectOptions::from_str(db_url)?
-            .create_if_missing(true)
-            .foreign_keys(true);
-        let pool = SqlitePoolOptions::new().connect_with(opts).await?;
+        let pool = db::connect(db_url).await?;
         migrate::run(&pool, &registry).await?;
         let events = EventBus::new();
         let store = Arc::new(Store::new(pool.clone(), registry.clone(), events.clone()));
@@ -72,10 +74,18 @@ impl Core {
         &self.events
     }
 
-    pub fn pool(&self) -> &SqlitePool {
+    pub fn pool(&self) -> &AnyPool {
         &self.pool
     }
 
+    /// Tail the change log every `every` and republish, on this process's bus, changes committed by
+    /// other processes on the same database — so a running server's SSE clients and runtime components
+    /// see writes from `channel-party shell` live. Dropping the handle stops it. §7.
+    #[must_use]
+    pub fn spawn_relay(&self, every: std::time::Duration) -> change_log::RelayHandle {
+        change_log::relay(self.pool.clone(), self.events.clone(), every)
+    }
+
     /// Supervise every registered `RuntimeComponent` (backfill-then-stream). Returns a handle that
     /// keeps the tasks alive — dropping it aborts them, so the caller must hold it. §7/§10.
     #[must_use]
@@ -6,14 +6,14 @@
 //! endpoints call. Sibling to `auth`.
 
 use cp_model::{Error, Item, ItemId, Result, TypeId, User, UserId};
-use sqlx::sqlite::SqliteRow;
-use sqlx::{Row, SqlitePool};
+use sqlx::any::AnyRow;
+use sqlx::{AnyPool, Row};
 
 fn db(e: sqlx::Error) -> Error {
     Error::Other(e.to_string())
 }
 
-fn item_from_row(row: &SqliteRow) -> Result<Item> {
+fn item_from_row(row: &AnyRow) -> Result<Item> {
     let container: Option<String> = row.try_get("container").map_err(db)?;
     Ok(Item {
         id: row
@@ -29,10 +29,11 @@ fn item_from_row(row: &SqliteRow) -> Result<Item> {
         external_key: row.try_get("external_key").map_err(db)?,
         payload: serde_json::from_str(&row.try_get::<String, _>("payload").map_err(db)?)
             .map_err(|e| Error::Other(e.to_string()))?,
+        rev: row.try_get("rev").map_err(db)?,
     })
 }
 
-fn user_from_row(row: &SqliteRow) -> Result<User> {
+fn user_from_row(row: &AnyRow) -> Result<User> {
     Ok(User {
         id: row
             .try_get::<String, _>("id")
@@ -46,7 +47,7 @@ fn user_from_row(row: &SqliteRow) -> Result<User> {
 /// Link a native user to an external item. `NotFound` if the item does not exist. Idempotent for the
 /// *same* user; a `Validation` conflict if the item is already linked to a *different* user (an external
 /// identity resolves up to at most one native user — §2). Never silently ignores that conflict.
-pub async fn link(pool: &SqlitePool, user: UserId, item: ItemId) -> Result<()> {
+pub async fn link(pool: &AnyPool, user: UserId, item: ItemId) -> Result<()> {
     if !item_exists(pool, item).await? {
         return Err(Error::NotFound);
     }
@@ -59,7 +60,7 @@ pub async fn link(pool: &SqlitePool, user: UserId, item: ItemId) -> Result<()> {
             existing.handle
         )));
     }
-    sqlx::query("INSERT INTO user_external_links (user_id, item_id) VALUES (?, ?)")
+    sqlx::query("INSERT INTO user_external_links (user_id, item_id) VALUES ($1, $2)")
         .bind(user.to_string())
         .bind(item.to_string())
         .execute(pool)
@@ -69,8 +70,8 @@ pub async fn link(pool: &SqlitePool, user: UserId, item: ItemId) -> Result<()> {
 }
 
 /// Remove a link. A no-op if it does not exist.
-pub async fn unlink(pool: &SqlitePool, user: UserId, item: ItemId) -> Result<()> {
-    sqlx::query("DELETE FROM user_external_links WHERE user_id = ? AND item_id = ?")
+pub async fn unlink(pool: &AnyPool, user: UserId, item: ItemId) -> Result<()> {
+    sqlx::query("DELETE FROM user_external_links WHERE user_id = $1 AND item_id = $2")
         .bind(user.to_string())
         .bind(item.to_string())
         .execute(pool)
@@ -80,11 +81,11 @@ pub async fn unlink(pool: &SqlitePool, user: UserId, item: ItemId) -> Result<()>
 }
 
 /// Forward: the external items a native user is linked to (its `linked-users`). §2.
-pub async fn linked_items(pool: &SqlitePool, user: UserId) -> Result<Vec<Item>> {
+pub async fn linked_items(pool: &AnyPool, user: UserId) -> Result<Vec<Item>> {
     let rows = sqlx::query(
-        "SELECT i.id, i.type_id, i.container, i.external_key, i.payload \
+        "SELECT i.id, i.type_id, i.container, i.external_key, i.payload, i.rev \
          FROM user_external_links l JOIN items i ON i.id = l.item_id \
-         WHERE l.user_id = ? ORDER BY i.id",
+         WHERE l.user_id = $1 AND i.trash_root IS NULL ORDER BY i.id",
     )
     .bind(user.to_string())
     .fetch_all(pool)
@@ -94,11 +95,11 @@ pub async fn linked_items(pool: &SqlitePool, user: UserId) -> Result<Vec<Item>>
 }
 
 /// Reverse: the native user an external item is linked to, if any — authorship resolution *up* the link
-/// (§2). `None` when the item is unlinked (or does not exist).
-pub async fn user_for_item(pool: &SqlitePool, item: ItemId) -> Result<Option<User>> {
+/// (§2). `None` when the item is unlinked (or does not exist, or is in the trash).
+pub async fn user_for_item(pool: &AnyPool, item: ItemId) -> Result<Option<User>> {
     let row = sqlx::query(
         "SELECT u.id, u.handle FROM user_external_links l JOIN users u ON u.id = l.user_id \
-         WHERE l.item_id = ?",
+         JOIN items i ON i.id = l.item_id WHERE l.item_id = $1 AND i.trash_root IS NULL",
     )
     .bind(item.to_string())
     .fetch_optional(pool)
@@ -107,8 +108,8 @@ pub async fn user_for_item(pool: &SqlitePool, item: ItemId) -> Result<Option<Use
     row.as_ref().map(user_from_row).transpose()
 }
 
-async fn item_exists(pool: &SqlitePool, item: ItemId) -> Result<bool> {
-    let row = sqlx::query("SELECT 1 FROM items WHERE id = ?")
+async fn item_exists(pool: &AnyPool, item: ItemId) -> Result<bool> {
+    let row = sqlx::query("SELECT 1 FROM items WHERE id = $1 AND trash_root IS NULL")
         .bind(item.to_string())
         .fetch_optional(pool)
         .await
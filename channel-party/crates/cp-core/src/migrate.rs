@@ -1,22 +1,262 @@
-//! The migrator. Runs core's init schema, then every registered kind-owned migration. §6/§10.
+//! The migrator. Applies core's migrations, then every registered kind-owned migration set, recording
+//! each applied step in the `_migrations` ledger so it runs exactly once. §6/§10.
+//!
+//! Every migration set is versioned independently under its `owner` (`"core"` for core's own, a kind's
+//! namespace for its `Migrations`): a set's version is simply how many of its steps the ledger records,
+//! so a kind can ship `0002_…` without core — or any other kind — changing. Each step runs in its own
+//! transaction together with its ledger row, so a failing `ALTER`/backfill leaves nothing half-applied.
+//! The ledger stores a SHA-256 of each step's SQL; editing an already-applied migration is refused at
+//! boot rather than silently diverging from the databases that ran the old text.
+//!
+//! Each set carries a step list per backend (`Migrations::steps_for`) and only the list matching the
+//! database runs; the ledger records whichever ran. Core's Postgres list starts with one step holding
+//! the schema sqlite reached over 0001–0010 (`migrations/postgres/`).
 //!
 //! Under Nix/crane the `.sql` files must be kept in the build source (the flake's `src` filter
 //! keeps `**/migrations/**`); a bare `cleanCargoSource` would strip them and `include_str!` would
 //! fail to compile — the same asset-filter concern as `andref-ipfs-depot`.
 
-use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use cp_model::{Backend, Migration, Migrations};
+use sha2::{Digest, Sha256};
+use sqlx::{AnyPool, Row};
 
 use crate::registry::Registry;
 
-/// Core's init schema (users / channels / items + the external-links edge). §3.
-const CORE_INIT: &str = include_str!("../migrations/0001_init.sql");
+/// The ledger owner core's own migrations are recorded under.
+pub const CORE_OWNER: &str = "core";
+
+/// Core's migrations (users / channels / items + the substrates). Append-only, like a kind's. §3.
+pub const CORE: Migrations = Migrations {
+    owner: CORE_OWNER,
+    steps: &[
+        Migration {
+            name: "0001_init",
+            sql: include_str!("../migrations/0001_init.sql"),
+        },
+        Migration {
+            name: "0002_revisions",
+            sql: include_str!("../migrations/0002_revisions.sql"),
+        },
+        Migration {
+            name: "0003_trash",
+            sql: include_str!("../migrations/0003_trash.sql"),
+        },
+        Migration {
+            name: "0004_envelope_rev",
+            sql: include_str!("../migrations/0004_envelope_rev.sql"),
+        },
+        Migration {
+            name: "0005_sort_index",
+            sql: include_str!("../migrations/0005_sort_index.sql"),
+        },
+        Migration {
+            name: "0006_spatial_index",
+            sql: include_str!("../migrations/0006_spatial_index.sql"),
+        },
+        Migration {
+            name: "0007_change_log",
+            sql: include_str!("../migrations/0007_change_log.sql"),
+        },
+        Migration {
+            name: "0008_runtime_checkpoint",
+            sql: include_str!("../migrations/0008_runtime_checkpoint.sql"),
+        },
+        Migration {
+            name: "0009_runtime_status",
+            sql: include_str!("../migrations/0009_runtime_status.sql"),
+        },
+        Migration {
+            name: "0010_operators",
+            sql: include_str!("../migrations/0010_operators.sql"),
+        },
+        Migration {
+            name: "0011_channel_roles",
+            sql: include_str!("../migrations/0011_channel_roles.sql"),
+        },
+        Migration {
+            name: "0012_invites",
+            sql: include_str!("../migrations/0012_invites.sql"),
+        },
+        Migration {
+            name: "0013_api_tokens",
+            sql: include_str!("../migrations/0013_api_tokens.sql"),
+        },
+        Migration {
+            name: "0014_session_details",
+            sql: include_str!("../migrations/0014_session_details.sql"),
+        },
+        Migration {
+            name: "0015_login_failures",
+            sql: include_str!("../migrations/0015_login_failures.sql"),
+        },
+        Migration {
+            name: "0016_totp",
+            sql: include_str!("../migrations/0016_totp.sql"),
+        },
+    ],
+    postgres: &[
+        Migration {
+            name: "0001_init",
+            sql: include_str!("../migrations/postgres/0001_init.sql"),
+        },
+        Migration {
+            name: "0011_channel_roles",
+            sql: include_str!("../migrations/postgres/0011_channel_roles.sql"),
+        },
+        Migration {
+            name: "0012_invites",
+            sql: include_str!("../migrations/postgres/0012_invites.sql"),
+        },
+        Migration {
+            name: "0013_api_tokens",
+            sql: include_str!("../migrations/postgres/0013_api_tokens.sql"),
+        },
+        Migration {
+            name: "0014_session_details",
+            sql: include_str!("../migrations/postgres/0014_session_details.sql"),
+        },
+        Migration {
+            name: "0015_login_failures",
+            sql: include_str!("../migrations/postgres/0015_login_failures.sql"),
+        },
+        Migration {
+            name: "0016_totp",
+            sql: include_str!("../migrations/postgres/0016_totp.sql"),
+        },
+    ],
+};
+
+/// The ledger itself. Bootstrapped (idempotently) before anything else, so it is not a migration.
+/// `applied_at` is written by [`apply`]; the sqlite default predates that and is kept for old ledgers.
+fn ledger(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Sqlite => {
+            "CREATE TABLE IF NOT EXISTS _migrations (
+                owner      TEXT NOT NULL,
+                name       TEXT NOT NULL,
+                checksum   TEXT NOT NULL,
+                applied_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+                PRIMARY KEY (owner, name)
+            )"
+        }
+        Backend::Postgres => {
+            "CREATE TABLE IF NOT EXISTS _migrations (
+                owner      TEXT NOT NULL,
+                name       TEXT NOT NULL,
+                checksum   TEXT NOT NULL,
+                applied_at TEXT NOT NULL,
+                PRIMARY KEY (owner, name)
+            )"
+        }
+    }
+}
+
+/// One row of the `_migrations` ledger. Read by the debug shell's `show migrations`. §8.
+#[derive(Clone, Debug)]
+pub struct AppliedMigration {
+    pub owner: String,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: String,
+}
+
+/// SHA-256 hex of a migration's SQL — the edit detector.
+fn checksum(sql: &str) -> String {
+    format!("{:x}", Sha256::digest(sql.as_bytes()))
+}
+
+/// Apply every pending migration: core's first, then each registered kind set in registration order.
+/// Already-applied steps are skipped after their checksum is verified. A set that ships no steps for
+/// this backend (while having some for the other) is refused before anything runs. §6/§10.
+pub async fn run(pool: &AnyPool, registry: &Registry) -> anyhow::Result<()> {
+    let backend = Backend::of(pool);
+    if let Some(set) = registry
+        .migrations()
+        .iter()
+        .find(|set| set.steps_for(backend).is_empty() && !set.steps.is_empty())
+    {
+        anyhow::bail!(
+            "`{}` ships no {} migrations; it cannot run on this database",
+            set.owner,
+            backend.as_str()
+        );
+    }
+    sqlx::raw_sql(ledger(backend)).execute(pool).await?;
+    apply(pool, &CORE, backend).await?;
+    for set in registry.migrations() {
+        apply(pool, set, backend).await?;
+    }
+    Ok(())
+}
+
+/// Apply one owner's pending steps, in declaration order, each in its own transaction.
+async fn apply(pool: &AnyPool, set: &Migrations, backend: Backend) -> anyhow::Result<()> {
+    let rows = sqlx::query("SELECT name, checksum FROM _migrations WHERE owner = $1")
+        .bind(set.owner)
+        .fetch_all(pool)
+        .await?;
+    let mut recorded = HashMap::new();
+    for row in &rows {
+        recorded.insert(
+            row.try_get::<String, _>("name")?,
+            row.try_get::<String, _>("checksum")?,
+        );
+    }
 
-/// Run core's init migration, then each registered kind migration in registration order. The SQL
-/// is idempotent, so re-running on every boot is safe in the scaffold. §6/§10.
-pub async fn run(pool: &SqlitePool, registry: &Registry) -> anyhow::Result<()> {
-    sqlx::raw_sql(CORE_INIT).execute(pool).await?;
-    for migration in registry.migrations() {
-        sqlx::raw_sql(migration.sql).execute(pool).await?;
+    for step in set.steps_for(backend) {
+        let sum = checksum(step.sql);
+        if let Some(stored) = recorded.get(step.name) {
+            if *stored != sum {
+                anyhow::bail!(
+                    "migration {}/{} was edited after it was applied (checksum {} != recorded {}); \
+                     ship a new migration instead",
+                    set.owner,
+                    step.name,
+                    &sum[..12],
+                    &stored[..stored.len().min(12)],
+                );
+            }
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(step.sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| anyhow::anyhow!("migration {}/{} failed: {e}", set.owner, step.name))?;
+        sqlx::query(
+            "INSERT INTO _migrations (owner, name, checksum, applied_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(set.owner)
+        .bind(step.name)
+        .bind(&sum)
+        .bind(crate::db::now())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        tracing::info!(owner = set.owner, name = step.name, "applied migration");
     }
     Ok(())
 }
+
+/// The ledger, ordered by owner then application order. §8.
+pub async fn applied(pool: &AnyPool) -> cp_model::Result<Vec<AppliedMigration>> {
+    let db = |e: sqlx::Error| cp_model::Error::Other(e.to_string());
+    let rows = sqlx::query(
+        "SELECT owner, name, checksum, applied_at FROM _migrations ORDER BY owner, applied_at, name",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter()
+        .map(|r| {
+            Ok(AppliedMigration {
+                owner: r.try_get("owner").map_err(db)?,
+                name: r.try_get("name").map_err(db)?,
+                checksum: r.try_get("checksum").map_err(db)?,
+                applied_at: r.try_get("applied_at").map_err(db)?,
+            })
+        })
+        .collect()
+}
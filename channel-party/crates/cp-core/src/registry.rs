@@ -5,7 +5,9 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use cp_model::{ChannelKind, ItemKind, Migration, Migrations, RuntimeComponent, TypeId};
+use cp_model::{
+    ChannelKind, DebugCommand, ItemKind, Migrations, Result, RuntimeComponent, TypeId, WriteCtx,
+};
 
 /// The resolved registry: two type-keyed kind tables, the runtime components, and all migrations.
 /// Cloneable (cheap: trait objects behind `Arc`) so it can be shared by core and the frontend. §10.
@@ -14,7 +16,7 @@ pub struct Registry {
     channels: HashMap<TypeId, Arc<dyn ChannelKind>>,
     items: HashMap<TypeId, Arc<dyn ItemKind>>,
     runtimes: Vec<Arc<dyn RuntimeComponent>>,
-    migrations: Vec<Migration>,
+    migrations: Vec<Migrations>,
 }
 
 impl Registry {
@@ -36,9 +38,70 @@ impl Registry {
         &self.runtimes
     }
 
-    pub fn migrations(&self) -> &[Migration] {
+    /// Every registered kind migration set, in registration order. Each applies independently (§6).
+    pub fn migrations(&self) -> &[Migrations] {
         &self.migrations
     }
+
+    /// Every kind-contributed debug command, sorted by namespace then name — what the shell lists and
+    /// dispatches `<namespace> <name> <args…>` to. §8.
+    pub fn debug_commands(&self) -> Vec<KindCommand> {
+        let channels = self.channels.values().flat_map(|kind| {
+            kind.debug_commands()
+                .into_iter()
+                .map(|command| KindCommand {
+                    namespace: kind.type_id().namespace().to_owned(),
+                    command,
+                    kind: CommandKind::Channel(kind.clone()),
+                })
+        });
+        let items = self.items.values().flat_map(|kind| {
+            kind.debug_commands()
+                .into_iter()
+                .map(|command| KindCommand {
+                    namespace: kind.type_id().namespace().to_owned(),
+                    command,
+                    kind: CommandKind::Item(kind.clone()),
+                })
+        });
+        let mut all: Vec<_> = channels.chain(items).collect();
+        all.sort_by(|a, b| (&a.namespace, &a.command.name).cmp(&(&b.namespace, &b.command.name)));
+        all
+    }
+}
+
+/// A kind-contributed debug command, resolved to the kind that runs it. §8.
+#[derive(Clone)]
+pub struct KindCommand {
+    /// The invocation prefix: the contributing kind's type-id namespace.
+    pub namespace: String,
+    pub command: DebugCommand,
+    kind: CommandKind,
+}
+
+#[derive(Clone)]
+enum CommandKind {
+    Channel(Arc<dyn ChannelKind>),
+    Item(Arc<dyn ItemKind>),
+}
+
+impl KindCommand {
+    /// The type id of the kind that contributed it.
+    pub fn type_id(&self) -> &TypeId {
+        match &self.kind {
+            CommandKind::Channel(kind) => kind.type_id(),
+            CommandKind::Item(kind) => kind.type_id(),
+        }
+    }
+
+    /// Run it through its kind's hook. Gating on `command.access` is the caller's job. §8.
+    pub async fn run(&self, cx: &dyn WriteCtx, args: &[String]) -> Result<String> {
+        let name = &self.command.name;
+        match &self.kind {
+            CommandKind::Channel(kind) => kind.run_debug_command(cx, name, args).await,
+            CommandKind::Item(kind) => kind.run_debug_command(cx, name, args).await,
+        }
+    }
 }
 
 /// Fluent builder mirroring the composition-root example in DESIGN §10.
@@ -88,9 +151,9 @@ impl RegistryBuilder {
         self
     }
 
-    /// Register a crate's kind-owned migrations. §6.
+    /// Register a crate's kind-owned migrations, versioned under its own `owner`. §6.
     pub fn migrations(mut self, migrations: Migrations) -> Self {
-        self.inner.migrations.extend(migrations.0.iter().copied());
+        self.inner.migrations.push(migrations);
         self
     }
 
@@ -1,21 +1,28 @@
 //! The one `RuntimeComponent` supervisor. Each component is a supervised, long-lived task that
 //! backfills then reacts to the change stream ("backfill-then-stream"). This module provides the
 //! concrete `RuntimeCtx` core hands each component and the supervisor that keeps it alive (restart +
-//! backoff), confines its writes by `WriteScope`, and drives `version()`-triggered resets. See DESIGN
-//! §7 and `design/runtime.md`.
+//! backoff), confines its writes by `WriteScope`, drives `version()`-triggered resets, and persists each
+//! component's offset in the durable change log so a restart resumes its stream where it stopped. A
+//! component's own progress (e.g. how far its backfill got) persists as an opaque checkpoint, dropped
+//! whenever a reset is due. The supervisor records each component's status (state, restarts, last
+//! error, last healthy run) for operators and takes their `restart`/`reset` requests, both through
+//! `runtime_component_state` so the shell in another process sees and steers the server's components.
+//! See DESIGN §7 and `design/runtime.md`.
 
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use cp_model::{
-    ChangeEvent, Channel, ChannelId, Interests, Item, ItemId, Node, Result, RuntimeComponent,
-    RuntimeCtx, RuntimeEvent, TypeId, WriteCtx, WriteScope,
+    Actor, ChangeEvent, Channel, ChannelId, Cursor, Interests, Item, ItemId, Json, Node, NodePage,
+    Result, RuntimeComponent, RuntimeCtx, RuntimeEvent, TypeId, WriteCtx, WriteScope,
 };
-use sqlx::SqlitePool;
-use tokio::sync::{broadcast, watch, Mutex};
+use sqlx::{AnyPool, Row};
+use tokio::sync::{watch, Mutex};
 use tokio::task::JoinSet;
 use tokio::time::{interval, Interval};
 
+use crate::change_log::{self, Follow};
 use crate::events::EventBus;
 use crate::registry::Registry;
 use crate::store::Store;
@@ -25,27 +32,36 @@ const BACKOFF_MIN: Duration = Duration::from_millis(200);
 const BACKOFF_MAX: Duration = Duration::from_secs(30);
 /// A run that lasted at least this long is treated as healthy: its next failure restarts from the floor.
 const HEALTHY_RUN: Duration = Duration::from_secs(30);
+/// How often a supervisor checks for operator requests (which may come from another process).
+const CONTROL_POLL: Duration = Duration::from_millis(500);
+/// Envelopes per `scan_since` page.
+const SCAN_PAGE: u32 = 500;
 
 /// The concrete context core hands a component: the interests-filtered change stream + scheduler, point
 /// reads, the `WriteScope`-confined write surface, the type-owned DB handle, and the reset flag. §7.
 pub struct CoreRuntimeCtx {
+    name: String,
     types: Vec<TypeId>,
     writes: WriteScope,
     store: Arc<Store>,
-    changes: Mutex<broadcast::Receiver<ChangeEvent>>,
+    changes: Mutex<Follow>,
+    /// The log offset last written to `runtime_component_state`.
+    saved: AtomicI64,
     interval: Option<Mutex<Interval>>,
-    pool: SqlitePool,
+    pool: AnyPool,
     shutdown: watch::Receiver<bool>,
     reset: bool,
 }
 
 impl CoreRuntimeCtx {
+    #[allow(clippy::too_many_arguments)]
     fn new(
+        name: String,
         interests: Interests,
         writes: WriteScope,
         store: Arc<Store>,
-        changes: broadcast::Receiver<ChangeEvent>,
-        pool: SqlitePool,
+        changes: Follow,
+        pool: AnyPool,
         shutdown: watch::Receiver<bool>,
         reset: bool,
     ) -> Self {
@@ -54,9 +70,11 @@ impl CoreRuntimeCtx {
             .schedule_secs
             .map(|s| Mutex::new(interval(Duration::from_secs(s.max(1)))));
         Self {
+            name,
             types: interests.types,
             writes,
             store,
+            saved: AtomicI64::new(changes.offset()),
             changes: Mutex::new(changes),
             interval,
             pool,
@@ -69,26 +87,51 @@ impl CoreRuntimeCtx {
         // Empty `types` ⇒ reacts to no change events (a schedule-only or pure-backfill component).
         self.types.contains(&ev.type_id)
     }
+
+    /// Persist the stream offset once it has moved. Called on entry to `next_event`, i.e. once the
+    /// component has finished with everything handed out before — so a crash redelivers, never skips.
+    async fn save_offset(&self, offset: i64) {
+        if self.saved.load(Ordering::Relaxed) == offset {
+            return;
+        }
+        let saved =
+            sqlx::query("UPDATE runtime_component_state SET log_offset = $1 WHERE name = $2")
+                .bind(offset)
+                .bind(&self.name)
+                .execute(&self.pool)
+                .await;
+        match saved {
+            Ok(_) => self.saved.store(offset, Ordering::Relaxed),
+            Err(e) => {
+                tracing::warn!(component = self.name, error = %e, "runtime: saving offset failed")
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl RuntimeCtx for CoreRuntimeCtx {
     async fn next_event(&self) -> Option<RuntimeEvent> {
         let mut shutdown = self.shutdown.clone();
+        let mut changes = self.changes.lock().await;
+        self.save_offset(changes.offset()).await;
         loop {
             if *shutdown.borrow() {
                 return None;
             }
-            let mut rx = self.changes.lock().await;
             tokio::select! {
                 biased;
                 () = wait_flag(&mut shutdown) => return None,
                 () = tick(self.interval.as_ref()) => return Some(RuntimeEvent::Tick),
-                recv = rx.recv() => match recv {
-                    Ok(ev) if self.interested(&ev) => return Some(RuntimeEvent::Change(ev)),
-                    Ok(_) => {}                                        // not of interest → keep waiting
-                    Err(broadcast::error::RecvError::Lagged(_)) => {}  // fell behind → skip, re-sync
-                    Err(broadcast::error::RecvError::Closed) => return None,
+                next = changes.next() => match next {
+                    Ok(Some(ev)) if self.interested(&ev) => return Some(RuntimeEvent::Change(ev)),
+                    Ok(Some(_)) => {}           // not of interest → keep waiting
+                    Ok(None) => return None,    // bus closed
+                    Err(e) => {
+                        // The log is unreadable for now; retry rather than end the component's run.
+                        tracing::warn!(component = self.name, error = %e, "runtime: change log read failed");
+                        tokio::time::sleep(BACKOFF_MIN).await;
+                    }
                 },
             }
         }
@@ -106,6 +149,36 @@ impl RuntimeCtx for CoreRuntimeCtx {
         self.store.scan_by_types(types).await
     }
 
+    async fn scan_since(&self, types: &[TypeId], cursor: Cursor) -> Result<NodePage> {
+        self.store.scan_since(types, &cursor, SCAN_PAGE).await
+    }
+
+    async fn checkpoint(&self, value: Json) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO runtime_component_checkpoint (name, value, updated_at) VALUES ($1, $2, $3) \
+             ON CONFLICT(name) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        )
+        .bind(&self.name)
+        .bind(value.to_string())
+        .bind(crate::db::now())
+        .execute(&self.pool)
+        .await
+        .map_err(db)?;
+        Ok(())
+    }
+
+    async fn last_checkpoint(&self) -> Result<Option<Json>> {
+        let value: Option<String> =
+            sqlx::query_scalar("SELECT value FROM runtime_component_checkpoint WHERE name = $1")
+                .bind(&self.name)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(db)?;
+        value
+            .map(|v| serde_json::from_str(&v).map_err(|e| cp_model::Error::Other(e.to_string())))
+            .transpose()
+    }
+
     fn writer(&self) -> Option<&dyn WriteCtx> {
         // Structural §7 confinement: a Derived component cannot obtain the envelope mutation API.
         match self.writes {
@@ -114,7 +187,7 @@ impl RuntimeCtx for CoreRuntimeCtx {
         }
     }
 
-    fn type_owned_db(&self) -> &SqlitePool {
+    fn type_owned_db(&self) -> &AnyPool {
         &self.pool
     }
 
@@ -159,12 +232,133 @@ impl RuntimeHandle {
     }
 }
 
+/// A supervised component's state, as recorded for operators. §7.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuntimeState {
+    Running,
+    /// Failed; waiting out the backoff before the next restart.
+    BackingOff,
+    /// Not supervised: shut down, or never started by a running process.
+    Stopped,
+}
+
+impl RuntimeState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuntimeState::Running => "running",
+            RuntimeState::BackingOff => "backing-off",
+            RuntimeState::Stopped => "stopped",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "running" => RuntimeState::Running,
+            "backing-off" => RuntimeState::BackingOff,
+            _ => RuntimeState::Stopped,
+        }
+    }
+}
+
+/// An operator request to a supervised component, from the shell or the admin API. Queued in the DB and
+/// taken by whichever process supervises the component, within `CONTROL_POLL`. §7.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// End the current run (or cut a backoff short) and start a fresh one.
+    Restart,
+    /// Restart with `reset_requested()` set, as a `version()` bump would, without bumping it.
+    Reset,
+}
+
+impl Control {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Control::Restart => "restart",
+            Control::Reset => "reset",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "restart" => Some(Control::Restart),
+            "reset" => Some(Control::Reset),
+            _ => None,
+        }
+    }
+}
+
+/// One component's supervision status, read by the shell's `show runtimes` and the admin API. §7.
+#[derive(Clone, Debug)]
+pub struct RuntimeStatus {
+    pub name: String,
+    pub state: RuntimeState,
+    /// The `version()` recorded at the component's last start.
+    pub version: u32,
+    /// Restarts since the supervising process booted — after failures and operator requests alike.
+    pub restarts: u64,
+    pub last_error: Option<String>,
+    /// When a run was last seen healthy (alive for at least `HEALTHY_RUN`), if ever.
+    pub last_healthy_at: Option<String>,
+    /// When `state` last changed.
+    pub updated_at: Option<String>,
+    /// A request not yet taken by the supervisor.
+    pub pending: Option<Control>,
+}
+
+/// Every component core has supervised on this database, by name. §7.
+pub async fn statuses(pool: &AnyPool) -> Result<Vec<RuntimeStatus>> {
+    let rows = sqlx::query(
+        "SELECT name, state, version, restarts, last_error, last_healthy_at, updated_at, control \
+         FROM runtime_component_state ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter()
+        .map(|r| {
+            let state: String = r.try_get("state").map_err(db)?;
+            let version: i64 = r.try_get("version").map_err(db)?;
+            let restarts: i64 = r.try_get("restarts").map_err(db)?;
+            let control: Option<String> = r.try_get("control").map_err(db)?;
+            Ok(RuntimeStatus {
+                name: r.try_get("name").map_err(db)?,
+                state: RuntimeState::parse(&state),
+                version: version as u32,
+                restarts: restarts.max(0) as u64,
+                last_error: r.try_get("last_error").map_err(db)?,
+                last_healthy_at: r.try_get("last_healthy_at").map_err(db)?,
+                updated_at: r.try_get("updated_at").map_err(db)?,
+                pending: control.as_deref().and_then(Control::parse),
+            })
+        })
+        .collect()
+}
+
+/// Queue an operator request for the named component. A pending reset is never downgraded to a plain
+/// restart. `NotFound` if core has never supervised a component by that name. §7.
+pub async fn request(pool: &AnyPool, name: &str, control: Control) -> Result<()> {
+    let queued = sqlx::query(
+        "UPDATE runtime_component_state \
+         SET control = CASE WHEN control = 'reset' THEN 'reset' ELSE $1 END WHERE name = $2",
+    )
+    .bind(control.as_str())
+    .bind(name)
+    .execute(pool)
+    .await
+    .map_err(db)?
+    .rows_affected();
+    if queued == 0 {
+        return Err(cp_model::Error::NotFound);
+    }
+    Ok(())
+}
+
 /// Supervise every registered component: one task each, restarted with capped backoff on failure. §7/§10.
 pub fn spawn(
     registry: Registry,
     store: Arc<Store>,
     events: EventBus,
-    pool: SqlitePool,
+    pool: AnyPool,
 ) -> RuntimeHandle {
     let (sd_tx, sd_rx) = watch::channel(false);
     let mut tasks = JoinSet::new();
@@ -182,79 +376,294 @@ pub fn spawn(
     }
 }
 
+/// How a component's run ended, as far as the supervisor is concerned.
+enum Outcome {
+    /// A clean exit (shutdown): not restarted.
+    Exited,
+    Failed(String),
+    /// An operator asked for a restart or reset; the run was dropped.
+    Control(Control),
+}
+
 async fn supervise(
     component: Arc<dyn RuntimeComponent>,
     store: Arc<Store>,
     events: EventBus,
-    pool: SqlitePool,
+    pool: AnyPool,
     mut shutdown: watch::Receiver<bool>,
 ) {
     let name = component.name().to_owned();
+    // Everything this component writes is attributed to it in the revision history.
+    let store = Arc::new(store.acting_as(Actor::Component(name.clone())));
     // A version bump since last boot means reset; persist the new version either way.
-    let reset = match reconcile_version(&pool, &name, component.version()).await {
+    let mut reset = match reconcile_version(&pool, &name, component.version()).await {
         Ok(reset) => reset,
         Err(e) => {
             tracing::error!(component = name, error = %e, "runtime: version reconcile failed");
             false
         }
     };
+    // A reset requested while nothing supervised the component still applies; a restart is moot.
+    if let Ok(Some(Control::Reset)) = take_control(&pool, &name).await {
+        reset |= force_reset(&pool, &name).await;
+    }
 
     let mut backoff = BACKOFF_MIN;
     loop {
         if *shutdown.borrow() {
             break;
         }
-        let cx = CoreRuntimeCtx::new(
-            component.interests(),
-            component.writes(),
-            store.clone(),
-            events.subscribe(),
-            pool.clone(),
-            shutdown.clone(),
-            reset,
-        );
+        record(&pool, &name, RuntimeState::Running, None).await;
+        // Resume the stream after the persisted offset: changes committed while the component was down
+        // (crashed, backing off, or the process stopped) are replayed from the log.
         let started = Instant::now();
-        match component.run(&cx).await {
-            Ok(()) => break, // clean exit (driven by shutdown via next_event → None)
+        let outcome = match resume(&pool, &events, &name).await {
             Err(e) => {
-                tracing::error!(component = name, error = %e, "runtime component failed; restarting")
+                tracing::error!(component = name, error = %e, "runtime: resuming the change log failed");
+                Outcome::Failed(e.to_string())
             }
+            Ok(changes) => {
+                // Changes the log no longer holds can only be recovered by rebuilding.
+                if changes.missed() {
+                    if let Err(e) = forget_progress(&pool, &name).await {
+                        tracing::error!(component = name, error = %e, "runtime: dropping the checkpoint failed");
+                    }
+                    reset = true;
+                }
+                let cx = CoreRuntimeCtx::new(
+                    name.clone(),
+                    component.interests(),
+                    component.writes(),
+                    store.clone(),
+                    changes,
+                    pool.clone(),
+                    shutdown.clone(),
+                    reset,
+                );
+                // An operator request drops the run at its next await, like an abort would: the stream
+                // offset is only saved once an event is handled, so nothing is skipped.
+                tokio::select! {
+                    run = component.run(&cx) => match run {
+                        Ok(()) => Outcome::Exited, // driven by shutdown via next_event → None
+                        Err(e) => {
+                            tracing::error!(component = name, error = %e, "runtime component failed; restarting");
+                            Outcome::Failed(e.to_string())
+                        }
+                    },
+                    control = await_control(&pool, &name, Some(started)) => Outcome::Control(control),
+                }
+            }
+        };
+        let mut control = match outcome {
+            Outcome::Exited => break,
+            Outcome::Control(control) => Some(control),
+            Outcome::Failed(error) => {
+                if *shutdown.borrow() {
+                    break;
+                }
+                // A long, healthy run resets the backoff; a fast-failing one keeps escalating.
+                if started.elapsed() >= HEALTHY_RUN {
+                    backoff = BACKOFF_MIN;
+                }
+                record(&pool, &name, RuntimeState::BackingOff, Some(&error)).await;
+                let cut_short = tokio::select! {
+                    () = tokio::time::sleep(backoff) => None,
+                    () = wait_flag(&mut shutdown) => break,
+                    control = await_control(&pool, &name, None) => Some(control),
+                };
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+                cut_short
+            }
+        };
+        // An operator restart starts over at once, from the backoff floor.
+        if let Some(control) = control.take() {
+            tracing::info!(component = name, ?control, "runtime: operator request");
+            if control == Control::Reset {
+                reset |= force_reset(&pool, &name).await;
+            }
+            backoff = BACKOFF_MIN;
         }
-        if *shutdown.borrow() {
-            break;
+        restarted(&pool, &name).await;
+    }
+    record(&pool, &name, RuntimeState::Stopped, None).await;
+}
+
+/// Resolve with the next operator request for `name`, polling every `CONTROL_POLL`. While a run is
+/// underway (`running_since`), also refresh `last_healthy_at` once it has lasted `HEALTHY_RUN`.
+async fn await_control(pool: &AnyPool, name: &str, running_since: Option<Instant>) -> Control {
+    let mut stamped: Option<Instant> = None;
+    loop {
+        tokio::time::sleep(CONTROL_POLL).await;
+        match take_control(pool, name).await {
+            Ok(Some(control)) => return control,
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(component = name, error = %e, "runtime: reading control requests failed")
+            }
         }
-        // A long, healthy run resets the backoff; a fast-failing one keeps escalating.
-        if started.elapsed() >= HEALTHY_RUN {
-            backoff = BACKOFF_MIN;
+        let healthy = running_since.is_some_and(|t| t.elapsed() >= HEALTHY_RUN);
+        if healthy && stamped.is_none_or(|t| t.elapsed() >= HEALTHY_RUN) {
+            stamped = Some(Instant::now());
+            let stamp = sqlx::query(
+                "UPDATE runtime_component_state SET last_healthy_at = $1 WHERE name = $2",
+            )
+            .bind(crate::db::now())
+            .bind(name)
+            .execute(pool)
+            .await;
+            if let Err(e) = stamp {
+                tracing::warn!(component = name, error = %e, "runtime: recording health failed");
+            }
         }
-        tokio::select! {
-            () = tokio::time::sleep(backoff) => {}
-            () = wait_flag(&mut shutdown) => break,
+    }
+}
+
+/// Take (and clear) a pending operator request. Cleared only if it is still the one read, so a request
+/// that lands in between is picked up by the next poll instead of being lost.
+async fn take_control(pool: &AnyPool, name: &str) -> Result<Option<Control>> {
+    let pending: Option<String> =
+        sqlx::query_scalar("SELECT control FROM runtime_component_state WHERE name = $1")
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+            .map_err(db)?
+            .flatten();
+    let Some(pending) = pending else {
+        return Ok(None);
+    };
+    let taken = sqlx::query(
+        "UPDATE runtime_component_state SET control = NULL WHERE name = $1 AND control = $2",
+    )
+    .bind(name)
+    .bind(&pending)
+    .execute(pool)
+    .await
+    .map_err(db)?
+    .rows_affected();
+    Ok((taken == 1).then(|| Control::parse(&pending)).flatten())
+}
+
+/// An operator reset: what a `version()` bump does at boot, without the bump — drop the checkpoint and
+/// restart the stream at the head (the component rebuilds from its backfill). Returns whether it took.
+async fn force_reset(pool: &AnyPool, name: &str) -> bool {
+    let reset = async {
+        let head = change_log::head(pool).await?;
+        sqlx::query("UPDATE runtime_component_state SET log_offset = $1 WHERE name = $2")
+            .bind(head)
+            .bind(name)
+            .execute(pool)
+            .await
+            .map_err(db)?;
+        clear_checkpoint(pool, name).await
+    };
+    match reset.await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!(component = name, error = %e, "runtime: reset failed");
+            false
         }
-        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+/// Record a state transition (and the error behind it, if any). Best-effort: status is for operators,
+/// so a failure to write it is logged rather than taken out on the component.
+async fn record(pool: &AnyPool, name: &str, state: RuntimeState, error: Option<&str>) {
+    let recorded = sqlx::query(
+        "UPDATE runtime_component_state SET state = $1, last_error = COALESCE($2, last_error), \
+         updated_at = $3 WHERE name = $4",
+    )
+    .bind(state.as_str())
+    .bind(error)
+    .bind(crate::db::now())
+    .bind(name)
+    .execute(pool)
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!(component = name, error = %e, "runtime: recording status failed");
+    }
+}
+
+async fn restarted(pool: &AnyPool, name: &str) {
+    let counted =
+        sqlx::query("UPDATE runtime_component_state SET restarts = restarts + 1 WHERE name = $1")
+            .bind(name)
+            .execute(pool)
+            .await;
+    if let Err(e) = counted {
+        tracing::warn!(component = name, error = %e, "runtime: recording status failed");
     }
 }
 
 /// Compare the component's `version()` to the stored one and persist the new value. Returns whether a
 /// reset is due — true only when a *different* prior version was recorded (a first-ever boot has nothing
-/// to reset). §7.
-async fn reconcile_version(pool: &SqlitePool, name: &str, version: u32) -> Result<bool> {
-    let db = |e: sqlx::Error| cp_model::Error::Other(e.to_string());
+/// to reset). A first boot or a reset starts the component's log offset at the current head: it
+/// rebuilds from a backfill, so there is nothing older to replay. A reset also drops its checkpoint.
+/// The restart count starts over with each boot. §7.
+async fn reconcile_version(pool: &AnyPool, name: &str, version: u32) -> Result<bool> {
     let stored: Option<i64> =
-        sqlx::query_scalar("SELECT version FROM runtime_component_state WHERE name = ?")
+        sqlx::query_scalar("SELECT version FROM runtime_component_state WHERE name = $1")
             .bind(name)
             .fetch_optional(pool)
             .await
             .map_err(db)?;
     let reset = matches!(stored, Some(v) if v as u32 != version);
+    let head = change_log::head(pool).await?;
     sqlx::query(
-        "INSERT INTO runtime_component_state (name, version) VALUES (?, ?) \
-         ON CONFLICT(name) DO UPDATE SET version = excluded.version",
+        "INSERT INTO runtime_component_state (name, version, log_offset) VALUES ($1, $2, $3) \
+         ON CONFLICT(name) DO UPDATE SET version = excluded.version, restarts = 0, \
+         log_offset = CASE WHEN $4 THEN excluded.log_offset \
+         ELSE runtime_component_state.log_offset END",
     )
     .bind(name)
     .bind(i64::from(version))
+    .bind(head)
+    .bind(reset)
     .execute(pool)
     .await
     .map_err(db)?;
+    if reset {
+        clear_checkpoint(pool, name).await?;
+    }
     Ok(reset)
 }
+
+/// The resumed stream missed changes: drop the checkpoint (the component rebuilds) and raise the offset
+/// to the horizon — everything at or below it is gone anyway, so a restart mid-rebuild keeps the
+/// rebuild's checkpoints instead of being told it missed changes all over again.
+async fn forget_progress(pool: &AnyPool, name: &str) -> Result<()> {
+    let horizon = change_log::horizon(pool).await?;
+    sqlx::query(
+        "UPDATE runtime_component_state \
+         SET log_offset = CASE WHEN log_offset < $1 THEN $1 ELSE log_offset END WHERE name = $2",
+    )
+    .bind(horizon)
+    .bind(name)
+    .execute(pool)
+    .await
+    .map_err(db)?;
+    clear_checkpoint(pool, name).await
+}
+
+async fn clear_checkpoint(pool: &AnyPool, name: &str) -> Result<()> {
+    sqlx::query("DELETE FROM runtime_component_checkpoint WHERE name = $1")
+        .bind(name)
+        .execute(pool)
+        .await
+        .map_err(db)?;
+    Ok(())
+}
+
+/// Follow the change log from the component's persisted offset.
+async fn resume(pool: &AnyPool, events: &EventBus, name: &str) -> Result<Follow> {
+    let offset: i64 =
+        sqlx::query_scalar("SELECT log_offset FROM runtime_component_state WHERE name = $1")
+            .bind(name)
+            .fetch_one(pool)
+            .await
+            .map_err(db)?;
+    change_log::follow(pool, events, Some(offset)).await
+}
+
+fn db(e: sqlx::Error) -> cp_model::Error {
+    cp_model::Error::Other(e.to_string())
+}
@@ -3,28 +3,34 @@
 //! (`children`/`descendants`/`seek_time`, DESIGN §5). `search` is the one primitive still stubbed: it
 //! needs the FTS index substrate (`TODO.md` #3). See `design/write-path.md` and `design/read-path.md`.
 //!
-//! Queries use runtime-checked `sqlx::query` / `QueryBuilder` (not the `query!` macros), so no `.sqlx`
-//! offline cache is needed yet (`TODO.md` #21).
+//! Queries use runtime-checked `sqlx::query` / `cp_model::QueryBuilder` (not the `query!` macros), so no
+//! `.sqlx` offline cache is needed yet (`TODO.md` #21). They run on either backend through sqlx's `Any`
+//! driver; only `search` and `within_bbox` branch on [`Backend`] (#37).
 
 use async_trait::async_trait;
 use cp_model::{
-    Channel, ChannelId, Cursor, Error, Filter, Item, ItemId, Json, NewChannel, NewItem, Node,
-    NodePage, Order, Page, Result, StoreCtx, SuperType, TypeId, Upsert, UserId, WriteCtx,
+    Actor, Backend, Batch, Channel, ChannelId, Cursor, Error, Filter, Item, ItemId, Json, Mutation,
+    NewChannel, NewItem, Node, NodePage, Order, Page, Patch, QueryBuilder, Rect, Result, Revision,
+    Role, StoreCtx, SuperType, TypeId, Upsert, UserId, WriteCtx,
 };
-use sqlx::sqlite::SqliteRow;
-use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use sqlx::any::AnyRow;
+use sqlx::{Any, AnyConnection, AnyPool, Row, Transaction};
 use ulid::Ulid;
 
+use crate::change_log;
 use crate::events::{ChangeEvent, ChangeOp, EnvelopeRef, EventBus};
 use crate::index;
 use crate::registry::Registry;
 
-/// The sqlite-backed store. Holds the pool, the registry (for `validate`/`index` on write), and the
-/// event bus (to emit after commit).
+/// The envelope store over either backend. Holds the pool, the registry (for `validate`/`index` on write), the
+/// event bus (to emit after commit), and the `Actor` its writes are attributed to in the revision
+/// history (`Actor::System` unless the handle was made with `acting_as`).
 pub struct Store {
-    pool: SqlitePool,
+    pool: AnyPool,
+    backend: Backend,
     registry: Registry,
     events: EventBus,
+    actor: Actor,
 }
 
 fn db(e: sqlx::Error) -> Error {
@@ -54,16 +60,356 @@ fn user_id(s: &str) -> Result<UserId> {
         .map_err(|_| Error::Other(format!("invalid user id: {s}")))
 }
 
+/// One unit of work: a transaction plus the change events it will emit. Events are held back until the
+/// transaction commits, so a rolled-back unit (any error, including mid-batch) emits nothing. Every
+/// mutation — single-shot or batched — runs inside one of these. See `design/write-path.md`.
+struct Unit {
+    tx: Transaction<'static, Any>,
+    events: Vec<ChangeEvent>,
+}
+
+/// Point read of a live channel within a connection (so a batch sees its own uncommitted writes). A
+/// trashed or trash-hidden channel reads as absent.
+async fn load_channel(conn: &mut AnyConnection, id: ChannelId) -> Result<Option<Channel>> {
+    let row = sqlx::query(
+        "SELECT type_id, container, payload, rev FROM channels WHERE id = $1 AND trash_root IS NULL",
+    )
+    .bind(id.to_string())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let container: Option<String> = row.try_get("container").map_err(db)?;
+    Ok(Some(Channel {
+        id,
+        type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
+        container: container.as_deref().map(channel_id).transpose()?,
+        payload: from_text(&row.try_get::<String, _>("payload").map_err(db)?)?,
+        rev: row.try_get("rev").map_err(db)?,
+    }))
+}
+
+/// Point read of a live item within a connection.
+async fn load_item(conn: &mut AnyConnection, id: ItemId) -> Result<Option<Item>> {
+    let row = sqlx::query(
+        "SELECT type_id, container, external_key, payload, rev FROM items \
+         WHERE id = $1 AND trash_root IS NULL",
+    )
+    .bind(id.to_string())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let container: Option<String> = row.try_get("container").map_err(db)?;
+    Ok(Some(Item {
+        id,
+        type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
+        container: container.as_deref().map(channel_id).transpose()?,
+        external_key: row.try_get("external_key").map_err(db)?,
+        payload: from_text(&row.try_get::<String, _>("payload").map_err(db)?)?,
+        rev: row.try_get("rev").map_err(db)?,
+    }))
+}
+
+/// Append an envelope's current state to `envelope_revisions` just before it is overwritten, attributed
+/// to `actor`. `column` selects the row (`id`, or `external_key` for an upsert — no match records
+/// nothing). One INSERT…SELECT, so the next `rev` is computed inside the same statement.
+async fn snapshot(
+    conn: &mut AnyConnection,
+    table: &'static str,
+    column: &'static str,
+    key: &str,
+    actor: &Actor,
+) -> Result<()> {
+    let super_type = if table == "channels" {
+        "channel"
+    } else {
+        "item"
+    };
+    sqlx::query(&format!(
+        "INSERT INTO envelope_revisions (envelope_id, super_type, rev, payload, container, actor)
+         SELECT e.id, '{super_type}',
+                COALESCE((SELECT MAX(r.rev) FROM envelope_revisions r WHERE r.envelope_id = e.id), 0) + 1,
+                e.payload, e.container, $1
+         FROM {table} e WHERE e.{column} = $2"
+    ))
+    .bind(actor.to_string())
+    .bind(key)
+    .execute(&mut *conn)
+    .await
+    .map_err(db)?;
+    Ok(())
+}
+
+/// An envelope's identity and trash state whatever its visibility — what restore/purge act on.
+struct Tomb {
+    type_id: TypeId,
+    container: Option<ChannelId>,
+    /// NULL = live; own id = trashed directly; an ancestor's id = hidden by that ancestor's tombstone.
+    trash_root: Option<String>,
+}
+
+impl Tomb {
+    fn visible(&self) -> bool {
+        self.trash_root.is_none()
+    }
+}
+
+async fn load_tomb(conn: &mut AnyConnection, target: EnvelopeRef) -> Result<Option<Tomb>> {
+    let (table, id) = match target {
+        EnvelopeRef::Channel(id) => ("channels", id.to_string()),
+        EnvelopeRef::Item(id) => ("items", id.to_string()),
+    };
+    let row = sqlx::query(&format!(
+        "SELECT type_id, container, trash_root FROM {table} WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let container: Option<String> = row.try_get("container").map_err(db)?;
+    Ok(Some(Tomb {
+        type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
+        container: container.as_deref().map(channel_id).transpose()?,
+        trash_root: row.try_get("trash_root").map_err(db)?,
+    }))
+}
+
+/// A container a write is about to put an envelope under must be live: creating or moving something
+/// into the trash would make it vanish unannounced. `None` (a root) is always fine.
+async fn require_live_container(
+    conn: &mut AnyConnection,
+    container: Option<ChannelId>,
+) -> Result<()> {
+    match container {
+        Some(c) if load_channel(conn, c).await?.is_none() => Err(Error::NotFound),
+        _ => Ok(()),
+    }
+}
+
+/// Refuse to move channel `id` under `container` when that is `id` itself or one of its descendants —
+/// the move would cut the subtree loose into a containment cycle.
+async fn require_not_below(
+    conn: &mut AnyConnection,
+    id: ChannelId,
+    container: Option<ChannelId>,
+) -> Result<()> {
+    let Some(container) = container else {
+        return Ok(());
+    };
+    let looped: Option<String> = sqlx::query_scalar(
+        "WITH RECURSIVE above(id, container) AS (
+             SELECT id, container FROM channels WHERE id = $1
+             UNION ALL SELECT c.id, c.container FROM channels c JOIN above a ON c.id = a.container
+         )
+         SELECT id FROM above WHERE id = $2",
+    )
+    .bind(container.to_string())
+    .bind(id.to_string())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db)?;
+    match looped {
+        Some(_) => Err(Error::Validation(
+            "a channel cannot move under itself or its own descendant".to_owned(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Every envelope strictly below `root` (sub-channels at any depth and the items they contain) with its
+/// trash state, deepest first — the order a cascading delete reports them in. Read inside the mutating
+/// transaction.
+async fn subtree_of(conn: &mut AnyConnection, root: ChannelId) -> Result<Vec<(EnvelopeRef, Tomb)>> {
+    let rows = sqlx::query(
+        "WITH RECURSIVE subtree(id, depth) AS (
+             SELECT id, 0 FROM channels WHERE id = $1
+             UNION ALL SELECT c.id, s.depth + 1 FROM channels c JOIN subtree s ON c.container = s.id
+         )
+         SELECT 'item' AS super_type, i.id AS id, i.type_id AS type_id, i.container AS container,
+                i.trash_root AS trash_root, s.depth + 1 AS depth
+           FROM items i JOIN subtree s ON i.container = s.id
+         UNION ALL
+         SELECT 'channel', c.id, c.type_id, c.container, c.trash_root, s.depth
+           FROM channels c JOIN subtree s ON c.id = s.id WHERE s.depth >= 1
+         ORDER BY depth DESC, id ASC",
+    )
+    .bind(root.to_string())
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db)?;
+    rows.iter()
+        .map(|row| {
+            let id: String = row.try_get("id").map_err(db)?;
+            let target = match row.try_get::<String, _>("super_type").map_err(db)?.as_str() {
+                "channel" => EnvelopeRef::Channel(channel_id(&id)?),
+                _ => EnvelopeRef::Item(item_id(&id)?),
+            };
+            let container: Option<String> = row.try_get("container").map_err(db)?;
+            Ok((
+                target,
+                Tomb {
+                    type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
+                    container: container.as_deref().map(channel_id).transpose()?,
+                    trash_root: row.try_get("trash_root").map_err(db)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Re-point `trash_root` from `from` to `to` across `root` and its whole subtree (NULL-safe compare). This
+/// is how a tombstone hides a subtree (NULL → root) and how restore reveals exactly what it hid (root →
+/// NULL, or → a still-trashed ancestor's root), without touching rows an inner tombstone owns.
+async fn retag_subtree(
+    conn: &mut AnyConnection,
+    root: ChannelId,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<()> {
+    for (table, member) in [("channels", "id"), ("items", "container")] {
+        sqlx::query(&format!(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT $1 UNION ALL SELECT c.id FROM channels c JOIN subtree s ON c.container = s.id
+             )
+             UPDATE {table} SET trash_root = $2
+             WHERE trash_root IS NOT DISTINCT FROM $3 AND {member} IN (SELECT id FROM subtree)"
+        ))
+        .bind(root.to_string())
+        .bind(to)
+        .bind(from)
+        .execute(&mut *conn)
+        .await
+        .map_err(db)?;
+    }
+    Ok(())
+}
+
+async fn stamp_deleted(conn: &mut AnyConnection, table: &'static str, id: &str) -> Result<()> {
+    sqlx::query(&format!("UPDATE {table} SET deleted_at = $1 WHERE id = $2"))
+        .bind(crate::db::now())
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db)?;
+    Ok(())
+}
+
+async fn clear_deleted(conn: &mut AnyConnection, table: &'static str, id: &str) -> Result<()> {
+    sqlx::query(&format!(
+        "UPDATE {table} SET deleted_at = NULL WHERE id = $1"
+    ))
+    .bind(id)
+    .execute(&mut *conn)
+    .await
+    .map_err(db)?;
+    Ok(())
+}
+
+/// The compare half of a conditional write. The unit holds the write lock from `db::begin_write`, so
+/// nothing can move the revision between this check and the update that bumps it.
+fn check_rev(expected: Option<i64>, current: i64) -> Result<()> {
+    match expected {
+        Some(expected) if expected != current => Err(Error::Conflict { expected, current }),
+        _ => Ok(()),
+    }
+}
+
+/// Apply a [`Patch`] to a payload in place. A JSON-patch is all-or-nothing: on any failing operation
+/// the payload is left as it was and the error is a validation error, like a payload `validate` rejects.
+fn apply_patch(payload: &mut Json, patch: &Patch) -> Result<()> {
+    match patch {
+        Patch::Merge(doc) => json_patch::merge(payload, doc),
+        Patch::Json(doc) => {
+            let ops: json_patch::Patch = serde_json::from_value(doc.clone())
+                .map_err(|e| Error::Validation(format!("malformed JSON-patch: {e}")))?;
+            json_patch::patch(payload, &ops)
+                .map_err(|e| Error::Validation(format!("JSON-patch failed: {e}")))?;
+        }
+    }
+    Ok(())
+}
+
+/// Only a directly-trashed envelope is restorable: a live one has nothing to restore, and one hidden by
+/// a trashed ancestor comes back with that ancestor.
+fn check_restorable(tomb: &Tomb, id: &str) -> Result<()> {
+    match tomb.trash_root.as_deref() {
+        Some(root) if root == id => Ok(()),
+        None => Err(Error::Validation(format!("{id} is not in the trash"))),
+        Some(root) => Err(Error::Validation(format!(
+            "{id} is hidden by trashed channel {root}; restore that instead"
+        ))),
+    }
+}
+
+/// The tombstone (if any) hiding `container` — `None` for a live container or a root.
+async fn container_trash_root(
+    conn: &mut AnyConnection,
+    container: Option<ChannelId>,
+) -> Result<Option<String>> {
+    let Some(c) = container else {
+        return Ok(None);
+    };
+    Ok(load_tomb(conn, EnvelopeRef::Channel(c))
+        .await?
+        .and_then(|t| t.trash_root))
+}
+
+/// The `(super_type, envelope_id)` a revision row is keyed by.
+fn revision_key(target: EnvelopeRef) -> (&'static str, String) {
+    match target {
+        EnvelopeRef::Channel(id) => ("channel", id.to_string()),
+        EnvelopeRef::Item(id) => ("item", id.to_string()),
+    }
+}
+
+fn row_to_revision(row: &AnyRow) -> Result<Revision> {
+    let container: Option<String> = row.try_get("container").map_err(db)?;
+    let actor: String = row.try_get("actor").map_err(db)?;
+    Ok(Revision {
+        rev: row.try_get("rev").map_err(db)?,
+        payload: from_text(&row.try_get::<String, _>("payload").map_err(db)?)?,
+        container: container.as_deref().map(channel_id).transpose()?,
+        actor: actor.parse().map_err(Error::Other)?,
+        recorded_at: row.try_get("recorded_at").map_err(db)?,
+    })
+}
+
 impl Store {
-    pub fn new(pool: SqlitePool, registry: Registry, events: EventBus) -> Self {
+    pub fn new(pool: AnyPool, registry: Registry, events: EventBus) -> Self {
         Self {
+            backend: Backend::of(&pool),
             pool,
             registry,
             events,
+            actor: Actor::System,
         }
     }
 
-    pub fn pool(&self) -> &SqlitePool {
+    /// A handle over the same database whose writes are attributed to `actor`: the HTTP API acts as
+    /// the session's user, the supervisor as each runtime component, the debug shell as the operator.
+    pub fn acting_as(&self, actor: Actor) -> Store {
+        Store {
+            pool: self.pool.clone(),
+            backend: self.backend,
+            registry: self.registry.clone(),
+            events: self.events.clone(),
+            actor,
+        }
+    }
+
+    pub fn actor(&self) -> &Actor {
+        &self.actor
+    }
+
+    pub fn pool(&self) -> &AnyPool {
         &self.pool
     }
 
@@ -74,21 +420,8 @@ impl Store {
     /// Point read of a channel envelope by id. Used by the generic API (§9) and internally by the
     /// write path; not part of the kind-facing `StoreCtx` discovery set.
     pub async fn get_channel(&self, id: ChannelId) -> Result<Option<Channel>> {
-        let row = sqlx::query("SELECT type_id, container, payload FROM channels WHERE id = ?")
-            .bind(id.to_string())
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(db)?;
-        let Some(row) = row else {
-            return Ok(None);
-        };
-        let container: Option<String> = row.try_get("container").map_err(db)?;
-        Ok(Some(Channel {
-            id,
-            type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
-            container: container.as_deref().map(channel_id).transpose()?,
-            payload: from_text(&row.try_get::<String, _>("payload").map_err(db)?)?,
-        }))
+        let mut conn = self.pool.acquire().await.map_err(db)?;
+        load_channel(&mut conn, id).await
     }
 
     /// Every channel + item of the given types, id-ordered. The backfill enumerator behind
@@ -98,45 +431,171 @@ impl Store {
         if types.is_empty() {
             return Ok(Vec::new());
         }
-        let mut qb = QueryBuilder::<Sqlite>::new("");
+        let mut qb = QueryBuilder::new("");
         select_channels(&mut qb);
-        qb.push(" WHERE 1 = 1");
         push_type_ids(&mut qb, Some(types));
         qb.push(" UNION ALL ");
         select_items(&mut qb);
-        qb.push(" WHERE 1 = 1");
         push_type_ids(&mut qb, Some(types));
         qb.push(" ORDER BY id ASC");
         let rows = qb.build().fetch_all(&self.pool).await.map_err(db)?;
         rows.iter().map(row_to_node).collect()
     }
 
+    /// One id-keyset page of [`scan_by_types`](Self::scan_by_types): the envelopes after `cursor`, at
+    /// most `limit` (capped like every page), with the next page's cursor. The resumable backfill
+    /// enumerator behind `RuntimeCtx::scan_since` (§7).
+    pub async fn scan_since(
+        &self,
+        types: &[TypeId],
+        cursor: &Cursor,
+        limit: u32,
+    ) -> Result<NodePage> {
+        if types.is_empty() || limit == 0 {
+            return Ok(NodePage {
+                nodes: Vec::new(),
+                next: Cursor(None),
+            });
+        }
+        let limit = limit.min(MAX_LIMIT);
+        let mut qb = QueryBuilder::new("");
+        select_channels(&mut qb);
+        push_type_ids(&mut qb, Some(types));
+        push_cursor(&mut qb, cursor, Order::TimeAsc);
+        qb.push(" UNION ALL ");
+        select_items(&mut qb);
+        push_type_ids(&mut qb, Some(types));
+        push_cursor(&mut qb, cursor, Order::TimeAsc);
+        qb.push(" ORDER BY id ASC LIMIT ")
+            .push_bind(i64::from(limit) + 1);
+        let rows = qb.build().fetch_all(&self.pool).await.map_err(db)?;
+        let mut nodes = rows.iter().map(row_to_node).collect::<Result<Vec<_>>>()?;
+        let next = if nodes.len() > limit as usize {
+            nodes.truncate(limit as usize);
+            Cursor(Some(node_id(nodes.last().expect("limit >= 1"))))
+        } else {
+            Cursor(None)
+        };
+        Ok(NodePage { nodes, next })
+    }
+
     /// Point read of an item envelope by id. §9.
     pub async fn get_item(&self, id: ItemId) -> Result<Option<Item>> {
-        let row =
-            sqlx::query("SELECT type_id, container, external_key, payload FROM items WHERE id = ?")
-                .bind(id.to_string())
-                .fetch_optional(&self.pool)
-                .await
-                .map_err(db)?;
-        let Some(row) = row else {
-            return Ok(None);
-        };
-        let container: Option<String> = row.try_get("container").map_err(db)?;
-        Ok(Some(Item {
-            id,
-            type_id: TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?),
-            container: container.as_deref().map(channel_id).transpose()?,
-            external_key: row.try_get("external_key").map_err(db)?,
-            payload: from_text(&row.try_get::<String, _>("payload").map_err(db)?)?,
-        }))
+        let mut conn = self.pool.acquire().await.map_err(db)?;
+        load_item(&mut conn, id).await
     }
-}
 
-// The write path. See `design/write-path.md`: validate -> tx -> persist -> index -> commit -> emit.
-#[async_trait]
-impl WriteCtx for Store {
-    async fn create_channel(&self, spec: NewChannel) -> Result<ChannelId> {
+    /// The revision history of a channel or item, oldest first: each superseded state plus who
+    /// replaced it and when. Empty for a never-changed envelope; kept after the envelope is deleted.
+    pub async fn revisions(&self, target: EnvelopeRef) -> Result<Vec<Revision>> {
+        let (super_type, id) = revision_key(target);
+        let rows = sqlx::query(
+            "SELECT rev, payload, container, actor, recorded_at FROM envelope_revisions
+             WHERE envelope_id = $1 AND super_type = $2 ORDER BY rev ASC",
+        )
+        .bind(id)
+        .bind(super_type)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db)?;
+        rows.iter().map(row_to_revision).collect()
+    }
+
+    /// Restore a channel or item to the payload + container recorded as revision `rev`. Runs through the
+    /// write path (validated, re-indexed, evented), and the state it replaces is itself recorded — so a
+    /// revert is attributed and can be reverted. `NotFound` if the envelope or the revision is missing;
+    /// `Validation` if a channel's old container now sits below it.
+    pub async fn revert(&self, target: EnvelopeRef, rev: i64) -> Result<()> {
+        let (super_type, id) = revision_key(target);
+        let mut u = self.begin().await?;
+        let row = sqlx::query(
+            "SELECT rev, payload, container, actor, recorded_at FROM envelope_revisions
+             WHERE envelope_id = $1 AND super_type = $2 AND rev = $3",
+        )
+        .bind(id)
+        .bind(super_type)
+        .bind(rev)
+        .fetch_optional(&mut *u.tx)
+        .await
+        .map_err(db)?
+        .ok_or(Error::NotFound)?;
+        let old = row_to_revision(&row)?;
+        match target {
+            EnvelopeRef::Channel(id) => {
+                let ch = load_channel(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+                require_live_container(&mut u.tx, old.container).await?;
+                // The old parent may since have moved below this channel; going back would close a loop.
+                require_not_below(&mut u.tx, id, old.container).await?;
+                self.rewrite_channel_in(&mut u, ch, old.payload, old.container)
+                    .await?;
+            }
+            EnvelopeRef::Item(id) => {
+                let item = load_item(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+                require_live_container(&mut u.tx, old.container).await?;
+                self.rewrite_item_in(&mut u, item, old.payload, old.container)
+                    .await?;
+            }
+        }
+        self.commit(u).await
+    }
+
+    /// [`WriteCtx::apply`], with `users` inserted first in the same unit: an archive import's users have
+    /// no `Mutation`, and must not outlive a batch that fails.
+    pub(crate) async fn apply_with_users(
+        &self,
+        users: &[crate::archive::ArchivedUser],
+        batch: Batch,
+    ) -> Result<()> {
+        let mut u = self.begin().await?;
+        for user in users {
+            sqlx::query(
+                "INSERT INTO users (id, handle, operator, password_hash) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(user.id.to_string())
+            .bind(&user.handle)
+            .bind(i64::from(user.operator))
+            .bind(&user.password_hash)
+            .execute(&mut *u.tx)
+            .await
+            .map_err(db)?;
+        }
+        for mutation in batch.into_mutations() {
+            self.mutate_in(&mut u, mutation).await?;
+        }
+        self.commit(u).await
+    }
+
+    /// Open a unit, taking the write lock up front (`db::begin_write` says why).
+    async fn begin(&self) -> Result<Unit> {
+        Ok(Unit {
+            tx: crate::db::begin_write(&self.pool).await.map_err(db)?,
+            events: Vec::new(),
+        })
+    }
+
+    /// Log a unit's queued events into the durable change log (stamping their `seq`), commit, then
+    /// publish them in mutation order. §7.
+    async fn commit(&self, mut unit: Unit) -> Result<()> {
+        for event in &mut unit.events {
+            change_log::append(&mut unit.tx, event).await?;
+        }
+        let seqs = || unit.events.iter().map(|e| e.seq);
+        self.events.claim(seqs());
+        if let Err(e) = unit.tx.commit().await {
+            self.events.unclaim(seqs());
+            return Err(db(e));
+        }
+        for event in unit.events {
+            self.events.publish(event);
+        }
+        Ok(())
+    }
+
+    // The per-mutation steps, each run inside a caller-owned `Unit`: validate via the owning kind ->
+    // persist -> inline index -> queue the change event. `WriteCtx`'s single-shot methods wrap one step
+    // in its own unit; `apply` runs a whole batch in one.
+
+    async fn create_channel_in(&self, u: &mut Unit, id: ChannelId, spec: NewChannel) -> Result<()> {
         let entry = {
             let kind = self
                 .registry
@@ -145,66 +604,62 @@ impl WriteCtx for Store {
             kind.validate(&spec.payload)?;
             kind.index(&spec.payload)
         };
-        let id = ChannelId::generate();
-
-        let mut tx = self.pool.begin().await.map_err(db)?;
-        sqlx::query("INSERT INTO channels (id, type_id, container, payload) VALUES (?, ?, ?, ?)")
-            .bind(id.to_string())
-            .bind(spec.type_id.as_str())
-            .bind(spec.container.map(|c| c.to_string()))
-            .bind(to_text(&spec.payload)?)
-            .execute(&mut *tx)
-            .await
-            .map_err(db)?;
+        require_live_container(&mut u.tx, spec.container).await?;
+        sqlx::query(
+            "INSERT INTO channels (id, type_id, container, payload) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(id.to_string())
+        .bind(spec.type_id.as_str())
+        .bind(spec.container.map(|c| c.to_string()))
+        .bind(to_text(&spec.payload)?)
+        .execute(&mut *u.tx)
+        .await
+        .map_err(db)?;
         if let Some(entry) = entry {
-            index::upsert(&mut tx, EnvelopeRef::Channel(id), &entry).await?;
+            index::upsert(&mut u.tx, EnvelopeRef::Channel(id), &entry).await?;
         }
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Created,
             target: EnvelopeRef::Channel(id),
             type_id: spec.type_id,
             container: spec.container,
         });
-        Ok(id)
+        Ok(())
     }
 
-    async fn create_item(&self, spec: NewItem) -> Result<ItemId> {
+    async fn create_item_in(&self, u: &mut Unit, id: ItemId, spec: NewItem) -> Result<()> {
         let entry = {
             let kind = self.registry.item(&spec.type_id).ok_or(Error::NotFound)?;
             kind.validate(&spec.payload)?;
             kind.index(&spec.payload)
         };
-        let id = ItemId::generate();
-
-        let mut tx = self.pool.begin().await.map_err(db)?;
+        require_live_container(&mut u.tx, spec.container).await?;
         sqlx::query(
-            "INSERT INTO items (id, type_id, container, external_key, payload) VALUES (?, ?, ?, ?, ?)",
+            "INSERT INTO items (id, type_id, container, external_key, payload) VALUES ($1, $2, $3, $4, $5)",
         )
         .bind(id.to_string())
         .bind(spec.type_id.as_str())
         .bind(spec.container.map(|c| c.to_string()))
         .bind(spec.external_key.as_deref())
         .bind(to_text(&spec.payload)?)
-        .execute(&mut *tx)
+        .execute(&mut *u.tx)
         .await
         .map_err(db)?;
         if let Some(entry) = entry {
-            index::upsert(&mut tx, EnvelopeRef::Item(id), &entry).await?;
+            index::upsert(&mut u.tx, EnvelopeRef::Item(id), &entry).await?;
         }
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Created,
             target: EnvelopeRef::Item(id),
             type_id: spec.type_id,
             container: spec.container,
         });
-        Ok(id)
+        Ok(())
     }
 
-    async fn upsert_item(&self, spec: NewItem) -> Result<Upsert<ItemId>> {
+    async fn upsert_item_in(&self, u: &mut Unit, spec: NewItem) -> Result<Upsert<ItemId>> {
         let key = spec
             .external_key
             .as_deref()
@@ -214,42 +669,61 @@ impl WriteCtx for Store {
             kind.validate(&spec.payload)?;
             kind.index(&spec.payload)
         };
+        require_live_container(&mut u.tx, spec.container).await?;
         let fresh = ItemId::generate();
+        // Records the prior state only when the key already exists (i.e. this upsert is an update).
+        snapshot(&mut u.tx, "items", "external_key", key, &self.actor).await?;
+
+        // Whether the row is hidden only by a trashed ancestor. The container checked live above is the
+        // one it lands in, so the move reveals it (below), and its old `trash_root` must not outlive it.
+        let hidden: Option<String> =
+            sqlx::query_scalar("SELECT id FROM items WHERE external_key = $1 AND trash_root <> id")
+                .bind(key)
+                .fetch_optional(&mut *u.tx)
+                .await
+                .map_err(db)?;
+        let revealed = hidden.is_some();
 
-        let mut tx = self.pool.begin().await.map_err(db)?;
         // One atomic statement (no read-then-write race). On conflict the *existing* id is returned,
         // so it is stable across updates (§3). The partial unique index needs its WHERE echoed here.
+        // A directly-trashed row stays in the trash; anything else takes the (live) new container's
+        // state, so a restore of a former ancestor can never strand it.
         let row = sqlx::query(
-            "INSERT INTO items (id, type_id, container, external_key, payload) VALUES (?, ?, ?, ?, ?)
+            "INSERT INTO items (id, type_id, container, external_key, payload) VALUES ($1, $2, $3, $4, $5)
              ON CONFLICT(external_key) WHERE external_key IS NOT NULL
-             DO UPDATE SET payload = excluded.payload, container = excluded.container
-             RETURNING id",
+             DO UPDATE SET payload = excluded.payload, container = excluded.container,
+                           rev = items.rev + 1,
+                           trash_root = CASE WHEN items.trash_root = items.id
+                                             THEN items.trash_root ELSE NULL END
+             RETURNING id, trash_root",
         )
         .bind(fresh.to_string())
         .bind(spec.type_id.as_str())
         .bind(spec.container.map(|c| c.to_string()))
         .bind(key)
         .bind(to_text(&spec.payload)?)
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut *u.tx)
         .await
         .map_err(db)?;
         let id = item_id(&row.try_get::<String, _>("id").map_err(db)?)?;
         let inserted = id == fresh;
-        if let Some(entry) = entry {
-            index::upsert(&mut tx, EnvelopeRef::Item(id), &entry).await?;
+        // Re-mirroring a trashed item refreshes it in place but leaves it in the trash, unannounced.
+        let trashed: Option<String> = row.try_get("trash_root").map_err(db)?;
+        // Re-projected even when the new payload indexes nothing, so the old rows don't outlive it.
+        index::upsert(&mut u.tx, EnvelopeRef::Item(id), &entry.unwrap_or_default()).await?;
+        if trashed.is_none() {
+            u.events.push(ChangeEvent {
+                seq: 0,
+                op: if inserted || revealed {
+                    ChangeOp::Created
+                } else {
+                    ChangeOp::Updated
+                },
+                target: EnvelopeRef::Item(id),
+                type_id: spec.type_id,
+                container: spec.container,
+            });
         }
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
-            op: if inserted {
-                ChangeOp::Created
-            } else {
-                ChangeOp::Updated
-            },
-            target: EnvelopeRef::Item(id),
-            type_id: spec.type_id,
-            container: spec.container,
-        });
         Ok(if inserted {
             Upsert::Inserted(id)
         } else {
@@ -257,74 +731,160 @@ impl WriteCtx for Store {
         })
     }
 
-    async fn set_channel_payload(&self, id: ChannelId, payload: Json) -> Result<()> {
-        let ch = self.get_channel(id).await?.ok_or(Error::NotFound)?;
+    async fn set_channel_payload_in(
+        &self,
+        u: &mut Unit,
+        id: ChannelId,
+        expected: Option<i64>,
+        payload: Json,
+    ) -> Result<()> {
+        let ch = load_channel(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        check_rev(expected, ch.rev)?;
+        let container = ch.container;
+        self.rewrite_channel_in(u, ch, payload, container).await
+    }
+
+    /// Replace a channel's payload and container together, recording its prior state. Shared by
+    /// `set_channel_payload` (container unchanged) and `revert`.
+    async fn rewrite_channel_in(
+        &self,
+        u: &mut Unit,
+        ch: Channel,
+        payload: Json,
+        container: Option<ChannelId>,
+    ) -> Result<()> {
         let entry = {
             let kind = self.registry.channel(&ch.type_id).ok_or(Error::NotFound)?;
             kind.validate(&payload)?;
             kind.index(&payload)
         };
-
-        let mut tx = self.pool.begin().await.map_err(db)?;
-        sqlx::query("UPDATE channels SET payload = ? WHERE id = ?")
-            .bind(to_text(&payload)?)
-            .bind(id.to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(db)?;
-        if let Some(entry) = entry {
-            index::upsert(&mut tx, EnvelopeRef::Channel(id), &entry).await?;
-        }
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+        let id = ch.id.to_string();
+        snapshot(&mut u.tx, "channels", "id", &id, &self.actor).await?;
+        sqlx::query(
+            "UPDATE channels SET payload = $1, container = $2, rev = rev + 1 WHERE id = $3",
+        )
+        .bind(to_text(&payload)?)
+        .bind(container.map(|c| c.to_string()))
+        .bind(&id)
+        .execute(&mut *u.tx)
+        .await
+        .map_err(db)?;
+        index::upsert(
+            &mut u.tx,
+            EnvelopeRef::Channel(ch.id),
+            &entry.unwrap_or_default(),
+        )
+        .await?;
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Updated,
-            target: EnvelopeRef::Channel(id),
+            target: EnvelopeRef::Channel(ch.id),
             type_id: ch.type_id,
-            container: ch.container,
+            container,
         });
         Ok(())
     }
 
-    async fn set_item_payload(&self, id: ItemId, payload: Json) -> Result<()> {
-        let item = self.get_item(id).await?.ok_or(Error::NotFound)?;
+    async fn patch_channel_payload_in(
+        &self,
+        u: &mut Unit,
+        id: ChannelId,
+        expected: Option<i64>,
+        patch: Patch,
+    ) -> Result<()> {
+        let mut ch = load_channel(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        check_rev(expected, ch.rev)?;
+        let mut payload = std::mem::take(&mut ch.payload);
+        apply_patch(&mut payload, &patch)?;
+        let container = ch.container;
+        self.rewrite_channel_in(u, ch, payload, container).await
+    }
+
+    async fn set_item_payload_in(
+        &self,
+        u: &mut Unit,
+        id: ItemId,
+        expected: Option<i64>,
+        payload: Json,
+    ) -> Result<()> {
+        let item = load_item(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        check_rev(expected, item.rev)?;
+        let container = item.container;
+        self.rewrite_item_in(u, item, payload, container).await
+    }
+
+    async fn patch_item_payload_in(
+        &self,
+        u: &mut Unit,
+        id: ItemId,
+        expected: Option<i64>,
+        patch: Patch,
+    ) -> Result<()> {
+        let mut item = load_item(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        check_rev(expected, item.rev)?;
+        let mut payload = std::mem::take(&mut item.payload);
+        apply_patch(&mut payload, &patch)?;
+        let container = item.container;
+        self.rewrite_item_in(u, item, payload, container).await
+    }
+
+    /// Replace an item's payload and container together, recording its prior state.
+    async fn rewrite_item_in(
+        &self,
+        u: &mut Unit,
+        item: Item,
+        payload: Json,
+        container: Option<ChannelId>,
+    ) -> Result<()> {
         let entry = {
             let kind = self.registry.item(&item.type_id).ok_or(Error::NotFound)?;
             kind.validate(&payload)?;
             kind.index(&payload)
         };
-
-        let mut tx = self.pool.begin().await.map_err(db)?;
-        sqlx::query("UPDATE items SET payload = ? WHERE id = ?")
+        let id = item.id.to_string();
+        snapshot(&mut u.tx, "items", "id", &id, &self.actor).await?;
+        sqlx::query("UPDATE items SET payload = $1, container = $2, rev = rev + 1 WHERE id = $3")
             .bind(to_text(&payload)?)
-            .bind(id.to_string())
-            .execute(&mut *tx)
+            .bind(container.map(|c| c.to_string()))
+            .bind(&id)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
-        if let Some(entry) = entry {
-            index::upsert(&mut tx, EnvelopeRef::Item(id), &entry).await?;
-        }
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+        index::upsert(
+            &mut u.tx,
+            EnvelopeRef::Item(item.id),
+            &entry.unwrap_or_default(),
+        )
+        .await?;
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Updated,
-            target: EnvelopeRef::Item(id),
+            target: EnvelopeRef::Item(item.id),
             type_id: item.type_id,
-            container: item.container,
+            container,
         });
         Ok(())
     }
 
-    async fn reparent_channel(&self, id: ChannelId, container: Option<ChannelId>) -> Result<()> {
-        let ch = self.get_channel(id).await?.ok_or(Error::NotFound)?;
+    async fn reparent_channel_in(
+        &self,
+        u: &mut Unit,
+        id: ChannelId,
+        container: Option<ChannelId>,
+    ) -> Result<()> {
+        let ch = load_channel(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        require_live_container(&mut u.tx, container).await?;
+        require_not_below(&mut u.tx, id, container).await?;
+        snapshot(&mut u.tx, "channels", "id", &id.to_string(), &self.actor).await?;
         // Container FK validates the new parent exists; payload/index unchanged (index is over payload).
-        sqlx::query("UPDATE channels SET container = ? WHERE id = ?")
+        sqlx::query("UPDATE channels SET container = $1, rev = rev + 1 WHERE id = $2")
             .bind(container.map(|c| c.to_string()))
             .bind(id.to_string())
-            .execute(&self.pool)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
-        self.events.publish(ChangeEvent {
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Updated,
             target: EnvelopeRef::Channel(id),
             type_id: ch.type_id,
@@ -333,15 +893,23 @@ impl WriteCtx for Store {
         Ok(())
     }
 
-    async fn reparent_item(&self, id: ItemId, container: Option<ChannelId>) -> Result<()> {
-        let item = self.get_item(id).await?.ok_or(Error::NotFound)?;
-        sqlx::query("UPDATE items SET container = ? WHERE id = ?")
+    async fn reparent_item_in(
+        &self,
+        u: &mut Unit,
+        id: ItemId,
+        container: Option<ChannelId>,
+    ) -> Result<()> {
+        let item = load_item(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        require_live_container(&mut u.tx, container).await?;
+        snapshot(&mut u.tx, "items", "id", &id.to_string(), &self.actor).await?;
+        sqlx::query("UPDATE items SET container = $1, rev = rev + 1 WHERE id = $2")
             .bind(container.map(|c| c.to_string()))
             .bind(id.to_string())
-            .execute(&self.pool)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
-        self.events.publish(ChangeEvent {
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Updated,
             target: EnvelopeRef::Item(id),
             type_id: item.type_id,
@@ -350,20 +918,29 @@ impl WriteCtx for Store {
         Ok(())
     }
 
-    async fn delete_channel(&self, id: ChannelId) -> Result<()> {
-        // Fetch first so the event can carry type_id/container; also confirms existence.
-        let ch = self.get_channel(id).await?.ok_or(Error::NotFound)?;
-        let mut tx = self.pool.begin().await.map_err(db)?;
-        // FK ON DELETE CASCADE removes child channels + items; their index rows are #3's concern.
-        sqlx::query("DELETE FROM channels WHERE id = ?")
-            .bind(id.to_string())
-            .execute(&mut *tx)
-            .await
-            .map_err(db)?;
-        index::delete(&mut tx, EnvelopeRef::Channel(id)).await?;
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+    async fn delete_channel_in(&self, u: &mut Unit, id: ChannelId) -> Result<()> {
+        // Fetch first so the event can carry type_id/container; also confirms it is live.
+        let ch = load_channel(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        // The tombstone hides the whole subtree: each descendant not already hidden by an inner
+        // tombstone disappears now, so it gets its own `Deleted` (deepest first, the channel last) for
+        // SSE + Derived components. Rows and index entries stay, for `restore`.
+        for (target, tomb) in subtree_of(&mut u.tx, id).await? {
+            if tomb.visible() {
+                u.events.push(ChangeEvent {
+                    seq: 0,
+                    op: ChangeOp::Deleted,
+                    target,
+                    type_id: tomb.type_id,
+                    container: tomb.container,
+                });
+            }
+        }
+        let key = id.to_string();
+        snapshot(&mut u.tx, "channels", "id", &key, &self.actor).await?;
+        retag_subtree(&mut u.tx, id, None, Some(&key)).await?;
+        stamp_deleted(&mut u.tx, "channels", &key).await?;
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Deleted,
             target: EnvelopeRef::Channel(id),
             type_id: ch.type_id,
@@ -372,18 +949,18 @@ impl WriteCtx for Store {
         Ok(())
     }
 
-    async fn delete_item(&self, id: ItemId) -> Result<()> {
-        let item = self.get_item(id).await?.ok_or(Error::NotFound)?;
-        let mut tx = self.pool.begin().await.map_err(db)?;
-        sqlx::query("DELETE FROM items WHERE id = ?")
-            .bind(id.to_string())
-            .execute(&mut *tx)
+    async fn delete_item_in(&self, u: &mut Unit, id: ItemId) -> Result<()> {
+        let item = load_item(&mut u.tx, id).await?.ok_or(Error::NotFound)?;
+        let key = id.to_string();
+        snapshot(&mut u.tx, "items", "id", &key, &self.actor).await?;
+        sqlx::query("UPDATE items SET trash_root = id WHERE id = $1")
+            .bind(&key)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
-        index::delete(&mut tx, EnvelopeRef::Item(id)).await?;
-        tx.commit().await.map_err(db)?;
-
-        self.events.publish(ChangeEvent {
+        stamp_deleted(&mut u.tx, "items", &key).await?;
+        u.events.push(ChangeEvent {
+            seq: 0,
             op: ChangeOp::Deleted,
             target: EnvelopeRef::Item(id),
             type_id: item.type_id,
@@ -392,29 +969,390 @@ impl WriteCtx for Store {
         Ok(())
     }
 
-    async fn add_member(&self, channel: ChannelId, user: UserId) -> Result<()> {
+    async fn restore_channel_in(&self, u: &mut Unit, id: ChannelId) -> Result<()> {
+        let target = EnvelopeRef::Channel(id);
+        let tomb = load_tomb(&mut u.tx, target).await?.ok_or(Error::NotFound)?;
+        let key = id.to_string();
+        check_restorable(&tomb, &key)?;
+        // Restoring under a container that is itself (still) trashed re-hides the subtree under that
+        // container's tombstone instead of leaving live envelopes inside a hidden channel.
+        let hidden_by = container_trash_root(&mut u.tx, tomb.container).await?;
+        let revealed = subtree_of(&mut u.tx, id).await?;
+        retag_subtree(&mut u.tx, id, Some(&key), hidden_by.as_deref()).await?;
+        clear_deleted(&mut u.tx, "channels", &key).await?;
+        if hidden_by.is_none() {
+            u.events.push(ChangeEvent {
+                seq: 0,
+                op: ChangeOp::Created,
+                target,
+                type_id: tomb.type_id,
+                container: tomb.container,
+            });
+            // Shallowest first, so a consumer sees a container before its contents.
+            for (target, t) in revealed.into_iter().rev() {
+                if t.trash_root.as_deref() == Some(key.as_str()) {
+                    u.events.push(ChangeEvent {
+                        seq: 0,
+                        op: ChangeOp::Created,
+                        target,
+                        type_id: t.type_id,
+                        container: t.container,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn restore_item_in(&self, u: &mut Unit, id: ItemId) -> Result<()> {
+        let target = EnvelopeRef::Item(id);
+        let tomb = load_tomb(&mut u.tx, target).await?.ok_or(Error::NotFound)?;
+        let key = id.to_string();
+        check_restorable(&tomb, &key)?;
+        let hidden_by = container_trash_root(&mut u.tx, tomb.container).await?;
+        sqlx::query("UPDATE items SET trash_root = $1 WHERE id = $2")
+            .bind(hidden_by.as_deref())
+            .bind(&key)
+            .execute(&mut *u.tx)
+            .await
+            .map_err(db)?;
+        clear_deleted(&mut u.tx, "items", &key).await?;
+        if hidden_by.is_none() {
+            u.events.push(ChangeEvent {
+                seq: 0,
+                op: ChangeOp::Created,
+                target,
+                type_id: tomb.type_id,
+                container: tomb.container,
+            });
+        }
+        Ok(())
+    }
+
+    async fn purge_channel_in(&self, u: &mut Unit, id: ChannelId) -> Result<()> {
+        let tomb = load_tomb(&mut u.tx, EnvelopeRef::Channel(id))
+            .await?
+            .ok_or(Error::NotFound)?;
+        // FK ON DELETE CASCADE removes the subtree's rows, but nothing else would learn about them:
+        // enumerate it first so each descendant gets its history row and loses its index row, and each
+        // one still visible emits its own `Deleted` (trashed ones already did when they were hidden).
+        for (target, t) in subtree_of(&mut u.tx, id).await? {
+            let (table, key) = match target {
+                EnvelopeRef::Channel(c) => ("channels", c.to_string()),
+                EnvelopeRef::Item(i) => ("items", i.to_string()),
+            };
+            snapshot(&mut u.tx, table, "id", &key, &self.actor).await?;
+            index::delete(&mut u.tx, target).await?;
+            if t.visible() {
+                u.events.push(ChangeEvent {
+                    seq: 0,
+                    op: ChangeOp::Deleted,
+                    target,
+                    type_id: t.type_id,
+                    container: t.container,
+                });
+            }
+        }
+        snapshot(&mut u.tx, "channels", "id", &id.to_string(), &self.actor).await?;
+        sqlx::query("DELETE FROM channels WHERE id = $1")
+            .bind(id.to_string())
+            .execute(&mut *u.tx)
+            .await
+            .map_err(db)?;
+        index::delete(&mut u.tx, EnvelopeRef::Channel(id)).await?;
+        if tomb.visible() {
+            u.events.push(ChangeEvent {
+                seq: 0,
+                op: ChangeOp::Deleted,
+                target: EnvelopeRef::Channel(id),
+                type_id: tomb.type_id,
+                container: tomb.container,
+            });
+        }
+        Ok(())
+    }
+
+    async fn purge_item_in(&self, u: &mut Unit, id: ItemId) -> Result<()> {
+        let tomb = load_tomb(&mut u.tx, EnvelopeRef::Item(id))
+            .await?
+            .ok_or(Error::NotFound)?;
+        snapshot(&mut u.tx, "items", "id", &id.to_string(), &self.actor).await?;
+        sqlx::query("DELETE FROM items WHERE id = $1")
+            .bind(id.to_string())
+            .execute(&mut *u.tx)
+            .await
+            .map_err(db)?;
+        index::delete(&mut u.tx, EnvelopeRef::Item(id)).await?;
+        if tomb.visible() {
+            u.events.push(ChangeEvent {
+                seq: 0,
+                op: ChangeOp::Deleted,
+                target: EnvelopeRef::Item(id),
+                type_id: tomb.type_id,
+                container: tomb.container,
+            });
+        }
+        Ok(())
+    }
+
+    async fn add_member_in(&self, u: &mut Unit, channel: ChannelId, user: UserId) -> Result<()> {
         // FKs enforce that both the channel and the (native) user exist. §2/§8.
-        sqlx::query("INSERT OR IGNORE INTO channel_members (channel_id, user_id) VALUES (?, ?)")
+        sqlx::query(
+            "INSERT INTO channel_members (channel_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
             .bind(channel.to_string())
             .bind(user.to_string())
-            .execute(&self.pool)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
         Ok(())
     }
 
-    async fn remove_member(&self, channel: ChannelId, user: UserId) -> Result<()> {
-        sqlx::query("DELETE FROM channel_members WHERE channel_id = ? AND user_id = ?")
+    async fn remove_member_in(&self, u: &mut Unit, channel: ChannelId, user: UserId) -> Result<()> {
+        sqlx::query("DELETE FROM channel_members WHERE channel_id = $1 AND user_id = $2")
             .bind(channel.to_string())
             .bind(user.to_string())
-            .execute(&self.pool)
+            .execute(&mut *u.tx)
             .await
             .map_err(db)?;
         Ok(())
     }
 
+    async fn grant_role_in(
+        &self,
+        u: &mut Unit,
+        channel: ChannelId,
+        user: UserId,
+        role: &Role,
+    ) -> Result<()> {
+        // FKs enforce that both the channel and the (native) user exist, as for `channel_members`. §18.
+        sqlx::query(
+            "INSERT INTO channel_roles (channel_id, user_id, role) VALUES ($1, $2, $3) \
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(channel.to_string())
+        .bind(user.to_string())
+        .bind(role.as_str())
+        .execute(&mut *u.tx)
+        .await
+        .map_err(db)?;
+        Ok(())
+    }
+
+    async fn revoke_role_in(
+        &self,
+        u: &mut Unit,
+        channel: ChannelId,
+        user: UserId,
+        role: &Role,
+    ) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM channel_roles WHERE channel_id = $1 AND user_id = $2 AND role = $3",
+        )
+        .bind(channel.to_string())
+        .bind(user.to_string())
+        .bind(role.as_str())
+        .execute(&mut *u.tx)
+        .await
+        .map_err(db)?;
+        Ok(())
+    }
+
+    /// Run one batched mutation in the unit; `Some` carries an upsert's outcome.
+    async fn mutate_in(&self, u: &mut Unit, mutation: Mutation) -> Result<Option<Upsert<ItemId>>> {
+        match mutation {
+            Mutation::CreateChannel { id, spec } => self.create_channel_in(u, id, spec).await?,
+            Mutation::CreateItem { id, spec } => self.create_item_in(u, id, spec).await?,
+            Mutation::UpsertItem(spec) => return self.upsert_item_in(u, spec).await.map(Some),
+            Mutation::SetChannelPayload(id, p) => {
+                self.set_channel_payload_in(u, id, None, p).await?
+            }
+            Mutation::SetItemPayload(id, p) => self.set_item_payload_in(u, id, None, p).await?,
+            Mutation::SetChannelPayloadIf(id, rev, p) => {
+                self.set_channel_payload_in(u, id, Some(rev), p).await?
+            }
+            Mutation::SetItemPayloadIf(id, rev, p) => {
+                self.set_item_payload_in(u, id, Some(rev), p).await?
+            }
+            Mutation::PatchChannelPayload { id, rev, patch } => {
+                self.patch_channel_payload_in(u, id, rev, patch).await?
+            }
+            Mutation::PatchItemPayload { id, rev, patch } => {
+                self.patch_item_payload_in(u, id, rev, patch).await?
+            }
+            Mutation::ReparentChannel(id, c) => self.reparent_channel_in(u, id, c).await?,
+            Mutation::ReparentItem(id, c) => self.reparent_item_in(u, id, c).await?,
+            Mutation::DeleteChannel(id) => self.delete_channel_in(u, id).await?,
+            Mutation::DeleteItem(id) => self.delete_item_in(u, id).await?,
+            Mutation::RestoreChannel(id) => self.restore_channel_in(u, id).await?,
+            Mutation::RestoreItem(id) => self.restore_item_in(u, id).await?,
+            Mutation::PurgeChannel(id) => self.purge_channel_in(u, id).await?,
+            Mutation::PurgeItem(id) => self.purge_item_in(u, id).await?,
+            Mutation::AddMember(ch, user) => self.add_member_in(u, ch, user).await?,
+            Mutation::RemoveMember(ch, user) => self.remove_member_in(u, ch, user).await?,
+            Mutation::GrantRole(ch, user, role) => self.grant_role_in(u, ch, user, &role).await?,
+            Mutation::RevokeRole(ch, user, role) => self.revoke_role_in(u, ch, user, &role).await?,
+        }
+        Ok(None)
+    }
+}
+
+// The write path. See `design/write-path.md`: validate -> tx -> persist -> index -> commit -> emit. Each
+// single-shot method is a one-mutation unit; `apply` commits a whole `Batch` as one.
+#[async_trait]
+impl WriteCtx for Store {
+    async fn create_channel(&self, spec: NewChannel) -> Result<ChannelId> {
+        let id = ChannelId::generate();
+        let mut u = self.begin().await?;
+        self.create_channel_in(&mut u, id, spec).await?;
+        self.commit(u).await?;
+        Ok(id)
+    }
+
+    async fn create_item(&self, spec: NewItem) -> Result<ItemId> {
+        let id = ItemId::generate();
+        let mut u = self.begin().await?;
+        self.create_item_in(&mut u, id, spec).await?;
+        self.commit(u).await?;
+        Ok(id)
+    }
+
+    async fn upsert_item(&self, spec: NewItem) -> Result<Upsert<ItemId>> {
+        let mut u = self.begin().await?;
+        let outcome = self.upsert_item_in(&mut u, spec).await?;
+        self.commit(u).await?;
+        Ok(outcome)
+    }
+
+    async fn set_channel_payload(&self, id: ChannelId, payload: Json) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.set_channel_payload_in(&mut u, id, None, payload)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn set_item_payload(&self, id: ItemId, payload: Json) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.set_item_payload_in(&mut u, id, None, payload).await?;
+        self.commit(u).await
+    }
+
+    async fn set_channel_payload_if(&self, id: ChannelId, rev: i64, payload: Json) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.set_channel_payload_in(&mut u, id, Some(rev), payload)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn set_item_payload_if(&self, id: ItemId, rev: i64, payload: Json) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.set_item_payload_in(&mut u, id, Some(rev), payload)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn patch_channel_payload(&self, id: ChannelId, patch: Patch) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.patch_channel_payload_in(&mut u, id, None, patch)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn patch_item_payload(&self, id: ItemId, patch: Patch) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.patch_item_payload_in(&mut u, id, None, patch).await?;
+        self.commit(u).await
+    }
+
+    async fn patch_channel_payload_if(&self, id: ChannelId, rev: i64, patch: Patch) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.patch_channel_payload_in(&mut u, id, Some(rev), patch)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn patch_item_payload_if(&self, id: ItemId, rev: i64, patch: Patch) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.patch_item_payload_in(&mut u, id, Some(rev), patch)
+            .await?;
+        self.commit(u).await
+    }
+
+    async fn reparent_channel(&self, id: ChannelId, container: Option<ChannelId>) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.reparent_channel_in(&mut u, id, container).await?;
+        self.commit(u).await
+    }
+
+    async fn reparent_item(&self, id: ItemId, container: Option<ChannelId>) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.reparent_item_in(&mut u, id, container).await?;
+        self.commit(u).await
+    }
+
+    async fn delete_channel(&self, id: ChannelId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.delete_channel_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn delete_item(&self, id: ItemId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.delete_item_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn restore_channel(&self, id: ChannelId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.restore_channel_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn restore_item(&self, id: ItemId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.restore_item_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn purge_channel(&self, id: ChannelId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.purge_channel_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn purge_item(&self, id: ItemId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.purge_item_in(&mut u, id).await?;
+        self.commit(u).await
+    }
+
+    async fn apply(&self, batch: Batch) -> Result<Vec<Upsert<ItemId>>> {
+        // All-or-nothing: an error drops `u` (rolling back) before any event is published.
+        let mut u = self.begin().await?;
+        let mut upserts = Vec::new();
+        for mutation in batch.into_mutations() {
+            if let Some(outcome) = self.mutate_in(&mut u, mutation).await? {
+                upserts.push(outcome);
+            }
+        }
+        self.commit(u).await?;
+        Ok(upserts)
+    }
+
+    async fn add_member(&self, channel: ChannelId, user: UserId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.add_member_in(&mut u, channel, user).await?;
+        self.commit(u).await
+    }
+
+    async fn remove_member(&self, channel: ChannelId, user: UserId) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.remove_member_in(&mut u, channel, user).await?;
+        self.commit(u).await
+    }
+
     async fn members(&self, channel: ChannelId) -> Result<Vec<UserId>> {
-        let rows = sqlx::query("SELECT user_id FROM channel_members WHERE channel_id = ?")
+        let rows = sqlx::query("SELECT user_id FROM channel_members WHERE channel_id = $1")
             .bind(channel.to_string())
             .fetch_all(&self.pool)
             .await
@@ -423,6 +1361,35 @@ impl WriteCtx for Store {
             .map(|r| user_id(&r.try_get::<String, _>("user_id").map_err(db)?))
             .collect()
     }
+
+    async fn grant_role(&self, channel: ChannelId, user: UserId, role: Role) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.grant_role_in(&mut u, channel, user, &role).await?;
+        self.commit(u).await
+    }
+
+    async fn revoke_role(&self, channel: ChannelId, user: UserId, role: Role) -> Result<()> {
+        let mut u = self.begin().await?;
+        self.revoke_role_in(&mut u, channel, user, &role).await?;
+        self.commit(u).await
+    }
+
+    async fn role_grants(&self, channel: ChannelId) -> Result<Vec<(UserId, Role)>> {
+        let rows = sqlx::query(
+            "SELECT user_id, role FROM channel_roles WHERE channel_id = $1 ORDER BY user_id, role",
+        )
+        .bind(channel.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db)?;
+        rows.iter()
+            .map(|r| {
+                let user = user_id(&r.try_get::<String, _>("user_id").map_err(db)?)?;
+                let role = r.try_get::<String, _>("role").map_err(db)?.parse()?;
+                Ok((user, role))
+            })
+            .collect()
+    }
 }
 
 // The read path / discovery primitives (`cp_model::StoreCtx`, DESIGN §5). A cursor is an opaque string
@@ -437,37 +1404,56 @@ impl WriteCtx for Store {
 const MAX_LIMIT: u32 = 1000;
 
 /// Keyset comparator for resuming after a cursor: `<` walks older ids (`TimeDesc`), `>` newer (`TimeAsc`).
+/// The sort-key orders compare `(sort_key, id)` the same way.
 fn cursor_cmp(order: Order) -> &'static str {
     match order {
-        Order::TimeDesc => "<",
-        Order::TimeAsc => ">",
+        Order::TimeDesc | Order::SortKeyDesc => "<",
+        Order::TimeAsc | Order::SortKeyAsc => ">",
     }
 }
 
 fn order_sql(order: Order) -> &'static str {
     match order {
-        Order::TimeDesc => "DESC",
-        Order::TimeAsc => "ASC",
+        Order::TimeDesc | Order::SortKeyDesc => "DESC",
+        Order::TimeAsc | Order::SortKeyAsc => "ASC",
     }
 }
 
-/// The shared discovery `SELECT` list for one super-type, tagged so both UNION arms round-trip through
-/// [`row_to_node`]. Channels have no `external_key`, so it is a literal `NULL` there. Caller appends the
-/// `WHERE`/filters.
-fn select_channels(qb: &mut QueryBuilder<'_, Sqlite>) {
+fn by_sort_key(order: Order) -> bool {
+    matches!(order, Order::SortKeyAsc | Order::SortKeyDesc)
+}
+
+/// A sort-key cursor is `"{id}:{sort_key}"`: ids never contain `:`, so the first one splits it whatever
+/// the key holds. Anything else was not minted by `children` and is refused.
+fn sort_key_cursor(cursor: &Cursor) -> Result<Option<(&str, &str)>> {
+    cursor
+        .0
+        .as_deref()
+        .map(|c| {
+            c.split_once(':')
+                .ok_or_else(|| Error::Validation(format!("malformed sort-key cursor: {c}")))
+        })
+        .transpose()
+}
+
+/// The live-channel arm of a discovery read, tagged so both UNION arms round-trip through
+/// [`row_to_node`]. Channels have no `external_key`, so it is a literal `NULL` there. Ends in
+/// `WHERE trash_root IS NULL`, so callers append `AND …`.
+fn select_channels(qb: &mut QueryBuilder) {
     qb.push(
-        "SELECT 'channel' AS super_type, id, type_id, container, NULL AS external_key, payload \
-         FROM channels",
+        "SELECT 'channel' AS super_type, id, type_id, container, NULL AS external_key, payload, rev \
+         FROM channels WHERE trash_root IS NULL",
     );
 }
-fn select_items(qb: &mut QueryBuilder<'_, Sqlite>) {
+fn select_items(qb: &mut QueryBuilder) {
     qb.push(
-        "SELECT 'item' AS super_type, id, type_id, container, external_key, payload FROM items",
+        "SELECT 'item' AS super_type, id, type_id, container, external_key, payload, rev \
+         FROM items WHERE trash_root IS NULL",
     );
 }
 
 /// `AND type_id IN (...)` for a non-empty type filter; a no-op otherwise (`None`/empty ⇒ unfiltered).
-fn push_type_ids(qb: &mut QueryBuilder<'_, Sqlite>, type_ids: Option<&[TypeId]>) {
+fn push_type_ids(qb: &mut QueryBuilder, type_ids: Option<&[TypeId]>) {
     let Some(ids) = type_ids.filter(|v| !v.is_empty()) else {
         return;
     };
@@ -482,7 +1468,7 @@ fn push_type_ids(qb: &mut QueryBuilder<'_, Sqlite>, type_ids: Option<&[TypeId]>)
 }
 
 /// The keyset predicate, when resuming from a cursor.
-fn push_cursor(qb: &mut QueryBuilder<'_, Sqlite>, cursor: &Cursor, order: Order) {
+fn push_cursor(qb: &mut QueryBuilder, cursor: &Cursor, order: Order) {
     if let Some(id) = &cursor.0 {
         qb.push(" AND id ")
             .push(cursor_cmp(order))
@@ -491,19 +1477,109 @@ fn push_cursor(qb: &mut QueryBuilder<'_, Sqlite>, cursor: &Cursor, order: Order)
     }
 }
 
+/// `WITH RECURSIVE subtree(id, depth) …` rooted at `scope` (depth 0), uncapped — the recursion
+/// `descendants` runs, shared by the scoped primitives so they all agree on what "in scope" means.
+fn subtree(scope: ChannelId) -> QueryBuilder {
+    let mut qb = QueryBuilder::new(
+        "WITH RECURSIVE subtree(id, depth) AS (SELECT id, 0 FROM channels WHERE id = ",
+    );
+    qb.push_bind(scope.to_string());
+    qb.push(
+        " UNION ALL SELECT c.id, s.depth + 1 FROM channels c \
+         JOIN subtree s ON c.container = s.id) ",
+    );
+    qb
+}
+
+/// The condition a `search_index` row matches `needle` under. On sqlite that is a literal FTS5
+/// phrase: wrap in quotes, double any embedded quote, so user input is taken verbatim — its
+/// `AND`/`OR`/`NEAR`/`*`/column-filter operators are text, not FTS query syntax (no injection). On
+/// Postgres it is a case-insensitive substring match on either column, with `LIKE`'s own wildcards
+/// escaped, which the `pg_trgm` GIN indexes serve — the same matches the trigram tokenizer gives.
+fn push_match(qb: &mut QueryBuilder, backend: Backend, needle: &str) {
+    match backend {
+        Backend::Sqlite => {
+            qb.push("search_index MATCH ")
+                .push_bind(format!("\"{}\"", needle.replace('"', "\"\"")));
+        }
+        Backend::Postgres => {
+            let escaped = needle
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            let pattern = format!("%{escaped}%");
+            qb.push("(search_index.name ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR search_index.text ILIKE ")
+                .push_bind(pattern)
+                .push(")");
+        }
+    }
+}
+
+/// The relevance of a matched row, more negative ⇒ more relevant: FTS5's bm25 `rank` on sqlite, the
+/// negated best `pg_trgm` word similarity of either column on Postgres.
+fn push_score(qb: &mut QueryBuilder, backend: Backend, needle: &str) {
+    match backend {
+        Backend::Sqlite => {
+            qb.push("search_index.rank");
+        }
+        Backend::Postgres => {
+            qb.push("-GREATEST(word_similarity(")
+                .push_bind(needle.to_owned())
+                .push(", coalesce(search_index.name, '')), word_similarity(")
+                .push_bind(needle.to_owned())
+                .push(", coalesce(search_index.text, '')))");
+        }
+    }
+}
+
+/// `AND id IN (…)` the envelopes of `super_type` whose indexed box overlaps `rect` (AABB overlap of
+/// `[min_x,max_x]×[min_y,max_y]` with the rectangle): R-tree range constraints on sqlite, the `&&` box
+/// operator the GiST index serves on Postgres.
+fn push_overlaps(qb: &mut QueryBuilder, backend: Backend, super_type: &'static str, rect: Rect) {
+    qb.push(
+        " AND id IN (SELECT k.envelope_id FROM spatial_index r \
+         JOIN spatial_key k ON k.rid = r.rid WHERE k.super_type = ",
+    );
+    qb.push_bind(super_type);
+    match backend {
+        Backend::Sqlite => {
+            qb.push(" AND r.max_x >= ").push_bind(rect.x0);
+            qb.push(" AND r.min_x <= ").push_bind(rect.x1);
+            qb.push(" AND r.max_y >= ").push_bind(rect.y0);
+            qb.push(" AND r.min_y <= ").push_bind(rect.y1);
+        }
+        Backend::Postgres => {
+            qb.push(" AND box(point(r.min_x, r.min_y), point(r.max_x, r.max_y)) && box(point(")
+                .push_bind(rect.x0)
+                .push(", ")
+                .push_bind(rect.y0)
+                .push("), point(")
+                .push_bind(rect.x1)
+                .push(", ")
+                .push_bind(rect.y1)
+                .push("))");
+        }
+    }
+    qb.push(")");
+}
+
 /// Rebuild a [`Node`] from a discovery row (either UNION arm), keyed on the tagged `super_type`.
-fn row_to_node(row: &SqliteRow) -> Result<Node> {
+fn row_to_node(row: &AnyRow) -> Result<Node> {
     let id: String = row.try_get("id").map_err(db)?;
     let type_id = TypeId::new(row.try_get::<String, _>("type_id").map_err(db)?);
     let container: Option<String> = row.try_get("container").map_err(db)?;
     let container = container.as_deref().map(channel_id).transpose()?;
     let payload = from_text(&row.try_get::<String, _>("payload").map_err(db)?)?;
+    let rev = row.try_get("rev").map_err(db)?;
     match row.try_get::<String, _>("super_type").map_err(db)?.as_str() {
         "channel" => Ok(Node::Channel(Channel {
             id: channel_id(&id)?,
             type_id,
             container,
             payload,
+            rev,
         })),
         "item" => Ok(Node::Item(Item {
             id: item_id(&id)?,
@@ -511,6 +1587,7 @@ fn row_to_node(row: &SqliteRow) -> Result<Node> {
             container,
             external_key: row.try_get("external_key").map_err(db)?,
             payload,
+            rev,
         })),
         other => Err(Error::Other(format!("unknown super_type in row: {other}"))),
     }
@@ -543,28 +1620,69 @@ impl StoreCtx for Store {
         let want_items = filter.super_type != Some(SuperType::Channel);
         let type_ids = filter.type_ids.as_deref();
 
+        let by_key = by_sort_key(order);
+        let key_cursor = if by_key {
+            sort_key_cursor(&page.cursor)?
+        } else {
+            None
+        };
+
         // One UNION arm per wanted super-type; the trailing ORDER BY/LIMIT applies to the whole
-        // compound. Fetch limit+1 to learn whether a further page exists without a second query.
-        let mut qb = QueryBuilder::<Sqlite>::new("");
+        // compound. Fetch limit+1 to learn whether a further page exists without a second query. A
+        // sort-key order wraps the compound to look each node's key up in `sort_index` and moves the
+        // keyset predicate outside, over `(sort_key, id)`.
+        let mut qb = QueryBuilder::new("");
+        if by_key {
+            qb.push(
+                "SELECT * FROM (SELECT n.*, COALESCE((SELECT s.sort_key FROM sort_index s \
+                 WHERE s.super_type = n.super_type AND s.envelope_id = n.id), '') AS sort_key FROM (",
+            );
+        }
+        let id_cursor = if by_key {
+            Cursor(None)
+        } else {
+            page.cursor.clone()
+        };
         if want_channels {
             select_channels(&mut qb);
-            qb.push(" WHERE container = ");
+            qb.push(" AND container = ");
             qb.push_bind(container.to_string());
             push_type_ids(&mut qb, type_ids);
-            push_cursor(&mut qb, &page.cursor, order);
+            push_cursor(&mut qb, &id_cursor, order);
         }
         if want_channels && want_items {
             qb.push(" UNION ALL ");
         }
         if want_items {
             select_items(&mut qb);
-            qb.push(" WHERE container = ");
+            qb.push(" AND container = ");
             qb.push_bind(container.to_string());
             push_type_ids(&mut qb, type_ids);
-            push_cursor(&mut qb, &page.cursor, order);
+            push_cursor(&mut qb, &id_cursor, order);
+        }
+        if by_key {
+            qb.push(") n) keyed");
+            if let Some((id, key)) = key_cursor {
+                let cmp = cursor_cmp(order);
+                qb.push(" WHERE sort_key ")
+                    .push(cmp)
+                    .push(" ")
+                    .push_bind(key.to_owned())
+                    .push(" OR (sort_key = ")
+                    .push_bind(key.to_owned())
+                    .push(" AND id ")
+                    .push(cmp)
+                    .push(" ")
+                    .push_bind(id.to_owned())
+                    .push(")");
+            }
+            qb.push(" ORDER BY sort_key ")
+                .push(order_sql(order))
+                .push(", id ");
+        } else {
+            qb.push(" ORDER BY id ");
         }
-        qb.push(" ORDER BY id ")
-            .push(order_sql(order))
+        qb.push(order_sql(order))
             .push(" LIMIT ")
             .push_bind(i64::from(limit) + 1);
 
@@ -572,7 +1690,13 @@ impl StoreCtx for Store {
         let mut nodes = rows.iter().map(row_to_node).collect::<Result<Vec<_>>>()?;
         let next = if nodes.len() > limit as usize {
             nodes.truncate(limit as usize);
-            Cursor(Some(node_id(nodes.last().expect("limit >= 1"))))
+            let last = node_id(nodes.last().expect("limit >= 1"));
+            if by_key {
+                let key: String = rows[limit as usize - 1].try_get("sort_key").map_err(db)?;
+                Cursor(Some(format!("{last}:{key}")))
+            } else {
+                Cursor(Some(last))
+            }
         } else {
             Cursor(None)
         };
@@ -593,7 +1717,7 @@ impl StoreCtx for Store {
         // `depth` capping the hops. A node's depth = its container's depth + 1, so descendant channels
         // are the subtree minus root (depth >= 1) and an item qualifies when its container sits within
         // `depth - 1` hops. Fetch-all (no pagination): the primitive is "whole subtree" by contract.
-        let mut qb = QueryBuilder::<Sqlite>::new(
+        let mut qb = QueryBuilder::new(
             "WITH RECURSIVE subtree(id, depth) AS (SELECT id, 0 FROM channels WHERE id = ",
         );
         qb.push_bind(root.to_string());
@@ -609,7 +1733,7 @@ impl StoreCtx for Store {
 
         if want_channels {
             select_channels(&mut qb);
-            qb.push(" WHERE id IN (SELECT id FROM subtree WHERE depth >= 1)");
+            qb.push(" AND id IN (SELECT id FROM subtree WHERE depth >= 1)");
             push_type_ids(&mut qb, type_ids);
         }
         if want_channels && want_items {
@@ -617,7 +1741,7 @@ impl StoreCtx for Store {
         }
         if want_items {
             select_items(&mut qb);
-            qb.push(" WHERE container IN (SELECT id FROM subtree");
+            qb.push(" AND container IN (SELECT id FROM subtree");
             if let Some(max) = depth {
                 qb.push(" WHERE depth <= ");
                 qb.push_bind(i64::from(max) - 1);
@@ -660,10 +1784,6 @@ impl StoreCtx for Store {
         }
         let limit = page.limit.min(MAX_LIMIT);
         let offset = decode_search_cursor(&page.cursor)?;
-        // Match the needle as a literal FTS5 phrase: wrap in quotes, double any embedded quote. This
-        // treats user input verbatim — its `AND`/`OR`/`NEAR`/`*`/column-filter operators are text, not
-        // FTS query syntax (no injection).
-        let match_expr = format!("\"{}\"", needle.replace('"', "\"\""));
 
         let want_channels = filter.super_type != Some(SuperType::Item);
         let want_items = filter.super_type != Some(SuperType::Channel);
@@ -673,25 +1793,24 @@ impl StoreCtx for Store {
         // scoping share one mental model. Each arm MATCHes the FTS row then INNER JOINs its envelope
         // table (rebuilding the Node and dropping orphaned index rows). `search_index.rank` is bm25 (more
         // negative ⇒ more relevant); `id` breaks ties into a total order for stable offset paging. The
-        // FTS table is referenced unaliased: FTS5's table-level `MATCH` needs the real table name.
-        let mut qb = QueryBuilder::<Sqlite>::new(
-            "WITH RECURSIVE subtree(id, depth) AS (SELECT id, 0 FROM channels WHERE id = ",
-        );
-        qb.push_bind(scope.to_string());
-        qb.push(
-            " UNION ALL SELECT c.id, s.depth + 1 FROM channels c \
-             JOIN subtree s ON c.container = s.id) ",
-        );
+        // FTS table is referenced unaliased: FTS5's table-level `MATCH` needs the real table name. On
+        // Postgres the same arms match with `push_match` (#37).
+        let mut qb = subtree(scope);
 
         if want_channels {
             qb.push(
                 "SELECT 'channel' AS super_type, c.id, c.type_id, c.container, NULL AS external_key, \
-                 c.payload, search_index.rank AS score \
-                 FROM search_index JOIN channels c ON c.id = search_index.envelope_id \
-                 WHERE search_index.super_type = 'channel' AND search_index MATCH ",
+                 c.payload, c.rev, ",
+            );
+            push_score(&mut qb, self.backend, needle);
+            qb.push(
+                " AS score FROM search_index JOIN channels c ON c.id = search_index.envelope_id \
+                 WHERE search_index.super_type = 'channel' AND ",
+            );
+            push_match(&mut qb, self.backend, needle);
+            qb.push(
+                " AND c.trash_root IS NULL AND c.id IN (SELECT id FROM subtree WHERE depth >= 1)",
             );
-            qb.push_bind(match_expr.clone());
-            qb.push(" AND c.id IN (SELECT id FROM subtree WHERE depth >= 1)");
             push_type_ids(&mut qb, type_ids);
         }
         if want_channels && want_items {
@@ -700,12 +1819,15 @@ impl StoreCtx for Store {
         if want_items {
             qb.push(
                 "SELECT 'item' AS super_type, i.id, i.type_id, i.container, i.external_key, i.payload, \
-                 search_index.rank AS score \
-                 FROM search_index JOIN items i ON i.id = search_index.envelope_id \
-                 WHERE search_index.super_type = 'item' AND search_index MATCH ",
+                 i.rev, ",
+            );
+            push_score(&mut qb, self.backend, needle);
+            qb.push(
+                " AS score FROM search_index JOIN items i ON i.id = search_index.envelope_id \
+                 WHERE search_index.super_type = 'item' AND ",
             );
-            qb.push_bind(match_expr.clone());
-            qb.push(" AND i.container IN (SELECT id FROM subtree)");
+            push_match(&mut qb, self.backend, needle);
+            qb.push(" AND i.trash_root IS NULL AND i.container IN (SELECT id FROM subtree)");
             push_type_ids(&mut qb, type_ids);
         }
         qb.push(" ORDER BY score ASC, id ASC LIMIT ")
@@ -724,18 +1846,106 @@ impl StoreCtx for Store {
         Ok(NodePage { nodes, next })
     }
 
+    async fn within_bbox(
+        &self,
+        scope: ChannelId,
+        rect: Rect,
+        filter: Filter,
+        page: Page,
+    ) -> Result<NodePage> {
+        // The R-tree over the `index()` projection's `coord`/`extent` (`spatial_index`), scoped to
+        // `scope`'s subtree with the same CTE as `search`. Id-ordered with the `children` keyset cursor,
+        // so a viewport pages like a feed. See `design/index-search.md`.
+        if !(rect.x0 <= rect.x1 && rect.y0 <= rect.y1) {
+            return Err(Error::Validation(format!(
+                "bbox needs x0 <= x1 and y0 <= y1, got {rect:?}"
+            )));
+        }
+        if page.limit == 0 {
+            return Ok(NodePage {
+                nodes: Vec::new(),
+                next: Cursor(None),
+            });
+        }
+        let limit = page.limit.min(MAX_LIMIT);
+        let want_channels = filter.super_type != Some(SuperType::Item);
+        let want_items = filter.super_type != Some(SuperType::Channel);
+        let type_ids = filter.type_ids.as_deref();
+
+        let mut qb = subtree(scope);
+        if want_channels {
+            select_channels(&mut qb);
+            qb.push(" AND id IN (SELECT id FROM subtree WHERE depth >= 1)");
+            push_type_ids(&mut qb, type_ids);
+            push_overlaps(&mut qb, self.backend, "channel", rect);
+            push_cursor(&mut qb, &page.cursor, Order::TimeAsc);
+        }
+        if want_channels && want_items {
+            qb.push(" UNION ALL ");
+        }
+        if want_items {
+            select_items(&mut qb);
+            qb.push(" AND container IN (SELECT id FROM subtree)");
+            push_type_ids(&mut qb, type_ids);
+            push_overlaps(&mut qb, self.backend, "item", rect);
+            push_cursor(&mut qb, &page.cursor, Order::TimeAsc);
+        }
+        qb.push(" ORDER BY id ASC LIMIT ")
+            .push_bind(i64::from(limit) + 1);
+
+        let rows = qb.build().fetch_all(&self.pool).await.map_err(db)?;
+        let mut nodes = rows.iter().map(row_to_node).collect::<Result<Vec<_>>>()?;
+        let next = if nodes.len() > limit as usize {
+            nodes.truncate(limit as usize);
+            Cursor(Some(node_id(nodes.last().expect("limit >= 1"))))
+        } else {
+            Cursor(None)
+        };
+        Ok(NodePage { nodes, next })
+    }
+
+    async fn get_channel(&self, id: ChannelId) -> Result<Option<Channel>> {
+        Store::get_channel(self, id).await
+    }
+
+    async fn get_item(&self, id: ItemId) -> Result<Option<Item>> {
+        Store::get_item(self, id).await
+    }
+
     async fn is_member(&self, channel: ChannelId, user: UserId) -> Result<bool> {
         // The read side of the `channel_members` substrate (§8), consulted by `Permission` policies. §18.
-        let row = sqlx::query("SELECT 1 FROM channel_members WHERE channel_id = ? AND user_id = ?")
-            .bind(channel.to_string())
-            .bind(user.to_string())
-            .fetch_optional(&self.pool)
-            .await
-            .map_err(db)?;
+        let row =
+            sqlx::query("SELECT 1 FROM channel_members WHERE channel_id = $1 AND user_id = $2")
+                .bind(channel.to_string())
+                .bind(user.to_string())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(db)?;
         Ok(row.is_some())
     }
 
-    fn type_owned_db(&self) -> &SqlitePool {
+    async fn roles(&self, channel: ChannelId, user: UserId) -> Result<Vec<Role>> {
+        // Grants on the channel or any channel above it (§18). A membership is a `member` grant on its
+        // own channel only: joining a space is not joining every (private) room in it.
+        let rows: Vec<String> = sqlx::query_scalar(
+            "WITH RECURSIVE chain(id, container) AS (
+                 SELECT id, container FROM channels WHERE id = $1
+                 UNION ALL SELECT c.id, c.container FROM channels c JOIN chain ON c.id = chain.container
+             )
+             SELECT role FROM channel_roles
+              WHERE user_id = $2 AND channel_id IN (SELECT id FROM chain)
+             UNION
+             SELECT 'member' FROM channel_members WHERE user_id = $2 AND channel_id = $1",
+        )
+        .bind(channel.to_string())
+        .bind(user.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db)?;
+        rows.iter().map(|r| r.parse()).collect()
+    }
+
+    fn type_owned_db(&self) -> &AnyPool {
         // The §6 escape hatch: an escape-hatch kind's `contents` reads its own namespaced tables
         // through this (e.g. `canvas` its R-tree). It is the same pool; kinds are trusted to touch only
         // their `<kind>_*` tables, never core's `channels`/`items`. See `design/runtime.md`.
@@ -0,0 +1,357 @@
+//! Brute-force protection for password login (DESIGN §2, `design/auth.md`, `TODO.md` #44). Every failed
+//! login is recorded in a ledger twice — against the handle tried and against the client address — and
+//! a subject that keeps failing must wait a doubling delay before its next attempt is checked, then is
+//! locked out for a while. A blocked attempt is refused without verifying the password, and refused the
+//! same way a wrong password is, for a handle that exists or not: the ledger never tells a guesser
+//! whether a handle is real. A successful login forgets the handle's failures — for a user with a second
+//! factor (#45), only once their code is right too. Lockouts are recorded for the debug shell to show
+//! and clear. Sibling to `auth`, whose [`auth::authenticate`] this wraps.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use cp_model::{Error, Result, User};
+use serde::Serialize;
+use sqlx::{Any, AnyPool, Row, Transaction};
+
+use crate::auth;
+
+/// How many failures a subject may have before attempts are delayed, and at how many it is locked out.
+struct Limits {
+    free: i64,
+    lock_after: i64,
+}
+
+/// A handle is one account, so it gets few tries.
+const HANDLE: Limits = Limits {
+    free: 3,
+    lock_after: 10,
+};
+
+/// An address may be shared (a NAT, an office), so it gets more.
+const IP: Limits = Limits {
+    free: 10,
+    lock_after: 50,
+};
+
+/// The first delay; each further failure doubles it, up to [`MAX_DELAY`].
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// How long a lockout lasts. A failure after it ends locks the subject out again at once.
+const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// A subject with no failure for this long starts over. Longer than [`LOCKOUT`], so sitting out a
+/// lockout does not reset the count.
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// What a ledger row counts failures against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Subject {
+    /// A handle as it was tried, whether or not a user has it.
+    Handle(String),
+    /// A client address, as the HTTP layer saw it.
+    Ip(String),
+}
+
+impl Subject {
+    fn kind(&self) -> &'static str {
+        match self {
+            Subject::Handle(_) => "handle",
+            Subject::Ip(_) => "ip",
+        }
+    }
+
+    fn value(&self) -> &str {
+        match self {
+            Subject::Handle(s) | Subject::Ip(s) => s,
+        }
+    }
+
+    fn limits(&self) -> &'static Limits {
+        match self {
+            Subject::Handle(_) => &HANDLE,
+            Subject::Ip(_) => &IP,
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.value())
+    }
+}
+
+impl Serialize for Subject {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// A subject locked out of password login, as the shell lists it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Lockout {
+    pub subject: Subject,
+    pub failures: i64,
+    pub locked_at: String,
+    pub until: String,
+}
+
+/// How long a subject that has just failed for the `failures`th time must wait, and whether that wait
+/// is a lockout.
+fn penalty(limits: &Limits, failures: i64) -> (Duration, bool) {
+    if failures >= limits.lock_after {
+        (LOCKOUT, true)
+    } else if failures > limits.free {
+        let doublings = u32::try_from(failures - limits.free - 1).unwrap_or(u32::MAX);
+        let delay = 2u32
+            .checked_pow(doublings)
+            .and_then(|factor| BASE_DELAY.checked_mul(factor))
+            .unwrap_or(MAX_DELAY);
+        (delay.min(MAX_DELAY), false)
+    } else {
+        (Duration::ZERO, false)
+    }
+}
+
+/// Check a password login, throttled: the login route's entry point. `ip` is the client address, if
+/// known. `None` for a wrong password, an unknown handle, and an attempt made while the handle or the
+/// address is blocked — indistinguishably, by design. Only checked attempts count as failures, so
+/// hammering a blocked subject does not extend its block. An attempt is counted as a failure before the
+/// password is verified, in the write that checks the block, and refunded if it was right: concurrent
+/// guesses each see the ones before them, so sending them in parallel does not skip the delays.
+pub async fn authenticate(
+    pool: &AnyPool,
+    handle: &str,
+    password: &str,
+    ip: Option<&str>,
+) -> Result<Option<User>> {
+    let mut subjects = vec![Subject::Handle(handle.to_owned())];
+    subjects.extend(ip.map(|ip| Subject::Ip(ip.to_owned())));
+    if !reserve(pool, &subjects).await? {
+        return Ok(None);
+    }
+    let Some(user) = auth::authenticate(pool, handle, password).await? else {
+        return Ok(None);
+    };
+    refund(pool, &subjects).await?;
+    // A 2FA user is not signed in yet: their failures are forgotten once the code is right too.
+    if !crate::totp::enabled(pool, user.id).await? {
+        forget(pool, &subjects[0]).await?;
+    }
+    Ok(Some(user))
+}
+
+/// In one write: unless one of `subjects` is blocked, count a failure against each of them ahead of
+/// the check it stands for. Whether the attempt may go ahead; [`refund`] takes the failure back.
+async fn reserve(pool: &AnyPool, subjects: &[Subject]) -> Result<bool> {
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    // Read the clock under the lock: a failure recorded while this waited must not look like a block.
+    let now = crate::db::now();
+    for subject in subjects {
+        let found: Option<String> = sqlx::query_scalar(
+            "SELECT kind FROM login_failures WHERE kind = $1 AND subject = $2 AND blocked_until > $3",
+        )
+        .bind(subject.kind())
+        .bind(subject.value())
+        .bind(&now)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db)?;
+        if found.is_some() {
+            tx.rollback().await.map_err(db)?;
+            return Ok(false);
+        }
+    }
+    for subject in subjects {
+        record_failure_in(&mut tx, subject).await?;
+    }
+    tx.commit().await.map_err(db)?;
+    Ok(true)
+}
+
+/// Take back a failure [`reserve`] counted for an attempt that turned out right: one fewer failure on
+/// each subject, and the delay or lockout that one set is lifted if the remaining count earns none.
+async fn refund(pool: &AnyPool, subjects: &[Subject]) -> Result<()> {
+    let now = SystemTime::now();
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    for subject in subjects {
+        let row = sqlx::query(
+            "SELECT failures, blocked_until, locked_at FROM login_failures \
+             WHERE kind = $1 AND subject = $2",
+        )
+        .bind(subject.kind())
+        .bind(subject.value())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db)?;
+        let Some(row) = row else {
+            continue;
+        };
+        let failures = row.try_get::<i64, _>("failures").map_err(db)? - 1;
+        let (wait, lockout) = penalty(subject.limits(), failures);
+        let mut blocked_until: String = row.try_get("blocked_until").map_err(db)?;
+        blocked_until = blocked_until.min(crate::db::timestamp(now + wait));
+        let mut locked_at: Option<String> = row.try_get("locked_at").map_err(db)?;
+        if !lockout {
+            locked_at = None;
+        }
+        sqlx::query(
+            "UPDATE login_failures SET failures = $1, blocked_until = $2, locked_at = $3 \
+             WHERE kind = $4 AND subject = $5",
+        )
+        .bind(failures.max(0))
+        .bind(blocked_until)
+        .bind(locked_at)
+        .bind(subject.kind())
+        .bind(subject.value())
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    }
+    tx.commit().await.map_err(db)
+}
+
+/// Whether any of `subjects` must still wait before an attempt is checked.
+pub(crate) async fn blocked(pool: &AnyPool, subjects: &[Subject]) -> Result<bool> {
+    let now = crate::db::now();
+    for subject in subjects {
+        let found: Option<String> = sqlx::query_scalar(
+            "SELECT kind FROM login_failures WHERE kind = $1 AND subject = $2 AND blocked_until > $3",
+        )
+        .bind(subject.kind())
+        .bind(subject.value())
+        .bind(&now)
+        .fetch_optional(pool)
+        .await
+        .map_err(db)?;
+        if found.is_some() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Count a failed attempt against each of `subjects`. `crate::totp` also counts wrong codes here.
+pub(crate) async fn fail(pool: &AnyPool, subjects: &[Subject]) -> Result<()> {
+    for subject in subjects {
+        record_failure(pool, subject).await?;
+    }
+    Ok(())
+}
+
+/// Count one failure against `subject` and set its next delay or lockout.
+async fn record_failure(pool: &AnyPool, subject: &Subject) -> Result<()> {
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    record_failure_in(&mut tx, subject).await?;
+    tx.commit().await.map_err(db)
+}
+
+/// [`record_failure`] inside an open write.
+async fn record_failure_in(tx: &mut Transaction<'static, Any>, subject: &Subject) -> Result<()> {
+    let now = SystemTime::now();
+    let row = sqlx::query(
+        "SELECT failures, last_failed_at, locked_at FROM login_failures \
+         WHERE kind = $1 AND subject = $2",
+    )
+    .bind(subject.kind())
+    .bind(subject.value())
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(db)?;
+    let forget_before = crate::db::ago(FORGET_AFTER);
+    let (previous, mut locked_at) = match row {
+        Some(row) if row.try_get::<String, _>("last_failed_at").map_err(db)? > forget_before => (
+            row.try_get::<i64, _>("failures").map_err(db)?,
+            row.try_get::<Option<String>, _>("locked_at").map_err(db)?,
+        ),
+        _ => (0, None),
+    };
+    let failures = previous + 1;
+    let (wait, lockout) = penalty(subject.limits(), failures);
+    if lockout {
+        locked_at = Some(crate::db::timestamp(now));
+        tracing::warn!(%subject, failures, "login locked out");
+    }
+    sqlx::query(
+        "INSERT INTO login_failures \
+         (kind, subject, failures, last_failed_at, blocked_until, locked_at) \
+         VALUES ($1, $2, $3, $4, $5, $6) \
+         ON CONFLICT (kind, subject) DO UPDATE SET failures = excluded.failures, \
+         last_failed_at = excluded.last_failed_at, blocked_until = excluded.blocked_until, \
+         locked_at = excluded.locked_at",
+    )
+    .bind(subject.kind())
+    .bind(subject.value())
+    .bind(failures)
+    .bind(crate::db::timestamp(now))
+    .bind(crate::db::timestamp(now + wait))
+    .bind(locked_at)
+    .execute(&mut **tx)
+    .await
+    .map_err(db)?;
+    Ok(())
+}
+
+/// Every subject locked out right now, the latest lockout first.
+pub async fn lockouts(pool: &AnyPool) -> Result<Vec<Lockout>> {
+    let rows = sqlx::query(
+        "SELECT kind, subject, failures, locked_at, blocked_until FROM login_failures \
+         WHERE locked_at IS NOT NULL AND blocked_until > $1 ORDER BY locked_at DESC",
+    )
+    .bind(crate::db::now())
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter()
+        .map(|row| {
+            let value: String = row.try_get("subject").map_err(db)?;
+            let subject = match row.try_get::<String, _>("kind").map_err(db)?.as_str() {
+                "handle" => Subject::Handle(value),
+                "ip" => Subject::Ip(value),
+                other => return Err(Error::Other(format!("unknown ledger kind `{other}`"))),
+            };
+            Ok(Lockout {
+                subject,
+                failures: row.try_get("failures").map_err(db)?,
+                locked_at: row.try_get("locked_at").map_err(db)?,
+                until: row.try_get("blocked_until").map_err(db)?,
+            })
+        })
+        .collect()
+}
+
+/// Forget a subject's failures, lifting any delay or lockout. `NotFound` if it has none on record.
+pub async fn clear(pool: &AnyPool, subject: &Subject) -> Result<()> {
+    if forget(pool, subject).await? == 0 {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+pub(crate) async fn forget(pool: &AnyPool, subject: &Subject) -> Result<u64> {
+    let affected = sqlx::query("DELETE FROM login_failures WHERE kind = $1 AND subject = $2")
+        .bind(subject.kind())
+        .bind(subject.value())
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(affected)
+}
+
+/// Delete ledger rows with no failure for [`FORGET_AFTER`], returning how many. They already count as
+/// clean; the session reaper runs this so the ledger does not keep every handle ever mistyped.
+pub async fn purge_stale(pool: &AnyPool) -> Result<u64> {
+    let affected = sqlx::query("DELETE FROM login_failures WHERE last_failed_at <= $1")
+        .bind(crate::db::ago(FORGET_AFTER))
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(affected)
+}
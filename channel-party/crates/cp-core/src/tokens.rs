@@ -0,0 +1,348 @@
+//! Personal access tokens (DESIGN §2, `design/auth.md`, `TODO.md` #42): named, revocable credentials for
+//! scripts and bots, sent as `Authorization: Bearer <token>` instead of a session cookie. A token acts as
+//! its user, narrowed by a scope set — [`Scopes`] — so it can do at most what its user can *and* what
+//! its scopes allow. Like a session, only the SHA-256 is stored. The HTTP layer lives in `cp-frontend`;
+//! this module is the store logic it and the debug shell call. Sibling to `auth`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use cp_model::{Action, ChannelId, Error, Result, User, UserId};
+use serde::Serialize;
+use sqlx::any::AnyRow;
+use sqlx::{AnyPool, Row};
+
+use crate::auth;
+
+/// The prefix every token carries, so one is recognisable in a config file or a leaked log.
+const PREFIX: &str = "cpt_";
+
+/// The longest lifetime a token may be given; for longer, mint one that never expires.
+pub const MAX_TTL: Duration = Duration::from_secs(5 * 365 * 24 * 60 * 60);
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+/// One thing a token may do: an action, anywhere or only within one channel's subtree. Actions are
+/// cumulative — `manage` covers `post`, and `post` covers `read` (`Action::View`). Written `read`,
+/// `post`, `manage`, or `<action>:<channel-id>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scope {
+    pub action: Action,
+    pub channel: Option<ChannelId>,
+}
+
+fn rank(action: Action) -> u8 {
+    match action {
+        Action::View => 0,
+        Action::Post => 1,
+        Action::Manage => 2,
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.action {
+            Action::View => "read",
+            Action::Post => "post",
+            Action::Manage => "manage",
+        })?;
+        match self.channel {
+            Some(ch) => write!(f, ":{ch}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (action, channel) = match s.split_once(':') {
+            Some((action, channel)) => (action, Some(channel)),
+            None => (s, None),
+        };
+        let action = match action {
+            "read" => Action::View,
+            "post" => Action::Post,
+            "manage" => Action::Manage,
+            _ => {
+                return Err(Error::Validation(format!(
+                    "unknown scope `{s}`: use read, post or manage, optionally `:<channel-id>`"
+                )))
+            }
+        };
+        let channel = channel
+            .map(|c| c.parse::<ChannelId>())
+            .transpose()
+            .map_err(|_| Error::Validation(format!("scope `{s}` names an invalid channel id")))?;
+        Ok(Scope { action, channel })
+    }
+}
+
+impl Serialize for Scope {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// What a request's credential may do, on top of what its user may. A session is [`Scopes::Full`]; a
+/// token is limited to its scope set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Scopes {
+    Full,
+    Limited(Vec<Scope>),
+}
+
+impl Scopes {
+    /// Whether the credential covers `action` on `channel`, or — with `None` — on no channel in
+    /// particular (the top level, operator routes), which only an unscoped grant covers.
+    pub async fn allows(
+        &self,
+        pool: &AnyPool,
+        action: Action,
+        channel: Option<ChannelId>,
+    ) -> Result<bool> {
+        let Scopes::Limited(scopes) = self else {
+            return Ok(true);
+        };
+        let mut roots = Vec::new();
+        for scope in scopes.iter().filter(|s| rank(s.action) >= rank(action)) {
+            match scope.channel {
+                None => return Ok(true),
+                Some(root) => roots.push(root),
+            }
+        }
+        let Some(channel) = channel else {
+            return Ok(false);
+        };
+        for root in roots {
+            if within(pool, channel, root).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether a read of `channel` should be judged as the token's user: it lies within a scope that
+    /// covers `action`, or above one — the channels a reader passes through to reach its scoped subtree.
+    /// Anything else is judged as an anonymous visitor would be (see `authz::ViewScope`).
+    pub async fn reaches(
+        &self,
+        pool: &AnyPool,
+        action: Action,
+        channel: ChannelId,
+    ) -> Result<bool> {
+        if self.allows(pool, action, Some(channel)).await? {
+            return Ok(true);
+        }
+        let Scopes::Limited(scopes) = self else {
+            return Ok(true);
+        };
+        for scope in scopes.iter().filter(|s| rank(s.action) >= rank(action)) {
+            if let Some(root) = scope.channel {
+                if within(pool, root, channel).await? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether this is a full session rather than a token.
+    pub fn is_full(&self) -> bool {
+        matches!(self, Scopes::Full)
+    }
+}
+
+/// Whether `channel` is `root` or sits anywhere below it.
+async fn within(pool: &AnyPool, channel: ChannelId, root: ChannelId) -> Result<bool> {
+    let found: Option<String> = sqlx::query_scalar(
+        "WITH RECURSIVE above(id, container) AS (
+             SELECT id, container FROM channels WHERE id = $1
+             UNION ALL SELECT c.id, c.container FROM channels c JOIN above a ON c.id = a.container
+         )
+         SELECT id FROM above WHERE id = $2",
+    )
+    .bind(channel.to_string())
+    .bind(root.to_string())
+    .fetch_optional(pool)
+    .await
+    .map_err(db)?;
+    Ok(found.is_some())
+}
+
+/// A token as listed. The secret is never stored, so it is not here.
+#[derive(Clone, Debug, Serialize)]
+pub struct Token {
+    pub id: String,
+    pub user_id: UserId,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+fn token_from_row(row: &AnyRow) -> Result<Token> {
+    let scopes: Vec<String> =
+        serde_json::from_str(&row.try_get::<String, _>("scopes").map_err(db)?)
+            .map_err(|e| Error::Other(e.to_string()))?;
+    Ok(Token {
+        id: row.try_get("id").map_err(db)?,
+        user_id: row
+            .try_get::<String, _>("user_id")
+            .map_err(db)?
+            .parse()
+            .map_err(|_| Error::Other("invalid user id".to_owned()))?,
+        name: row.try_get("name").map_err(db)?,
+        scopes: scopes.iter().map(|s| s.parse()).collect::<Result<_>>()?,
+        created_at: row.try_get("created_at").map_err(db)?,
+        expires_at: row.try_get("expires_at").map_err(db)?,
+        last_used_at: row.try_get("last_used_at").map_err(db)?,
+    })
+}
+
+const COLUMNS: &str = "id, user_id, name, scopes, created_at, expires_at, last_used_at";
+
+/// Mint a token for `user`, returning it and its secret (shown once). `Validation` for an empty name or
+/// scope set or a lifetime over [`MAX_TTL`], `NotFound` for a scope naming a channel that does not
+/// exist; `ttl` of `None` never expires.
+pub async fn mint(
+    pool: &AnyPool,
+    user: UserId,
+    name: &str,
+    scopes: Vec<Scope>,
+    ttl: Option<Duration>,
+) -> Result<(Token, String)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Validation("a token needs a name".to_owned()));
+    }
+    if scopes.is_empty() {
+        return Err(Error::Validation(
+            "a token needs at least one scope".to_owned(),
+        ));
+    }
+    let expires_at = match ttl {
+        None => None,
+        Some(ttl) => match SystemTime::now().checked_add(ttl) {
+            Some(at) if ttl <= MAX_TTL => Some(crate::db::timestamp(at)),
+            _ => {
+                return Err(Error::Validation(format!(
+                    "a token lasts at most {} days",
+                    MAX_TTL.as_secs() / 86_400
+                )))
+            }
+        },
+    };
+    for channel in scopes.iter().filter_map(|s| s.channel) {
+        let exists: Option<String> = sqlx::query_scalar("SELECT id FROM channels WHERE id = $1")
+            .bind(channel.to_string())
+            .fetch_optional(pool)
+            .await
+            .map_err(db)?;
+        if exists.is_none() {
+            return Err(Error::NotFound);
+        }
+    }
+    let secret = format!("{PREFIX}{}", auth::random_token());
+    let id = ulid::Ulid::new().to_string();
+    let encoded: Vec<String> = scopes.iter().map(ToString::to_string).collect();
+    sqlx::query(
+        "INSERT INTO api_tokens (id, token_hash, user_id, name, scopes, created_at, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(&id)
+    .bind(auth::sha256_hex(secret.as_bytes()))
+    .bind(user.to_string())
+    .bind(name)
+    .bind(serde_json::to_string(&encoded).map_err(|e| Error::Other(e.to_string()))?)
+    .bind(crate::db::now())
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .map_err(db)?;
+    let token = get(pool, &id).await?.ok_or(Error::NotFound)?;
+    Ok((token, secret))
+}
+
+/// One token by id, or `None`.
+pub async fn get(pool: &AnyPool, id: &str) -> Result<Option<Token>> {
+    let row = sqlx::query(&format!("SELECT {COLUMNS} FROM api_tokens WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db)?;
+    row.as_ref().map(token_from_row).transpose()
+}
+
+/// A user's tokens, expired ones included, oldest first.
+pub async fn list(pool: &AnyPool, user: UserId) -> Result<Vec<Token>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {COLUMNS} FROM api_tokens WHERE user_id = $1 ORDER BY id"
+    ))
+    .bind(user.to_string())
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+    rows.iter().map(token_from_row).collect()
+}
+
+/// Revoke a token. `NotFound` if there is no such token.
+pub async fn revoke(pool: &AnyPool, id: &str) -> Result<()> {
+    let affected = sqlx::query("DELETE FROM api_tokens WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    if affected == 0 {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+/// Resolve a bearer secret to its user and scopes, stamping `last_used_at`. `None` if the token is
+/// unknown, revoked or expired.
+pub async fn resolve(pool: &AnyPool, secret: &str) -> Result<Option<(User, Scopes)>> {
+    let hash = auth::sha256_hex(secret.as_bytes());
+    let row = sqlx::query(
+        "SELECT t.id AS token_id, t.scopes AS scopes, u.id AS id, u.handle AS handle \
+         FROM api_tokens t JOIN users u ON u.id = t.user_id \
+         WHERE t.token_hash = $1 AND (t.expires_at IS NULL OR t.expires_at > $2)",
+    )
+    .bind(&hash)
+    .bind(crate::db::now())
+    .fetch_optional(pool)
+    .await
+    .map_err(db)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let token_id: String = row.try_get("token_id").map_err(db)?;
+    let scopes: Vec<String> =
+        serde_json::from_str(&row.try_get::<String, _>("scopes").map_err(db)?)
+            .map_err(|e| Error::Other(e.to_string()))?;
+    let scopes = scopes
+        .iter()
+        .map(|s| s.parse())
+        .collect::<Result<Vec<Scope>>>()?;
+    let user = User {
+        id: row
+            .try_get::<String, _>("id")
+            .map_err(db)?
+            .parse()
+            .map_err(|_| Error::Other("invalid user id".to_owned()))?,
+        handle: row.try_get("handle").map_err(db)?,
+    };
+    sqlx::query("UPDATE api_tokens SET last_used_at = $1 WHERE id = $2")
+        .bind(crate::db::now())
+        .bind(&token_id)
+        .execute(pool)
+        .await
+        .map_err(db)?;
+    Ok(Some((user, Scopes::Limited(scopes))))
+}
@@ -0,0 +1,474 @@
+//! TOTP second factor (DESIGN §2, `design/auth.md`, `TODO.md` #45): optional RFC 6238 codes for native
+//! users. A user enrolls by scanning a provisioning URI and confirming with a first code, which also
+//! issues single-use recovery codes (stored hashed, shown once). From then on a correct password earns
+//! only a short-lived *pending login* token, exchanged with a code for a session. Wrong codes count
+//! against the handle in `crate::throttle`, like wrong passwords. The HTTP layer lives in `cp-frontend`;
+//! the debug shell's `reset-totp` calls [`reset`]. Sibling to `auth`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cp_model::{Error, Result, User, UserId};
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+use sha1::Sha1;
+use sqlx::{Any, AnyPool, Row, Transaction};
+
+use crate::auth;
+use crate::throttle::{self, Subject};
+
+/// Digits per code, and seconds per time step: what every authenticator app assumes.
+const DIGITS: u32 = 6;
+const STEP_SECS: u64 = 30;
+
+/// How many steps either side of now a code is still accepted, for clock drift.
+const SKEW: u64 = 1;
+
+/// Secret length in bytes: 160 bits, the HMAC-SHA1 block RFC 4226 recommends.
+const SECRET_LEN: usize = 20;
+
+/// How many recovery codes an enrollment issues.
+const RECOVERY_CODES: usize = 10;
+
+/// How long a pending login waits for its code, and how many wrong codes it takes.
+const PENDING_TTL: Duration = Duration::from_secs(5 * 60);
+const PENDING_ATTEMPTS: i64 = 5;
+
+fn db(e: sqlx::Error) -> Error {
+    Error::Other(e.to_string())
+}
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32, unpadded — the form provisioning URIs carry a secret in.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for &b in bytes {
+        buffer = (buffer << 8) | u32::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in s.trim_end_matches('=').bytes() {
+        let value = BASE32.iter().position(|&b| b == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// RFC 4226 HOTP: the dynamic truncation of HMAC-SHA1(key, counter), as a [`DIGITS`]-digit number.
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC takes a key of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(DIGITS)
+}
+
+fn step_at(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / STEP_SECS
+}
+
+/// The code a base32 `secret` shows at `at` — what the user's authenticator app displays then.
+/// `Validation` if the secret is not base32.
+pub fn code_at(secret: &str, at: SystemTime) -> Result<String> {
+    let key = base32_decode(secret)
+        .ok_or_else(|| Error::Validation("a TOTP secret must be base32".to_owned()))?;
+    Ok(format!(
+        "{:0width$}",
+        hotp(&key, step_at(at)),
+        width = DIGITS as usize
+    ))
+}
+
+/// The time step `code` is valid for within [`SKEW`] of now, if any, and later than `last_step` — a
+/// code is accepted once.
+fn matching_step(secret: &str, last_step: Option<i64>, code: &str) -> Option<u64> {
+    if !is_totp_code(code) {
+        return None;
+    }
+    let key = base32_decode(secret)?;
+    let code: u32 = code.parse().ok()?;
+    let now = step_at(SystemTime::now());
+    (now.saturating_sub(SKEW)..=now + SKEW)
+        .filter(|step| last_step.is_none_or(|last| i64::try_from(*step).is_ok_and(|s| s > last)))
+        .find(|step| hotp(&key, *step) == code)
+}
+
+/// A code as typed: digits of a TOTP code, or a recovery code with its dashes and case dropped.
+fn normalize(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// A new enrollment: the secret, and the `otpauth://` URI an authenticator app scans (as a QR code) to
+/// add it. Both are shown to the user once, and are not a second factor until confirmed.
+#[derive(Clone, Debug, Serialize)]
+pub struct Enrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+/// A user's second factor, as they see it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Status {
+    pub enabled: bool,
+    pub recovery_codes_left: i64,
+}
+
+/// The `otpauth://` provisioning URI for `secret`, labelled `issuer:handle`.
+fn provisioning_uri(issuer: &str, handle: &str, secret: &str) -> String {
+    let escape = |s: &str| {
+        s.bytes().fold(String::new(), |mut out, b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("%{b:02X}"));
+            }
+            out
+        })
+    };
+    let issuer = escape(issuer);
+    format!(
+        "otpauth://totp/{issuer}:{}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}\
+         &period={STEP_SECS}",
+        escape(handle)
+    )
+}
+
+/// Whether `user` has a confirmed second factor — whether a password alone signs them in.
+pub async fn enabled(pool: &AnyPool, user: UserId) -> Result<bool> {
+    let found: Option<String> = sqlx::query_scalar(
+        "SELECT user_id FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL",
+    )
+    .bind(user.to_string())
+    .fetch_optional(pool)
+    .await
+    .map_err(db)?;
+    Ok(found.is_some())
+}
+
+/// `user`'s second factor and how many unused recovery codes it has left.
+pub async fn status(pool: &AnyPool, user: UserId) -> Result<Status> {
+    let enabled = enabled(pool, user).await?;
+    let left: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM totp_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user.to_string())
+    .fetch_one(pool)
+    .await
+    .map_err(db)?;
+    Ok(Status {
+        enabled,
+        recovery_codes_left: left,
+    })
+}
+
+/// Start enrolling `user`: a fresh secret, replacing any unconfirmed one. `Validation` if a second
+/// factor is already enabled — disable it first. `issuer` names this instance in the user's app.
+pub async fn begin_enrollment(pool: &AnyPool, user: &User, issuer: &str) -> Result<Enrollment> {
+    let mut bytes = [0u8; SECRET_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = base32_encode(&bytes);
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let enabled_at: Option<Option<String>> =
+        sqlx::query_scalar("SELECT enabled_at FROM user_totp WHERE user_id = $1")
+            .bind(user.id.to_string())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db)?;
+    if matches!(enabled_at, Some(Some(_))) {
+        return Err(Error::Validation(
+            "two-factor authentication is already enabled".to_owned(),
+        ));
+    }
+    sqlx::query(
+        "INSERT INTO user_totp (user_id, secret, created_at) VALUES ($1, $2, $3) \
+         ON CONFLICT (user_id) DO UPDATE SET secret = excluded.secret, \
+         created_at = excluded.created_at, last_step = NULL",
+    )
+    .bind(user.id.to_string())
+    .bind(&secret)
+    .bind(crate::db::now())
+    .execute(&mut *tx)
+    .await
+    .map_err(db)?;
+    tx.commit().await.map_err(db)?;
+    let uri = provisioning_uri(issuer, &user.handle, &secret);
+    Ok(Enrollment { secret, uri })
+}
+
+/// Finish enrolling `user` with a first code from their app, enabling the second factor and returning
+/// its recovery codes — shown this once. `NotFound` if no enrollment is pending; `Validation` for a
+/// wrong code.
+pub async fn confirm_enrollment(pool: &AnyPool, user: UserId, code: &str) -> Result<Vec<String>> {
+    let code = normalize(code);
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let row = sqlx::query(
+        "SELECT secret, last_step FROM user_totp WHERE user_id = $1 AND enabled_at IS NULL",
+    )
+    .bind(user.to_string())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db)?
+    .ok_or(Error::NotFound)?;
+    let secret: String = row.try_get("secret").map_err(db)?;
+    let last_step: Option<i64> = row.try_get("last_step").map_err(db)?;
+    let step = matching_step(&secret, last_step, &code)
+        .ok_or_else(|| Error::Validation("that code is not valid".to_owned()))?;
+    sqlx::query("UPDATE user_totp SET enabled_at = $1, last_step = $2 WHERE user_id = $3")
+        .bind(crate::db::now())
+        .bind(step as i64)
+        .bind(user.to_string())
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user.to_string())
+        .execute(&mut *tx)
+        .await
+        .map_err(db)?;
+    let mut codes = Vec::with_capacity(RECOVERY_CODES);
+    for _ in 0..RECOVERY_CODES {
+        let mut bytes = [0u8; 10];
+        OsRng.fill_bytes(&mut bytes);
+        let raw = base32_encode(&bytes).to_ascii_lowercase();
+        sqlx::query("INSERT INTO totp_recovery_codes (code_hash, user_id) VALUES ($1, $2)")
+            .bind(auth::sha256_hex(raw.as_bytes()))
+            .bind(user.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(db)?;
+        let groups: Vec<&str> = (0..raw.len()).step_by(4).map(|i| &raw[i..i + 4]).collect();
+        codes.push(groups.join("-"));
+    }
+    tx.commit().await.map_err(db)?;
+    Ok(codes)
+}
+
+/// Check a code against `user`'s enabled second factor, spending it: a TOTP code is not accepted again,
+/// and a recovery code is used up.
+async fn spend(tx: &mut Transaction<'static, Any>, user: UserId, code: &str) -> Result<bool> {
+    let code = normalize(code);
+    if is_totp_code(&code) {
+        let row = sqlx::query(
+            "SELECT secret, last_step FROM user_totp \
+             WHERE user_id = $1 AND enabled_at IS NOT NULL",
+        )
+        .bind(user.to_string())
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(db)?;
+        let Some(row) = row else {
+            return Ok(false);
+        };
+        let secret: String = row.try_get("secret").map_err(db)?;
+        let last_step: Option<i64> = row.try_get("last_step").map_err(db)?;
+        let Some(step) = matching_step(&secret, last_step, &code) else {
+            return Ok(false);
+        };
+        sqlx::query("UPDATE user_totp SET last_step = $1 WHERE user_id = $2")
+            .bind(step as i64)
+            .bind(user.to_string())
+            .execute(&mut **tx)
+            .await
+            .map_err(db)?;
+        return Ok(true);
+    }
+    let used = sqlx::query(
+        "UPDATE totp_recovery_codes SET used_at = $1 \
+         WHERE code_hash = $2 AND user_id = $3 AND used_at IS NULL",
+    )
+    .bind(crate::db::now())
+    .bind(auth::sha256_hex(code.as_bytes()))
+    .bind(user.to_string())
+    .execute(&mut **tx)
+    .await
+    .map_err(db)?
+    .rows_affected();
+    Ok(used == 1)
+}
+
+/// Turn off `user`'s second factor, proven with a current code or a recovery code. `NotFound` if none
+/// is enabled; `Validation` for a wrong code, or while the handle or `ip` is throttled. A wrong code
+/// counts against both as at login, so a stolen session cannot guess its way to turning 2FA off.
+pub async fn disable(pool: &AnyPool, user: UserId, code: &str, ip: Option<&str>) -> Result<()> {
+    if !enabled(pool, user).await? {
+        return Err(Error::NotFound);
+    }
+    let Some(found) = auth::user(pool, user).await? else {
+        return Err(Error::NotFound);
+    };
+    let mut subjects = vec![Subject::Handle(found.handle)];
+    subjects.extend(ip.map(|ip| Subject::Ip(ip.to_owned())));
+    if throttle::blocked(pool, &subjects).await? {
+        return Err(Error::Validation(
+            "too many wrong codes; try again later".to_owned(),
+        ));
+    }
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    if !spend(&mut tx, user, code).await? {
+        tx.rollback().await.map_err(db)?;
+        throttle::fail(pool, &subjects).await?;
+        return Err(Error::Validation("that code is not valid".to_owned()));
+    }
+    remove(&mut tx, user).await?;
+    tx.commit().await.map_err(db)?;
+    throttle::forget(pool, &subjects[0]).await?;
+    Ok(())
+}
+
+/// Remove `user`'s second factor, enabled or pending, with no code — the shell's `reset-totp`, for a
+/// user who lost their device and their recovery codes. Whether there was one.
+pub async fn reset(pool: &AnyPool, user: UserId) -> Result<bool> {
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    let removed = remove(&mut tx, user).await?;
+    tx.commit().await.map_err(db)?;
+    Ok(removed)
+}
+
+async fn remove(tx: &mut Transaction<'static, Any>, user: UserId) -> Result<bool> {
+    for table in ["totp_recovery_codes", "pending_logins"] {
+        sqlx::query(&format!("DELETE FROM {table} WHERE user_id = $1"))
+            .bind(user.to_string())
+            .execute(&mut **tx)
+            .await
+            .map_err(db)?;
+    }
+    let removed = sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(user.to_string())
+        .execute(&mut **tx)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(removed > 0)
+}
+
+/// The first step of a 2FA user's login: their password was right, so mint a pending login token for
+/// them to exchange with a code ([`complete_login`]). Lives [`PENDING_TTL`].
+pub async fn begin_login(pool: &AnyPool, user: UserId) -> Result<String> {
+    let token = auth::random_token();
+    sqlx::query("INSERT INTO pending_logins (token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(auth::sha256_hex(token.as_bytes()))
+        .bind(user.to_string())
+        .bind(crate::db::timestamp(SystemTime::now() + PENDING_TTL))
+        .execute(pool)
+        .await
+        .map_err(db)?;
+    Ok(token)
+}
+
+/// The second step: exchange a pending login token and a code (TOTP or recovery) for the user, whom
+/// the caller then gives a session. `None` for an unknown or expired token, a wrong code, or an attempt
+/// while the handle or `ip` is throttled — indistinguishably, as at the first step. A token takes
+/// [`PENDING_ATTEMPTS`] wrong codes before it is spent, and each counts against the handle and address.
+/// The attempt count is read and bumped in one write transaction, so concurrent guesses cannot share a
+/// count.
+pub async fn complete_login(
+    pool: &AnyPool,
+    pending: &str,
+    code: &str,
+    ip: Option<&str>,
+) -> Result<Option<User>> {
+    let hash = auth::sha256_hex(pending.as_bytes());
+    let user_id: Option<String> = sqlx::query_scalar(
+        "SELECT user_id FROM pending_logins WHERE token_hash = $1 AND expires_at > $2",
+    )
+    .bind(&hash)
+    .bind(crate::db::now())
+    .fetch_optional(pool)
+    .await
+    .map_err(db)?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    let user_id: UserId = user_id
+        .parse()
+        .map_err(|_| Error::Other("invalid user id".to_owned()))?;
+    let Some(user) = auth::user(pool, user_id).await? else {
+        return Ok(None);
+    };
+    let mut subjects = vec![Subject::Handle(user.handle.clone())];
+    subjects.extend(ip.map(|ip| Subject::Ip(ip.to_owned())));
+    if throttle::blocked(pool, &subjects).await? {
+        return Ok(None);
+    }
+
+    let mut tx = crate::db::begin_write(pool).await.map_err(db)?;
+    // Re-read under the write lock: an attempt that got here first may have spent the token.
+    let attempts: Option<i64> = sqlx::query_scalar(
+        "SELECT attempts FROM pending_logins WHERE token_hash = $1 AND expires_at > $2",
+    )
+    .bind(&hash)
+    .bind(crate::db::now())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db)?;
+    let Some(attempts) = attempts else {
+        tx.rollback().await.map_err(db)?;
+        return Ok(None);
+    };
+    let accepted = spend(&mut tx, user.id, code).await?;
+    if accepted || attempts + 1 >= PENDING_ATTEMPTS {
+        sqlx::query("DELETE FROM pending_logins WHERE token_hash = $1")
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(db)?;
+    } else {
+        sqlx::query("UPDATE pending_logins SET attempts = attempts + 1 WHERE token_hash = $1")
+            .bind(&hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(db)?;
+    }
+    tx.commit().await.map_err(db)?;
+
+    if accepted {
+        throttle::forget(pool, &subjects[0]).await?;
+        Ok(Some(user))
+    } else {
+        throttle::fail(pool, &subjects).await?;
+        Ok(None)
+    }
+}
+
+/// Delete expired pending logins, returning how many. The session reaper runs this.
+pub async fn purge_expired_pending(pool: &AnyPool) -> Result<u64> {
+    let affected = sqlx::query("DELETE FROM pending_logins WHERE expires_at <= $1")
+        .bind(crate::db::now())
+        .execute(pool)
+        .await
+        .map_err(db)?
+        .rows_affected();
+    Ok(affected)
+}
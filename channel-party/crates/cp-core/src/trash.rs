@@ -0,0 +1,102 @@
+//! Trash retention: the built-in `RuntimeComponent` that purges tombstones older than a configured age.
+//! `delete_*` only tombstones (§3); without this, trashed envelopes would be kept forever. It is an
+//! ordinary `Primary` component — registered in the composition root like any other — that reads the
+//! expired tombstones from core's tables and purges each through the write path, so purges are
+//! attributed (`component:trash-retention`), recorded in the revision history, and evented. §7.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_model::{
+    ChannelId, Error, Interests, ItemId, Result, RuntimeComponent, RuntimeCtx, WriteCtx, WriteScope,
+};
+use sqlx::{AnyPool, Row};
+
+/// The component's `name()`, and the actor its purges are attributed to.
+pub const NAME: &str = "trash-retention";
+
+/// How often the sweep runs (and once at start). Capped by the retention age, so a short retention is
+/// still honoured promptly.
+const SWEEP_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// Purges every tombstone older than `max_age`. Build with [`retention`].
+pub struct TrashRetention {
+    max_age: Duration,
+}
+
+/// The trash-retention component for a given maximum tombstone age.
+pub fn retention(max_age: Duration) -> TrashRetention {
+    TrashRetention { max_age }
+}
+
+#[async_trait]
+impl RuntimeComponent for TrashRetention {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn interests(&self) -> Interests {
+        Interests {
+            schedule_secs: Some(SWEEP_EVERY.min(self.max_age).as_secs().max(1)),
+            types: Vec::new(),
+        }
+    }
+
+    fn writes(&self) -> WriteScope {
+        WriteScope::Primary
+    }
+
+    async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
+        let writer = cx
+            .writer()
+            .ok_or_else(|| Error::Other("trash-retention needs a Primary writer".to_owned()))?;
+        // The first scheduler tick fires immediately, so a boot sweeps before the first interval.
+        while cx.next_event().await.is_some() {
+            let purged = sweep(cx.type_owned_db(), writer, self.max_age).await?;
+            if purged > 0 {
+                tracing::info!(purged, "trash-retention: purged expired tombstones");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Purge every envelope trashed directly more than `max_age` ago; returns how many were purged.
+/// Channels go first, so an item inside an expiring channel is purged by the cascade — its own purge
+/// then finds nothing, which is not an error. §3.
+pub async fn sweep(pool: &AnyPool, writer: &dyn WriteCtx, max_age: Duration) -> Result<usize> {
+    let db = |e: sqlx::Error| Error::Other(e.to_string());
+    let rows = sqlx::query(
+        "SELECT 'channel' AS super_type, id FROM channels
+           WHERE trash_root = id AND deleted_at <= $1
+         UNION ALL
+         SELECT 'item', id FROM items
+           WHERE trash_root = id AND deleted_at <= $1
+         ORDER BY super_type ASC, id ASC",
+    )
+    .bind(crate::db::ago(max_age))
+    .fetch_all(pool)
+    .await
+    .map_err(db)?;
+
+    let mut purged = 0;
+    for row in &rows {
+        let id: String = row.try_get("id").map_err(db)?;
+        let result = match row.try_get::<String, _>("super_type").map_err(db)?.as_str() {
+            "channel" => match id.parse::<ChannelId>() {
+                Ok(cid) => writer.purge_channel(cid).await,
+                Err(_) => Err(Error::Other(format!("invalid channel id: {id}"))),
+            },
+            _ => match id.parse::<ItemId>() {
+                Ok(iid) => writer.purge_item(iid).await,
+                Err(_) => Err(Error::Other(format!("invalid item id: {id}"))),
+            },
+        };
+        match result {
+            Ok(()) => purged += 1,
+            Err(Error::NotFound) => {} // already gone with an expired ancestor
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(purged)
+}
@@ -0,0 +1,435 @@
+//! Export/import round trips (`TODO.md` #36) between two tempfile stores: ids survive, kinds re-validate
+//! and re-index on import, memberships / links / kind-owned rows come along, password hashes only when
+//! asked, any conflict refuses the whole import, and kind rows never reach a table their owner does
+//! not create. Uses throwaway test kinds with their own migration set rather than a concrete kind
+//! crate (DESIGN §12).
+
+mod common;
+
+use async_trait::async_trait;
+use cp_core::archive::{self, ExportOptions, ImportOptions, Record};
+use cp_core::{Core, Registry};
+use cp_model::{
+    Channel, ChannelId, ChannelKind, Cursor, Error, Filter, IndexEntry, ItemKind, Json, Migration,
+    Migrations, NewChannel, NewItem, Page, Result, Role, StoreCtx, TypeId, WriteCtx,
+};
+use serde_json::json;
+
+struct Room(TypeId);
+
+#[async_trait]
+impl ChannelKind for Room {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _cx: &dyn StoreCtx, _ch: &Channel, _q: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the archive test")
+    }
+}
+
+/// A note must have a string `body`, which it indexes for search.
+struct Note(TypeId);
+
+impl ItemKind for Note {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    fn validate(&self, payload: &Json) -> Result<()> {
+        match payload.get("body") {
+            Some(Json::String(_)) => Ok(()),
+            _ => Err(Error::Validation("a note needs a string body".to_owned())),
+        }
+    }
+    fn index(&self, payload: &Json) -> Option<IndexEntry> {
+        Some(IndexEntry {
+            text: Some(payload.get("body")?.as_str()?.to_owned()),
+            ..Default::default()
+        })
+    }
+}
+
+/// The test kind's own table, which a whole-store archive dumps.
+static LAB: Migrations = Migrations {
+    owner: "lab",
+    steps: &[Migration {
+        name: "0001_lab",
+        sql: "-- a kind-owned table\nCREATE TABLE IF NOT EXISTS lab_notes (id TEXT PRIMARY KEY, n INTEGER, raw BLOB);",
+    }],
+    postgres: &[Migration {
+        name: "0001_lab",
+        sql: "CREATE TABLE IF NOT EXISTS lab_notes (id TEXT PRIMARY KEY, n INTEGER, raw BYTEA);",
+    }],
+};
+
+async fn core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Room(TypeId::new("room")))
+        .item(Note(TypeId::new("note")))
+        .migrations(LAB)
+        .build();
+    (dir, Core::open(&url, registry).await.unwrap())
+}
+
+fn room(name: &str, container: Option<ChannelId>) -> NewChannel {
+    NewChannel {
+        type_id: TypeId::new("room"),
+        container,
+        payload: json!({ "name": name }),
+    }
+}
+
+fn note(body: &str, container: ChannelId, key: Option<&str>) -> NewItem {
+    NewItem {
+        type_id: TypeId::new("note"),
+        container: Some(container),
+        external_key: key.map(str::to_owned),
+        payload: json!({ "body": body }),
+    }
+}
+
+struct Seeded {
+    top: ChannelId,
+    sub: ChannelId,
+    kept: cp_model::ItemId,
+    alice: cp_model::UserId,
+    bob: cp_model::UserId,
+}
+
+/// alice (with a password) is a member and the owner of `top`; bob is linked to the keyed note in `sub`
+/// and holds a custom role there.
+async fn seed(core: &Core) -> Seeded {
+    let store = core.store();
+    let pool = core.pool();
+    let alice = cp_core::auth::provision_user(pool, "alice").await.unwrap();
+    cp_core::auth::set_password(pool, "alice", "hunter22")
+        .await
+        .unwrap();
+    let bob = cp_core::auth::provision_user(pool, "bob").await.unwrap();
+    cp_core::auth::provision_user(pool, "carol").await.unwrap();
+
+    let top = store.create_channel(room("top", None)).await.unwrap();
+    let sub = store.create_channel(room("sub", Some(top))).await.unwrap();
+    store
+        .create_item(note("hello world", top, None))
+        .await
+        .unwrap();
+    let kept = store
+        .create_item(note("keyed", sub, Some("ext:1")))
+        .await
+        .unwrap();
+    let gone = store.create_item(note("trashed", sub, None)).await.unwrap();
+    store.delete_item(gone).await.unwrap();
+    store.add_member(top, alice).await.unwrap();
+    store.grant_role(top, alice, Role::Owner).await.unwrap();
+    store
+        .grant_role(sub, bob, "scribe".parse().unwrap())
+        .await
+        .unwrap();
+    cp_core::links::link(pool, bob, kept).await.unwrap();
+    sqlx::query("INSERT INTO lab_notes (id, n, raw) VALUES ('a', 7, $1)")
+        .bind(vec![0x00u8, 0xff])
+        .execute(pool)
+        .await
+        .unwrap();
+    Seeded {
+        top,
+        sub,
+        kept,
+        alice,
+        bob,
+    }
+}
+
+/// Export → JSONL → parse, as the CLI does.
+async fn round_trip(core: &Core, options: &ExportOptions) -> Vec<Record> {
+    let records = archive::export(&core.store(), options).await.unwrap();
+    archive::from_jsonl(&archive::to_jsonl(&records).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn a_whole_store_round_trips_with_ids_edges_and_kind_rows() {
+    let (_a, source) = core().await;
+    let s = seed(&source).await;
+    let records = round_trip(&source, &ExportOptions::default()).await;
+    let Record::Header(header) = &records[0] else {
+        panic!("the header comes first")
+    };
+    assert_eq!((header.root, header.password_hashes), (None, false));
+    assert_eq!(header.kind_tables.len(), 1);
+    assert_eq!(header.kind_tables[0].table, "lab_notes");
+
+    let (_b, target) = core().await;
+    let report = archive::import(&target.store(), records, &ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+    assert_eq!(
+        (
+            report.users,
+            report.channels,
+            report.items,
+            report.members,
+            report.roles,
+            report.links
+        ),
+        (3, 2, 2, 1, 2, 1),
+        "the trashed note is not exported"
+    );
+    assert_eq!((report.kind_rows, report.kind_rows_skipped), (1, 0));
+
+    // Same ids, same tree, same edges.
+    let store = target.store();
+    let sub = store.get_channel(s.sub).await.unwrap().unwrap();
+    assert_eq!(sub.container, Some(s.top));
+    let kept = store.get_item(s.kept).await.unwrap().unwrap();
+    assert_eq!(kept.external_key.as_deref(), Some("ext:1"));
+    assert!(store.is_member(s.top, s.alice).await.unwrap());
+    assert_eq!(
+        store.role_grants(s.top).await.unwrap(),
+        vec![(s.alice, Role::Owner)]
+    );
+    let linked = cp_core::links::user_for_item(target.pool(), s.kept)
+        .await
+        .unwrap();
+    assert_eq!(linked.map(|u| u.id), Some(s.bob));
+    // The kind's index() ran on import: the note is searchable.
+    let hits = store
+        .search(
+            s.top,
+            "hello",
+            Filter::default(),
+            Page {
+                cursor: Cursor(None),
+                limit: 10,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(hits.nodes.len(), 1);
+    // The BLOB survived its hex round trip.
+    let raw: Vec<u8> = sqlx::query_scalar("SELECT raw FROM lab_notes WHERE id = 'a'")
+        .fetch_one(target.pool())
+        .await
+        .unwrap();
+    assert_eq!(raw, vec![0x00, 0xff]);
+    // No hashes unless asked: alice exists but cannot log in.
+    assert!(
+        cp_core::auth::authenticate(target.pool(), "alice", "hunter22")
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn password_hashes_travel_only_when_asked() {
+    let (_a, source) = core().await;
+    seed(&source).await;
+    let with = ExportOptions {
+        password_hashes: true,
+        ..Default::default()
+    };
+    let records = round_trip(&source, &with).await;
+    let (_b, target) = core().await;
+    archive::import(&target.store(), records, &ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(
+        cp_core::auth::authenticate(target.pool(), "alice", "hunter22")
+            .await
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn any_conflict_refuses_the_whole_import() {
+    let (_a, source) = core().await;
+    seed(&source).await;
+    let records = round_trip(&source, &ExportOptions::default()).await;
+
+    // Importing a store into itself: every id is taken.
+    let report = archive::import(&source.store(), records.clone(), &ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(report
+        .conflicts
+        .iter()
+        .any(|c| c.reason.contains("channel with this id already exists")));
+    assert!(report
+        .conflicts
+        .iter()
+        .any(|c| c.reason.contains("external key `ext:1` is already in use")));
+
+    // A payload the kind rejects, and a type this build does not register, are conflicts too; and a
+    // clashing handle. Nothing at all is written.
+    let (_b, target) = core().await;
+    cp_core::auth::provision_user(target.pool(), "alice")
+        .await
+        .unwrap();
+    let mut tampered = records;
+    for record in &mut tampered {
+        match record {
+            Record::Item(i) if i.external_key.is_some() => i.payload = json!({ "body": 3 }),
+            Record::Channel(c) if c.container.is_some() => c.type_id = TypeId::new("gone"),
+            _ => {}
+        }
+    }
+    let report = archive::import(&target.store(), tampered, &ImportOptions::default())
+        .await
+        .unwrap();
+    let reasons: Vec<String> = report.conflicts.iter().map(ToString::to_string).collect();
+    assert_eq!(reasons.len(), 3, "{reasons:?}");
+    assert!(reasons.iter().any(|r| r.contains("needs a string body")));
+    assert!(reasons
+        .iter()
+        .any(|r| r.contains("type `gone` is not registered")));
+    assert!(reasons.iter().any(|r| r.starts_with("user @alice")));
+    assert_eq!(report.channels, 0);
+    let channels: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channels")
+        .fetch_one(target.pool())
+        .await
+        .unwrap();
+    assert_eq!(channels, 0, "nothing was written");
+}
+
+#[tokio::test]
+async fn kind_rows_go_only_into_their_owners_tables() {
+    let (_a, source) = core().await;
+    seed(&source).await;
+    let mut records = round_trip(&source, &ExportOptions::default()).await;
+    let Some(Record::User(alice)) = records
+        .iter()
+        .find(|r| matches!(r, Record::User(u) if u.handle == "alice"))
+        .cloned()
+    else {
+        panic!("alice is archived")
+    };
+    let row = |owner: &str, table: &str, row: Json| Record::KindRow {
+        owner: owner.to_owned(),
+        table: table.to_owned(),
+        row: row.as_object().unwrap().clone(),
+    };
+    records.extend([
+        // A core table, under the kind's name or any other: never written.
+        row(
+            "lab",
+            "users",
+            json!({ "id": "01J0000000000000000000000X", "handle": "mallory", "operator": 1 }),
+        ),
+        row(
+            "core",
+            "channel_roles",
+            json!({ "channel": "x", "user": alice.id.to_string(), "role": "owner" }),
+        ),
+        // The kind's own table, under another owner or with a column it lacks.
+        row("other", "lab_notes", json!({ "id": "b", "n": 1 })),
+        row(
+            "lab",
+            "lab_notes",
+            json!({ "id": "c", "n\") VALUES ('d', 1); --": 1 }),
+        ),
+    ]);
+
+    let (_b, target) = core().await;
+    let report = archive::import(&target.store(), records, &ImportOptions::default())
+        .await
+        .unwrap();
+    assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+    assert_eq!((report.kind_rows, report.kind_rows_skipped), (1, 4));
+    let mallory: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE handle = 'mallory'")
+        .fetch_one(target.pool())
+        .await
+        .unwrap();
+    assert_eq!(mallory, 0);
+    let notes: Vec<String> = sqlx::query_scalar("SELECT id FROM lab_notes ORDER BY id")
+        .fetch_all(target.pool())
+        .await
+        .unwrap();
+    assert_eq!(notes, vec!["a".to_owned()]);
+}
+
+#[tokio::test]
+async fn a_failed_batch_leaves_no_users_behind() {
+    let (_a, source) = core().await;
+    seed(&source).await;
+    let mut records = round_trip(&source, &ExportOptions::default()).await;
+    // Two notes under one external key pass the checks against the store, but not the write.
+    for record in &mut records {
+        if let Record::Item(i) = record {
+            i.external_key = Some("ext:dup".to_owned());
+        }
+    }
+
+    let (_b, target) = core().await;
+    assert!(
+        archive::import(&target.store(), records, &ImportOptions::default())
+            .await
+            .is_err()
+    );
+    let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(target.pool())
+        .await
+        .unwrap();
+    assert_eq!(users, 0);
+}
+
+#[tokio::test]
+async fn a_subtree_exports_its_own_users_and_grafts_under_into() {
+    let (_a, source) = core().await;
+    let s = seed(&source).await;
+    let records = round_trip(
+        &source,
+        &ExportOptions {
+            root: Some(s.sub),
+            ..Default::default()
+        },
+    )
+    .await;
+    let users: Vec<_> = records
+        .iter()
+        .filter_map(|r| match r {
+            Record::User(u) => Some(u.handle.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        users,
+        ["bob"],
+        "only the users its links, memberships and roles mention"
+    );
+    assert!(!records.iter().any(|r| matches!(r, Record::KindRow { .. })));
+
+    let (_b, target) = core().await;
+    let host = target
+        .store()
+        .create_channel(room("host", None))
+        .await
+        .unwrap();
+    let report = archive::import(
+        &target.store(),
+        records,
+        &ImportOptions { into: Some(host) },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        (report.channels, report.items, report.roles, report.links),
+        (1, 1, 1, 1)
+    );
+    let sub = target.store().get_channel(s.sub).await.unwrap().unwrap();
+    assert_eq!(sub.container, Some(host), "re-homed under --into");
+}
+
+#[test]
+fn only_archives_parse() {
+    assert!(archive::from_jsonl("{\"record\":\"user\",\"id\":\"x\"}").is_err());
+    let err = archive::from_jsonl(
+        "{\"record\":\"header\",\"format\":\"channel-party-archive\",\"version\":99,\
+         \"exported_at_ms\":0,\"root\":null,\"password_hashes\":false,\"kind_tables\":[]}",
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("version 99"), "{err}");
+}
@@ -1,11 +1,16 @@
 //! Integration tests for native-user auth + sessions (`TODO.md` #17): password provisioning +
-//! verification and the session lifecycle, against a real tempfile sqlite.
+//! verification and the session lifecycle, against a real tempfile sqlite — including sliding expiry,
+//! the owner's session list, logging out everywhere and reaping expired rows (#43).
 
+mod common;
+
+use cp_core::auth::SessionOrigin;
 use cp_core::{auth, Core, Registry};
+use cp_model::Error;
 
 async fn core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let core = Core::open(&url, Registry::builder().build()).await.unwrap();
     (dir, core)
 }
@@ -51,7 +56,9 @@ async fn sessions_resolve_and_revoke() {
     let uid = auth::provision_user(pool, "bob").await.unwrap();
     auth::set_password(pool, "bob", "pw").await.unwrap();
 
-    let token = auth::create_session(pool, uid).await.unwrap();
+    let token = auth::create_session(pool, uid, &Default::default())
+        .await
+        .unwrap();
     assert_eq!(
         auth::resolve_session(pool, &token)
             .await
@@ -71,3 +78,110 @@ async fn sessions_resolve_and_revoke() {
     auth::delete_session(pool, &token).await.unwrap();
     assert!(auth::resolve_session(pool, &token).await.unwrap().is_none());
 }
+
+/// Backdate one session's `last_seen_at` and `expires_at` (session-style text).
+async fn backdate(pool: &sqlx::AnyPool, id: &str, last_seen: &str, expires: &str) {
+    sqlx::query("UPDATE sessions SET last_seen_at = $1, expires_at = $2 WHERE id = $3")
+        .bind(last_seen)
+        .bind(expires)
+        .bind(id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn sessions_slide_list_and_revoke() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let alice = auth::provision_user(pool, "alice").await.unwrap();
+    let bob = auth::provision_user(pool, "bob").await.unwrap();
+
+    let origin = SessionOrigin {
+        user_agent: Some("curl/8".to_owned()),
+        ip: Some("192.0.2.1".to_owned()),
+    };
+    let laptop = auth::create_session(pool, alice, &origin).await.unwrap();
+    let phone = auth::create_session(pool, alice, &Default::default())
+        .await
+        .unwrap();
+    let sessions = auth::list_sessions(pool, alice, Some(&laptop))
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 2);
+    let current = sessions.iter().find(|s| s.current).unwrap();
+    assert_eq!(current.user_agent.as_deref(), Some("curl/8"));
+    assert_eq!(current.ip.as_deref(), Some("192.0.2.1"));
+    let phone_id = sessions.iter().find(|s| !s.current).unwrap().id.clone();
+
+    // A use slides a stale session's expiry forward.
+    let laptop_id = current.id.clone();
+    backdate(
+        pool,
+        &laptop_id,
+        "2000-01-01 00:00:00",
+        "2999-01-01 00:00:00",
+    )
+    .await;
+    auth::resolve_session(pool, &laptop).await.unwrap().unwrap();
+    let renewed = auth::list_sessions(pool, alice, Some(&laptop))
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.current)
+        .unwrap();
+    assert!(renewed.last_seen_at.as_str() > "2000-01-01 00:00:00");
+    assert!(renewed.expires_at.as_str() < "2999-01-01 00:00:00");
+
+    // Bob cannot revoke alice's session; alice can.
+    assert!(matches!(
+        auth::revoke_session(pool, bob, &phone_id).await,
+        Err(Error::NotFound)
+    ));
+    auth::revoke_session(pool, alice, &phone_id).await.unwrap();
+    assert!(auth::resolve_session(pool, &phone).await.unwrap().is_none());
+
+    // A new password logs alice out everywhere.
+    auth::create_session(pool, alice, &Default::default())
+        .await
+        .unwrap();
+    auth::set_password(pool, "alice", "correct horse")
+        .await
+        .unwrap();
+    assert!(auth::list_sessions(pool, alice, None)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(auth::resolve_session(pool, &laptop)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn expired_sessions_are_reaped() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let alice = auth::provision_user(pool, "alice").await.unwrap();
+    let stale = auth::create_session(pool, alice, &Default::default())
+        .await
+        .unwrap();
+    let live = auth::create_session(pool, alice, &Default::default())
+        .await
+        .unwrap();
+    let id = auth::list_sessions(pool, alice, Some(&stale))
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.current)
+        .unwrap()
+        .id;
+    backdate(pool, &id, "2000-01-01 00:00:00", "2000-01-31 00:00:00").await;
+
+    // Expired is already dead; the reaper only tidies it away.
+    assert!(auth::resolve_session(pool, &stale).await.unwrap().is_none());
+    assert_eq!(auth::purge_expired_sessions(pool).await.unwrap(), 1);
+    assert_eq!(auth::purge_expired_sessions(pool).await.unwrap(), 0);
+    assert!(auth::resolve_session(pool, &live).await.unwrap().is_some());
+    assert_eq!(auth::revoke_all_sessions(pool, alice).await.unwrap(), 1);
+}
@@ -1,12 +1,15 @@
 //! Authorization dispatch (`TODO.md` #18, `design/permissions.md`) against a real tempfile sqlite.
 //! Throwaway kinds (DESIGN §12) exercise core's genericity: deny-by-default for a kind with no
-//! `Permission`, an "allow" policy, and a membership-riding policy over the `channel_members` substrate.
+//! `Permission`, an "allow" policy, a membership-riding policy over the `channel_members` substrate,
+//! and the ready-made `RolePermission` over the inherited `channel_roles` substrate (#40).
+
+mod common;
 
 use async_trait::async_trait;
 use cp_core::{auth, authz, Core, Registry};
 use cp_model::{
-    Action, Channel, ChannelKind, Json, NewChannel, Permission, Result, StoreCtx, TypeId, UserId,
-    WriteCtx,
+    Action, Channel, ChannelId, ChannelKind, Grant, Json, NewChannel, Permission, Result, Role,
+    RolePermission, StoreCtx, TypeId, UserId, WriteCtx,
 };
 
 /// Grants `Post` to anyone; denies everything else.
@@ -78,24 +81,47 @@ impl ChannelKind for ClosedChannel {
     }
 }
 
+/// Rides `RolePermission`: the default grants, except that `scribe`s (a custom role) post.
+struct RolesChannel(TypeId, RolePermission);
+#[async_trait]
+impl ChannelKind for RolesChannel {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _: &dyn StoreCtx, _: &Channel, _: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the authz test")
+    }
+    fn permission(&self) -> Option<&dyn Permission> {
+        Some(&self.1)
+    }
+}
+
 async fn test_core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let registry = Registry::builder()
         .channel(OpenChannel(TypeId::new("open")))
         .channel(MembersChannel(TypeId::new("members")))
         .channel(ClosedChannel(TypeId::new("closed")))
+        .channel(RolesChannel(
+            TypeId::new("roles"),
+            RolePermission::new().post(Grant::AtLeast(Role::Custom("scribe".into()))),
+        ))
         .build();
     let core = Core::open(&url, registry).await.unwrap();
     (dir, core)
 }
 
 async fn channel_of(core: &Core, type_id: &str) -> Channel {
+    channel_under(core, type_id, None).await
+}
+
+async fn channel_under(core: &Core, type_id: &str, container: Option<ChannelId>) -> Channel {
     let store = core.store();
     let cid = store
         .create_channel(NewChannel {
             type_id: TypeId::new(type_id),
-            container: None,
+            container,
             payload: serde_json::json!({}),
         })
         .await
@@ -160,3 +186,67 @@ async fn membership_gates_post() {
             .unwrap()
     );
 }
+
+#[test]
+fn built_in_roles_rank_and_custom_roles_match_themselves() {
+    assert!(Role::Owner.satisfies(&Role::Admin));
+    assert!(Role::Moderator.satisfies(&Role::Member));
+    assert!(!Role::Member.satisfies(&Role::Moderator));
+    let scribe: Role = "scribe".parse().unwrap();
+    assert_eq!(scribe, Role::Custom("scribe".into()));
+    assert!(scribe.satisfies(&scribe));
+    assert!(!Role::Owner.satisfies(&scribe));
+    assert!(!scribe.satisfies(&Role::Member));
+    assert_eq!("admin".parse::<Role>().unwrap(), Role::Admin);
+    assert!("".parse::<Role>().is_err());
+    assert!("two words".parse::<Role>().is_err());
+}
+
+#[tokio::test]
+async fn roles_inherit_down_the_container_chain() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let alice = auth::provision_user(store.pool(), "alice").await.unwrap();
+    let bob = auth::provision_user(store.pool(), "bob").await.unwrap();
+    let top = channel_of(&core, "roles").await;
+    let sub = channel_under(&core, "roles", Some(top.id)).await;
+    let allowed = |ch: &Channel, user: UserId, action: Action| {
+        let (store, ch) = (store.clone(), ch.clone());
+        let registry = core.registry();
+        async move {
+            authz::authorize(registry, &*store, &ch, user, action)
+                .await
+                .unwrap()
+        }
+    };
+
+    // An admin of the top channel manages the channel beneath it too, but holds no custom role.
+    store.grant_role(top.id, alice, Role::Admin).await.unwrap();
+    assert_eq!(store.roles(sub.id, alice).await.unwrap(), vec![Role::Admin]);
+    assert!(allowed(&sub, alice, Action::Manage).await);
+    assert!(!allowed(&sub, alice, Action::Post).await);
+
+    // A membership counts as `member` on its own channel — enough to view, not to manage — and does
+    // not reach the channel beneath. A custom grant on the lower channel lets bob post there only.
+    store.add_member(top.id, bob).await.unwrap();
+    assert_eq!(store.roles(top.id, bob).await.unwrap(), vec![Role::Member]);
+    assert!(!allowed(&top, bob, Action::Manage).await);
+    assert!(store.roles(sub.id, bob).await.unwrap().is_empty());
+    store
+        .grant_role(sub.id, bob, Role::Custom("scribe".into()))
+        .await
+        .unwrap();
+    assert!(allowed(&sub, bob, Action::Post).await);
+    assert!(!allowed(&top, bob, Action::Post).await);
+
+    // Granting is idempotent; revoking removes only that role. The listing is direct grants only.
+    store.grant_role(top.id, alice, Role::Admin).await.unwrap();
+    assert_eq!(
+        store.role_grants(top.id).await.unwrap(),
+        vec![(alice, Role::Admin)]
+    );
+    assert_eq!(store.role_grants(sub.id).await.unwrap().len(), 1);
+    store.revoke_role(top.id, alice, Role::Admin).await.unwrap();
+    assert!(!allowed(&sub, alice, Action::Manage).await);
+    assert!(store.roles(sub.id, alice).await.unwrap().is_empty());
+}
@@ -0,0 +1,217 @@
+//! The durable change log (`TODO.md` #30) against a real tempfile sqlite: every committed mutation is
+//! logged in its own transaction with a `seq` the bus carries too, `follow` replays from an offset and
+//! then goes live without gaps or duplicates, compaction/expiry bound the table while the horizon
+//! tells a resumer it fell too far behind, and the relay carries another process's commits onto this
+//! one's bus. Throwaway kinds (DESIGN §12).
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_core::{change_log, ChangeOp, Core, EnvelopeRef, Registry};
+use cp_model::{
+    Batch, Channel, ChannelKind, ItemKind, Json, NewChannel, NewItem, Result, StoreCtx, TypeId,
+    WriteCtx,
+};
+use serde_json::json;
+use tokio::time::timeout;
+
+struct Room(TypeId);
+
+#[async_trait]
+impl ChannelKind for Room {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _cx: &dyn StoreCtx, _ch: &Channel, _q: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the change-log test")
+    }
+}
+
+struct Msg(TypeId);
+
+impl ItemKind for Msg {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+}
+
+async fn test_core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Room(TypeId::new("room")))
+        .item(Msg(TypeId::new("msg")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+fn room() -> NewChannel {
+    NewChannel {
+        type_id: TypeId::new("room"),
+        container: None,
+        payload: json!({}),
+    }
+}
+
+fn msg(container: cp_model::ChannelId) -> NewItem {
+    NewItem {
+        type_id: TypeId::new("msg"),
+        container: Some(container),
+        external_key: None,
+        payload: json!({}),
+    }
+}
+
+#[tokio::test]
+async fn commits_are_logged_with_the_seq_the_bus_carries() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let mut rx = core.events().subscribe();
+
+    let cid = store.create_channel(room()).await.unwrap();
+    let mut batch = Batch::new();
+    batch.create_item(msg(cid));
+    batch.create_item(msg(cid));
+    store.apply(batch).await.unwrap();
+    store.delete_channel(cid).await.unwrap();
+
+    let mut live = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        live.push(event.seq);
+    }
+    let logged: Vec<i64> = change_log::read(core.pool(), 0, 100)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| e.seq)
+        .collect();
+    assert_eq!(live, logged, "the bus and the log agree, in commit order");
+    assert_eq!(logged, (1..=logged.len() as i64).collect::<Vec<_>>());
+    assert_eq!(
+        change_log::head(core.pool()).await.unwrap(),
+        logged.len() as i64
+    );
+
+    // A failed unit logs nothing.
+    let missing = cp_model::ChannelId::generate();
+    assert!(store.create_item(msg(missing)).await.is_err());
+    assert_eq!(
+        change_log::head(core.pool()).await.unwrap(),
+        logged.len() as i64
+    );
+}
+
+#[tokio::test]
+async fn follow_replays_from_an_offset_then_goes_live() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = store.create_channel(room()).await.unwrap();
+    store.create_item(msg(cid)).await.unwrap();
+    let offset = change_log::head(core.pool()).await.unwrap();
+    let second = store.create_item(msg(cid)).await.unwrap();
+
+    let mut changes = change_log::follow(core.pool(), core.events(), Some(offset))
+        .await
+        .unwrap();
+    assert!(!changes.missed());
+    let replayed = changes.next().await.unwrap().unwrap();
+    assert!(
+        matches!(replayed.target, EnvelopeRef::Item(id) if id == second),
+        "resumes strictly after the offset"
+    );
+
+    let third = store.create_item(msg(cid)).await.unwrap();
+    let live = timeout(Duration::from_secs(5), changes.next())
+        .await
+        .expect("live event arrived")
+        .unwrap()
+        .unwrap();
+    assert!(matches!(live.target, EnvelopeRef::Item(id) if id == third));
+    assert_eq!(live.seq, replayed.seq + 1);
+    assert_eq!(changes.offset(), live.seq);
+}
+
+#[tokio::test]
+async fn compaction_keeps_the_latest_change_and_expiry_raises_the_horizon() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = store.create_channel(room()).await.unwrap();
+    let iid = store.create_item(msg(cid)).await.unwrap();
+    store
+        .set_item_payload(iid, json!({ "v": 2 }))
+        .await
+        .unwrap();
+    store
+        .set_item_payload(iid, json!({ "v": 3 }))
+        .await
+        .unwrap();
+
+    assert_eq!(change_log::compact(core.pool()).await.unwrap(), 2);
+    let left = change_log::read(core.pool(), 0, 100).await.unwrap();
+    assert_eq!(left.len(), 2, "one row per envelope survives");
+    assert!(matches!(left[1].op, ChangeOp::Updated));
+    assert_eq!(left[1].seq, 4);
+    assert_eq!(change_log::horizon(core.pool()).await.unwrap(), 0);
+
+    // Zero retention expires everything; `seq` keeps counting from where it was.
+    assert_eq!(
+        change_log::expire(core.pool(), Duration::ZERO)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(change_log::horizon(core.pool()).await.unwrap(), 4);
+    store
+        .set_item_payload(iid, json!({ "v": 4 }))
+        .await
+        .unwrap();
+    assert_eq!(change_log::head(core.pool()).await.unwrap(), 5);
+
+    // Resuming from behind the horizon is flagged; from at or past it is not.
+    let stale = change_log::follow(core.pool(), core.events(), Some(1))
+        .await
+        .unwrap();
+    assert!(stale.missed());
+    let mut fresh = change_log::follow(core.pool(), core.events(), Some(4))
+        .await
+        .unwrap();
+    assert!(!fresh.missed());
+    assert_eq!(fresh.next().await.unwrap().unwrap().seq, 5);
+}
+
+#[tokio::test]
+async fn the_relay_publishes_other_processes_commits_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let open = || async {
+        let registry = Registry::builder()
+            .channel(Room(TypeId::new("room")))
+            .item(Msg(TypeId::new("msg")))
+            .build();
+        Core::open(&url, registry).await.unwrap()
+    };
+    // Two cores on one file stand in for the server and a `channel-party shell` process.
+    let server = open().await;
+    let shell = open().await;
+    let _relay = server.spawn_relay(Duration::from_millis(20));
+    let mut rx = server.events().subscribe();
+    // Let the relay take its starting point.
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let cid = shell.store().create_channel(room()).await.unwrap();
+    let relayed = timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("relayed")
+        .unwrap();
+    assert!(matches!(relayed.target, EnvelopeRef::Channel(id) if id == cid));
+
+    // The server's own write reaches its bus once — from the write path, not again from the relay.
+    let iid = server.store().create_item(msg(cid)).await.unwrap();
+    let local = rx.recv().await.unwrap();
+    assert!(matches!(local.target, EnvelopeRef::Item(id) if id == iid));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(rx.try_recv().is_err(), "no duplicate from the relay");
+}
@@ -0,0 +1,24 @@
+//! Shared by the integration suites: the database each test runs against. A tempfile sqlite by
+//! default; with `CP_TEST_POSTGRES` set to a server URL (`postgres://user@host/postgres`), a fresh
+//! database on that server per test instead, so every suite also runs on Postgres (#37).
+
+use sqlx::{Connection, Executor, PgConnection};
+
+/// A database URL for one test: `dir`'s `t.db`, or a new Postgres database named for the test run.
+pub async fn db_url(dir: &tempfile::TempDir) -> String {
+    let Ok(server) = std::env::var("CP_TEST_POSTGRES") else {
+        return format!("sqlite:{}", dir.path().join("t.db").display());
+    };
+    let name = format!("cp_test_{}", ulid::Ulid::new().to_string().to_lowercase());
+    let mut admin = PgConnection::connect(&server)
+        .await
+        .expect("CP_TEST_POSTGRES is reachable");
+    admin
+        .execute(format!("CREATE DATABASE {name}").as_str())
+        .await
+        .expect("create the test database");
+    let (base, _) = server
+        .rsplit_once('/')
+        .expect("CP_TEST_POSTGRES names a database");
+    format!("{base}/{name}")
+}
@@ -1,16 +1,20 @@
 //! Integration tests for the gated debug shell (`TODO.md` #6): the write-mode gate, direct-read
-//! commands, envelope CRUD through the mutation API, and capability-gated membership. Uses throwaway
+//! commands, envelope CRUD through the mutation API, capability-gated membership, kind-contributed
+//! commands, and non-interactive scripts (`$last`, `--json`, stop on first error). Uses throwaway
 //! test kinds (one with membership, one without) rather than a concrete kind crate (DESIGN §12).
 
+mod common;
+
 use async_trait::async_trait;
-use cp_core::debug::DebugShell;
+use cp_core::debug::{DebugShell, ShellArgs, ShellInput};
 use cp_core::{Core, Registry};
 use cp_model::{
-    Channel, ChannelKind, Item, ItemKind, Json, Membership, Result, StoreCtx, TypeId, UserId,
-    WriteCtx,
+    Channel, ChannelId, ChannelKind, DebugAccess, DebugCommand, Error, Item, ItemKind, Json,
+    Membership, Patch, Result, StoreCtx, TypeId, UserId, WriteCtx,
 };
 
-/// A channel that accepts users (membership via the generic edge substrate) and summarizes its name.
+/// A channel that accepts users (membership via the generic edge substrate), summarizes its name,
+/// and contributes a read (`room echo`) and a write (`room rename`) debug command.
 struct Room(TypeId);
 
 #[async_trait]
@@ -30,6 +34,41 @@ impl ChannelKind for Room {
     fn membership(&self) -> Option<&dyn Membership> {
         Some(self)
     }
+    fn debug_commands(&self) -> Vec<DebugCommand> {
+        vec![
+            DebugCommand {
+                name: "echo".into(),
+                access: DebugAccess::Read,
+                help: "<words…>    echo the parsed args".into(),
+            },
+            DebugCommand {
+                name: "rename".into(),
+                access: DebugAccess::Write,
+                help: "<channel-id> <name>    rename a room".into(),
+            },
+        ]
+    }
+    async fn run_debug_command(
+        &self,
+        cx: &dyn WriteCtx,
+        command: &str,
+        args: &[String],
+    ) -> Result<String> {
+        match (command, args) {
+            ("echo", args) => Ok(args.join("|")),
+            ("rename", [id, name]) => {
+                let id: ChannelId = id
+                    .parse()
+                    .map_err(|_| Error::Validation(format!("`{id}` is not a channel id")))?;
+                cx.patch_channel_payload(id, Patch::Merge(serde_json::json!({ "name": name })))
+                    .await?;
+                Ok(format!("renamed {id}"))
+            }
+            _ => Err(Error::Validation(
+                "usage: room rename <channel-id> <name>".to_owned(),
+            )),
+        }
+    }
 }
 
 #[async_trait]
@@ -74,7 +113,7 @@ impl ItemKind for Msg {
 
 async fn shell() -> (tempfile::TempDir, DebugShell) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let registry = Registry::builder()
         .channel(Room(TypeId::new("room")))
         .channel(Locked(TypeId::new("locked")))
@@ -190,6 +229,242 @@ async fn membership_is_capability_gated() {
     assert_eq!(sh.eval(&format!("members {room}")).await, "(no members)");
 }
 
+#[tokio::test]
+async fn grant_and_revoke_roles() {
+    let (_dir, mut sh) = shell().await;
+    sh.enable_write_mode();
+
+    let room = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let uid = created_id(&sh.eval("create-user alice").await, "user");
+    assert_eq!(
+        sh.eval(&format!("roles {room}")).await,
+        "(no roles granted here)"
+    );
+
+    let granted = sh.eval(&format!("grant-role {room} alice admin")).await;
+    assert!(granted.contains("granted admin"), "{granted}");
+    assert_eq!(
+        sh.eval(&format!("roles {room}")).await,
+        format!("{uid}  admin")
+    );
+
+    // A bad role name or unknown handle is a clean message.
+    let bad = sh.eval(&format!("grant-role {room} alice \"\"")).await;
+    assert!(!bad.contains("granted"), "{bad}");
+    assert!(sh
+        .eval(&format!("grant-role {room} nobody admin"))
+        .await
+        .contains("no user with handle"));
+
+    assert!(sh
+        .eval(&format!("revoke-role {room} alice admin"))
+        .await
+        .contains("revoked admin"));
+    assert_eq!(
+        sh.eval(&format!("roles {room}")).await,
+        "(no roles granted here)"
+    );
+
+    // The write-mode gate applies.
+    sh.disable_write_mode();
+    assert!(sh
+        .eval(&format!("grant-role {room} alice admin"))
+        .await
+        .contains("read-only"));
+}
+
+#[tokio::test]
+async fn invites_are_minted_listed_and_revoked() {
+    let (_dir, mut sh) = shell().await;
+    assert!(sh.eval("create-invite 1").await.contains("read-only"));
+    sh.enable_write_mode();
+    assert_eq!(sh.eval("show invites").await, "(no open invites)");
+
+    let room = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let locked = created_id(&sh.eval("create-channel locked {}").await, "channel");
+    let minted = sh.eval(&format!("create-invite 3 {room}")).await;
+    let id = minted
+        .strip_prefix("created invite ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or_else(|| panic!("unexpected output: {minted}"))
+        .to_owned();
+    assert!(
+        minted.contains("3 uses") && minted.contains(": code "),
+        "{minted}"
+    );
+    let listed = sh.eval("show invites").await;
+    assert!(
+        listed.starts_with(&id) && listed.contains("uses_left=3") && listed.contains(&room),
+        "{listed}"
+    );
+
+    // Bad input is a clean message.
+    assert!(sh.eval("create-invite").await.starts_with("usage:"));
+    assert!(sh
+        .eval("create-invite 0")
+        .await
+        .contains("at least one use"));
+    assert!(sh
+        .eval(&format!("create-invite 1 {locked}"))
+        .await
+        .contains("does not accept users"));
+
+    assert!(sh
+        .eval(&format!("revoke-invite {id}"))
+        .await
+        .contains("revoked invite"));
+    assert_eq!(sh.eval("show invites").await, "(no open invites)");
+    assert!(sh
+        .eval(&format!("revoke-invite {id}"))
+        .await
+        .contains("no invite with id"));
+}
+
+#[tokio::test]
+async fn api_tokens_are_minted_listed_and_revoked() {
+    let (_dir, mut sh) = shell().await;
+    assert!(sh
+        .eval("create-token alice ci read")
+        .await
+        .contains("read-only"));
+    sh.enable_write_mode();
+    sh.eval("create-user alice").await;
+    assert_eq!(sh.eval("show tokens alice").await, "(no API tokens)");
+
+    let room = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let minted = sh
+        .eval(&format!("create-token alice ci read,post:{room} 30"))
+        .await;
+    let id = minted
+        .strip_prefix("created token ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or_else(|| panic!("unexpected output: {minted}"))
+        .to_owned();
+    assert!(minted.contains(": cpt_"), "{minted}");
+    let listed = sh.eval("show tokens alice").await;
+    assert!(
+        listed.starts_with(&id)
+            && listed.contains(&format!("scopes=read,post:{room}"))
+            && listed.contains("last_used=never"),
+        "{listed}"
+    );
+
+    // Bad input is a clean message.
+    assert!(sh.eval("create-token alice").await.starts_with("usage:"));
+    assert!(sh
+        .eval("create-token alice ci write")
+        .await
+        .contains("unknown scope"));
+    assert!(sh
+        .eval("create-token nobody ci read")
+        .await
+        .contains("no user with handle"));
+
+    assert!(sh
+        .eval(&format!("revoke-token {id}"))
+        .await
+        .contains("revoked token"));
+    assert_eq!(sh.eval("show tokens alice").await, "(no API tokens)");
+    assert!(sh
+        .eval(&format!("revoke-token {id}"))
+        .await
+        .contains("no token with id"));
+}
+
+#[tokio::test]
+async fn sessions_are_shown_and_revoked() {
+    let dir = tempfile::tempdir().unwrap();
+    let core = Core::open(&common::db_url(&dir).await, Registry::builder().build())
+        .await
+        .unwrap();
+    let mut sh = DebugShell::new(Registry::builder().build(), core.store());
+    sh.enable_write_mode();
+    let alice = created_id(&sh.eval("create-user alice").await, "user");
+    assert_eq!(sh.eval("show sessions alice").await, "(no live sessions)");
+
+    // Sessions come from logins, which the shell does not do.
+    for _ in 0..2 {
+        cp_core::auth::create_session(core.pool(), alice.parse().unwrap(), &Default::default())
+            .await
+            .unwrap();
+    }
+    assert_eq!(sh.eval("show sessions alice").await.lines().count(), 2);
+    assert_eq!(
+        sh.eval("revoke-sessions alice").await,
+        "revoked 2 session(s) for @alice"
+    );
+    assert_eq!(sh.eval("show sessions alice").await, "(no live sessions)");
+}
+
+#[tokio::test]
+async fn lockouts_are_shown_and_cleared() {
+    let dir = tempfile::tempdir().unwrap();
+    let core = Core::open(&common::db_url(&dir).await, Registry::builder().build())
+        .await
+        .unwrap();
+    let mut sh = DebugShell::new(Registry::builder().build(), core.store());
+    sh.enable_write_mode();
+    assert_eq!(sh.eval("show lockouts").await, "(no lockouts)");
+
+    // Failed logins come from the login route, which the shell does not do. Sit out each delay.
+    for _ in 0..10 {
+        sqlx::query("UPDATE login_failures SET blocked_until = ''")
+            .execute(core.pool())
+            .await
+            .unwrap();
+        let attempt = cp_core::throttle::authenticate(core.pool(), "mallory", "guess", None);
+        assert!(attempt.await.unwrap().is_none());
+    }
+    let shown = sh.eval("show lockouts").await;
+    assert!(shown.starts_with("handle mallory  failures=10"), "{shown}");
+
+    assert_eq!(
+        sh.eval("clear-lockout handle mallory").await,
+        "cleared handle mallory"
+    );
+    assert_eq!(sh.eval("show lockouts").await, "(no lockouts)");
+    assert!(sh
+        .eval("clear-lockout handle mallory")
+        .await
+        .contains("no failed logins on record"));
+    assert!(sh.eval("clear-lockout mallory").await.starts_with("usage:"));
+}
+
+#[tokio::test]
+async fn a_second_factor_is_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let core = Core::open(&common::db_url(&dir).await, Registry::builder().build())
+        .await
+        .unwrap();
+    let mut sh = DebugShell::new(Registry::builder().build(), core.store());
+    sh.enable_write_mode();
+    let id: cp_model::UserId = created_id(&sh.eval("create-user alice").await, "user")
+        .parse()
+        .unwrap();
+    assert_eq!(
+        sh.eval("reset-totp alice").await,
+        "@alice has no second factor"
+    );
+
+    // Enrollment is the user's, over HTTP; the shell only takes it away.
+    let alice = cp_model::User {
+        id,
+        handle: "alice".to_owned(),
+    };
+    let enrollment = cp_core::totp::begin_enrollment(core.pool(), &alice, "test")
+        .await
+        .unwrap();
+    let code = cp_core::totp::code_at(&enrollment.secret, std::time::SystemTime::now()).unwrap();
+    cp_core::totp::confirm_enrollment(core.pool(), id, &code)
+        .await
+        .unwrap();
+    assert_eq!(
+        sh.eval("reset-totp alice").await,
+        "reset the second factor for @alice"
+    );
+    assert!(!cp_core::totp::enabled(core.pool(), id).await.unwrap());
+}
+
 #[tokio::test]
 async fn bad_input_is_a_clean_message_not_a_panic() {
     let (_dir, mut sh) = shell().await;
@@ -309,3 +584,271 @@ async fn link_user_provisions_and_lists_external_links() {
         .await
         .contains("read-only"));
 }
+
+#[tokio::test]
+async fn show_migrations_lists_the_ledger() {
+    let (_dir, mut sh) = shell().await;
+    let out = sh.eval("show migrations").await;
+    assert!(out.contains("core/0001_init"), "{out}");
+    assert!(out.contains("checksum="), "{out}");
+}
+
+#[tokio::test]
+async fn history_and_revert() {
+    let (_dir, mut sh) = shell().await;
+    sh.enable_write_mode();
+    let cid = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let iid = created_id(
+        &sh.eval(&format!("create-item {cid} msg {{\"body\":\"first\"}}"))
+            .await,
+        "item",
+    );
+    assert_eq!(sh.eval(&format!("history {iid}")).await, "(no revisions)");
+
+    sh.eval(&format!("set-payload {iid} {{\"body\":\"second\"}}"))
+        .await;
+    let history = sh.eval(&format!("history {iid}")).await;
+    assert!(history.starts_with("rev 1 "), "{history}");
+    assert!(history.contains("by operator"), "{history}");
+    assert!(history.contains("\"first\""), "{history}");
+
+    sh.disable_write_mode();
+    assert_eq!(
+        sh.eval(&format!("revert {iid} 1")).await,
+        "read-only; run enable-write-mode first"
+    );
+    sh.enable_write_mode();
+    let out = sh.eval(&format!("revert {iid} 1")).await;
+    assert!(out.contains("reverted"), "{out}");
+    assert!(sh.eval(&format!("inspect {iid}")).await.contains("first"));
+    assert!(sh.eval(&format!("history {iid}")).await.contains("rev 2"));
+    assert!(sh
+        .eval(&format!("revert {iid} 9"))
+        .await
+        .contains("no revision 9"));
+}
+
+#[tokio::test]
+async fn trash_restore_and_purge() {
+    let (_dir, mut sh) = shell().await;
+    sh.enable_write_mode();
+    let cid = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let iid = created_id(
+        &sh.eval(&format!("create-item {cid} msg {{\"body\":\"hi\"}}"))
+            .await,
+        "item",
+    );
+    assert_eq!(sh.eval("show trash").await, "(trash is empty)");
+
+    sh.eval(&format!("delete {iid}")).await;
+    let trash = sh.eval("show trash").await;
+    assert!(
+        trash.starts_with(&format!("item {iid}  type=msg")),
+        "{trash}"
+    );
+    assert!(sh
+        .eval(&format!("restore {iid}"))
+        .await
+        .contains("restored item"));
+    assert!(sh.eval(&format!("show items {cid}")).await.contains(&iid));
+
+    sh.eval(&format!("delete {cid}")).await;
+    assert_eq!(sh.eval("show channels").await, "(no channels)");
+    let out = sh.eval(&format!("purge {cid}")).await;
+    assert!(out.contains("purged channel"), "{out}");
+    assert_eq!(sh.eval("show trash").await, "(trash is empty)");
+    assert!(sh
+        .eval(&format!("restore {cid}"))
+        .await
+        .contains("no channel or item"));
+}
+
+#[tokio::test]
+async fn patch_merges_or_applies_operations() {
+    let (_dir, mut sh) = shell().await;
+    sh.enable_write_mode();
+    let cid = created_id(&sh.eval("create-channel room {}").await, "channel");
+    let iid = created_id(
+        &sh.eval(&format!(
+            "create-item {cid} msg {{\"body\":\"hi\",\"pinned\":true}}"
+        ))
+        .await,
+        "item",
+    );
+
+    let out = sh
+        .eval(&format!(
+            "patch {iid} {{\"body\":\"edited\",\"pinned\":null}}"
+        ))
+        .await;
+    assert!(out.contains("patched item"), "{out}");
+    let inspected = sh.eval(&format!("inspect {iid}")).await;
+    assert!(
+        inspected.contains("edited") && !inspected.contains("pinned"),
+        "{inspected}"
+    );
+
+    let out = sh
+        .eval(&format!(
+            "patch {iid} [{{\"op\":\"add\",\"path\":\"/tag\",\"value\":\"x\"}}]"
+        ))
+        .await;
+    assert!(out.contains("patched item"), "{out}");
+    assert!(sh.eval(&format!("inspect {iid}")).await.contains("\"tag\""));
+
+    let out = sh
+        .eval(&format!(
+            "patch {iid} [{{\"op\":\"remove\",\"path\":\"/nope\"}}]"
+        ))
+        .await;
+    assert!(out.contains("JSON-patch failed"), "{out}");
+}
+
+#[tokio::test]
+async fn kind_commands_dispatch_by_namespace_and_respect_the_gate() {
+    let (_dir, mut sh) = shell().await;
+
+    // `help` lists contributed commands under their namespace, marking the write-gated ones.
+    let help = sh.eval("help").await;
+    assert!(help.contains("room echo <words…>"), "{help}");
+    assert!(help.contains("room rename <channel-id> <name>    rename a room  [write]"));
+
+    // A read command runs in read-only mode, with whitespace-split args.
+    assert_eq!(sh.eval("room echo a  b c").await, "a|b|c");
+    // A write command is refused until write mode is on.
+    assert_eq!(
+        sh.eval("room rename 0000 x").await,
+        "read-only; run enable-write-mode first"
+    );
+
+    sh.enable_write_mode();
+    let room = created_id(
+        &sh.eval("create-channel room {\"name\":\"general\"}").await,
+        "channel",
+    );
+    assert_eq!(
+        sh.eval(&format!("room rename {room} lobby")).await,
+        format!("renamed {room}")
+    );
+    assert!(sh.eval(&format!("inspect {room}")).await.contains("lobby"));
+
+    // Kind errors surface as text; unknown names and a bare namespace get a usage hint.
+    assert!(sh
+        .eval("room rename nope x")
+        .await
+        .contains("not a channel id"));
+    assert!(sh
+        .eval("room frob")
+        .await
+        .contains("unknown command `room frob`"));
+    assert_eq!(sh.eval("room").await, "usage: room <echo | rename> …");
+    // A namespace without contributed commands is just an unknown command.
+    assert!(sh
+        .eval("locked x")
+        .await
+        .contains("unknown command `locked`"));
+}
+
+#[tokio::test]
+async fn scripts_chain_last_and_stop_at_the_first_error() {
+    let (_dir, mut sh) = shell().await;
+    sh.enable_write_mode();
+    sh.lock_mode();
+
+    let script = "\
+# seed a room with one message
+create-channel room {\"name\":\"general\"}
+create-item $last msg {\"body\":\"hi\"}
+
+inspect $last
+frobnicate
+show channels
+";
+    let mut replies = Vec::new();
+    let err = sh
+        .run_script(script.lines(), |r| replies.push(r.json.clone()))
+        .await
+        .unwrap_err();
+    // Line numbers count the comment and the blank line; nothing after the failure ran.
+    assert_eq!((err.line, err.command.as_str()), (6, "frobnicate"));
+    assert!(err.message.contains("unknown command"), "{}", err.message);
+    assert_eq!(replies.len(), 3);
+
+    // `$last` followed each create: the item went into the new channel, and `inspect` saw the item.
+    let (room, item) = (&replies[0], &replies[1]);
+    assert_eq!(room["action"], "created");
+    assert_eq!(room["super_type"], "channel");
+    assert_eq!(item["super_type"], "item");
+    assert_eq!(replies[2]["id"], item["id"]);
+    assert_eq!(replies[2]["container"], room["id"]);
+    assert_eq!(replies[2]["payload"]["body"], "hi");
+    assert_eq!(sh.last(), item["id"].as_str());
+
+    // A locked session cannot change its own mode.
+    assert!(sh
+        .exec("disable-write-mode")
+        .await
+        .unwrap_err()
+        .contains("--write"));
+}
+
+#[tokio::test]
+async fn every_reply_has_a_json_form() {
+    let (_dir, mut sh) = shell().await;
+    // Nothing created yet: `$last` is an error, not a literal.
+    assert!(sh
+        .exec("inspect $last")
+        .await
+        .unwrap_err()
+        .contains("`$last` is unset"));
+    // Lists are arrays (empty rather than a placeholder line).
+    let empty = sh.exec("show channels").await.unwrap();
+    assert_eq!(
+        (empty.text.as_str(), &empty.json),
+        ("(no channels)", &serde_json::json!([]))
+    );
+
+    sh.enable_write_mode();
+    let user = sh.exec("create-user alice").await.unwrap();
+    assert_eq!(user.json["handle"], "alice");
+    assert_eq!(sh.last(), user.json["id"].as_str());
+    sh.exec("create-channel room {\"name\":\"general\"}")
+        .await
+        .unwrap();
+    let channels = sh.exec("show channels").await.unwrap();
+    assert_eq!(channels.json[0]["summary"], "#general");
+    assert_eq!(channels.render(true), channels.json.to_string());
+    assert_eq!(
+        sh.exec("room echo a b").await.unwrap().json,
+        serde_json::json!({ "output": "a|b" })
+    );
+}
+
+#[test]
+fn shell_args_parse_modes_and_reject_mixtures() {
+    let parse = |args: &[&str]| ShellArgs::parse(args.iter().map(|a| a.to_string()));
+    assert_eq!(parse(&[]).unwrap(), ShellArgs::default());
+    let exec = parse(&[
+        "--write",
+        "--exec",
+        "show users",
+        "--json",
+        "--exec",
+        "help",
+    ])
+    .unwrap();
+    assert!(exec.write && exec.json);
+    assert_eq!(
+        exec.input,
+        ShellInput::Exec(vec!["show users".into(), "help".into()])
+    );
+    assert_eq!(
+        parse(&["--script", "seed.cpsh"]).unwrap().input,
+        ShellInput::Script("seed.cpsh".into())
+    );
+    assert!(parse(&["--script", "a", "--exec", "help"]).is_err());
+    assert!(parse(&["--script"]).is_err());
+    assert!(parse(&["--frob"])
+        .unwrap_err()
+        .contains("unknown shell argument"));
+}
@@ -0,0 +1,298 @@
+//! Invite codes + self-registration (`TODO.md` #41) against a real tempfile sqlite: the handle and
+//! password policies, spending uses down to nothing (a taken handle is only reported for a valid code),
+//! expiry and its cap, revocation, and joining the channels an invite names through the kind's
+//! `Membership`, where a refused join does not undo the account. Throwaway kinds accept users, refuse
+//! every join, or take no users at all (DESIGN §12).
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_core::invites::{self, NewInvite};
+use cp_core::{auth, Core, Registry};
+use cp_model::{
+    Channel, ChannelId, ChannelKind, Error, Json, Membership, NewChannel, Result, StoreCtx, TypeId,
+    UserId, WriteCtx,
+};
+
+/// Accepts users on the generic `channel_members` substrate.
+struct Room(TypeId);
+#[async_trait]
+impl ChannelKind for Room {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _: &dyn StoreCtx, _: &Channel, _: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the invite test")
+    }
+    fn membership(&self) -> Option<&dyn Membership> {
+        Some(self)
+    }
+}
+#[async_trait]
+impl Membership for Room {
+    async fn add_user(&self, cx: &dyn WriteCtx, ch: &Channel, u: UserId) -> Result<()> {
+        cx.add_member(ch.id, u).await
+    }
+    async fn remove_user(&self, cx: &dyn WriteCtx, ch: &Channel, u: UserId) -> Result<()> {
+        cx.remove_member(ch.id, u).await
+    }
+    async fn members(&self, cx: &dyn WriteCtx, ch: &Channel) -> Result<Vec<UserId>> {
+        cx.members(ch.id).await
+    }
+}
+
+/// Accepts users in principle, but every join fails — a kind whose `Membership` refuses.
+struct Full(TypeId);
+#[async_trait]
+impl ChannelKind for Full {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _: &dyn StoreCtx, _: &Channel, _: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the invite test")
+    }
+    fn membership(&self) -> Option<&dyn Membership> {
+        Some(self)
+    }
+}
+#[async_trait]
+impl Membership for Full {
+    async fn add_user(&self, _: &dyn WriteCtx, _: &Channel, _: UserId) -> Result<()> {
+        Err(Error::Validation("this room is full".to_owned()))
+    }
+    async fn remove_user(&self, _: &dyn WriteCtx, _: &Channel, _: UserId) -> Result<()> {
+        Ok(())
+    }
+    async fn members(&self, _: &dyn WriteCtx, _: &Channel) -> Result<Vec<UserId>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Does not accept users.
+struct Board(TypeId);
+#[async_trait]
+impl ChannelKind for Board {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _: &dyn StoreCtx, _: &Channel, _: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the invite test")
+    }
+}
+
+async fn core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Room(TypeId::new("room")))
+        .channel(Board(TypeId::new("board")))
+        .channel(Full(TypeId::new("full")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+async fn channel(core: &Core, type_id: &str) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new(type_id),
+            container: None,
+            payload: serde_json::json!({}),
+        })
+        .await
+        .unwrap()
+}
+
+fn is_validation(r: Result<impl std::fmt::Debug>) -> bool {
+    matches!(r, Err(Error::Validation(_)))
+}
+
+#[test]
+fn handle_and_password_policies() {
+    for ok in ["alice", "op", "a.b-c_d", "7seas"] {
+        assert!(auth::validate_handle(ok).is_ok(), "{ok}");
+    }
+    for bad in ["a", "Alice", "al ice", "-alice", "ålice", &"x".repeat(33)] {
+        assert!(auth::validate_handle(bad).is_err(), "{bad}");
+    }
+    assert!(auth::validate_password("alice", "correct horse").is_ok());
+    assert!(auth::validate_password("alice", "short").is_err());
+    assert!(auth::validate_password("alicealice", "AliceAlice").is_err());
+    assert!(auth::validate_password("alice", &"x".repeat(257)).is_err());
+}
+
+#[tokio::test]
+async fn provisioning_enforces_the_handle_policy() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    assert!(is_validation(
+        auth::provision_user(pool, "Bad Handle").await
+    ));
+    assert!(auth::provision_user(pool, "alice").await.is_ok());
+}
+
+#[tokio::test]
+async fn an_invite_is_spent_down_to_nothing() {
+    let (_dir, core) = core().await;
+    let store = core.store();
+    let (invite, code) = invites::mint(
+        &store,
+        None,
+        NewInvite {
+            uses: 2,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(invite.uses_left, 2);
+    assert!(invite.expires_at.is_some());
+
+    // A bad code, a weak password or a taken handle spend nothing.
+    assert!(is_validation(
+        invites::register(&store, "nope", "alice", "correct horse").await
+    ));
+    assert!(is_validation(
+        invites::register(&store, &code, "alice", "pw").await
+    ));
+    let alice = invites::register(&store, &code, "alice", "correct horse")
+        .await
+        .unwrap();
+    assert!(is_validation(
+        invites::register(&store, &code, "alice", "correct horse").await
+    ));
+    assert_eq!(
+        invites::list(core.pool(), None).await.unwrap()[0].uses_left,
+        1
+    );
+    // Without a valid code, a taken handle looks like any other refusal.
+    let Err(Error::Validation(msg)) =
+        invites::register(&store, "nope", "alice", "correct horse").await
+    else {
+        panic!("a bad code is refused")
+    };
+    assert_eq!(msg, "invalid invite code");
+
+    // The new user logs in with the chosen password.
+    let authed = auth::authenticate(core.pool(), "alice", "correct horse")
+        .await
+        .unwrap()
+        .expect("registered user authenticates");
+    assert_eq!(authed.id, alice.id);
+
+    // The last use removes the invite; after that the code is just invalid.
+    invites::register(&store, &code, "bob", "battery staple")
+        .await
+        .unwrap();
+    assert!(invites::list(core.pool(), None).await.unwrap().is_empty());
+    assert!(invites::get(core.pool(), &invite.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(is_validation(
+        invites::register(&store, &code, "carol", "correct horse").await
+    ));
+}
+
+#[tokio::test]
+async fn expired_and_revoked_invites_are_refused() {
+    let (_dir, core) = core().await;
+    let store = core.store();
+    let (_, stale) = invites::mint(
+        &store,
+        None,
+        NewInvite {
+            ttl: Some(Duration::ZERO),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(is_validation(
+        invites::register(&store, &stale, "alice", "correct horse").await
+    ));
+    // A lifetime past the cap is refused rather than overflowing the clock.
+    for ttl in [invites::MAX_TTL + Duration::from_secs(1), Duration::MAX] {
+        let spec = NewInvite {
+            ttl: Some(ttl),
+            ..Default::default()
+        };
+        assert!(is_validation(invites::mint(&store, None, spec).await));
+    }
+
+    let (invite, code) = invites::mint(&store, None, NewInvite::default())
+        .await
+        .unwrap();
+    invites::revoke(core.pool(), &invite.id).await.unwrap();
+    assert!(matches!(
+        invites::revoke(core.pool(), &invite.id).await,
+        Err(Error::NotFound)
+    ));
+    assert!(is_validation(
+        invites::register(&store, &code, "alice", "correct horse").await
+    ));
+    assert!(!auth::handle_taken(core.pool(), "alice").await.unwrap());
+}
+
+#[tokio::test]
+async fn registration_joins_the_invited_channels() {
+    let (_dir, core) = core().await;
+    let store = core.store();
+    let op = auth::provision_user(core.pool(), "op").await.unwrap();
+    let room = channel(&core, "room").await;
+    let board = channel(&core, "board").await;
+
+    // A channel that does not accept users cannot be attached.
+    assert!(is_validation(
+        invites::mint(
+            &store,
+            Some(op),
+            NewInvite {
+                channels: vec![board],
+                ..Default::default()
+            },
+        )
+        .await
+    ));
+
+    let (invite, code) = invites::mint(
+        &store,
+        Some(op),
+        NewInvite {
+            channels: vec![room],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(invite.created_by, Some(op));
+    assert_eq!(invite.channels, vec![room]);
+    assert_eq!(invites::list(core.pool(), Some(op)).await.unwrap().len(), 1);
+    let alice = invites::register(&store, &code, "alice", "correct horse")
+        .await
+        .unwrap();
+    assert_eq!(store.members(room).await.unwrap(), vec![alice.id]);
+
+    // The account is made before the joins: one that fails does not undo it or fail the call.
+    let full = channel(&core, "full").await;
+    let (_, code) = invites::mint(
+        &store,
+        Some(op),
+        NewInvite {
+            channels: vec![full, room],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let bob = invites::register(&store, &code, "bob", "battery staple")
+        .await
+        .unwrap();
+    assert!(store.members(room).await.unwrap().contains(&bob.id));
+    assert!(auth::authenticate(core.pool(), "bob", "battery staple")
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -3,6 +3,8 @@
 //! hardcoded "cached-user" type: link both directions, idempotency, the one-user-per-item conflict, the
 //! missing-item error, unlink, and FK cascade on item delete.
 
+mod common;
+
 use async_trait::async_trait;
 use cp_core::{auth, links, Core, Registry};
 use cp_model::{
@@ -30,7 +32,7 @@ impl ItemKind for TestItem {
 
 async fn test_core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let registry = Registry::builder()
         .channel(TestChannel(TypeId::new("test")))
         .item(TestItem(TypeId::new("test")))
@@ -118,10 +120,16 @@ async fn unlink_and_cascade_on_item_delete() {
     links::unlink(pool, alice, item).await.unwrap();
     assert!(links::user_for_item(pool, item).await.unwrap().is_none());
 
-    // Deleting the item cascades the link away (FK ON DELETE CASCADE).
+    // Trashing the item hides the link; purging it cascades the link away (FK ON DELETE CASCADE).
     let item2 = an_item(&core).await;
     links::link(pool, alice, item2).await.unwrap();
     store.delete_item(item2).await.unwrap();
     assert!(links::user_for_item(pool, item2).await.unwrap().is_none());
     assert_eq!(links::linked_items(pool, alice).await.unwrap().len(), 0);
+    store.purge_item(item2).await.unwrap();
+    let edges: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_external_links")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(edges, 0);
 }
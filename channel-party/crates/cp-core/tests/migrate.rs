@@ -0,0 +1,192 @@
+//! Integration tests for the version-tracked migrator (`TODO.md` #7) against a real tempfile sqlite:
+//! each step applies exactly once (so a non-idempotent `ALTER` is safe across reboots), kind sets
+//! version independently of core, an edited migration is refused, a failing step rolls back whole, and
+//! core's data steps carry existing rows forward.
+
+mod common;
+
+use cp_core::migrate::{self, CORE_OWNER};
+use cp_core::{Core, Registry};
+use cp_model::{Backend, Migration, Migrations};
+
+// The test SQL is portable, so each set ships the same steps for both backends.
+
+const V1_STEPS: &[Migration] = &[Migration {
+    name: "0001_widget_init",
+    sql: "CREATE TABLE widget_box (id TEXT PRIMARY KEY);",
+}];
+
+static V1: Migrations = Migrations {
+    owner: "widget",
+    steps: V1_STEPS,
+    postgres: V1_STEPS,
+};
+
+/// V1 plus a non-idempotent second step: re-running the `ALTER` would fail with "duplicate column".
+const V2_STEPS: &[Migration] = &[
+    Migration {
+        name: "0001_widget_init",
+        sql: "CREATE TABLE widget_box (id TEXT PRIMARY KEY);",
+    },
+    Migration {
+        name: "0002_widget_color",
+        sql: "ALTER TABLE widget_box ADD COLUMN color TEXT NOT NULL DEFAULT 'red';",
+    },
+];
+
+static V2: Migrations = Migrations {
+    owner: "widget",
+    steps: V2_STEPS,
+    postgres: V2_STEPS,
+};
+
+/// V1 with its first step edited after the fact.
+const EDITED_STEPS: &[Migration] = &[Migration {
+    name: "0001_widget_init",
+    sql: "CREATE TABLE widget_box (id TEXT PRIMARY KEY, extra TEXT);",
+}];
+
+static EDITED: Migrations = Migrations {
+    owner: "widget",
+    steps: EDITED_STEPS,
+    postgres: EDITED_STEPS,
+};
+
+/// A step that creates a table and then fails — the whole step must roll back.
+const BROKEN_STEPS: &[Migration] = &[Migration {
+    name: "0001_broken",
+    sql: "CREATE TABLE broken_half (id TEXT); INSERT INTO no_such_table VALUES (1);",
+}];
+
+static BROKEN: Migrations = Migrations {
+    owner: "broken",
+    steps: BROKEN_STEPS,
+    postgres: BROKEN_STEPS,
+};
+
+/// A kind that only ships sqlite migrations.
+static SQLITE_ONLY: Migrations = Migrations {
+    owner: "sqlite-only",
+    steps: V1_STEPS,
+    postgres: &[],
+};
+
+async fn open(url: &str, set: &Migrations) -> anyhow::Result<Core> {
+    Core::open(url, Registry::builder().migrations(*set).build()).await
+}
+
+#[tokio::test]
+async fn applies_each_step_once_and_records_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+
+    let core = open(&url, &V1).await.unwrap();
+    let ledger = migrate::applied(core.pool()).await.unwrap();
+    let names: Vec<_> = ledger
+        .iter()
+        .map(|m| format!("{}/{}", m.owner, m.name))
+        .collect();
+    let expected: &[&str] = match Backend::of(core.pool()) {
+        Backend::Sqlite => &[
+            "core/0001_init",
+            "core/0002_revisions",
+            "core/0003_trash",
+            "core/0004_envelope_rev",
+            "core/0005_sort_index",
+            "core/0006_spatial_index",
+            "core/0007_change_log",
+            "core/0008_runtime_checkpoint",
+            "core/0009_runtime_status",
+            "core/0010_operators",
+            "core/0011_channel_roles",
+            "core/0012_invites",
+            "core/0013_api_tokens",
+            "core/0014_session_details",
+            "core/0015_login_failures",
+            "core/0016_totp",
+            "widget/0001_widget_init",
+        ],
+        Backend::Postgres => &[
+            "core/0001_init",
+            "core/0011_channel_roles",
+            "core/0012_invites",
+            "core/0013_api_tokens",
+            "core/0014_session_details",
+            "core/0015_login_failures",
+            "core/0016_totp",
+            "widget/0001_widget_init",
+        ],
+    };
+    assert_eq!(names, expected);
+    drop(core);
+
+    // Reboot with a newer kind version: only the pending ALTER runs; a re-run of it would fail.
+    let core = open(&url, &V2).await.unwrap();
+    let core2 = open(&url, &V2).await.expect("second boot is a no-op");
+    let ledger = migrate::applied(core2.pool()).await.unwrap();
+    assert_eq!(ledger.iter().filter(|m| m.owner == "widget").count(), 2);
+    sqlx::query("INSERT INTO widget_box (id) VALUES ('w1')")
+        .execute(core.pool())
+        .await
+        .unwrap();
+    let color: String = sqlx::query_scalar("SELECT color FROM widget_box WHERE id = 'w1'")
+        .fetch_one(core.pool())
+        .await
+        .unwrap();
+    assert_eq!(color, "red", "the ALTER from 0002 applied");
+}
+
+#[tokio::test]
+async fn kind_sets_version_independently_of_core() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let core = open(&url, &V2).await.unwrap();
+    let ledger = migrate::applied(core.pool()).await.unwrap();
+    assert_eq!(
+        ledger.iter().filter(|m| m.owner == CORE_OWNER).count(),
+        migrate::CORE.steps_for(Backend::of(core.pool())).len()
+    );
+    assert_eq!(ledger.iter().filter(|m| m.owner == "widget").count(), 2);
+}
+
+#[tokio::test]
+async fn an_edited_migration_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    drop(open(&url, &V1).await.unwrap());
+
+    let err = open(&url, &EDITED).await.err().expect("checksum mismatch");
+    assert!(err.to_string().contains("widget/0001_widget_init"), "{err}");
+    assert!(err.to_string().contains("edited"), "{err}");
+}
+
+#[tokio::test]
+async fn a_failing_step_rolls_back_whole() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let err = open(&url, &BROKEN).await.err().expect("step fails");
+    assert!(err.to_string().contains("broken/0001_broken"), "{err}");
+
+    // Neither the half-created table nor a ledger row survived.
+    let core = Core::open(&url, Registry::builder().build()).await.unwrap();
+    let half = sqlx::query("SELECT id FROM broken_half")
+        .fetch_optional(core.pool())
+        .await;
+    assert!(half.is_err(), "broken_half was rolled back");
+    let ledger = migrate::applied(core.pool()).await.unwrap();
+    assert!(ledger.iter().all(|m| m.owner != "broken"));
+}
+
+#[tokio::test]
+async fn a_kind_without_migrations_for_the_backend_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let opened = open(&url, &SQLITE_ONLY).await;
+    match Backend::from_url(&url).unwrap() {
+        Backend::Sqlite => assert!(opened.is_ok()),
+        Backend::Postgres => {
+            let err = opened.err().expect("no postgres steps");
+            assert!(err.to_string().contains("sqlite-only"), "{err}");
+        }
+    }
+}
@@ -2,13 +2,15 @@
 //! tempfile sqlite. Data is seeded through the write path, so these exercise reads and writes together.
 //! Uses throwaway test kinds rather than a concrete kind crate (DESIGN §12).
 
+mod common;
+
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
 use cp_core::{Core, Registry};
 use cp_model::{
-    Channel, ChannelId, ChannelKind, Cursor, Filter, ItemKind, Json, NewChannel, NewItem, Node,
-    NodePage, Order, Page, Result, StoreCtx, SuperType, TypeId, WriteCtx,
+    Channel, ChannelId, ChannelKind, Cursor, Error, Filter, IndexEntry, ItemKind, Json, NewChannel,
+    NewItem, Node, NodePage, Order, Page, Result, StoreCtx, SuperType, TypeId, WriteCtx,
 };
 
 struct TestChannel(TypeId);
@@ -29,11 +31,19 @@ impl ItemKind for TestItem {
     fn type_id(&self) -> &TypeId {
         &self.0
     }
+    /// A `rank` payload field, when present, is the declared sort key.
+    fn index(&self, payload: &Json) -> Option<IndexEntry> {
+        let rank = payload.get("rank")?.as_str()?;
+        Some(IndexEntry {
+            sort_key: Some(rank.to_owned()),
+            ..Default::default()
+        })
+    }
 }
 
 async fn test_core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     // Two channel types (to test type filtering) and one item type.
     let registry = Registry::builder()
         .channel(TestChannel(room()))
@@ -159,6 +169,70 @@ async fn children_are_time_ordered_and_paginate() {
     assert_eq!(asc, desc_rev, "ascending is descending reversed");
 }
 
+#[tokio::test]
+async fn children_order_by_sort_key_with_id_tiebreak() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let room_id = store.create_channel(ch(room(), None)).await.unwrap();
+
+    let mut ranked = Vec::new();
+    for rank in ["c", "a", "b", "a", "c"] {
+        let mut spec = item(room_id);
+        spec.payload = serde_json::json!({ "rank": rank });
+        ranked.push((rank, store.create_item(spec).await.unwrap().to_string()));
+    }
+    // No declared key sorts as the empty one: first ascending, last descending.
+    let unranked = store.create_item(item(room_id)).await.unwrap().to_string();
+    ranked.push(("", unranked.clone()));
+    ranked.sort();
+    let want: Vec<String> = ranked.iter().map(|(_, id)| id.clone()).collect();
+
+    let items = Filter {
+        super_type: Some(SuperType::Item),
+        type_ids: None,
+    };
+    // Page size 2 puts page boundaries inside both runs of equal keys.
+    let asc = collect_all(&*store, room_id, items.clone(), Order::SortKeyAsc, 2).await;
+    assert_eq!(asc, want, "(sort_key, id) ascending across pages");
+    let desc = collect_all(&*store, room_id, items.clone(), Order::SortKeyDesc, 4).await;
+    let want_desc: Vec<String> = want.iter().rev().cloned().collect();
+    assert_eq!(desc, want_desc, "descending is ascending reversed");
+
+    // A rewrite re-projects the key in the same transaction.
+    let first = want[1].parse().unwrap();
+    store
+        .set_item_payload(first, serde_json::json!({ "rank": "z" }))
+        .await
+        .unwrap();
+    let asc = collect_all(&*store, room_id, items.clone(), Order::SortKeyAsc, 10).await;
+    assert_eq!(asc.last(), Some(&want[1]));
+
+    // Dropping the key drops its row: the item sorts with the unranked one again.
+    store
+        .set_item_payload(first, serde_json::json!({}))
+        .await
+        .unwrap();
+    let asc = collect_all(&*store, room_id, items.clone(), Order::SortKeyAsc, 10).await;
+    let mut head = asc[..2].to_vec();
+    head.sort();
+    let mut blank = vec![unranked, want[1].clone()];
+    blank.sort();
+    assert_eq!(head, blank);
+
+    let bad = store
+        .children(
+            room_id,
+            items,
+            Page {
+                cursor: Cursor(Some("not-a-cursor".into())),
+                limit: 2,
+            },
+            Order::SortKeyAsc,
+        )
+        .await;
+    assert!(matches!(bad, Err(Error::Validation(_))));
+}
+
 #[tokio::test]
 async fn children_filter_by_super_type_and_type() {
     let (_dir, core) = test_core().await;
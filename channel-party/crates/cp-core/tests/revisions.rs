@@ -0,0 +1,215 @@
+//! Integration tests for envelope revision history: every overwrite appends the prior payload +
+//! container with the acting principal, history survives a delete, and `revert` restores a revision
+//! through the write path (itself recorded). Throwaway test kinds, as in `write_path.rs` (DESIGN §12).
+
+mod common;
+
+use async_trait::async_trait;
+use cp_core::{Core, EnvelopeRef, Registry};
+use cp_model::{
+    Actor, Channel, ChannelId, ChannelKind, Error, ItemKind, Json, NewChannel, NewItem, Result,
+    StoreCtx, TypeId, UserId, WriteCtx,
+};
+use serde_json::json;
+
+struct TestChannel(TypeId);
+
+#[async_trait]
+impl ChannelKind for TestChannel {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+
+    async fn contents(&self, _cx: &dyn StoreCtx, _ch: &Channel, _query: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the revisions test")
+    }
+}
+
+struct TestItem(TypeId);
+
+impl ItemKind for TestItem {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+
+    fn validate(&self, payload: &Json) -> Result<()> {
+        if payload.is_object() {
+            Ok(())
+        } else {
+            Err(Error::Validation("expected a JSON object".to_owned()))
+        }
+    }
+}
+
+async fn test_core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(TestChannel(TypeId::new("test")))
+        .item(TestItem(TypeId::new("test")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+async fn channel(core: &Core) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("test"),
+            container: None,
+            payload: json!({}),
+        })
+        .await
+        .unwrap()
+}
+
+fn item(container: ChannelId, key: Option<&str>, payload: Json) -> NewItem {
+    NewItem {
+        type_id: TypeId::new("test"),
+        container: Some(container),
+        external_key: key.map(str::to_owned),
+        payload,
+    }
+}
+
+#[tokio::test]
+async fn updates_record_prior_state_and_actor() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let a = channel(&core).await;
+    let b = channel(&core).await;
+    let iid = store
+        .create_item(item(a, None, json!({ "body": "v1" })))
+        .await
+        .unwrap();
+    assert!(store
+        .revisions(EnvelopeRef::Item(iid))
+        .await
+        .unwrap()
+        .is_empty());
+
+    let alice = UserId::generate();
+    store
+        .acting_as(Actor::User(alice))
+        .set_item_payload(iid, json!({ "body": "v2" }))
+        .await
+        .unwrap();
+    store
+        .acting_as(Actor::Component("mover".to_owned()))
+        .reparent_item(iid, Some(b))
+        .await
+        .unwrap();
+
+    let revs = store.revisions(EnvelopeRef::Item(iid)).await.unwrap();
+    assert_eq!(revs.len(), 2);
+    assert_eq!(revs[0].rev, 1);
+    assert_eq!(revs[0].payload["body"], "v1");
+    assert_eq!(revs[0].container, Some(a));
+    assert_eq!(revs[0].actor, Actor::User(alice));
+    assert_eq!(revs[1].rev, 2);
+    assert_eq!(revs[1].payload["body"], "v2");
+    assert_eq!(revs[1].actor, Actor::Component("mover".to_owned()));
+}
+
+#[tokio::test]
+async fn upsert_update_and_delete_are_recorded() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = channel(&core).await;
+    let id = store
+        .upsert_item(item(cid, Some("ext:1"), json!({ "n": 1 })))
+        .await
+        .unwrap()
+        .id();
+    store
+        .upsert_item(item(cid, Some("ext:1"), json!({ "n": 2 })))
+        .await
+        .unwrap();
+    store.delete_item(id).await.unwrap();
+
+    // History outlives the envelope: the upsert's prior state, then the final state before delete.
+    let revs = store.revisions(EnvelopeRef::Item(id)).await.unwrap();
+    let ns: Vec<_> = revs.iter().map(|r| r.payload["n"].clone()).collect();
+    assert_eq!(ns, [json!(1), json!(2)]);
+    assert!(revs.iter().all(|r| r.actor == Actor::System));
+}
+
+#[tokio::test]
+async fn revert_restores_and_is_itself_recorded() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let a = channel(&core).await;
+    let b = channel(&core).await;
+    let iid = store
+        .create_item(item(a, None, json!({ "body": "original" })))
+        .await
+        .unwrap();
+    store
+        .set_item_payload(iid, json!({ "body": "vandalised" }))
+        .await
+        .unwrap();
+    store.reparent_item(iid, Some(b)).await.unwrap();
+
+    store
+        .acting_as(Actor::Operator)
+        .revert(EnvelopeRef::Item(iid), 1)
+        .await
+        .unwrap();
+    let restored = store.get_item(iid).await.unwrap().unwrap();
+    assert_eq!(restored.payload["body"], "original");
+    assert_eq!(restored.container, Some(a));
+
+    let revs = store.revisions(EnvelopeRef::Item(iid)).await.unwrap();
+    assert_eq!(revs.len(), 3);
+    assert_eq!(revs[2].payload["body"], "vandalised");
+    assert_eq!(revs[2].container, Some(b));
+    assert_eq!(revs[2].actor, Actor::Operator);
+
+    assert!(matches!(
+        store.revert(EnvelopeRef::Item(iid), 99).await,
+        Err(Error::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn a_rejected_write_records_nothing() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = channel(&core).await;
+    let iid = store.create_item(item(cid, None, json!({}))).await.unwrap();
+    let err = store.set_item_payload(iid, json!("not an object")).await;
+    assert!(matches!(err, Err(Error::Validation(_))));
+    assert!(store
+        .revisions(EnvelopeRef::Item(iid))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn reverting_into_a_former_descendant_is_refused() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let p = channel(&core).await;
+    let a = store
+        .create_channel(NewChannel {
+            type_id: TypeId::new("test"),
+            container: Some(p),
+            payload: json!({}),
+        })
+        .await
+        .unwrap();
+    // Revision 1 records `a` inside `p`; then `a` comes out and `p` goes under it.
+    store.reparent_channel(a, None).await.unwrap();
+    store.reparent_channel(p, Some(a)).await.unwrap();
+
+    assert!(matches!(
+        store.revert(EnvelopeRef::Channel(a), 1).await,
+        Err(Error::Validation(_))
+    ));
+    assert_eq!(store.get_channel(a).await.unwrap().unwrap().container, None);
+    assert_eq!(
+        store.get_channel(p).await.unwrap().unwrap().container,
+        Some(a)
+    );
+}
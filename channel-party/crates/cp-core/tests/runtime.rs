@@ -1,16 +1,22 @@
 //! Integration tests for the `RuntimeComponent` supervisor + `RuntimeCtx` (`TODO.md` #4) against a real
 //! tempfile sqlite. Uses throwaway components that record what the ctx hands them into a shared log
 //! (DESIGN §12) — so these assert the supervisor's behavior generically, without a concrete kind:
-//! backfill-then-stream, `interests` filtering, `WriteScope` confinement, `version()` reset, shutdown.
+//! backfill-then-stream, `interests` filtering, `WriteScope` confinement, `version()` reset, shutdown,
+//! resuming the stream from the persisted change-log offset, resumable backfill via checkpoints, and
+//! the operator surface (recorded status, `restart`/`reset` requests).
 
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use cp_core::runtime::{self, Control, RuntimeState};
 use cp_core::{Core, Registry};
 use cp_model::{
-    ChangeOp, EnvelopeRef, Interests, ItemKind, NewItem, Node, Result, RuntimeComponent,
-    RuntimeCtx, RuntimeEvent, TypeId, WriteCtx, WriteScope,
+    ChangeOp, Cursor, EnvelopeRef, Error, Interests, ItemKind, NewItem, Node, Result,
+    RuntimeComponent, RuntimeCtx, RuntimeEvent, TypeId, WriteCtx, WriteScope,
 };
 use tokio::sync::Mutex;
 
@@ -123,6 +129,83 @@ impl RuntimeComponent for PrimaryProbe {
     }
 }
 
+/// A backfill-only component that resumes from its checkpoint: logs the checkpoint it finds, scans
+/// what follows it with `scan_since`, and checkpoints the last id it saw.
+struct Resumer {
+    log: Arc<Mutex<Vec<String>>>,
+    version: u32,
+}
+#[async_trait]
+impl RuntimeComponent for Resumer {
+    fn name(&self) -> &str {
+        "resumer"
+    }
+    fn version(&self) -> u32 {
+        self.version
+    }
+    async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
+        let last = cx.last_checkpoint().await?;
+        let mut log = self.log.lock().await;
+        log.push(format!("checkpoint:{}", last.clone().unwrap_or_default()));
+        let after = last.and_then(|c| c.get("after")?.as_str().map(str::to_owned));
+        let mut cursor = Cursor(after.clone());
+        let mut seen = after;
+        loop {
+            let page = cx.scan_since(&[TypeId::new(ITEM)], cursor).await?;
+            for node in page.nodes {
+                let id = node_id(&node);
+                log.push(format!("scanned:{id}"));
+                seen = Some(id);
+            }
+            let Some(next) = page.next.0 else {
+                break;
+            };
+            cursor = Cursor(Some(next));
+        }
+        if let Some(seen) = seen {
+            cx.checkpoint(serde_json::json!({ "after": seen })).await?;
+        }
+        log.push("ready".to_owned());
+        drop(log);
+        while cx.next_event().await.is_some() {}
+        Ok(())
+    }
+}
+
+/// Fails its first run, then logs what each later run is handed (reset flag, checkpoint), checkpoints
+/// its run count, and idles.
+struct Flaky {
+    log: Arc<Mutex<Vec<String>>>,
+    runs: AtomicU32,
+}
+#[async_trait]
+impl RuntimeComponent for Flaky {
+    fn name(&self) -> &str {
+        "flaky"
+    }
+    async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
+        let run = self.runs.fetch_add(1, Ordering::SeqCst);
+        if run == 0 {
+            return Err(Error::Other("boom".to_owned()));
+        }
+        let last = cx.last_checkpoint().await?.unwrap_or_default();
+        self.log.lock().await.push(format!(
+            "run:{run}:reset={}:checkpoint={last}",
+            cx.reset_requested()
+        ));
+        cx.checkpoint(serde_json::json!(run)).await?;
+        while cx.next_event().await.is_some() {}
+        Ok(())
+    }
+}
+
+fn node_id(node: &Node) -> String {
+    match node {
+        Node::Channel(c) => c.id.to_string(),
+        Node::Item(i) => i.id.to_string(),
+    }
+}
+
 fn item(type_id: &str) -> NewItem {
     NewItem {
         type_id: TypeId::new(type_id),
@@ -165,7 +248,7 @@ async fn open(url: &str, log: Arc<Mutex<Vec<String>>>, version: u32) -> Core {
 #[tokio::test]
 async fn supervisor_backfills_streams_filters_and_confines() {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let log = Arc::new(Mutex::new(Vec::new()));
     let core = open(&url, log.clone(), 0).await;
     let store = core.store();
@@ -218,7 +301,7 @@ async fn supervisor_backfills_streams_filters_and_confines() {
 #[tokio::test]
 async fn version_bump_requests_reset() {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
 
     // First boot at version 0: records the version, no reset (nothing prior to reset).
     let log1 = Arc::new(Mutex::new(Vec::new()));
@@ -238,3 +321,160 @@ async fn version_bump_requests_reset() {
     wait_until(&log2, |l| l.contains(&"reset".to_owned())).await;
     h.shutdown().await;
 }
+
+#[tokio::test]
+async fn a_restart_replays_changes_made_while_down() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+
+    let log1 = Arc::new(Mutex::new(Vec::new()));
+    let core = open(&url, log1.clone(), 0).await;
+    let h = core.spawn_runtime();
+    wait_until(&log1, |l| l.iter().any(|s| s.starts_with("writer:"))).await;
+    let seen = core.store().create_item(item(ITEM)).await.unwrap();
+    wait_until(&log1, |l| {
+        l.iter().any(|s| s == &format!("change:created:{seen}"))
+    })
+    .await;
+    h.shutdown().await;
+
+    // Nothing is running while this commits; only the log can deliver it.
+    let missed = core.store().create_item(item(ITEM)).await.unwrap();
+    drop(core);
+
+    let log2 = Arc::new(Mutex::new(Vec::new()));
+    let core = open(&url, log2.clone(), 0).await;
+    let h = core.spawn_runtime();
+    let snap = wait_until(&log2, |l| {
+        l.iter().any(|s| s == &format!("change:created:{missed}"))
+    })
+    .await;
+    assert!(
+        !snap.iter().any(|s| s == &format!("change:created:{seen}")),
+        "the handled change is not redelivered: {snap:?}"
+    );
+    assert!(!snap.contains(&"reset".to_owned()), "{snap:?}");
+    h.shutdown().await;
+}
+
+async fn open_resumer(url: &str, log: Arc<Mutex<Vec<String>>>, version: u32) -> Core {
+    let registry = Registry::builder()
+        .item(TestItem(TypeId::new(ITEM)))
+        .item(TestItem(TypeId::new(OTHER)))
+        .runtime(Resumer { log, version })
+        .build();
+    Core::open(url, registry).await.unwrap()
+}
+
+/// Boot the resumer once and return its log up to the end of its backfill.
+async fn resumer_pass(url: &str, version: u32) -> Vec<String> {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let core = open_resumer(url, log.clone(), version).await;
+    let h = core.spawn_runtime();
+    let snap = wait_until(&log, |l| l.contains(&"ready".to_owned())).await;
+    h.shutdown().await;
+    snap
+}
+
+#[tokio::test]
+async fn checkpoints_resume_backfill_and_drop_on_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let core = open_resumer(&url, Arc::new(Mutex::new(Vec::new())), 0).await;
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        ids.push(core.store().create_item(item(ITEM)).await.unwrap());
+        core.store().create_item(item(OTHER)).await.unwrap();
+    }
+
+    // The store-level enumerator pages by id, type-filtered, without gaps or dups.
+    let first = core
+        .store()
+        .scan_since(&[TypeId::new(ITEM)], &Cursor(None), 2)
+        .await
+        .unwrap();
+    let rest = core
+        .store()
+        .scan_since(&[TypeId::new(ITEM)], &first.next, 2)
+        .await
+        .unwrap();
+    assert!(rest.next.0.is_none());
+    let paged: Vec<_> = first.nodes.iter().chain(&rest.nodes).map(node_id).collect();
+    let mut want: Vec<_> = ids.iter().map(ToString::to_string).collect();
+    want.sort();
+    assert_eq!(paged, want);
+    drop(core);
+
+    let scanned = |log: &[String]| log.iter().filter(|s| s.starts_with("scanned:")).count();
+    // First boot: no checkpoint, so the whole backfill runs.
+    let snap = resumer_pass(&url, 0).await;
+    assert_eq!(snap[0], "checkpoint:null");
+    assert_eq!(scanned(&snap), 3);
+
+    // A restart resumes after the checkpoint: only the newer envelope is read.
+    let core = open_resumer(&url, Arc::new(Mutex::new(Vec::new())), 0).await;
+    let newer = core.store().create_item(item(ITEM)).await.unwrap();
+    drop(core);
+    let snap = resumer_pass(&url, 0).await;
+    assert!(snap[0].starts_with("checkpoint:{"), "{snap:?}");
+    assert_eq!(snap[1..], [format!("scanned:{newer}"), "ready".to_owned()]);
+
+    // A version bump is a reset: the checkpoint is gone and the backfill starts over.
+    let snap = resumer_pass(&url, 1).await;
+    assert_eq!(snap[0], "checkpoint:null");
+    assert_eq!(scanned(&snap), 4);
+}
+
+#[tokio::test]
+async fn status_records_failures_and_operators_restart_and_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let registry = Registry::builder()
+        .runtime(Flaky {
+            log: log.clone(),
+            runs: AtomicU32::new(0),
+        })
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    let h = core.spawn_runtime();
+
+    // The first run fails; the restart after the backoff is counted and the error kept.
+    wait_until(&log, |l| l.iter().any(|s| s.starts_with("run:1:"))).await;
+    let status = runtime::statuses(core.pool()).await.unwrap().remove(0);
+    assert_eq!(status.name, "flaky");
+    assert_eq!(status.state, RuntimeState::Running);
+    assert_eq!(status.restarts, 1);
+    assert_eq!(status.last_error.as_deref(), Some("boom"));
+
+    // A restart keeps progress; a reset drops it and sets `reset_requested()`, with no version bump.
+    runtime::request(core.pool(), "flaky", Control::Restart)
+        .await
+        .unwrap();
+    let snap = wait_until(&log, |l| l.iter().any(|s| s.starts_with("run:2:"))).await;
+    assert!(
+        snap.contains(&"run:2:reset=false:checkpoint=1".to_owned()),
+        "{snap:?}"
+    );
+    runtime::request(core.pool(), "flaky", Control::Reset)
+        .await
+        .unwrap();
+    let snap = wait_until(&log, |l| l.iter().any(|s| s.starts_with("run:3:"))).await;
+    assert!(
+        snap.contains(&"run:3:reset=true:checkpoint=null".to_owned()),
+        "{snap:?}"
+    );
+    let status = runtime::statuses(core.pool()).await.unwrap().remove(0);
+    assert_eq!(
+        (status.restarts, status.version, status.pending),
+        (3, 0, None)
+    );
+
+    assert!(matches!(
+        runtime::request(core.pool(), "nope", Control::Restart).await,
+        Err(Error::NotFound)
+    ));
+    h.shutdown().await;
+    let status = runtime::statuses(core.pool()).await.unwrap().remove(0);
+    assert_eq!(status.state, RuntimeState::Stopped);
+}
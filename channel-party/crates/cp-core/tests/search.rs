@@ -3,6 +3,8 @@
 //! Uses throwaway test kinds (a name-indexing channel, a text-indexing item) rather than a concrete
 //! kind crate (DESIGN §12) — which also proves search is generic over type, not tied to `basic`.
 
+mod common;
+
 use async_trait::async_trait;
 use cp_core::{Core, Registry};
 use cp_model::{
@@ -49,7 +51,7 @@ impl ItemKind for Msg {
 
 async fn test_core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     // One channel kind registered under two type strings (to test the type filter) + one item kind.
     let registry = Registry::builder()
         .channel(Room(TypeId::new("room")))
@@ -0,0 +1,193 @@
+//! Integration tests for the core R-tree substrate + `StoreCtx::within_bbox` against a real tempfile
+//! sqlite. Data is seeded through the write path, so `index()` actually populates `spatial_index`.
+//! Throwaway kinds (DESIGN §12): a board channel and a pin item projecting `{x, y, w?, h?}` — proving a
+//! board-shaped kind gets viewport queries without canvas's escape hatch.
+
+mod common;
+
+use async_trait::async_trait;
+use cp_core::{Core, Registry};
+use cp_model::{
+    Channel, ChannelId, ChannelKind, Cursor, Error, Filter, IndexEntry, ItemId, ItemKind, Json,
+    NewChannel, NewItem, Node, Page, Rect, Result, StoreCtx, TypeId, WriteCtx,
+};
+use serde_json::json;
+
+struct Board(TypeId);
+
+#[async_trait]
+impl ChannelKind for Board {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _cx: &dyn StoreCtx, _ch: &Channel, _q: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the spatial test")
+    }
+}
+
+/// A pin at `(x, y)`; with `w`/`h` it covers a rectangle instead of a point.
+struct Pin(TypeId);
+
+impl ItemKind for Pin {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    fn index(&self, payload: &Json) -> Option<IndexEntry> {
+        let at = |k: &str| payload.get(k).and_then(Json::as_f64);
+        Some(IndexEntry {
+            coord: Some((at("x")?, at("y")?)),
+            extent: at("w").zip(at("h")),
+            ..Default::default()
+        })
+    }
+}
+
+async fn test_core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Board(TypeId::new("board")))
+        .item(Pin(TypeId::new("pin")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+async fn board(core: &Core, container: Option<ChannelId>) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("board"),
+            container,
+            payload: json!({}),
+        })
+        .await
+        .unwrap()
+}
+
+async fn pin(core: &Core, container: ChannelId, payload: Json) -> ItemId {
+    core.store()
+        .create_item(NewItem {
+            type_id: TypeId::new("pin"),
+            container: Some(container),
+            external_key: None,
+            payload,
+        })
+        .await
+        .unwrap()
+}
+
+fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Rect {
+    Rect { x0, y0, x1, y1 }
+}
+
+fn page(limit: u32) -> Page {
+    Page {
+        cursor: Cursor(None),
+        limit,
+    }
+}
+
+/// Item ids overlapping `r` within `scope`, sorted.
+async fn ids(core: &Core, scope: ChannelId, r: Rect) -> Vec<ItemId> {
+    let p = core
+        .store()
+        .within_bbox(scope, r, Filter::default(), page(100))
+        .await
+        .unwrap();
+    p.nodes
+        .into_iter()
+        .map(|n| match n {
+            Node::Item(i) => i.id,
+            Node::Channel(c) => panic!("unexpected channel {}", c.id),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn bbox_finds_points_and_overlapping_rectangles_in_scope() {
+    let (_dir, core) = test_core().await;
+    let top = board(&core, None).await;
+    let inner = board(&core, Some(top)).await;
+    let other = board(&core, None).await;
+    let near = pin(&core, top, json!({ "x": 5.0, "y": 5.0 })).await;
+    let far = pin(&core, top, json!({ "x": 500.0, "y": 500.0 })).await;
+    // Anchored outside the viewport but wide enough to reach into it.
+    let wide = pin(
+        &core,
+        inner,
+        json!({ "x": -50.0, "y": 0.0, "w": 60.0, "h": 2.0 }),
+    )
+    .await;
+    pin(&core, other, json!({ "x": 5.0, "y": 5.0 })).await;
+    pin(&core, top, json!({ "note": "unplaced" })).await;
+
+    let mut want = vec![near, wide];
+    want.sort_by_key(|id| id.to_string());
+    assert_eq!(ids(&core, top, rect(0.0, 0.0, 10.0, 10.0)).await, want);
+    assert_eq!(ids(&core, inner, rect(0.0, 0.0, 10.0, 10.0)).await, [wide]);
+    assert_eq!(
+        ids(&core, top, rect(400.0, 400.0, 600.0, 600.0)).await,
+        [far]
+    );
+
+    let bad = core
+        .store()
+        .within_bbox(top, rect(10.0, 0.0, 0.0, 10.0), Filter::default(), page(10))
+        .await;
+    assert!(matches!(bad, Err(Error::Validation(_))));
+}
+
+#[tokio::test]
+async fn bbox_tracks_moves_deletes_and_paginates() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = board(&core, None).await;
+    let mut placed = Vec::new();
+    for i in 0..5 {
+        placed.push(pin(&core, top, json!({ "x": f64::from(i), "y": 0.0 })).await);
+    }
+    let view = rect(-1.0, -1.0, 10.0, 1.0);
+
+    // Page size 2 walks the id-ordered viewport without gaps or dups.
+    let mut walked = Vec::new();
+    let mut cursor = Cursor(None);
+    loop {
+        let p = store
+            .within_bbox(
+                top,
+                view,
+                Filter::default(),
+                Page {
+                    cursor: cursor.clone(),
+                    limit: 2,
+                },
+            )
+            .await
+            .unwrap();
+        walked.extend(p.nodes.iter().map(|n| match n {
+            Node::Item(i) => i.id,
+            Node::Channel(_) => unreachable!(),
+        }));
+        if p.next.0.is_none() {
+            break;
+        }
+        cursor = p.next;
+    }
+    let mut want = placed.clone();
+    want.sort_by_key(|id| id.to_string());
+    assert_eq!(walked, want);
+
+    // A move re-projects in the write transaction; a delete drops the box.
+    store
+        .set_item_payload(placed[0], json!({ "x": 100.0, "y": 100.0 }))
+        .await
+        .unwrap();
+    store.delete_item(placed[1]).await.unwrap();
+    let left = ids(&core, top, view).await;
+    assert_eq!(left.len(), 3);
+    assert!(!left.contains(&placed[0]) && !left.contains(&placed[1]));
+    assert_eq!(
+        ids(&core, top, rect(99.0, 99.0, 101.0, 101.0)).await,
+        [placed[0]]
+    );
+}
@@ -0,0 +1,169 @@
+//! Brute-force protection for password login (`TODO.md` #44) against a real tempfile sqlite: the
+//! per-handle and per-address ledger, the delay and then the lockout it imposes, blocked attempts being
+//! refused like wrong passwords (known handle or not), a burst of concurrent guesses counted one by
+//! one, and clearing and forgetting ledger rows.
+
+mod common;
+
+use cp_core::throttle::{self, Subject};
+use cp_core::{auth, db, Core, Registry};
+use cp_model::Error;
+use std::time::Duration;
+
+async fn core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let core = Core::open(&url, Registry::builder().build()).await.unwrap();
+    (dir, core)
+}
+
+/// Lift every current delay or lockout without forgetting a failure, as waiting it out would.
+async fn wait_out(pool: &sqlx::AnyPool) {
+    sqlx::query("UPDATE login_failures SET blocked_until = $1")
+        .bind(db::ago(Duration::from_secs(1)))
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn login(pool: &sqlx::AnyPool, handle: &str, password: &str, ip: &str) -> bool {
+    throttle::authenticate(pool, handle, password, Some(ip))
+        .await
+        .unwrap()
+        .is_some()
+}
+
+#[tokio::test]
+async fn failures_delay_then_lock_out_a_handle() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let alice = auth::provision_user(pool, "alice").await.unwrap();
+    auth::set_password(pool, "alice", "hunter22").await.unwrap();
+
+    // A few free tries; the next failure imposes a delay that refuses even the right password.
+    for _ in 0..4 {
+        assert!(!login(pool, "alice", "wrong", "10.0.0.1").await);
+    }
+    assert!(!login(pool, "alice", "hunter22", "10.0.0.2").await);
+    assert!(throttle::lockouts(pool).await.unwrap().is_empty());
+
+    // Once the delay has passed, a success forgets the handle's failures.
+    wait_out(pool).await;
+    let user = throttle::authenticate(pool, "alice", "hunter22", None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.id, alice);
+    assert!(matches!(
+        throttle::clear(pool, &Subject::Handle("alice".to_owned())).await,
+        Err(Error::NotFound)
+    ));
+
+    // Keep failing through the delays and the handle is locked out, from any address.
+    for attempt in 0..10 {
+        wait_out(pool).await;
+        assert!(!login(pool, "alice", "wrong", &format!("10.0.1.{attempt}")).await);
+    }
+    let lockouts = throttle::lockouts(pool).await.unwrap();
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(lockouts[0].subject, Subject::Handle("alice".to_owned()));
+    assert_eq!(lockouts[0].failures, 10);
+    assert!(!login(pool, "alice", "hunter22", "10.0.2.1").await);
+
+    throttle::clear(pool, &Subject::Handle("alice".to_owned()))
+        .await
+        .unwrap();
+    assert!(throttle::lockouts(pool).await.unwrap().is_empty());
+    assert!(login(pool, "alice", "hunter22", "10.0.2.1").await);
+}
+
+/// `n` wrong passwords for `handle` sent at once, each from its own address.
+async fn guess_at_once(pool: &sqlx::AnyPool, handle: &str, n: usize) {
+    let mut guesses = tokio::task::JoinSet::new();
+    for i in 0..n {
+        let (pool, handle) = (pool.clone(), handle.to_owned());
+        guesses.spawn(async move {
+            let ip = format!("10.0.3.{i}");
+            throttle::authenticate(&pool, &handle, "wrong", Some(&ip)).await
+        });
+    }
+    while let Some(res) = guesses.join_next().await {
+        assert!(res.unwrap().unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn a_burst_of_guesses_cannot_outrun_the_lockout() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    auth::provision_user(pool, "alice").await.unwrap();
+    auth::set_password(pool, "alice", "hunter22").await.unwrap();
+    // One failure short of the lockout, with no delay pending.
+    sqlx::query(
+        "INSERT INTO login_failures (kind, subject, failures, last_failed_at, blocked_until) \
+         VALUES ('handle', 'alice', 9, $1, $1)",
+    )
+    .bind(db::now())
+    .execute(pool)
+    .await
+    .unwrap();
+
+    // Sent at once, the first guess locks the handle and the rest are refused unchecked.
+    guess_at_once(pool, "alice", 20).await;
+    let lockouts = throttle::lockouts(pool).await.unwrap();
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(lockouts[0].subject, Subject::Handle("alice".to_owned()));
+    assert_eq!(lockouts[0].failures, 10);
+    assert!(!login(pool, "alice", "hunter22", "10.0.4.1").await);
+}
+
+#[tokio::test]
+async fn unknown_handles_and_addresses_are_throttled_alike() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    auth::provision_user(pool, "alice").await.unwrap();
+    auth::set_password(pool, "alice", "hunter22").await.unwrap();
+
+    // A handle nobody has runs up the same ledger a real one does.
+    for _ in 0..4 {
+        assert!(!login(pool, "nobody", "guess", "10.0.0.1").await);
+        assert!(!login(pool, "alice", "guess", "10.0.0.2").await);
+    }
+    let rows: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT subject, failures FROM login_failures WHERE kind = 'handle' ORDER BY subject",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    assert_eq!(rows, [("alice".to_owned(), 4), ("nobody".to_owned(), 4)]);
+
+    // One address spraying many handles is held up on its own ledger row, not the handles'.
+    wait_out(pool).await;
+    throttle::clear(pool, &Subject::Handle("alice".to_owned()))
+        .await
+        .unwrap();
+    for n in 0..11 {
+        assert!(!login(pool, &format!("user{n}"), "guess", "10.9.9.9").await);
+    }
+    assert!(!login(pool, "alice", "hunter22", "10.9.9.9").await);
+    assert!(login(pool, "alice", "hunter22", "10.0.0.3").await);
+}
+
+#[tokio::test]
+async fn stale_failures_are_forgotten() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    assert!(!login(pool, "nobody", "guess", "10.0.0.1").await);
+    assert_eq!(throttle::purge_stale(pool).await.unwrap(), 0);
+
+    sqlx::query("UPDATE login_failures SET last_failed_at = $1")
+        .bind(db::ago(Duration::from_secs(2 * 60 * 60)))
+        .execute(pool)
+        .await
+        .unwrap();
+    assert_eq!(throttle::purge_stale(pool).await.unwrap(), 2);
+    assert!(matches!(
+        throttle::clear(pool, &Subject::Ip("10.0.0.1".to_owned())).await,
+        Err(Error::NotFound)
+    ));
+}
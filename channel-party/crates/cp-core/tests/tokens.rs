@@ -0,0 +1,229 @@
+//! Personal access tokens (`TODO.md` #42) against a real tempfile sqlite: scope strings, minting,
+//! resolving (and stamping `last_used_at`), expiry and revocation, scopes covering a channel's subtree,
+//! and a `ViewScope` narrowed to a token's `read` scopes. A throwaway kind (DESIGN §12) shows its
+//! channels to any signed-in user and to no anonymous visitor, so a narrowed read is visible.
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_core::authz::ViewScope;
+use cp_core::tokens::{self, Scope, Scopes};
+use cp_core::{auth, Core, Registry};
+use cp_model::{
+    Action, Channel, ChannelId, ChannelKind, Error, Json, NewChannel, Permission, Result, StoreCtx,
+    TypeId, UserId, WriteCtx,
+};
+
+/// Grants everything to any signed-in user; nothing to anonymous visitors.
+struct Private(TypeId);
+#[async_trait]
+impl ChannelKind for Private {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _: &dyn StoreCtx, _: &Channel, _: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the token test")
+    }
+    fn permission(&self) -> Option<&dyn Permission> {
+        Some(self)
+    }
+}
+#[async_trait]
+impl Permission for Private {
+    async fn authorize(&self, _: &dyn StoreCtx, _: &Channel, _: UserId, _: Action) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+async fn core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Private(TypeId::new("private")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+async fn channel(core: &Core, container: Option<ChannelId>) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("private"),
+            container,
+            payload: serde_json::json!({}),
+        })
+        .await
+        .unwrap()
+}
+
+fn scopes(list: &[&str]) -> Vec<Scope> {
+    list.iter().map(|s| s.parse().unwrap()).collect()
+}
+
+#[test]
+fn scope_strings_round_trip() {
+    let cid = ChannelId::generate();
+    for s in ["read", "post", "manage", &format!("post:{cid}")] {
+        assert_eq!(s.parse::<Scope>().unwrap().to_string(), s);
+    }
+    assert_eq!("read".parse::<Scope>().unwrap().action, Action::View);
+    for bad in ["", "write", "read:", "read:nope"] {
+        assert!(
+            matches!(bad.parse::<Scope>(), Err(Error::Validation(_))),
+            "{bad}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_token_resolves_until_revoked_or_expired() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let alice = auth::provision_user(pool, "alice").await.unwrap();
+
+    assert!(matches!(
+        tokens::mint(pool, alice, " ", scopes(&["read"]), None).await,
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        tokens::mint(pool, alice, "ci", Vec::new(), None).await,
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        tokens::mint(
+            pool,
+            alice,
+            "ci",
+            vec![Scope {
+                action: Action::View,
+                channel: Some(ChannelId::generate())
+            }],
+            None
+        )
+        .await,
+        Err(Error::NotFound)
+    ));
+
+    let (token, secret) = tokens::mint(pool, alice, "ci", scopes(&["post"]), None)
+        .await
+        .unwrap();
+    assert!(secret.starts_with("cpt_"));
+    assert!(token.expires_at.is_none() && token.last_used_at.is_none());
+
+    let (user, granted) = tokens::resolve(pool, &secret).await.unwrap().unwrap();
+    assert_eq!(user.id, alice);
+    assert_eq!(granted, Scopes::Limited(scopes(&["post"])));
+    let listed = tokens::list(pool, alice).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].last_used_at.is_some());
+    assert!(tokens::resolve(pool, "cpt_nope").await.unwrap().is_none());
+
+    tokens::revoke(pool, &token.id).await.unwrap();
+    assert!(tokens::resolve(pool, &secret).await.unwrap().is_none());
+    assert!(matches!(
+        tokens::revoke(pool, &token.id).await,
+        Err(Error::NotFound)
+    ));
+
+    let (_, stale) = tokens::mint(pool, alice, "old", scopes(&["read"]), Some(Duration::ZERO))
+        .await
+        .unwrap();
+    assert!(tokens::resolve(pool, &stale).await.unwrap().is_none());
+
+    // An expiry is stored like every other timestamp, so it compares with the clock correctly.
+    let (token, fresh) = tokens::mint(
+        pool,
+        alice,
+        "new",
+        scopes(&["read"]),
+        Some(Duration::from_secs(60)),
+    )
+    .await
+    .unwrap();
+    assert!(token.expires_at.unwrap().ends_with('Z'));
+    assert!(tokens::resolve(pool, &fresh).await.unwrap().is_some());
+    for ttl in [tokens::MAX_TTL + Duration::from_secs(1), Duration::MAX] {
+        assert!(matches!(
+            tokens::mint(pool, alice, "far", scopes(&["read"]), Some(ttl)).await,
+            Err(Error::Validation(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn scopes_cover_actions_and_subtrees() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let top = channel(&core, None).await;
+    let child = channel(&core, Some(top)).await;
+    let other = channel(&core, None).await;
+
+    assert!(Scopes::Full
+        .allows(pool, Action::Manage, None)
+        .await
+        .unwrap());
+
+    let post = Scopes::Limited(scopes(&["post"]));
+    assert!(post.allows(pool, Action::View, Some(other)).await.unwrap());
+    assert!(post.allows(pool, Action::Post, None).await.unwrap());
+    assert!(!post
+        .allows(pool, Action::Manage, Some(other))
+        .await
+        .unwrap());
+
+    let manage_top = Scopes::Limited(scopes(&[&format!("manage:{top}")]));
+    assert!(manage_top
+        .allows(pool, Action::Manage, Some(child))
+        .await
+        .unwrap());
+    assert!(manage_top
+        .allows(pool, Action::View, Some(top))
+        .await
+        .unwrap());
+    assert!(!manage_top
+        .allows(pool, Action::View, Some(other))
+        .await
+        .unwrap());
+    assert!(!manage_top.allows(pool, Action::Manage, None).await.unwrap());
+
+    // A channel above a scoped subtree is reached through, but not covered.
+    let read_child = Scopes::Limited(scopes(&[&format!("read:{child}")]));
+    assert!(!read_child
+        .allows(pool, Action::View, Some(top))
+        .await
+        .unwrap());
+    assert!(read_child.reaches(pool, Action::View, top).await.unwrap());
+    assert!(!read_child.reaches(pool, Action::View, other).await.unwrap());
+}
+
+#[tokio::test]
+async fn a_limited_view_sees_only_its_read_scopes() {
+    let (_dir, core) = core().await;
+    let alice = auth::provision_user(core.pool(), "alice").await.unwrap();
+    let top = channel(&core, None).await;
+    let child = channel(&core, Some(top)).await;
+    let sibling = channel(&core, Some(top)).await;
+    let other = channel(&core, None).await;
+
+    let full = ViewScope::new(core.store(), Some(alice));
+    for id in [top, child, sibling, other] {
+        assert!(StoreCtx::get_channel(&full, id).await.unwrap().is_some());
+    }
+
+    let limited = ViewScope::new(core.store(), Some(alice))
+        .limited(Scopes::Limited(scopes(&[&format!("read:{child}")])));
+    assert!(StoreCtx::get_channel(&limited, child)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(StoreCtx::get_channel(&limited, sibling)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(StoreCtx::get_channel(&limited, other)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,309 @@
+//! TOTP second factor (`TODO.md` #45) against a real tempfile sqlite: RFC 6238 codes, enrollment and
+//! its recovery codes, the two-step login (pending token exchanged with a code, throttled like a
+//! password), turning the factor off, and the operator's reset.
+
+mod common;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cp_core::throttle::{self, Subject};
+use cp_core::{auth, totp, Core, Registry};
+use cp_model::{Error, User};
+
+async fn core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let core = Core::open(&url, Registry::builder().build()).await.unwrap();
+    (dir, core)
+}
+
+/// A code from the step after the current one: still within the accepted skew, and later than any code
+/// already spent.
+fn next_code(secret: &str) -> String {
+    totp::code_at(secret, SystemTime::now() + Duration::from_secs(30)).unwrap()
+}
+
+/// Enroll alice and confirm with a current code, returning her, the secret and the recovery codes.
+async fn enrolled(pool: &sqlx::AnyPool) -> (User, String, Vec<String>) {
+    let id = auth::provision_user(pool, "alice").await.unwrap();
+    let alice = User {
+        id,
+        handle: "alice".to_owned(),
+    };
+    let enrollment = totp::begin_enrollment(pool, &alice, "channel-party")
+        .await
+        .unwrap();
+    let code = totp::code_at(&enrollment.secret, SystemTime::now()).unwrap();
+    let recovery = totp::confirm_enrollment(pool, id, &code).await.unwrap();
+    (alice, enrollment.secret, recovery)
+}
+
+#[test]
+fn codes_match_the_rfc_6238_vectors() {
+    // RFC 6238 appendix B's SHA-1 seed, base32; its 8-digit codes, truncated to our 6.
+    let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    for (at, code) in [
+        (59, "287082"),
+        (1_111_111_109, "081804"),
+        (1_234_567_890, "005924"),
+        (2_000_000_000, "279037"),
+    ] {
+        let at = UNIX_EPOCH + Duration::from_secs(at);
+        assert_eq!(totp::code_at(secret, at).unwrap(), code);
+        assert_eq!(totp::code_at(&secret.to_lowercase(), at).unwrap(), code);
+    }
+    assert!(matches!(
+        totp::code_at("not base32!", SystemTime::now()),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[tokio::test]
+async fn enrollment_is_confirmed_then_turned_off_with_a_code() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let id = auth::provision_user(pool, "alice").await.unwrap();
+    let alice = User {
+        id,
+        handle: "alice".to_owned(),
+    };
+    assert!(matches!(
+        totp::confirm_enrollment(pool, id, "123456").await,
+        Err(Error::NotFound)
+    ));
+
+    let enrollment = totp::begin_enrollment(pool, &alice, "My Instance")
+        .await
+        .unwrap();
+    assert_eq!(enrollment.secret.len(), 32);
+    assert_eq!(
+        enrollment.uri,
+        format!(
+            "otpauth://totp/My%20Instance:alice?secret={}&issuer=My%20Instance&algorithm=SHA1\
+             &digits=6&period=30",
+            enrollment.secret
+        )
+    );
+    assert!(!totp::enabled(pool, id).await.unwrap());
+
+    // A wrong code leaves the enrollment pending; a right one enables it and issues recovery codes.
+    assert!(matches!(
+        totp::confirm_enrollment(pool, id, "abc").await,
+        Err(Error::Validation(_))
+    ));
+    let code = totp::code_at(&enrollment.secret, SystemTime::now()).unwrap();
+    let recovery = totp::confirm_enrollment(pool, id, &code).await.unwrap();
+    assert_eq!(recovery.len(), 10);
+    assert!(totp::enabled(pool, id).await.unwrap());
+    assert_eq!(
+        totp::status(pool, id).await.unwrap().recovery_codes_left,
+        10
+    );
+    assert!(matches!(
+        totp::begin_enrollment(pool, &alice, "x").await,
+        Err(Error::Validation(_))
+    ));
+
+    // The confirming code is spent; a recovery code, typed loosely, turns the factor off.
+    assert!(matches!(
+        totp::disable(pool, id, &code, None).await,
+        Err(Error::Validation(_))
+    ));
+    totp::disable(pool, id, &format!(" {} ", recovery[0].to_uppercase()), None)
+        .await
+        .unwrap();
+    assert!(!totp::enabled(pool, id).await.unwrap());
+    assert_eq!(totp::status(pool, id).await.unwrap().recovery_codes_left, 0);
+    assert!(matches!(
+        totp::disable(pool, id, &code, None).await,
+        Err(Error::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn a_pending_login_is_exchanged_with_a_code() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let (alice, secret, recovery) = enrolled(pool).await;
+
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    assert!(
+        totp::complete_login(pool, "nope", &next_code(&secret), None)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(totp::complete_login(pool, &pending, "000000", None)
+        .await
+        .unwrap()
+        .is_none());
+    let code = next_code(&secret);
+    let user = totp::complete_login(pool, &pending, &code, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.id, alice.id);
+
+    // The token is spent, and so is the code.
+    assert!(totp::complete_login(pool, &pending, &recovery[0], None)
+        .await
+        .unwrap()
+        .is_none());
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    assert!(totp::complete_login(pool, &pending, &code, None)
+        .await
+        .unwrap()
+        .is_none());
+
+    // A recovery code works once.
+    assert!(totp::complete_login(pool, &pending, &recovery[0], None)
+        .await
+        .unwrap()
+        .is_some());
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    assert!(totp::complete_login(pool, &pending, &recovery[0], None)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        totp::status(pool, alice.id)
+            .await
+            .unwrap()
+            .recovery_codes_left,
+        9
+    );
+
+    // An expired token is refused.
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    sqlx::query("UPDATE pending_logins SET expires_at = $1")
+        .bind(cp_core::db::ago(Duration::from_secs(1)))
+        .execute(pool)
+        .await
+        .unwrap();
+    assert!(totp::complete_login(pool, &pending, &recovery[1], None)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(totp::purge_expired_pending(pool).await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn wrong_codes_are_throttled_like_wrong_passwords() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let (alice, _secret, recovery) = enrolled(pool).await;
+    auth::set_password(pool, "alice", "hunter22").await.unwrap();
+
+    // The password alone no longer forgets the handle's failures, so it cannot reset the ledger
+    // between guesses at the code.
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    for _ in 0..3 {
+        assert!(
+            totp::complete_login(pool, &pending, "000000", Some("10.0.0.1"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+    assert!(throttle::authenticate(pool, "alice", "hunter22", None)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(
+        totp::complete_login(pool, &pending, "000000", Some("10.0.0.1"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        totp::complete_login(pool, &pending, &recovery[0], Some("10.0.0.2"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Once the handle is cleared, the token's last wrong code spends it.
+    throttle::clear(pool, &Subject::Handle("alice".to_owned()))
+        .await
+        .unwrap();
+    assert!(totp::complete_login(pool, &pending, "000000", None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(totp::complete_login(pool, &pending, &recovery[0], None)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        totp::status(pool, alice.id)
+            .await
+            .unwrap()
+            .recovery_codes_left,
+        10
+    );
+}
+
+#[tokio::test]
+async fn concurrent_wrong_codes_share_one_attempt_count() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let (alice, _secret, recovery) = enrolled(pool).await;
+
+    // One guess short of spending the token, two guesses at once: the second sees the first's count.
+    let pending = totp::begin_login(pool, alice.id).await.unwrap();
+    sqlx::query("UPDATE pending_logins SET attempts = 3")
+        .execute(pool)
+        .await
+        .unwrap();
+    let (a, b) = tokio::join!(
+        totp::complete_login(pool, &pending, "000000", None),
+        totp::complete_login(pool, &pending, "111111", None),
+    );
+    assert!(a.unwrap().is_none() && b.unwrap().is_none());
+    assert!(totp::complete_login(pool, &pending, &recovery[0], None)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn wrong_codes_to_turn_the_factor_off_are_throttled() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let (alice, _secret, recovery) = enrolled(pool).await;
+
+    // A session alone cannot guess its way past the second factor: after a few wrong codes even a
+    // right one is refused, and the factor stays on.
+    for _ in 0..4 {
+        assert!(matches!(
+            totp::disable(pool, alice.id, "000000", Some("10.0.0.1")).await,
+            Err(Error::Validation(_))
+        ));
+    }
+    assert!(matches!(
+        totp::disable(pool, alice.id, &recovery[0], Some("10.0.0.2")).await,
+        Err(Error::Validation(_))
+    ));
+    assert!(totp::enabled(pool, alice.id).await.unwrap());
+
+    throttle::clear(pool, &Subject::Handle("alice".to_owned()))
+        .await
+        .unwrap();
+    totp::disable(pool, alice.id, &recovery[0], Some("10.0.0.1"))
+        .await
+        .unwrap();
+    assert!(!totp::enabled(pool, alice.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn reset_removes_the_second_factor() {
+    let (_dir, core) = core().await;
+    let pool = core.pool();
+    let (alice, _secret, _recovery) = enrolled(pool).await;
+    totp::begin_login(pool, alice.id).await.unwrap();
+
+    assert!(totp::reset(pool, alice.id).await.unwrap());
+    assert!(!totp::enabled(pool, alice.id).await.unwrap());
+    assert_eq!(totp::purge_expired_pending(pool).await.unwrap(), 0);
+    assert!(!totp::reset(pool, alice.id).await.unwrap());
+}
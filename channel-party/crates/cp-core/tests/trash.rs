@@ -0,0 +1,291 @@
+//! Soft delete (`TODO.md` #25) against a real tempfile sqlite: `delete_*` tombstones instead of
+//! removing, every read path hides the tombstoned subtree, `restore_*` brings back exactly what one
+//! tombstone hid, `purge_*` is the old hard delete, and `trash::sweep` purges expired tombstones.
+//! Throwaway kinds (DESIGN §12) with a name/body FTS projection so search is covered too.
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use cp_core::{trash, ChangeOp, Core, EnvelopeRef, Registry};
+use cp_model::{
+    Channel, ChannelId, ChannelKind, Cursor, Error, Filter, IndexEntry, ItemId, ItemKind, Json,
+    NewChannel, NewItem, Order, Page, Result, StoreCtx, TypeId, WriteCtx,
+};
+use serde_json::json;
+
+struct Room(TypeId);
+
+#[async_trait]
+impl ChannelKind for Room {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    async fn contents(&self, _cx: &dyn StoreCtx, _ch: &Channel, _q: Json) -> Result<Json> {
+        unreachable!("contents is not exercised by the trash test")
+    }
+    fn index(&self, payload: &Json) -> Option<IndexEntry> {
+        let name = payload.get("name")?.as_str()?;
+        Some(IndexEntry {
+            name: Some(name.to_owned()),
+            ..Default::default()
+        })
+    }
+}
+
+struct Msg(TypeId);
+
+impl ItemKind for Msg {
+    fn type_id(&self) -> &TypeId {
+        &self.0
+    }
+    fn index(&self, payload: &Json) -> Option<IndexEntry> {
+        let body = payload.get("body")?.as_str()?;
+        Some(IndexEntry {
+            text: Some(body.to_owned()),
+            ..Default::default()
+        })
+    }
+}
+
+async fn test_core() -> (tempfile::TempDir, Core) {
+    let dir = tempfile::tempdir().unwrap();
+    let url = common::db_url(&dir).await;
+    let registry = Registry::builder()
+        .channel(Room(TypeId::new("room")))
+        .item(Msg(TypeId::new("msg")))
+        .build();
+    let core = Core::open(&url, registry).await.unwrap();
+    (dir, core)
+}
+
+async fn room(core: &Core, name: &str, container: Option<ChannelId>) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("room"),
+            container,
+            payload: json!({ "name": name }),
+        })
+        .await
+        .unwrap()
+}
+
+async fn msg(core: &Core, body: &str, container: ChannelId) -> ItemId {
+    core.store()
+        .create_item(NewItem {
+            type_id: TypeId::new("msg"),
+            container: Some(container),
+            external_key: None,
+            payload: json!({ "body": body }),
+        })
+        .await
+        .unwrap()
+}
+
+fn page() -> Page {
+    Page {
+        cursor: Cursor::default(),
+        limit: 50,
+    }
+}
+
+async fn visible(core: &Core, root: ChannelId) -> usize {
+    core.store()
+        .descendants(root, Filter::default(), None)
+        .await
+        .unwrap()
+        .len()
+}
+
+async fn hits(core: &Core, scope: ChannelId, text: &str) -> usize {
+    core.store()
+        .search(scope, text, Filter::default(), page())
+        .await
+        .unwrap()
+        .nodes
+        .len()
+}
+
+#[tokio::test]
+async fn trash_hides_from_every_read_and_restore_brings_it_back() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = room(&core, "top", None).await;
+    let side = room(&core, "side", Some(top)).await;
+    msg(&core, "hello trash", side).await;
+    assert_eq!(visible(&core, top).await, 2);
+    assert_eq!(hits(&core, top, "trash").await, 1);
+
+    let mut rx = core.events().subscribe();
+    store.delete_channel(side).await.unwrap();
+    assert!(store.get_channel(side).await.unwrap().is_none());
+    assert_eq!(visible(&core, top).await, 0);
+    let kids = store
+        .children(top, Filter::default(), page(), Order::TimeAsc)
+        .await
+        .unwrap();
+    assert!(kids.nodes.is_empty());
+    assert_eq!(hits(&core, top, "trash").await, 0);
+    // Writes into the hidden channel are refused like writes into a missing one.
+    let orphan = store
+        .create_item(NewItem {
+            type_id: TypeId::new("msg"),
+            container: Some(side),
+            external_key: None,
+            payload: json!({ "body": "late" }),
+        })
+        .await;
+    assert!(matches!(orphan, Err(Error::NotFound)));
+    while rx.try_recv().is_ok() {}
+
+    store.restore_channel(side).await.unwrap();
+    assert_eq!(visible(&core, top).await, 2);
+    assert_eq!(hits(&core, top, "trash").await, 1);
+    // Restore is a creation as far as subscribers are concerned: the channel, then its contents.
+    let first = rx.try_recv().unwrap();
+    assert!(matches!(first.op, ChangeOp::Created));
+    assert!(matches!(first.target, EnvelopeRef::Channel(id) if id == side));
+    let second = rx.try_recv().unwrap();
+    assert!(matches!(second.op, ChangeOp::Created));
+    assert!(matches!(second.target, EnvelopeRef::Item(_)));
+    assert!(rx.try_recv().is_err());
+
+    // A live envelope has nothing to restore.
+    assert!(matches!(
+        store.restore_channel(side).await,
+        Err(Error::Validation(_))
+    ));
+}
+
+#[tokio::test]
+async fn inner_tombstones_survive_an_outer_restore() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = room(&core, "top", None).await;
+    let side = room(&core, "side", Some(top)).await;
+    let keep = msg(&core, "keep", side).await;
+    let gone = msg(&core, "gone", side).await;
+
+    store.delete_item(gone).await.unwrap();
+    store.delete_channel(side).await.unwrap();
+    // Hidden by the channel's tombstone, not its own: restore the channel instead.
+    assert!(matches!(
+        store.restore_item(keep).await,
+        Err(Error::Validation(_))
+    ));
+
+    store.restore_channel(side).await.unwrap();
+    assert!(store.get_item(keep).await.unwrap().is_some());
+    assert!(
+        store.get_item(gone).await.unwrap().is_none(),
+        "trashed before the channel, so still trashed after"
+    );
+
+    // Restoring into a container that is itself in the trash re-hides under that tombstone.
+    store.delete_channel(side).await.unwrap();
+    store.restore_item(gone).await.unwrap();
+    assert!(store.get_item(gone).await.unwrap().is_none());
+    store.restore_channel(side).await.unwrap();
+    assert!(store.get_item(gone).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn an_upsert_out_of_a_trashed_channel_revives_the_item() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let old = room(&core, "old", None).await;
+    let new = room(&core, "new", None).await;
+    let mirrored = |container| NewItem {
+        type_id: TypeId::new("msg"),
+        container: Some(container),
+        external_key: Some("ext:1".to_owned()),
+        payload: json!({ "body": "mirrored" }),
+    };
+    let id = store.upsert_item(mirrored(old)).await.unwrap().id();
+    store.delete_channel(old).await.unwrap();
+    assert!(store.get_item(id).await.unwrap().is_none());
+
+    // Moved into a live channel, the item is live there, announced as a creation.
+    let mut rx = core.events().subscribe();
+    assert_eq!(store.upsert_item(mirrored(new)).await.unwrap().id(), id);
+    let ev = rx.try_recv().unwrap();
+    assert!(matches!(ev.op, ChangeOp::Created));
+    assert_eq!(visible(&core, new).await, 1);
+    assert_eq!(hits(&core, new, "mirrored").await, 1);
+
+    // The old tombstone no longer governs it either way.
+    store.restore_channel(old).await.unwrap();
+    assert_eq!(visible(&core, old).await, 0);
+    store.delete_channel(old).await.unwrap();
+    assert!(store.get_item(id).await.unwrap().is_some());
+
+    // A directly-trashed item stays in the trash, and can still be restored.
+    store.delete_item(id).await.unwrap();
+    store.upsert_item(mirrored(new)).await.unwrap();
+    assert!(store.get_item(id).await.unwrap().is_none());
+    store.restore_item(id).await.unwrap();
+    assert_eq!(visible(&core, new).await, 1);
+}
+
+#[tokio::test]
+async fn purge_removes_rows_but_keeps_history() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = room(&core, "top", None).await;
+    let iid = msg(&core, "v1", top).await;
+
+    store.delete_item(iid).await.unwrap();
+    store.purge_item(iid).await.unwrap();
+    assert!(matches!(
+        store.restore_item(iid).await,
+        Err(Error::NotFound)
+    ));
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items")
+        .fetch_one(core.pool())
+        .await
+        .unwrap();
+    assert_eq!(rows, 0);
+    let revs = store.revisions(EnvelopeRef::Item(iid)).await.unwrap();
+    assert_eq!(
+        revs.len(),
+        2,
+        "the delete and the purge each recorded the prior state"
+    );
+    assert!(revs.iter().all(|r| r.payload["body"] == "v1"));
+}
+
+#[tokio::test]
+async fn sweep_purges_only_expired_tombstones() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = room(&core, "top", None).await;
+    let side = room(&core, "side", Some(top)).await;
+    let inner = msg(&core, "inner", side).await;
+    let live = msg(&core, "live", top).await;
+    store.delete_item(inner).await.unwrap();
+    store.delete_channel(side).await.unwrap();
+
+    let week = Duration::from_secs(7 * 24 * 60 * 60);
+    assert_eq!(
+        trash::sweep(core.pool(), store.as_ref(), week)
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(store.restore_channel(side).await.is_ok());
+    store.delete_channel(side).await.unwrap();
+
+    // Zero retention: everything directly trashed is expired. The channel's purge takes the item
+    // with it, so only one purge is counted for the pair.
+    let purged = trash::sweep(core.pool(), store.as_ref(), Duration::ZERO)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items")
+        .fetch_one(core.pool())
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+    assert!(store.get_item(live).await.unwrap().is_some());
+}
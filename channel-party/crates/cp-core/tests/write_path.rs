@@ -1,11 +1,13 @@
 //! Integration tests for the core write path (`TODO.md` #1) against a real tempfile sqlite. Uses a
 //! throwaway test kind so core's genericity is exercised, not a concrete kind crate (DESIGN §12).
 
+mod common;
+
 use async_trait::async_trait;
 use cp_core::{ChangeOp, Core, EnvelopeRef, Registry};
 use cp_model::{
-    Channel, ChannelKind, Error, IndexEntry, ItemKind, Json, NewChannel, NewItem, Result, StoreCtx,
-    TypeId, Upsert, UserId, WriteCtx,
+    Batch, Channel, ChannelKind, Error, IndexEntry, ItemKind, Json, NewChannel, NewItem, Patch,
+    Result, StoreCtx, TypeId, Upsert, UserId, WriteCtx,
 };
 
 struct TestChannel(TypeId);
@@ -57,7 +59,7 @@ fn require_object(payload: &Json) -> Result<()> {
 
 async fn test_core() -> (tempfile::TempDir, Core) {
     let dir = tempfile::tempdir().unwrap();
-    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let url = common::db_url(&dir).await;
     let registry = Registry::builder()
         .channel(TestChannel(TypeId::new("test")))
         .item(TestItem(TypeId::new("test")))
@@ -189,7 +191,7 @@ async fn membership_substrate() {
 
     // Users come from auth (TODO #17); insert one directly to satisfy the channel_members FK.
     let uid = UserId::generate();
-    sqlx::query("INSERT INTO users (id, handle) VALUES (?, ?)")
+    sqlx::query("INSERT INTO users (id, handle) VALUES ($1, $2)")
         .bind(uid.to_string())
         .bind("alice")
         .execute(core.pool())
@@ -201,3 +203,295 @@ async fn membership_substrate() {
     store.remove_member(cid, uid).await.unwrap();
     assert!(store.members(cid).await.unwrap().is_empty());
 }
+
+async fn insert_user(core: &Core, handle: &str) -> UserId {
+    let uid = UserId::generate();
+    sqlx::query("INSERT INTO users (id, handle) VALUES ($1, $2)")
+        .bind(uid.to_string())
+        .bind(handle)
+        .execute(core.pool())
+        .await
+        .unwrap();
+    uid
+}
+
+#[tokio::test]
+async fn batch_commits_together_and_emits_after_commit() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let uid = insert_user(&core, "alice").await;
+    let mut rx = core.events().subscribe();
+
+    // "Create a channel and add its creator as a member", plus an upsert into the new channel.
+    let mut batch = Batch::new();
+    let cid = batch.create_channel(new_channel(serde_json::json!({ "name": "general" })));
+    batch.add_member(cid, uid).upsert_item(NewItem {
+        type_id: TypeId::new("test"),
+        container: Some(cid),
+        external_key: Some("ext:1".to_owned()),
+        payload: serde_json::json!({}),
+    });
+    let upserts = store.apply(batch).await.unwrap();
+
+    assert!(matches!(upserts.as_slice(), [Upsert::Inserted(_)]));
+    assert!(store.get_channel(cid).await.unwrap().is_some());
+    assert_eq!(store.members(cid).await.unwrap(), vec![uid]);
+    // The channel's inline index projection was written inside the batch too.
+    let indexed: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM search_index WHERE envelope_id = $1")
+            .bind(cid.to_string())
+            .fetch_one(core.pool())
+            .await
+            .unwrap();
+    assert_eq!(indexed, 1);
+
+    // One event per envelope mutation, in batch order.
+    let first = rx.try_recv().unwrap();
+    assert!(matches!(first.target, EnvelopeRef::Channel(id) if id == cid));
+    let second = rx.try_recv().unwrap();
+    assert!(matches!(second.op, ChangeOp::Created));
+    assert!(matches!(second.target, EnvelopeRef::Item(_)));
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn failing_batch_rolls_back_whole_and_emits_nothing() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let mut rx = core.events().subscribe();
+
+    let mut batch = Batch::new();
+    let cid = batch.create_channel(new_channel(serde_json::json!({ "name": "doomed" })));
+    // Fails validation after the channel insert already ran in the batch's transaction.
+    batch.set_channel_payload(cid, serde_json::json!("not an object"));
+    let err = store.apply(batch).await;
+
+    assert!(matches!(err, Err(Error::Validation(_))));
+    assert!(store.get_channel(cid).await.unwrap().is_none());
+    let indexed: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM search_index")
+        .fetch_one(core.pool())
+        .await
+        .unwrap();
+    assert_eq!(indexed, 0);
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn deleting_a_channel_reports_its_subtree_and_purge_unindexes_it() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let root = store
+        .create_channel(new_channel(serde_json::json!({ "name": "root" })))
+        .await
+        .unwrap();
+    let child = store
+        .create_channel(NewChannel {
+            type_id: TypeId::new("test"),
+            container: Some(root),
+            payload: serde_json::json!({ "name": "child" }),
+        })
+        .await
+        .unwrap();
+    let item = store
+        .create_item(NewItem {
+            type_id: TypeId::new("test"),
+            container: Some(child),
+            external_key: None,
+            payload: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    let mut rx = core.events().subscribe();
+
+    store.delete_channel(root).await.unwrap();
+
+    // Deepest first, the deleted channel itself last — one `Deleted` per removed envelope.
+    let mut deleted = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        assert!(matches!(event.op, ChangeOp::Deleted));
+        deleted.push(match event.target {
+            EnvelopeRef::Channel(id) => id.to_string(),
+            EnvelopeRef::Item(id) => id.to_string(),
+        });
+    }
+    assert_eq!(
+        deleted,
+        [item.to_string(), child.to_string(), root.to_string()]
+    );
+    // Trashed, not gone: the index rows stay for `restore`. Purging drops them, silently — every
+    // envelope in the subtree already reported its `Deleted` when the tombstone hid it.
+    let indexed = || async {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM search_index")
+            .fetch_one(core.pool())
+            .await
+            .unwrap()
+    };
+    assert_eq!(indexed().await, 2);
+    store.purge_channel(root).await.unwrap();
+    assert_eq!(indexed().await, 0, "no orphaned index rows");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn conditional_setters_reject_a_stale_rev() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = store
+        .create_channel(new_channel(serde_json::json!({ "name": "a" })))
+        .await
+        .unwrap();
+    assert_eq!(store.get_channel(cid).await.unwrap().unwrap().rev, 1);
+
+    store
+        .set_channel_payload_if(cid, 1, serde_json::json!({ "name": "b" }))
+        .await
+        .unwrap();
+    assert_eq!(store.get_channel(cid).await.unwrap().unwrap().rev, 2);
+
+    let mut rx = core.events().subscribe();
+    let stale = store
+        .set_channel_payload_if(cid, 1, serde_json::json!({ "name": "c" }))
+        .await;
+    assert!(matches!(
+        stale,
+        Err(Error::Conflict {
+            expected: 1,
+            current: 2
+        })
+    ));
+    let ch = store.get_channel(cid).await.unwrap().unwrap();
+    assert_eq!(ch.payload["name"], "b");
+    assert!(rx.try_recv().is_err(), "a refused write emits nothing");
+
+    // Unconditional writes and reparents bump it too; a stale step fails its whole batch.
+    store
+        .set_channel_payload(cid, serde_json::json!({ "name": "d" }))
+        .await
+        .unwrap();
+    let iid = store
+        .create_item(NewItem {
+            type_id: TypeId::new("test"),
+            container: Some(cid),
+            external_key: None,
+            payload: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    let mut batch = Batch::new();
+    batch
+        .set_item_payload_if(iid, 1, serde_json::json!({ "n": 1 }))
+        .set_channel_payload_if(cid, 2, serde_json::json!({ "name": "e" }));
+    assert!(matches!(
+        store.apply(batch).await,
+        Err(Error::Conflict { current: 3, .. })
+    ));
+    assert_eq!(store.get_item(iid).await.unwrap().unwrap().rev, 1);
+}
+
+#[tokio::test]
+async fn patches_apply_in_the_write_path() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let cid = store
+        .create_channel(new_channel(
+            serde_json::json!({ "name": "a", "topic": "t", "tags": ["x"] }),
+        ))
+        .await
+        .unwrap();
+
+    // Merge-patch: set one key, drop another (null), leave the rest.
+    store
+        .patch_channel_payload(
+            cid,
+            Patch::Merge(serde_json::json!({ "name": "b", "topic": null })),
+        )
+        .await
+        .unwrap();
+    let ch = store.get_channel(cid).await.unwrap().unwrap();
+    assert_eq!(
+        ch.payload,
+        serde_json::json!({ "name": "b", "tags": ["x"] })
+    );
+    assert_eq!(ch.rev, 2);
+    // The inline index saw the patched payload.
+    let name: String = sqlx::query_scalar("SELECT name FROM search_index WHERE envelope_id = $1")
+        .bind(cid.to_string())
+        .fetch_one(core.pool())
+        .await
+        .unwrap();
+    assert_eq!(name, "b");
+
+    // JSON-patch: operations against the current payload, under a rev condition.
+    store
+        .patch_channel_payload_if(
+            cid,
+            2,
+            Patch::Json(serde_json::json!([
+                { "op": "add", "path": "/tags/-", "value": "y" },
+                { "op": "test", "path": "/name", "value": "b" }
+            ])),
+        )
+        .await
+        .unwrap();
+    let ch = store.get_channel(cid).await.unwrap().unwrap();
+    assert_eq!(ch.payload["tags"], serde_json::json!(["x", "y"]));
+
+    // A failing op, a patched result the kind rejects, or a stale rev writes nothing.
+    let failed = store
+        .patch_channel_payload(
+            cid,
+            Patch::Json(serde_json::json!([{ "op": "remove", "path": "/missing" }])),
+        )
+        .await;
+    assert!(matches!(failed, Err(Error::Validation(_))));
+    let invalid = store
+        .patch_channel_payload(cid, Patch::Merge(serde_json::json!("not an object")))
+        .await;
+    assert!(matches!(invalid, Err(Error::Validation(_))));
+    let stale = store
+        .patch_channel_payload_if(cid, 2, Patch::Merge(serde_json::json!({ "name": "c" })))
+        .await;
+    assert!(matches!(stale, Err(Error::Conflict { current: 3, .. })));
+    assert_eq!(store.get_channel(cid).await.unwrap().unwrap().rev, 3);
+}
+
+#[tokio::test]
+async fn reparenting_refuses_a_cycle() {
+    let (_dir, core) = test_core().await;
+    let store = core.store();
+    let top = store
+        .create_channel(new_channel(serde_json::json!({ "name": "top" })))
+        .await
+        .unwrap();
+    let mid = store
+        .create_channel(NewChannel {
+            container: Some(top),
+            ..new_channel(serde_json::json!({ "name": "mid" }))
+        })
+        .await
+        .unwrap();
+    let leaf = store
+        .create_channel(NewChannel {
+            container: Some(mid),
+            ..new_channel(serde_json::json!({ "name": "leaf" }))
+        })
+        .await
+        .unwrap();
+
+    // Under itself, or under anything below it: refused, and nothing moves.
+    for target in [top, mid, leaf] {
+        assert!(matches!(
+            store.reparent_channel(top, Some(target)).await,
+            Err(Error::Validation(_))
+        ));
+    }
+    assert_eq!(store.get_channel(top).await.unwrap().unwrap().rev, 1);
+
+    // Sideways and up are fine.
+    store.reparent_channel(leaf, Some(top)).await.unwrap();
+    store.reparent_channel(mid, None).await.unwrap();
+    assert_eq!(
+        store.get_channel(leaf).await.unwrap().unwrap().container,
+        Some(top)
+    );
+}
@@ -0,0 +1,96 @@
+//! The admin HTTP surface (DESIGN §8): the debug shell's operational commands, for operators — native
+//! users the shell marked with `set-operator`. Runtime supervision only, for now: each component's
+//! status, and `restart`/`reset` requests, which the supervisor takes within a poll. The state lives in
+//! `cp_core::runtime`; this is the endpoint layer.
+
+use axum::extract::{FromRequestParts, Path, State};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use cp_core::runtime::{self, Control};
+use cp_model::{Error, User};
+use serde_json::json;
+
+use crate::auth::CurrentUser;
+use crate::AppState;
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+/// A signed-in operator. Rejects `401` without a session (via [`CurrentUser`]) and `403` for a user
+/// who is not an operator, or who signed in with an API token lacking unscoped `manage`.
+pub struct Operator(pub User);
+
+impl FromRequestParts<AppState> for Operator {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let caller = CurrentUser::from_request_parts(parts, state).await?;
+        match caller.is_operator(state).await {
+            Ok(true) => Ok(Operator(caller.0)),
+            Ok(false) => Err(error(StatusCode::FORBIDDEN, "forbidden")),
+            Err(e) => Err(error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())),
+        }
+    }
+}
+
+/// `GET /api/admin/runtimes` → every supervised component's status, by name.
+pub async fn get_runtimes(State(state): State<AppState>, _op: Operator) -> Response {
+    let store = state.core.store();
+    match runtime::statuses(store.pool()).await {
+        Ok(statuses) => {
+            let body: Vec<_> = statuses
+                .iter()
+                .map(|s| {
+                    json!({
+                        "name": s.name,
+                        "state": s.state.as_str(),
+                        "version": s.version,
+                        "restarts": s.restarts,
+                        "last_error": s.last_error,
+                        "last_healthy_at": s.last_healthy_at,
+                        "updated_at": s.updated_at,
+                        "pending": s.pending.map(Control::as_str),
+                    })
+                })
+                .collect();
+            Json(body).into_response()
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// `POST /api/admin/runtimes/{name}/{restart|reset}` → 202 once queued; 404 for an unknown component
+/// or action.
+pub async fn control_runtime(
+    State(state): State<AppState>,
+    Operator(op): Operator,
+    Path((name, action)): Path<(String, String)>,
+) -> Response {
+    let Some(control) = Control::parse(&action) else {
+        return error(StatusCode::NOT_FOUND, "unknown action");
+    };
+    let store = state.core.store();
+    match runtime::request(store.pool(), &name, control).await {
+        Ok(()) => {
+            tracing::info!(
+                operator = op.handle,
+                component = name,
+                ?control,
+                "admin: runtime request"
+            );
+            (
+                StatusCode::ACCEPTED,
+                Json(json!({ "name": name, "pending": control.as_str() })),
+            )
+                .into_response()
+        }
+        Err(Error::NotFound) => error(StatusCode::NOT_FOUND, "no such runtime component"),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
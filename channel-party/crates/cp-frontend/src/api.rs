@@ -1,22 +1,40 @@
 //! The generic HTTP API (DESIGN §9). These endpoints are type-agnostic: the envelope reads return the
 //! universal fields, and `contents` resolves the channel's kind and dispatches to it — core never
 //! `match`es on a concrete type. `query` and the contents response are opaque to core (§5).
+//!
+//! Every read is gated by `Action::View` for the caller, signed in or not (§18): through a
+//! `cp_core::authz::ViewScope`, so an envelope the caller may not see is a 404, exactly as if it did not
+//! exist, and `contents` runs against a store that leaves hidden nodes out.
+//!
+//! A caller signed in with an API token (#42) is further limited to the token's scopes: reads outside
+//! its `read` scopes are judged as for an anonymous visitor, and a write outside its `post`/`manage`
+//! scopes is a 403 even where the user could make it.
+
+use std::sync::Arc;
 
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
-use cp_model::{Action, ChannelId, Error, ItemId, NewItem, TypeId, UserId, WriteCtx};
+use cp_core::authz::ViewScope;
+use cp_core::tokens::Scopes;
+use cp_model::{
+    Action, Actor, Batch, Channel, ChannelId, EnvelopeRef, Error, ItemId, ItemKind, Membership,
+    NewChannel, NewItem, Patch, Role, StoreCtx, TypeId, UserId, WriteCtx,
+};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-use crate::auth::CurrentUser;
+use crate::auth::{CurrentUser, OptionalUser};
 use crate::AppState;
 
-/// Map a core `Error` to an HTTP status: missing → 404, bad payload → 400, else 500.
+/// Map a core `Error` to an HTTP status: missing → 404, bad payload → 400, stale revision → 409, else
+/// 500. (A route driven by `If-Match` answers a stale revision with 412 instead — see [`if_match`].)
 fn error_response(e: Error) -> (StatusCode, Json<Value>) {
     let status = match e {
         Error::NotFound => StatusCode::NOT_FOUND,
         Error::Validation(_) => StatusCode::BAD_REQUEST,
+        Error::Conflict { .. } => StatusCode::CONFLICT,
         Error::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
     };
     (status, Json(json!({ "error": e.to_string() })))
@@ -46,25 +64,91 @@ fn ok<T: serde::Serialize>(value: &T) -> (StatusCode, Json<Value>) {
     }
 }
 
-/// `GET /api/channels/:id` -> the channel envelope (generic: `id`, `type_id`, `container`, `payload`).
-/// §9. The `type_id` is what lets the type-agnostic shell mount the right island.
+/// An envelope's `rev` as a strong entity tag (`"3"`), for `ETag` / `If-Match`. §3.
+fn etag(rev: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{rev}\"")).expect("a quoted integer is a valid header value")
+}
+
+/// A 200 envelope response carrying the envelope's `ETag`.
+fn ok_tagged<T: serde::Serialize>(value: &T, rev: i64) -> Response {
+    let mut res = ok(value).into_response();
+    res.headers_mut().insert(header::ETAG, etag(rev));
+    res
+}
+
+/// The revision an `If-Match` header pins a write to: `Ok(None)` when absent or `*` (any current
+/// representation — the write is unconditional), `Ok(Some(rev))` for a single strong tag. Weak tags
+/// and lists are refused with 400 rather than guessed at: the API only ever issues one strong tag.
+fn if_match(headers: &HeaderMap) -> Result<Option<i64>, (StatusCode, Json<Value>)> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .and_then(|v| v.parse().ok())
+        .map(Some)
+        .ok_or_else(|| bad_request("If-Match must be a single entity tag from ETag"))
+}
+
+/// 412 for a stale `If-Match`, with the current `ETag` so the client can re-read and retry.
+fn precondition_failed(current: i64) -> Response {
+    let mut res = (
+        StatusCode::PRECONDITION_FAILED,
+        Json(json!({ "error": "precondition failed", "current_rev": current })),
+    )
+        .into_response();
+    res.headers_mut().insert(header::ETAG, etag(current));
+    res
+}
+
+/// The caller's view of the store for one request (§18), narrowed to a token's scopes.
+fn view_scope(state: &AppState, viewer: &OptionalUser) -> ViewScope {
+    ViewScope::new(state.core.store(), viewer.id()).limited(viewer.1.clone())
+}
+
+/// The gate a token's scopes add to a write: 403 unless they cover `action` on `channel` (`None`: the
+/// top level, which only an unscoped grant covers). A session passes.
+pub(crate) async fn authorize_scope(
+    state: &AppState,
+    scopes: &Scopes,
+    action: Action,
+    channel: Option<ChannelId>,
+) -> Result<(), Response> {
+    match scopes.allows(state.core.pool(), action, channel).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(forbidden().into_response()),
+        Err(e) => Err(error_response(e).into_response()),
+    }
+}
+
+/// `GET /api/channels/:id` -> the channel envelope (generic: `id`, `type_id`, `container`, `payload`,
+/// `rev`), with `rev` as the `ETag`. §9. The `type_id` is what lets the type-agnostic shell mount the
+/// right island. A channel the caller may not view is a 404.
 pub async fn get_channel(
+    viewer: OptionalUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> (StatusCode, Json<Value>) {
+) -> Response {
     let Ok(cid) = id.parse::<ChannelId>() else {
-        return bad_request("invalid channel id");
+        return bad_request("invalid channel id").into_response();
     };
-    match state.core.store().get_channel(cid).await {
-        Ok(Some(ch)) => ok(&ch),
-        Ok(None) => not_found("channel"),
-        Err(e) => error_response(e),
+    match StoreCtx::get_channel(&view_scope(&state, &viewer), cid).await {
+        Ok(Some(ch)) => ok_tagged(&ch, ch.rev),
+        Ok(None) => not_found("channel").into_response(),
+        Err(e) => error_response(e).into_response(),
     }
 }
 
 /// `POST /api/channels/:id/contents { query }` -> the channel kind's type-defined contents. §5/§9.
-/// The request body is the (opaque) query; the channel's kind interprets it.
+/// The request body is the (opaque) query; the channel's kind interprets it, against the caller's
+/// view of the store — nodes the caller may not view never appear in the result.
 pub async fn channel_contents(
+    viewer: OptionalUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(query): Json<Value>,
@@ -72,13 +156,13 @@ pub async fn channel_contents(
     let Ok(cid) = id.parse::<ChannelId>() else {
         return bad_request("invalid channel id");
     };
-    let store = state.core.store();
-    let ch = match store.get_channel(cid).await {
+    let scope = view_scope(&state, &viewer);
+    let ch = match StoreCtx::get_channel(&scope, cid).await {
         Ok(Some(ch)) => ch,
         Ok(None) => return not_found("channel"),
         Err(e) => return error_response(e),
     };
-    match cp_core::contents::dispatch(&state.registry, &*store, &ch, query).await {
+    match cp_core::contents::dispatch(&state.registry, &scope, &ch, query).await {
         Ok(value) => (StatusCode::OK, Json(value)),
         Err(e) => error_response(e),
     }
@@ -94,12 +178,13 @@ pub struct PostItemBody {
 }
 
 /// `POST /api/channels/:id/items { type_id, payload }` -> create an item in the channel as the current
-/// user (§18, `design/permissions.md`). Requires a session (the `CurrentUser` extractor → 401), the
-/// channel kind's `Permission` to allow `Post` (→ 403, deny-by-default), and a known item type (→ 400).
+/// user (§18, `design/permissions.md`). Requires a session or token (the `CurrentUser` extractor → 401),
+/// the channel kind's `Permission` to allow `Post` and a token's scopes to cover it (→ 403,
+/// deny-by-default), and a known item type (→ 400). A channel the caller may not view is a 404.
 /// The author is stamped server-side via the item kind's `with_author` (§2); `validate` + persist happen
 /// in the write path. On success: 201 with the new id.
 pub async fn post_item(
-    CurrentUser(user): CurrentUser,
+    CurrentUser(user, scopes): CurrentUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(body): Json<PostItemBody>,
@@ -108,7 +193,8 @@ pub async fn post_item(
         return bad_request("invalid channel id");
     };
     let store = state.core.store();
-    let ch = match store.get_channel(cid).await {
+    let scope = ViewScope::new(store.clone(), Some(user.id)).limited(scopes.clone());
+    let ch = match StoreCtx::get_channel(&scope, cid).await {
         Ok(Some(ch)) => ch,
         Ok(None) => return not_found("channel"),
         Err(e) => return error_response(e),
@@ -118,12 +204,18 @@ pub async fn post_item(
         Ok(false) => return forbidden(),
         Err(e) => return error_response(e),
     }
+    match scopes.allows(store.pool(), Action::Post, Some(cid)).await {
+        Ok(true) => {}
+        Ok(false) => return forbidden(),
+        Err(e) => return error_response(e),
+    }
     let type_id = TypeId::new(&body.type_id);
     let Some(kind) = state.registry.item(&type_id) else {
         return bad_request("unknown item type");
     };
     let payload = kind.with_author(body.payload, user.id);
     match store
+        .acting_as(Actor::User(user.id))
         .create_item(NewItem {
             type_id,
             container: Some(cid),
@@ -140,49 +232,595 @@ pub async fn post_item(
     }
 }
 
-/// `GET /api/items/:id` -> the item envelope (generic). §9.
+/// The gate for administering a channel over HTTP: the channel's kind must grant `Manage`, and a token's
+/// scopes must cover it (403, deny-by-default). A channel the caller may not even view is a 404, as on
+/// the read routes.
+pub(crate) async fn authorize_manage(
+    state: &AppState,
+    user: UserId,
+    scopes: &Scopes,
+    cid: ChannelId,
+) -> Result<Channel, Response> {
+    let store = state.core.store();
+    let scope = ViewScope::new(store.clone(), Some(user)).limited(scopes.clone());
+    let ch = match StoreCtx::get_channel(&scope, cid).await {
+        Ok(Some(ch)) => ch,
+        Ok(None) => return Err(not_found("channel").into_response()),
+        Err(e) => return Err(error_response(e).into_response()),
+    };
+    authorize_scope(state, scopes, Action::Manage, Some(cid)).await?;
+    match cp_core::authz::authorize(&state.registry, &*store, &ch, user, Action::Manage).await {
+        Ok(true) => Ok(ch),
+        Ok(false) => Err(forbidden().into_response()),
+        Err(e) => Err(error_response(e).into_response()),
+    }
+}
+
+/// The gate for placing a channel under `container` (creating or moving it there): `Manage` on the
+/// destination. The top level has no channel to grant it, so only an operator may place a channel there
+/// (and, with a token, only under unscoped `manage`).
+async fn authorize_destination(
+    state: &AppState,
+    user: UserId,
+    scopes: &Scopes,
+    container: Option<ChannelId>,
+) -> Result<(), Response> {
+    match container {
+        Some(cid) => authorize_manage(state, user, scopes, cid).await.map(|_| ()),
+        None => {
+            authorize_scope(state, scopes, Action::Manage, None).await?;
+            match cp_core::auth::is_operator(state.core.pool(), user).await {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(forbidden().into_response()),
+                Err(e) => Err(error_response(e).into_response()),
+            }
+        }
+    }
+}
+
+/// A 200 with the channel's current envelope and `ETag`, after a structural write.
+async fn channel_envelope(state: &AppState, cid: ChannelId) -> Response {
+    match state.core.store().get_channel(cid).await {
+        Ok(Some(ch)) => ok_tagged(&ch, ch.rev),
+        Ok(None) => not_found("channel").into_response(),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// The body of `POST /api/channels`: the new channel's type, where it goes (`null` = the top level),
+/// and its opaque payload.
+#[derive(Deserialize)]
+pub struct CreateChannelBody {
+    type_id: String,
+    #[serde(default)]
+    container: Option<ChannelId>,
+    #[serde(default)]
+    payload: Value,
+}
+
+/// `POST /api/channels { type_id, container, payload }` -> create a channel as the current user
+/// (§18). Requires a session (401), `Manage` on the destination ([`authorize_destination`]: 403, or
+/// 404 for a container the user cannot view) and a known channel type (400). The creator is granted
+/// `owner` on the new channel in the same write, and if its kind accepts users, also joins it there: a
+/// channel that does not exist cannot be reached through its kind's `Membership`, so the edge goes in
+/// the batch, and a failure leaves no half-made channel to retry into a duplicate. On success: 201 with
+/// the new id.
+pub async fn create_channel(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Json(body): Json<CreateChannelBody>,
+) -> Response {
+    if let Err(res) = authorize_destination(&state, user.id, &scopes, body.container).await {
+        return res;
+    }
+    let type_id = TypeId::new(&body.type_id);
+    let Some(kind) = state.registry.channel(&type_id) else {
+        return bad_request("unknown channel type").into_response();
+    };
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    let mut batch = Batch::new();
+    let cid = batch.create_channel(NewChannel {
+        type_id,
+        container: body.container,
+        payload: body.payload,
+    });
+    batch.grant_role(cid, user.id, Role::Owner);
+    if kind.membership().is_some() {
+        batch.add_member(cid, user.id);
+    }
+    if let Err(e) = writer.apply(batch).await {
+        return error_response(e).into_response();
+    }
+    (StatusCode::CREATED, Json(json!({ "id": cid.to_string() }))).into_response()
+}
+
+/// `PATCH /api/channels/:id` -> patch a channel's payload as the current user, under `Manage`
+/// ([`authorize_manage`]). The `Content-Type` and `If-Match` handling is `PATCH /api/items/:id`'s: 415
+/// for an unknown patch format, 412 with the current `ETag` when stale. The kind's `validate` runs on
+/// the result (400). On success: 200 with the channel and its new `ETag`.
+pub async fn patch_channel(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(doc): Json<Value>,
+) -> Response {
+    let Ok(cid) = id.parse::<ChannelId>() else {
+        return bad_request("invalid channel id").into_response();
+    };
+    let patch = match patch_document(&headers, doc) {
+        Ok(patch) => patch,
+        Err(res) => return res.into_response(),
+    };
+    let expected = match if_match(&headers) {
+        Ok(expected) => expected,
+        Err(res) => return res.into_response(),
+    };
+    if let Err(res) = authorize_manage(&state, user.id, &scopes, cid).await {
+        return res;
+    }
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    let written = match expected {
+        Some(rev) => writer.patch_channel_payload_if(cid, rev, patch).await,
+        None => writer.patch_channel_payload(cid, patch).await,
+    };
+    match written {
+        Ok(()) => channel_envelope(&state, cid).await,
+        Err(Error::Conflict { current, .. }) => precondition_failed(current),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// `DELETE /api/channels/:id` -> move a channel and its subtree to the trash (§3), under `Manage`.
+/// Restoring and purging stay with the operator's shell. On success: 204.
+pub async fn delete_channel(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let Ok(cid) = id.parse::<ChannelId>() else {
+        return bad_request("invalid channel id").into_response();
+    };
+    if let Err(res) = authorize_manage(&state, user.id, &scopes, cid).await {
+        return res;
+    }
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    match writer.delete_channel(cid).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// The body of `POST /api/channels/:id/move`: the new container (`null` = the top level).
+#[derive(Deserialize)]
+pub struct MoveChannelBody {
+    container: Option<ChannelId>,
+}
+
+/// `POST /api/channels/:id/move { container }` -> reparent a channel as the current user. Takes
+/// `Manage` on the channel and on the destination ([`authorize_destination`]). Moving a channel under
+/// itself or its own descendant is a 400. On success: 200 with the channel and its new `ETag`.
+pub async fn move_channel(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<MoveChannelBody>,
+) -> Response {
+    let Ok(cid) = id.parse::<ChannelId>() else {
+        return bad_request("invalid channel id").into_response();
+    };
+    if let Err(res) = authorize_manage(&state, user.id, &scopes, cid).await {
+        return res;
+    }
+    if let Err(res) = authorize_destination(&state, user.id, &scopes, body.container).await {
+        return res;
+    }
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    match writer.reparent_channel(cid, body.container).await {
+        Ok(()) => channel_envelope(&state, cid).await,
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// A channel's `Membership` capability, or a 400 naming a kind that does not accept users (the
+/// shell's refusal, §8).
+fn membership_of<'a>(
+    state: &'a AppState,
+    ch: &Channel,
+) -> Result<&'a dyn Membership, (StatusCode, Json<Value>)> {
+    state
+        .registry
+        .channel(&ch.type_id)
+        .and_then(|kind| kind.membership())
+        .ok_or_else(|| {
+            bad_request(&format!(
+                "channel-type `{}` does not accept users",
+                ch.type_id
+            ))
+        })
+}
+
+/// `GET /api/channels/:id/members` -> `{ members: [user id…] }`, as the kind's `Membership` reports
+/// them. Gated like every read: a channel the caller may not view is a 404.
+pub async fn get_members(
+    viewer: OptionalUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let Ok(cid) = id.parse::<ChannelId>() else {
+        return bad_request("invalid channel id").into_response();
+    };
+    let ch = match StoreCtx::get_channel(&view_scope(&state, &viewer), cid).await {
+        Ok(Some(ch)) => ch,
+        Ok(None) => return not_found("channel").into_response(),
+        Err(e) => return error_response(e).into_response(),
+    };
+    let membership = match membership_of(&state, &ch) {
+        Ok(membership) => membership,
+        Err(res) => return res.into_response(),
+    };
+    match membership.members(&*state.core.store(), &ch).await {
+        Ok(members) => {
+            let ids: Vec<String> = members.iter().map(ToString::to_string).collect();
+            Json(json!({ "members": ids })).into_response()
+        }
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// `PUT /api/channels/:id/members/:user` and `DELETE …` -> add or remove a member through the kind's
+/// `Membership`, under `Manage`. An unknown user is a 404. On success: 204.
+async fn change_membership(
+    state: AppState,
+    user: UserId,
+    scopes: Scopes,
+    id: String,
+    member: String,
+    add: bool,
+) -> Response {
+    let Ok(cid) = id.parse::<ChannelId>() else {
+        return bad_request("invalid channel id").into_response();
+    };
+    let Ok(member) = member.parse::<UserId>() else {
+        return bad_request("invalid user id").into_response();
+    };
+    let ch = match authorize_manage(&state, user, &scopes, cid).await {
+        Ok(ch) => ch,
+        Err(res) => return res,
+    };
+    let membership = match membership_of(&state, &ch) {
+        Ok(membership) => membership,
+        Err(res) => return res.into_response(),
+    };
+    match cp_core::auth::user(state.core.pool(), member).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found("user").into_response(),
+        Err(e) => return error_response(e).into_response(),
+    }
+    let writer = state.core.store().acting_as(Actor::User(user));
+    let changed = if add {
+        membership.add_user(&writer, &ch, member).await
+    } else {
+        membership.remove_user(&writer, &ch, member).await
+    };
+    match changed {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// `PUT /api/channels/:id/members/:user` -> add a member ([`change_membership`]).
+pub async fn add_member(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path((id, member)): Path<(String, String)>,
+) -> Response {
+    change_membership(state, user.id, scopes, id, member, true).await
+}
+
+/// `DELETE /api/channels/:id/members/:user` -> remove a member ([`change_membership`]).
+pub async fn remove_member(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path((id, member)): Path<(String, String)>,
+) -> Response {
+    change_membership(state, user.id, scopes, id, member, false).await
+}
+
+/// `GET /api/items/:id` -> the item envelope (generic), with `rev` as the `ETag`. §9. An item in a
+/// channel the caller may not view is a 404.
 pub async fn get_item(
+    viewer: OptionalUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    let Ok(iid) = id.parse::<ItemId>() else {
+        return bad_request("invalid item id").into_response();
+    };
+    match StoreCtx::get_item(&view_scope(&state, &viewer), iid).await {
+        Ok(Some(item)) => ok_tagged(&item, item.rev),
+        Ok(None) => not_found("item").into_response(),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// The body of `PUT /api/items/:id`: the replacement payload. The item's type and container are fixed.
+#[derive(Deserialize)]
+pub struct PutItemBody {
+    payload: Value,
+}
+
+/// The shared gate for editing an item over HTTP. Editing is posting: it takes the item's container
+/// channel's `Post` permission, and a token's scopes must cover it (403, deny-by-default — an item with
+/// no container has no channel to grant it), like `post_item`. Someone else's item also takes `Manage`
+/// there, since the edit re-stamps it as the editor's. An item the caller may not view is a 404.
+/// Resolves to the item's kind, for server-side author stamping.
+async fn authorize_edit<'a>(
+    state: &'a AppState,
+    user: UserId,
+    scopes: &Scopes,
+    iid: ItemId,
+) -> Result<&'a Arc<dyn ItemKind>, Response> {
+    let store = state.core.store();
+    let scope = ViewScope::new(store.clone(), Some(user)).limited(scopes.clone());
+    let item = match StoreCtx::get_item(&scope, iid).await {
+        Ok(Some(item)) => item,
+        Ok(None) => return Err(not_found("item").into_response()),
+        Err(e) => return Err(error_response(e).into_response()),
+    };
+    let ch = match item.container {
+        Some(cid) => match store.get_channel(cid).await {
+            Ok(Some(ch)) => ch,
+            Ok(None) => return Err(forbidden().into_response()),
+            Err(e) => return Err(error_response(e).into_response()),
+        },
+        None => return Err(forbidden().into_response()),
+    };
+    let kind = state
+        .registry
+        .item(&item.type_id)
+        .ok_or_else(|| bad_request("unknown item type").into_response())?;
+    let action = if authored_by(kind.as_ref(), &item.payload, user) {
+        Action::Post
+    } else {
+        Action::Manage
+    };
+    for action in [Action::Post, action] {
+        match cp_core::authz::authorize(&state.registry, &*store, &ch, user, action).await {
+            Ok(true) => {}
+            Ok(false) => return Err(forbidden().into_response()),
+            Err(e) => return Err(error_response(e).into_response()),
+        }
+        authorize_scope(state, scopes, action, Some(ch.id)).await?;
+    }
+    Ok(kind)
+}
+
+/// Whether `payload` is `user`'s own: every field the kind stamps already holds their stamp. A kind that
+/// stamps no author (a canvas box) has no author to protect, so any poster may edit its items.
+fn authored_by(kind: &dyn ItemKind, payload: &Value, user: UserId) -> bool {
+    let Value::Object(stamp) = kind.with_author(json!({}), user) else {
+        return true;
+    };
+    stamp
+        .iter()
+        .all(|(key, value)| payload.get(key) == Some(value))
+}
+
+/// Finish an edit: a stale `If-Match` is a 412 with the current `ETag` (nothing was written); success is
+/// 200 with the updated item and its new `ETag`.
+async fn edited(state: &AppState, iid: ItemId, written: cp_model::Result<()>) -> Response {
+    match written {
+        Ok(()) => {}
+        Err(Error::Conflict { current, .. }) => return precondition_failed(current),
+        Err(e) => return error_response(e).into_response(),
+    }
+    match state.core.store().get_item(iid).await {
+        Ok(Some(item)) => ok_tagged(&item, item.rev),
+        Ok(None) => not_found("item").into_response(),
+        Err(e) => error_response(e).into_response(),
+    }
+}
+
+/// `PUT /api/items/:id { payload }` -> replace an item's payload as the current user, under the edit
+/// gate ([`authorize_edit`]: 401/403; another user's item needs `Manage`), with the author re-stamped
+/// server-side. With `If-Match: "<rev>"` the write is conditional — a stale tag is a 412 carrying the
+/// current `ETag`, and nothing is written; without it the write is last-write-wins. On success: 200
+/// with the item and its new `ETag`.
+pub async fn put_item(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<PutItemBody>,
+) -> Response {
+    let Ok(iid) = id.parse::<ItemId>() else {
+        return bad_request("invalid item id").into_response();
+    };
+    let expected = match if_match(&headers) {
+        Ok(expected) => expected,
+        Err(res) => return res.into_response(),
+    };
+    let kind = match authorize_edit(&state, user.id, &scopes, iid).await {
+        Ok(kind) => kind,
+        Err(res) => return res,
+    };
+    let payload = kind.with_author(body.payload, user.id);
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    let written = match expected {
+        Some(rev) => writer.set_item_payload_if(iid, rev, payload).await,
+        None => writer.set_item_payload(iid, payload).await,
+    };
+    edited(&state, iid, written).await
+}
+
+/// `PATCH /api/items/:id` -> patch an item's payload as the current user, under the same gate and
+/// `If-Match` handling as `PUT`. The body's `Content-Type` picks the format:
+/// `application/merge-patch+json` (RFC 7396) or `application/json-patch+json` (RFC 6902); anything
+/// else is a 415. Core applies the patch inside the write transaction, then `validate` + `index()` run
+/// on the result (a patch that cannot apply is a 400). Authorship is re-stamped by folding the kind's
+/// `with_author` fields into the patch, so a patch cannot forge them.
+pub async fn patch_item(
+    CurrentUser(user, scopes): CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(doc): Json<Value>,
+) -> Response {
+    let Ok(iid) = id.parse::<ItemId>() else {
+        return bad_request("invalid item id").into_response();
+    };
+    let patch = match patch_document(&headers, doc) {
+        Ok(patch) => patch,
+        Err(res) => return res.into_response(),
+    };
+    let expected = match if_match(&headers) {
+        Ok(expected) => expected,
+        Err(res) => return res.into_response(),
+    };
+    let kind = match authorize_edit(&state, user.id, &scopes, iid).await {
+        Ok(kind) => kind,
+        Err(res) => return res,
+    };
+    let patch = stamp_author(kind.as_ref(), patch, user.id);
+    let writer = state.core.store().acting_as(Actor::User(user.id));
+    let written = match expected {
+        Some(rev) => writer.patch_item_payload_if(iid, rev, patch).await,
+        None => writer.patch_item_payload(iid, patch).await,
+    };
+    edited(&state, iid, written).await
+}
+
+/// Read a `PATCH` body as the patch its `Content-Type` names: `application/merge-patch+json` (RFC 7396)
+/// or `application/json-patch+json` (RFC 6902). Anything else is a 415.
+fn patch_document(headers: &HeaderMap, doc: Value) -> Result<Patch, (StatusCode, Json<Value>)> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(str::trim)
+        .unwrap_or_default();
+    match content_type {
+        "application/merge-patch+json" => Ok(Patch::Merge(doc)),
+        "application/json-patch+json" => Ok(Patch::Json(doc)),
+        _ => Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(json!({
+                "error": "use application/merge-patch+json or application/json-patch+json"
+            })),
+        )),
+    }
+}
+
+/// Fold the kind's server-stamped authorship fields (what `with_author` adds to an empty object) into
+/// a patch: merged into a merge-patch, appended as `add` operations to a JSON-patch. A kind with no
+/// notion of an author stamps nothing, and the patch is unchanged.
+fn stamp_author(kind: &dyn ItemKind, patch: Patch, user: UserId) -> Patch {
+    let Value::Object(stamp) = kind.with_author(json!({}), user) else {
+        return patch;
+    };
+    match patch {
+        Patch::Merge(doc) => Patch::Merge(kind.with_author(doc, user)),
+        Patch::Json(Value::Array(mut ops)) => {
+            for (key, value) in stamp {
+                let pointer = format!("/{}", key.replace('~', "~0").replace('/', "~1"));
+                ops.push(json!({ "op": "add", "path": pointer, "value": value }));
+            }
+            Patch::Json(Value::Array(ops))
+        }
+        // Not an operation array: core rejects it as a malformed JSON-patch.
+        other => other,
+    }
+}
+
+/// `GET /api/items/:id/revisions` -> the item's revision history, oldest first: each superseded payload
+/// and container, the acting principal, and when (§3). A deleted item's history stays readable; an id
+/// with neither an item nor any history is a 404, and so is history the caller may not view — judged
+/// by the live item, or for a deleted one by the container it was last in.
+pub async fn get_item_revisions(
+    viewer: OptionalUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> (StatusCode, Json<Value>) {
     let Ok(iid) = id.parse::<ItemId>() else {
         return bad_request("invalid item id");
     };
-    match state.core.store().get_item(iid).await {
-        Ok(Some(item)) => ok(&item),
-        Ok(None) => not_found("item"),
-        Err(e) => error_response(e),
+    let store = state.core.store();
+    let revisions = match store.revisions(EnvelopeRef::Item(iid)).await {
+        Ok(revisions) => revisions,
+        Err(e) => return error_response(e),
+    };
+    let scope = view_scope(&state, &viewer);
+    let visible = match store.get_item(iid).await {
+        Ok(Some(item)) => scope.item(&item).await,
+        Ok(None) => match revisions.last() {
+            Some(last) => scope.container(last.container).await,
+            None => return not_found("item"),
+        },
+        Err(e) => return error_response(e),
+    };
+    match visible {
+        Ok(true) => {}
+        Ok(false) => return not_found("item"),
+        Err(e) => return error_response(e),
+    }
+    match serde_json::to_value(&revisions) {
+        Ok(v) => (StatusCode::OK, Json(json!({ "revisions": v }))),
+        Err(e) => error_response(Error::Other(e.to_string())),
     }
 }
 
 /// `GET /api/users/:id/links` -> the external cached-user items this native user is linked to (§2/§19,
-/// `design/linked-users.md`). Read-only — links are shell-provisioned. Bad id -> 400.
+/// `design/linked-users.md`), less any the caller may not view. Read-only — links are
+/// shell-provisioned. Bad id -> 400.
 pub async fn get_user_links(
+    viewer: OptionalUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> (StatusCode, Json<Value>) {
     let Ok(uid) = id.parse::<UserId>() else {
         return bad_request("invalid user id");
     };
-    match cp_core::links::linked_items(state.core.pool(), uid).await {
-        Ok(items) => match serde_json::to_value(&items) {
-            Ok(v) => (StatusCode::OK, Json(json!({ "items": v }))),
-            Err(e) => error_response(Error::Other(e.to_string())),
-        },
-        Err(e) => error_response(e),
+    let items = match cp_core::links::linked_items(state.core.pool(), uid).await {
+        Ok(items) => items,
+        Err(e) => return error_response(e),
+    };
+    let scope = view_scope(&state, &viewer);
+    let mut visible = Vec::with_capacity(items.len());
+    for item in items {
+        match scope.item(&item).await {
+            Ok(true) => visible.push(item),
+            Ok(false) => {}
+            Err(e) => return error_response(e),
+        }
+    }
+    match serde_json::to_value(&visible) {
+        Ok(v) => (StatusCode::OK, Json(json!({ "items": v }))),
+        Err(e) => error_response(Error::Other(e.to_string())),
     }
 }
 
 /// `GET /api/items/:id/linked-user` -> the native user an external cached-user item resolves up to, or
 /// 404 if it is unlinked (authorship resolution, §2/§19). The `cached-message` island calls this to show
-/// "this external author = native user Alice."
+/// "this external author = native user Alice." An item the caller may not view is a 404 too.
 pub async fn get_item_linked_user(
+    viewer: OptionalUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> (StatusCode, Json<Value>) {
     let Ok(iid) = id.parse::<ItemId>() else {
         return bad_request("invalid item id");
     };
+    let scope = view_scope(&state, &viewer);
+    match state.core.store().get_item(iid).await {
+        Ok(Some(item)) => match scope.item(&item).await {
+            Ok(true) => {}
+            Ok(false) => return not_found("linked user"),
+            Err(e) => return error_response(e),
+        },
+        Ok(None) => return not_found("linked user"),
+        Err(e) => return error_response(e),
+    }
     match cp_core::links::user_for_item(state.core.pool(), iid).await {
         Ok(Some(user)) => ok(&user),
         Ok(None) => not_found("linked user"),
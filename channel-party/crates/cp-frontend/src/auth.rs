@@ -1,14 +1,23 @@
-//! HTTP auth: login / logout / me + the `CurrentUser` extractor (DESIGN §2, `design/auth.md`, #17).
-//! The store logic (hashing, sessions) is `cp_core::auth`; this is the cookie + endpoint layer. Accounts
-//! are shell-provisioned — there is no registration route.
+//! HTTP auth: login (and its TOTP step, #45) / logout / me / register, the caller's session list (#43),
+//! and the `CurrentUser` / `OptionalUser` extractors (DESIGN §2, `design/auth.md`, #17).
+//! The store logic (hashing, sessions, invites, API tokens, second factors) is `cp_core::auth`,
+//! `cp_core::invites`, `cp_core::tokens` and `cp_core::totp`, and login goes through `cp_core::throttle`
+//! (#44); this is the cookie + endpoint layer. Accounts are shell-provisioned, or self-registered with an
+//! invite code (#41). A request is signed in by the session cookie or by an `Authorization: Bearer` API
+//! token (#42), which also limits it to the token's scopes.
 
-use axum::extract::{FromRequestParts, State};
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRequestParts, Path, State};
+use axum::http::header;
 use axum::http::request::Parts;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
-use cp_model::User;
+use cp_core::auth::SessionOrigin;
+use cp_core::tokens::Scopes;
+use cp_model::{Action, User, UserId};
 use serde::Deserialize;
 use serde_json::json;
 
@@ -23,6 +32,19 @@ pub struct LoginBody {
     password: String,
 }
 
+#[derive(Deserialize)]
+pub struct LoginTotpBody {
+    pending: String,
+    code: String,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterBody {
+    code: String,
+    handle: String,
+    password: String,
+}
+
 fn unauthorized() -> Response {
     (
         StatusCode::UNAUTHORIZED,
@@ -52,16 +74,111 @@ fn session_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
+/// Where a request comes from, for a new session's record (#43) and the login throttle (#44): its
+/// `User-Agent`, and the peer address — or, with `CP_TRUST_PROXY=<n>` (behind `n` reverse proxies that
+/// each append to it), the `X-Forwarded-For` hop the outermost proxy appended. Hops left of that one are
+/// whatever the client sent, so they are never read; with fewer hops than proxies, the peer is used.
+pub struct Origin(pub SessionOrigin);
+
+/// The client address in an `X-Forwarded-For` value written through `trusted` proxies: the `trusted`th
+/// hop from the right, or `None` if there are fewer hops than that.
+fn forwarded_for(value: &str, trusted: usize) -> Option<String> {
+    let hops: Vec<&str> = value.split(',').map(str::trim).collect();
+    let hop = hops.len().checked_sub(trusted.max(1)).map(|i| hops[i])?;
+    (!hop.is_empty()).then(|| hop.to_owned())
+}
+
+impl FromRequestParts<AppState> for Origin {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        let trusted = std::env::var("CP_TRUST_PROXY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|&n| n > 0);
+        // A proxy may add its hop as another header line rather than append to the client's, so every
+        // line is read, in order.
+        let forwarded_lines: Vec<&str> = parts
+            .headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .collect();
+        let forwarded = trusted
+            .filter(|_| !forwarded_lines.is_empty())
+            .and_then(|n| forwarded_for(&forwarded_lines.join(","), n));
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        Ok(Origin(SessionOrigin {
+            user_agent: header(header::USER_AGENT.as_str()),
+            ip: forwarded.or(peer),
+        }))
+    }
+}
+
 /// `POST /api/auth/login {handle, password}` → 200 + `Set-Cookie` on success, 401 on bad credentials.
+/// Attempts are throttled per handle and per client address (`cp_core::throttle`, #44); a blocked attempt
+/// is the same 401, so the response never says whether a handle exists or is locked out. A user with a
+/// second factor (#45) gets 200 `{ totp_required: true, pending }` and no cookie instead: `pending` is
+/// exchanged with a code at `POST /api/auth/login/totp`.
 pub async fn login(
     State(state): State<AppState>,
+    Origin(origin): Origin,
     jar: CookieJar,
     Json(body): Json<LoginBody>,
 ) -> Response {
     let pool = state.core.store();
     let pool = pool.pool();
-    match cp_core::auth::authenticate(pool, &body.handle, &body.password).await {
-        Ok(Some(user)) => match cp_core::auth::create_session(pool, user.id).await {
+    let ip = origin.ip.as_deref();
+    let user = match cp_core::throttle::authenticate(pool, &body.handle, &body.password, ip).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return unauthorized(),
+        Err(e) => return internal_error(e),
+    };
+    match cp_core::totp::enabled(pool, user.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return match cp_core::totp::begin_login(pool, user.id).await {
+                Ok(pending) => {
+                    Json(json!({ "totp_required": true, "pending": pending })).into_response()
+                }
+                Err(e) => internal_error(e),
+            }
+        }
+        Err(e) => return internal_error(e),
+    }
+    match cp_core::auth::create_session(pool, user.id, &origin).await {
+        Ok(token) => (jar.add(session_cookie(token)), Json(user)).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `POST /api/auth/login/totp {pending, code}` → 200 + `Set-Cookie` with the user: the second step of a
+/// 2FA login (#45). `code` is a current TOTP code or an unused recovery code. 401 for an unknown or
+/// expired `pending`, a wrong code, or a throttled attempt, all alike.
+pub async fn login_totp(
+    State(state): State<AppState>,
+    Origin(origin): Origin,
+    jar: CookieJar,
+    Json(body): Json<LoginTotpBody>,
+) -> Response {
+    let pool = state.core.store();
+    let pool = pool.pool();
+    let ip = origin.ip.as_deref();
+    match cp_core::totp::complete_login(pool, &body.pending, &body.code, ip).await {
+        Ok(Some(user)) => match cp_core::auth::create_session(pool, user.id, &origin).await {
             Ok(token) => (jar.add(session_cookie(token)), Json(user)).into_response(),
             Err(e) => internal_error(e),
         },
@@ -70,26 +187,171 @@ pub async fn login(
     }
 }
 
+/// `POST /api/auth/register {code, handle, password}` → 201 + `Set-Cookie` with the new user: spends one
+/// use of an invite code and signs the user in (#41). 400 for a handle or password the core policy
+/// refuses, a code that is unknown, spent or expired (one message for all three), or — only once the
+/// code is good — a taken handle.
+pub async fn register(
+    State(state): State<AppState>,
+    Origin(origin): Origin,
+    jar: CookieJar,
+    Json(body): Json<RegisterBody>,
+) -> Response {
+    let store = state.core.store();
+    let user =
+        match cp_core::invites::register(&store, &body.code, &body.handle, &body.password).await {
+            Ok(user) => user,
+            Err(e @ cp_model::Error::Validation(_)) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": e.to_string() })),
+                )
+                    .into_response()
+            }
+            Err(e) => return internal_error(e),
+        };
+    match cp_core::auth::create_session(store.pool(), user.id, &origin).await {
+        Ok(token) => (
+            StatusCode::CREATED,
+            jar.add(session_cookie(token)),
+            Json(user),
+        )
+            .into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// The cookie that clears the session cookie.
+fn cookie_removal() -> Cookie<'static> {
+    let mut removal = Cookie::new(COOKIE, "");
+    removal.set_path("/");
+    removal.make_removal();
+    removal
+}
+
 /// `POST /api/auth/logout` → revokes the session (if any) and clears the cookie. Always 204.
 pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> Response {
     if let Some(cookie) = jar.get(COOKIE) {
         let store = state.core.store();
         let _ = cp_core::auth::delete_session(store.pool(), cookie.value()).await;
     }
-    let mut removal = Cookie::new(COOKIE, "");
-    removal.set_path("/");
-    removal.make_removal();
-    (jar.add(removal), StatusCode::NO_CONTENT).into_response()
+    (jar.add(cookie_removal()), StatusCode::NO_CONTENT).into_response()
+}
+
+/// The 403 for a caller who signed in with an API token, on routes that manage credentials (sessions,
+/// tokens, the second factor): only a session may, so a leaked token cannot entrench itself. `None` for a session.
+pub(crate) fn session_only(caller: &CurrentUser) -> Option<Response> {
+    (!caller.1.is_full()).then(|| {
+        (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "credentials are managed from a session" })),
+        )
+            .into_response()
+    })
+}
+
+/// `GET /api/auth/sessions` → the caller's live sessions, most recently seen first, `current` marking
+/// this one (#43). Session only.
+pub async fn list_sessions(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    let token = jar.get(COOKIE).map(|c| c.value().to_owned());
+    match cp_core::auth::list_sessions(state.core.pool(), caller.0.id, token.as_deref()).await {
+        Ok(sessions) => Json(json!({ "sessions": sessions })).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `DELETE /api/auth/sessions/{id}` → 204, signing that session out. Someone else's session is a 404,
+/// like a missing one. Session only.
+pub async fn revoke_session(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    match cp_core::auth::revoke_session(state.core.pool(), caller.0.id, &id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(cp_model::Error::NotFound) => {
+            (StatusCode::NOT_FOUND, Json(json!({ "error": "not found" }))).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+/// `DELETE /api/auth/sessions` → 204: log out everywhere, this session included, and clear the cookie.
+/// API tokens are separate credentials and are not touched. Session only.
+pub async fn revoke_all_sessions(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    match cp_core::auth::revoke_all_sessions(state.core.pool(), caller.0.id).await {
+        Ok(_) => (jar.add(cookie_removal()), StatusCode::NO_CONTENT).into_response(),
+        Err(e) => internal_error(e),
+    }
 }
 
 /// `GET /api/auth/me` → the current user, or 401 (the extractor rejects an absent/expired session).
-pub async fn me(CurrentUser(user): CurrentUser) -> Response {
+pub async fn me(CurrentUser(user, _): CurrentUser) -> Response {
     Json(user).into_response()
 }
 
-/// The authenticated principal, resolved from the session cookie. Rejects `401` when there is no valid
-/// session. Reuse this on any future protected route (writes, permissions #18). §2/§17.
-pub struct CurrentUser(pub User);
+/// The request's credential: an `Authorization: Bearer` API token if the header is present (an
+/// unknown, revoked or expired one, or a header that is not a bearer token, is a 401 — never a silent
+/// fall back to the cookie), else the session cookie. `None` when there is neither, or the session is
+/// unknown or expired.
+async fn credential(parts: &Parts, state: &AppState) -> Result<Option<(User, Scopes)>, Response> {
+    let pool = state.core.pool();
+    if let Some(value) = parts.headers.get(header::AUTHORIZATION) {
+        let Some(secret) = value
+            .to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+        else {
+            return Err(unauthorized());
+        };
+        return match cp_core::tokens::resolve(pool, secret).await {
+            Ok(Some(found)) => Ok(Some(found)),
+            Ok(None) => Err(unauthorized()),
+            Err(e) => Err(internal_error(e)),
+        };
+    }
+    let jar = CookieJar::from_headers(&parts.headers);
+    let Some(token) = jar.get(COOKIE).map(|c| c.value().to_owned()) else {
+        return Ok(None);
+    };
+    match cp_core::auth::resolve_session(pool, &token).await {
+        Ok(user) => Ok(user.map(|user| (user, Scopes::Full))),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// The authenticated principal, resolved from the session cookie or an API token, with what that
+/// credential may do ([`Scopes::Full`] for a session). Rejects `401` when there is no valid credential.
+/// Reuse this on any future protected route (writes, permissions #18). §2/§17.
+pub struct CurrentUser(pub User, pub Scopes);
+
+impl CurrentUser {
+    /// Whether the caller acts as an operator: the user is one, and the credential is a session or a
+    /// token with unscoped `manage`.
+    pub async fn is_operator(&self, state: &AppState) -> cp_model::Result<bool> {
+        let pool = state.core.pool();
+        Ok(cp_core::auth::is_operator(pool, self.0.id).await?
+            && self.1.allows(pool, Action::Manage, None).await?)
+    }
+}
 
 impl FromRequestParts<AppState> for CurrentUser {
     type Rejection = Response;
@@ -98,15 +360,34 @@ impl FromRequestParts<AppState> for CurrentUser {
         parts: &mut Parts,
         state: &AppState,
     ) -> Result<Self, Self::Rejection> {
-        let jar = CookieJar::from_headers(&parts.headers);
-        let Some(token) = jar.get(COOKIE).map(|c| c.value().to_owned()) else {
-            return Err(unauthorized());
-        };
-        let store = state.core.store();
-        match cp_core::auth::resolve_session(store.pool(), &token).await {
-            Ok(Some(user)) => Ok(CurrentUser(user)),
-            Ok(None) => Err(unauthorized()),
-            Err(e) => Err(internal_error(e)),
+        match credential(parts, state).await? {
+            Some((user, scopes)) => Ok(CurrentUser(user, scopes)),
+            None => Err(unauthorized()),
         }
     }
 }
+
+/// The caller's user if there is one, for the read routes, which also serve anonymous visitors
+/// (`Action::View`, §18). A missing, unknown or expired session is simply `None`, never a 401; a bad
+/// bearer token is still a 401, since the caller meant to sign in.
+pub struct OptionalUser(pub Option<User>, pub Scopes);
+
+impl OptionalUser {
+    pub fn id(&self) -> Option<UserId> {
+        self.0.as_ref().map(|u| u.id)
+    }
+}
+
+impl FromRequestParts<AppState> for OptionalUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(match credential(parts, state).await? {
+            Some((user, scopes)) => OptionalUser(Some(user), scopes),
+            None => OptionalUser(None, Scopes::Full),
+        })
+    }
+}
@@ -0,0 +1,135 @@
+//! Invite codes over HTTP (#41, `design/auth.md`): minting, listing and withdrawing the codes
+//! `POST /api/auth/register` spends. An operator may mint any invite; anyone else may mint one only for
+//! channels they hold `Manage` on, and must name at least one. The store logic is `cp_core::invites`.
+
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use cp_core::invites::{self, NewInvite};
+use cp_model::{Action, ChannelId, Error};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::auth::CurrentUser;
+use crate::AppState;
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+fn core_error(e: Error) -> Response {
+    match e {
+        Error::NotFound => error(StatusCode::NOT_FOUND, "not found"),
+        Error::Validation(msg) => error(StatusCode::BAD_REQUEST, &msg),
+        e => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// The body of `POST /api/invites`. Every field is optional: one use, [`invites::DEFAULT_TTL`], no
+/// channels.
+#[derive(Deserialize)]
+pub struct CreateInviteBody {
+    #[serde(default)]
+    uses: Option<u32>,
+    #[serde(default)]
+    expires_in_hours: Option<u64>,
+    #[serde(default)]
+    channels: Vec<ChannelId>,
+}
+
+/// `POST /api/invites { uses, expires_in_hours, channels }` → 201 `{ invite, code }`. The code is in this
+/// response only. 403 for a non-operator naming no channels, or a channel they may not manage (404 if
+/// they cannot see it); 400 for zero uses, a zero lifetime or one past [`invites::MAX_TTL`], or a
+/// channel that does not accept users.
+pub async fn create_invite(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Json(body): Json<CreateInviteBody>,
+) -> Response {
+    let operator = match caller.is_operator(&state).await {
+        Ok(operator) => operator,
+        Err(e) => return core_error(e),
+    };
+    let CurrentUser(user, scopes) = caller;
+    if !operator {
+        if body.channels.is_empty() {
+            return error(StatusCode::FORBIDDEN, "forbidden");
+        }
+        for &cid in &body.channels {
+            if let Err(res) = crate::api::authorize_manage(&state, user.id, &scopes, cid).await {
+                return res;
+            }
+        }
+    }
+    let ttl = match body.expires_in_hours {
+        None => Some(invites::DEFAULT_TTL),
+        Some(0) => return error(StatusCode::BAD_REQUEST, "expires_in_hours must be positive"),
+        Some(hours) => match hours.checked_mul(60 * 60) {
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => return error(StatusCode::BAD_REQUEST, "expires_in_hours is too large"),
+        },
+    };
+    let spec = NewInvite {
+        uses: body.uses.unwrap_or(1),
+        ttl,
+        channels: body.channels,
+    };
+    match invites::mint(&state.core.store(), Some(user.id), spec).await {
+        Ok((invite, code)) => (
+            StatusCode::CREATED,
+            Json(json!({ "invite": invite, "code": code })),
+        )
+            .into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `GET /api/invites` → the open invites: every one for an operator, else the caller's own. A token
+/// needs unscoped `manage` (403).
+pub async fn list_invites(caller: CurrentUser, State(state): State<AppState>) -> Response {
+    if let Err(res) = crate::api::authorize_scope(&state, &caller.1, Action::Manage, None).await {
+        return res;
+    }
+    let pool = state.core.pool();
+    let scope = match caller.is_operator(&state).await {
+        Ok(true) => None,
+        Ok(false) => Some(caller.0.id),
+        Err(e) => return core_error(e),
+    };
+    match invites::list(pool, scope).await {
+        Ok(list) => Json(json!({ "invites": list })).into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `DELETE /api/invites/{id}` → 204. An operator may withdraw any invite, anyone else only their own;
+/// someone else's invite is a 404, like a missing one. A token needs unscoped `manage` (403).
+pub async fn revoke_invite(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    if let Err(res) = crate::api::authorize_scope(&state, &caller.1, Action::Manage, None).await {
+        return res;
+    }
+    let pool = state.core.pool();
+    let invite = match invites::get(pool, &id).await {
+        Ok(Some(invite)) => invite,
+        Ok(None) => return core_error(Error::NotFound),
+        Err(e) => return core_error(e),
+    };
+    if invite.created_by != Some(caller.0.id) {
+        match caller.is_operator(&state).await {
+            Ok(true) => {}
+            Ok(false) => return core_error(Error::NotFound),
+            Err(e) => return core_error(e),
+        }
+    }
+    match invites::revoke(pool, &id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => core_error(e),
+    }
+}
@@ -7,16 +7,20 @@
 //! `/api/events` streams the change bus over SSE (§5/§9). The only remaining `501`-free-but-empty
 //! surface is the `/ext` per-kind mount (no kind contributes routes yet).
 
+pub mod admin;
 pub mod api;
 pub mod auth;
+pub mod invites;
 pub mod sse;
 pub mod static_files;
+pub mod tokens;
+pub mod totp;
 
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post, put};
 use axum::Router;
 use cp_core::{Core, Registry};
 
@@ -32,11 +36,32 @@ pub struct AppState {
 /// real routes via `tower::ServiceExt::oneshot` without binding a socket. §9.
 pub fn router(state: AppState) -> Router {
     Router::new()
-        .route("/api/channels/{id}", get(api::get_channel))
+        // Structure over HTTP: create, patch, trash and move channels, each gated by `Manage` on the
+        // channel and on its destination. §18.
+        .route("/api/channels", post(api::create_channel))
+        .route(
+            "/api/channels/{id}",
+            get(api::get_channel)
+                .patch(api::patch_channel)
+                .delete(api::delete_channel),
+        )
+        .route("/api/channels/{id}/move", post(api::move_channel))
+        // Membership through the kind's `Membership` capability; changes take `Manage`. §8/§18.
+        .route("/api/channels/{id}/members", get(api::get_members))
+        .route(
+            "/api/channels/{id}/members/{user}",
+            put(api::add_member).delete(api::remove_member),
+        )
         .route("/api/channels/{id}/contents", post(api::channel_contents))
         // Authenticated write: post an item into a channel, gated by the kind's Permission. §18.
         .route("/api/channels/{id}/items", post(api::post_item))
-        .route("/api/items/{id}", get(api::get_item))
+        // Replace or patch an item's payload, conditionally on `If-Match` (optimistic concurrency,
+        // §3/§18).
+        .route(
+            "/api/items/{id}",
+            get(api::get_item).put(api::put_item).patch(api::patch_item),
+        )
+        .route("/api/items/{id}/revisions", get(api::get_item_revisions))
         // linked-users reads: a user's external links, and an item's authorship resolution. §2/§19.
         .route("/api/users/{id}/links", get(api::get_user_links))
         .route(
@@ -44,10 +69,42 @@ pub fn router(state: AppState) -> Router {
             get(api::get_item_linked_user),
         )
         .route("/api/events", get(sse::events))
-        // Native-user auth (provisioned accounts; §2/§17). No registration route.
+        // Native-user auth (provisioned accounts, or invite registration; §2/§17).
         .route("/api/auth/login", post(auth::login))
+        .route("/api/auth/login/totp", post(auth::login_totp))
         .route("/api/auth/logout", post(auth::logout))
         .route("/api/auth/me", get(auth::me))
+        .route("/api/auth/register", post(auth::register))
+        // The caller's sessions: list, revoke one, or log out everywhere. #43.
+        .route(
+            "/api/auth/sessions",
+            get(auth::list_sessions).delete(auth::revoke_all_sessions),
+        )
+        .route("/api/auth/sessions/{id}", delete(auth::revoke_session))
+        // Personal API tokens, managed from a session; sent as `Authorization: Bearer`. #42.
+        .route(
+            "/api/auth/tokens",
+            get(tokens::list_tokens).post(tokens::create_token),
+        )
+        .route("/api/auth/tokens/{id}", delete(tokens::revoke_token))
+        // The caller's TOTP second factor: status, enroll, confirm, turn off. #45.
+        .route(
+            "/api/auth/totp",
+            get(totp::get_totp).post(totp::enroll).delete(totp::disable),
+        )
+        .route("/api/auth/totp/confirm", post(totp::confirm))
+        // Invite codes, minted by operators and by channel admins for their channels. #41.
+        .route(
+            "/api/invites",
+            get(invites::list_invites).post(invites::create_invite),
+        )
+        .route("/api/invites/{id}", delete(invites::revoke_invite))
+        // Operator-only runtime supervision, mirroring the shell's `show runtimes`/`restart`/`reset`. §8.
+        .route("/api/admin/runtimes", get(admin::get_runtimes))
+        .route(
+            "/api/admin/runtimes/{name}/{action}",
+            post(admin::control_runtime),
+        )
         // Channel kinds may contribute extra routes (webhooks, etc.) under /ext/<type>. None do yet;
         // the mount point exists so the surface is stable. §4/§9.
         .nest("/ext", Router::<AppState>::new())
@@ -71,6 +128,11 @@ pub async fn serve(core: Core, registry: Registry, addr: SocketAddr) -> anyhow::
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!(%addr, web_dir = %web_dir.display(), "channel-party frontend listening");
-    axum::serve(listener, app).await?;
+    // Peer addresses reach the handlers for the session list (#43).
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
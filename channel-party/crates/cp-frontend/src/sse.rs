@@ -1,23 +1,28 @@
-//! Live updates over Server-Sent Events, backed by the core change bus (DESIGN §7/§9). The write path
-//! emits a `ChangeEvent` after every committed mutation; this forwards each to subscribed clients as an
-//! SSE `change` event, optionally filtered to one channel scope. The wire shape is a frontend concern,
-//! so it is built here rather than by deriving serde onto core's event type.
+//! Live updates over Server-Sent Events, backed by the core change bus and durable change log (DESIGN
+//! §7/§9). The write path logs and emits a `ChangeEvent` after every committed mutation; this forwards
+//! each to subscribed clients as an SSE `change` event, optionally filtered to one channel scope. Each
+//! frame's `id` is the event's log `seq`, so a reconnecting `EventSource` sends `Last-Event-ID` and
+//! resumes exactly where it left off. The wire shape is a frontend concern, so it is built here rather
+//! than by deriving serde onto core's event type. Each subscriber only gets frames about what it may
+//! view (`Action::View`, §18).
 
 use std::convert::Infallible;
 
 use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use cp_core::{ChangeEvent, ChangeOp, EnvelopeRef};
+use cp_core::authz::ViewScope;
+use cp_core::{change_log, ChangeEvent, ChangeOp, EnvelopeRef};
 use cp_model::ChannelId;
 use serde::Deserialize;
 use serde_json::json;
-use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
-use tokio_stream::wrappers::BroadcastStream;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
 
+use crate::auth::OptionalUser;
 use crate::AppState;
 
 /// `?scope=<channel id>` restricts the stream to one channel; absent = the whole firehose.
@@ -26,10 +31,24 @@ pub struct EventsQuery {
     scope: Option<String>,
 }
 
+/// Frames buffered per connection between the follower task and the response body.
+const BUFFER: usize = 64;
+
 /// `GET /api/events[?scope=…]` -> an SSE stream of change events. §9. A `scope` keeps events whose
 /// container is that channel, plus changes to the channel envelope itself (so a channel view learns
-/// both "my contents changed" and "I was renamed/deleted").
-pub async fn events(State(state): State<AppState>, Query(q): Query<EventsQuery>) -> Response {
+/// both "my contents changed" and "I was renamed/deleted"). With `Last-Event-ID`, every change
+/// committed after that `seq` is replayed from the change log first; when the log no longer reaches
+/// back that far, a `lagged` frame tells the client to resync before the replay.
+///
+/// Events are filtered per subscriber, for the session or token it connected with (or none): a frame
+/// goes out only if the caller may view the changed envelope, judged as [`ViewScope::event`] does. Each
+/// event is judged afresh, so a membership granted or revoked mid-stream takes effect from the next frame.
+pub async fn events(
+    viewer: OptionalUser,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<EventsQuery>,
+) -> Response {
     let scope = match q.scope {
         Some(s) => match s.parse::<ChannelId>() {
             Ok(id) => Some(id),
@@ -44,24 +63,82 @@ pub async fn events(State(state): State<AppState>, Query(q): Query<EventsQuery>)
         None => None,
     };
 
-    // Subscribing here (before the handler returns) means any write committed after the client has the
-    // response is guaranteed to reach this receiver via the broadcast buffer.
-    let rx = state.core.events().subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(move |result| match result {
-        Ok(event) => {
-            if scope.is_some_and(|s| !in_scope(&event, s)) {
-                None
-            } else {
-                Some(Ok::<Event, Infallible>(change_event(&event)))
+    let after = match headers.get("last-event-id") {
+        None => None,
+        Some(v) => match v.to_str().ok().and_then(|v| v.trim().parse::<i64>().ok()) {
+            Some(seq) => Some(seq),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": "invalid Last-Event-ID" })),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    // Following here (before the handler returns) subscribes to the bus, so any write committed after
+    // the client has the response is guaranteed to reach this stream. A lagging client no longer loses
+    // events: the follower falls back to the log.
+    let mut changes = match change_log::follow(state.core.pool(), state.core.events(), after).await
+    {
+        Ok(changes) => changes,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    };
+    let (tx, rx) = mpsc::channel::<Event>(BUFFER);
+    let store = state.core.store();
+    let scopes = viewer.1.clone();
+    let viewer = viewer.id();
+    tokio::spawn(async move {
+        if changes.missed() {
+            let frame = Event::default()
+                .event("lagged")
+                .data(changes.offset().to_string());
+            if tx.send(frame).await.is_err() {
+                return;
             }
         }
-        // A client too slow for the 1024-deep buffer misses events; tell it to resync rather than drop
-        // silently. It stays subscribed and resumes with live events.
-        Err(BroadcastStreamRecvError::Lagged(n)) => {
-            Some(Ok(Event::default().event("lagged").data(n.to_string())))
+        loop {
+            let next = tokio::select! {
+                () = tx.closed() => return, // client went away
+                next = changes.next() => next,
+            };
+            let event = match next {
+                Ok(Some(event)) => event,
+                Ok(None) => return,
+                Err(e) => {
+                    tracing::warn!(error = %e, "sse: change log read failed");
+                    return; // the client reconnects with Last-Event-ID
+                }
+            };
+            if scope.is_some_and(|s| !in_scope(&event, s)) {
+                continue;
+            }
+            match ViewScope::new(store.clone(), viewer)
+                .limited(scopes.clone())
+                .event(&event)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::warn!(error = %e, "sse: view check failed");
+                    return; // the client reconnects with Last-Event-ID
+                }
+            }
+            if tx.send(change_event(&event)).await.is_err() {
+                return;
+            }
         }
     });
 
+    let stream = ReceiverStream::new(rx).map(Ok::<Event, Infallible>);
     Sse::new(stream)
         .keep_alive(KeepAlive::default())
         .into_response()
@@ -91,5 +168,8 @@ fn change_event(event: &ChangeEvent) -> Event {
         "type_id": event.type_id.as_str(),
         "container": event.container.map(|c| c.to_string()),
     });
-    Event::default().event("change").data(data.to_string())
+    Event::default()
+        .event("change")
+        .id(event.seq.to_string())
+        .data(data.to_string())
 }
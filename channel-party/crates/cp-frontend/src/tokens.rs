@@ -0,0 +1,109 @@
+//! Personal access tokens over HTTP (#42, `design/auth.md`): a signed-in user mints, lists and revokes
+//! their own API tokens. Only a session may manage tokens — a request made with a token is a 403 here,
+//! so a leaked token cannot mint itself a wider one. The store logic is `cp_core::tokens`.
+
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use cp_core::tokens::{self, Scope};
+use cp_model::Error;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::auth::{session_only, CurrentUser};
+use crate::AppState;
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+fn core_error(e: Error) -> Response {
+    match e {
+        Error::NotFound => error(StatusCode::NOT_FOUND, "not found"),
+        Error::Validation(msg) => error(StatusCode::BAD_REQUEST, &msg),
+        e => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// The body of `POST /api/auth/tokens`. `scopes` are strings as [`Scope`] parses them (`read`, `post`,
+/// `manage`, optionally `:<channel-id>`); no `expires_in_days` means the token never expires.
+#[derive(Deserialize)]
+pub struct CreateTokenBody {
+    name: String,
+    scopes: Vec<String>,
+    #[serde(default)]
+    expires_in_days: Option<u64>,
+}
+
+/// `POST /api/auth/tokens { name, scopes, expires_in_days }` → 201 `{ token, secret }`. The secret is in
+/// this response only. 400 for an empty name or scope set, an unknown scope, or a zero lifetime or one
+/// past [`tokens::MAX_TTL`]; 404 for a scope naming a channel that does not exist.
+pub async fn create_token(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Json(body): Json<CreateTokenBody>,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    let scopes = match body
+        .scopes
+        .iter()
+        .map(|s| s.parse::<Scope>())
+        .collect::<cp_model::Result<Vec<_>>>()
+    {
+        Ok(scopes) => scopes,
+        Err(e) => return core_error(e),
+    };
+    let ttl = match body.expires_in_days {
+        None => None,
+        Some(0) => return error(StatusCode::BAD_REQUEST, "expires_in_days must be positive"),
+        Some(days) => match days.checked_mul(24 * 60 * 60) {
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => return error(StatusCode::BAD_REQUEST, "expires_in_days is too large"),
+        },
+    };
+    match tokens::mint(state.core.pool(), caller.0.id, &body.name, scopes, ttl).await {
+        Ok((token, secret)) => (
+            StatusCode::CREATED,
+            Json(json!({ "token": token, "secret": secret })),
+        )
+            .into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `GET /api/auth/tokens` → the caller's tokens, expired ones included.
+pub async fn list_tokens(caller: CurrentUser, State(state): State<AppState>) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    match tokens::list(state.core.pool(), caller.0.id).await {
+        Ok(list) => Json(json!({ "tokens": list })).into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `DELETE /api/auth/tokens/{id}` → 204. Someone else's token is a 404, like a missing one.
+pub async fn revoke_token(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    let pool = state.core.pool();
+    match tokens::get(pool, &id).await {
+        Ok(Some(token)) if token.user_id == caller.0.id => {}
+        Ok(_) => return core_error(Error::NotFound),
+        Err(e) => return core_error(e),
+    }
+    match tokens::revoke(pool, &id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => core_error(e),
+    }
+}
@@ -0,0 +1,96 @@
+//! A user's TOTP second factor over HTTP (#45, `design/auth.md`): status, enrollment, confirmation and
+//! turning it off. Like API tokens, only a session may manage it — a request made with a token is a
+//! 403 here. The login step that takes a code is `auth::login_totp`; the store logic is `cp_core::totp`.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use cp_core::totp;
+use cp_model::Error;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::auth::{session_only, CurrentUser, Origin};
+use crate::AppState;
+
+/// What names this instance in the user's authenticator app, unless `CP_TOTP_ISSUER` says otherwise.
+const DEFAULT_ISSUER: &str = "channel-party";
+
+fn error(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({ "error": msg }))).into_response()
+}
+
+fn core_error(e: Error) -> Response {
+    match e {
+        Error::NotFound => error(StatusCode::NOT_FOUND, "not found"),
+        Error::Validation(msg) => error(StatusCode::BAD_REQUEST, &msg),
+        e => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// The body of `POST /api/auth/totp/confirm` and `DELETE /api/auth/totp`.
+#[derive(Deserialize)]
+pub struct CodeBody {
+    code: String,
+}
+
+/// `GET /api/auth/totp` → `{ enabled, recovery_codes_left }`.
+pub async fn get_totp(caller: CurrentUser, State(state): State<AppState>) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    match totp::status(state.core.pool(), caller.0.id).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `POST /api/auth/totp` → `{ secret, uri }`: start enrolling, replacing an unconfirmed enrollment. Not
+/// a second factor until confirmed. 400 if one is already enabled.
+pub async fn enroll(caller: CurrentUser, State(state): State<AppState>) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    let issuer = std::env::var("CP_TOTP_ISSUER").unwrap_or_else(|_| DEFAULT_ISSUER.to_owned());
+    match totp::begin_enrollment(state.core.pool(), &caller.0, &issuer).await {
+        Ok(enrollment) => Json(enrollment).into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `POST /api/auth/totp/confirm { code }` → `{ recovery_codes }`: enable the second factor with a first
+/// code from the app. The recovery codes are in this response only. 400 for a wrong code; 404 if no
+/// enrollment is pending.
+pub async fn confirm(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Json(body): Json<CodeBody>,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    match totp::confirm_enrollment(state.core.pool(), caller.0.id, &body.code).await {
+        Ok(codes) => Json(json!({ "recovery_codes": codes })).into_response(),
+        Err(e) => core_error(e),
+    }
+}
+
+/// `DELETE /api/auth/totp { code }` → 204: turn the second factor off, proven with a current code or a
+/// recovery code. 400 for a wrong code, or while wrong codes have the handle or address throttled
+/// (#44); 404 if none is enabled.
+pub async fn disable(
+    caller: CurrentUser,
+    State(state): State<AppState>,
+    Origin(origin): Origin,
+    Json(body): Json<CodeBody>,
+) -> Response {
+    if let Some(res) = session_only(&caller) {
+        return res;
+    }
+    let ip = origin.ip.as_deref();
+    match totp::disable(state.core.pool(), caller.0.id, &body.code, ip).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => core_error(e),
+    }
+}
@@ -0,0 +1,140 @@
+//! The admin runtime surface over the real router via `oneshot`: operator gating (401 / 403 / 200),
+//! component status, and queuing `restart`/`reset` requests the supervisor then takes. Core's own
+//! change-log retention component stands in for any supervised component.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::Response;
+use axum::Router;
+use cp_core::runtime::{self, RuntimeState};
+use cp_core::{auth, change_log, Core, Registry};
+use cp_frontend::{router, AppState};
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tower::ServiceExt;
+
+const COMPONENT: &str = "change-log-retention";
+
+async fn json_body(res: Response) -> Value {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn req(method: &str, uri: &str, cookie: Option<&str>) -> Request<Body> {
+    let mut b = Request::builder().method(method).uri(uri);
+    if let Some(c) = cookie {
+        b = b.header(header::COOKIE, c);
+    }
+    b.body(Body::empty()).unwrap()
+}
+
+/// A session cookie for a freshly provisioned user.
+async fn cookie_for(core: &Core, handle: &str) -> String {
+    let store = core.store();
+    let id = auth::provision_user(store.pool(), handle).await.unwrap();
+    let token = auth::create_session(store.pool(), id, &Default::default())
+        .await
+        .unwrap();
+    format!("cp_session={token}")
+}
+
+async fn app(core: Arc<Core>, registry: Registry, dir: &tempfile::TempDir) -> Router {
+    router(AppState {
+        core,
+        registry,
+        web_dir: dir.path().to_path_buf(),
+    })
+}
+
+#[tokio::test]
+async fn operators_see_runtime_status_and_queue_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let registry = Registry::builder()
+        .runtime(change_log::retention(Duration::from_secs(3600)))
+        .build();
+    let core = Arc::new(Core::open(&url, registry.clone()).await.unwrap());
+    let handle = core.spawn_runtime();
+    for _ in 0..100 {
+        let statuses = runtime::statuses(core.pool()).await.unwrap();
+        if statuses.iter().any(|s| s.state == RuntimeState::Running) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let user = cookie_for(&core, "alice").await;
+    let operator = cookie_for(&core, "root").await;
+    auth::set_operator(core.pool(), "root", true).await.unwrap();
+    let app = app(core.clone(), registry, &dir).await;
+
+    let res = app
+        .clone()
+        .oneshot(req("GET", "/api/admin/runtimes", None))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = app
+        .clone()
+        .oneshot(req("GET", "/api/admin/runtimes", Some(&user)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .clone()
+        .oneshot(req("GET", "/api/admin/runtimes", Some(&operator)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    assert_eq!(body[0]["name"], COMPONENT);
+    assert_eq!(body[0]["state"], "running");
+    assert_eq!(body[0]["restarts"], 0);
+
+    let uri = format!("/api/admin/runtimes/{COMPONENT}/restart");
+    let res = app
+        .clone()
+        .oneshot(req("POST", &uri, Some(&user)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .clone()
+        .oneshot(req("POST", &uri, Some(&operator)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    for (uri, status) in [
+        ("/api/admin/runtimes/nope/restart", StatusCode::NOT_FOUND),
+        (
+            "/api/admin/runtimes/change-log-retention/explode",
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req("POST", uri, Some(&operator)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{uri}");
+    }
+
+    // The supervisor takes the request within a poll and restarts the component.
+    let mut restarted = false;
+    for _ in 0..100 {
+        let s = runtime::statuses(core.pool()).await.unwrap().remove(0);
+        if s.restarts == 1 && s.pending.is_none() && s.state == RuntimeState::Running {
+            restarted = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(restarted, "{:?}", runtime::statuses(core.pool()).await);
+    handle.shutdown().await;
+    let s = runtime::statuses(core.pool()).await.unwrap().remove(0);
+    assert_eq!(s.state, RuntimeState::Stopped);
+}
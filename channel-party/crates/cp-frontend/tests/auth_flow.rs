@@ -1,14 +1,19 @@
 //! End-to-end auth flow (`TODO.md` #17): login → me → logout over the real router via `oneshot`,
 //! propagating the session cookie. A user is provisioned with a password up front (the shell path,
-//! called directly), since there is no registration endpoint.
+//! called directly); invite registration has its own suite, `invites.rs`. The session list, revoking one
+//! session and logging out everywhere (#43) ride the same cookies. Throttled logins (#44) are refused
+//! exactly like bad passwords, and a TOTP second factor (#45) turns login into two steps.
 
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use axum::body::Body;
+use axum::extract::ConnectInfo;
 use axum::http::{header, Request, StatusCode};
 use axum::response::Response;
 use axum::Router;
-use cp_core::{auth, Core, Registry};
+use cp_core::{auth, totp, Core, Registry};
 use cp_frontend::{router, AppState};
 use http_body_util::BodyExt;
 use serde_json::{json, Value};
@@ -126,3 +131,320 @@ async fn login_me_logout_flow() {
         "session was revoked on logout"
     );
 }
+
+/// A bodiless request with a session cookie.
+fn with_cookie(method: &str, uri: &str, cookie: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sessions_are_listed_and_revoked() {
+    let (_dir, app) = app().await;
+
+    // One login from a known browser and peer, one from nowhere in particular.
+    let mut req = login_req("alice", "hunter2");
+    req.headers_mut()
+        .insert(header::USER_AGENT, "test-browser/1.0".parse().unwrap());
+    req.extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 7], 4321))));
+    let laptop = session_cookie(&app.clone().oneshot(req).await.unwrap());
+    let res = app
+        .clone()
+        .oneshot(login_req("alice", "hunter2"))
+        .await
+        .unwrap();
+    let phone = session_cookie(&res);
+
+    let res = app
+        .clone()
+        .oneshot(with_cookie("GET", "/api/auth/sessions", &laptop))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let listed = json_body(res).await;
+    let sessions = listed["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let current: Vec<_> = sessions.iter().filter(|s| s["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["user_agent"], "test-browser/1.0");
+    assert_eq!(current[0]["ip"], "192.0.2.7");
+    let other = sessions.iter().find(|s| s["current"] == false).unwrap();
+    let other_id = other["id"].as_str().unwrap();
+
+    // Revoking the other session signs the phone out, and only once.
+    let uri = format!("/api/auth/sessions/{other_id}");
+    let res = app
+        .clone()
+        .oneshot(with_cookie("DELETE", &uri, &laptop))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app.clone().oneshot(me_req(Some(&phone))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = app
+        .clone()
+        .oneshot(with_cookie("DELETE", &uri, &laptop))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Logging out everywhere takes this session too, and clears its cookie.
+    let res = app
+        .clone()
+        .oneshot(login_req("alice", "hunter2"))
+        .await
+        .unwrap();
+    let tablet = session_cookie(&res);
+    let res = app
+        .clone()
+        .oneshot(with_cookie("DELETE", "/api/auth/sessions", &laptop))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(res.headers().get(header::SET_COOKIE).is_some());
+    for cookie in [&laptop, &tablet] {
+        let res = app.clone().oneshot(me_req(Some(cookie))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn throttled_logins_look_like_bad_passwords() {
+    let (_dir, app) = app().await;
+    let refused = |res: Response| async move {
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().get(header::SET_COOKIE).is_none());
+        json_body(res).await
+    };
+
+    let wrong = refused(
+        app.clone()
+            .oneshot(login_req("alice", "wrong"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    // Each handle is held back after a few failures: the right password is then refused exactly like a
+    // wrong one, and a handle that exists cannot be told from one that does not.
+    for handle in ["alice", "nobody"] {
+        for _ in 0..4 {
+            app.clone()
+                .oneshot(login_req(handle, "wrong"))
+                .await
+                .unwrap();
+        }
+        let res = app
+            .clone()
+            .oneshot(login_req(handle, "hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(refused(res).await, wrong);
+    }
+}
+
+#[tokio::test]
+async fn a_spoofed_forwarded_hop_does_not_dodge_the_throttle() {
+    // Behind two proxies the client is the hop the outer one appended, second from the right. No other
+    // test here sends `X-Forwarded-For`, so setting this for the whole binary changes nothing else.
+    std::env::set_var("CP_TRUST_PROXY", "2");
+    let (_dir, app) = app().await;
+    let via = |req: Request<Body>, lines: &[&str]| {
+        let mut req = req;
+        for line in lines {
+            req.headers_mut()
+                .append("x-forwarded-for", line.parse().unwrap());
+        }
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 4321))));
+        req
+    };
+    let session_ip = |res: Response| {
+        let app = app.clone();
+        async move {
+            let cookie = session_cookie(&res);
+            let res = app
+                .oneshot(with_cookie("GET", "/api/auth/sessions", &cookie))
+                .await
+                .unwrap();
+            let sessions = json_body(res).await["sessions"].clone();
+            let current = sessions
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|s| s["current"] == true);
+            current.unwrap()["ip"].clone()
+        }
+    };
+
+    // Enough failures from one address to hold it back, each claiming a different client on the left.
+    for n in 0..11 {
+        let req = via(
+            login_req(&format!("user{n}"), "wrong"),
+            &[&format!("203.0.113.{n}, 198.51.100.9, 10.0.0.1")],
+        );
+        app.clone().oneshot(req).await.unwrap();
+    }
+    let req = via(
+        login_req("alice", "hunter2"),
+        &["203.0.113.99, 198.51.100.9, 10.0.0.1"],
+    );
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // The client's own header line is read with the proxies' lines, not instead of them.
+    let req = via(
+        login_req("alice", "hunter2"),
+        &["198.51.100.10", "198.51.100.9, 10.0.0.1"],
+    );
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Another address is not held back, and its session records that hop.
+    let req = via(
+        login_req("alice", "hunter2"),
+        &["198.51.100.9", "198.51.100.10, 10.0.0.1"],
+    );
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(session_ip(res).await, "198.51.100.10");
+
+    // Fewer hops than proxies: the leftmost is the client's own, so the peer is recorded instead.
+    let req = via(login_req("alice", "hunter2"), &["198.51.100.9"]);
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(session_ip(res).await, "192.0.2.1");
+}
+
+/// A JSON request with a session cookie.
+fn json_with_cookie(method: &str, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn login_totp_req(pending: &str, code: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/auth/login/totp")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "pending": pending, "code": code }).to_string(),
+        ))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn totp_enrollment_makes_login_two_steps() {
+    let (_dir, app) = app().await;
+    let res = app
+        .clone()
+        .oneshot(login_req("alice", "hunter2"))
+        .await
+        .unwrap();
+    let cookie = session_cookie(&res);
+
+    // Enroll, then confirm with a code from the "app".
+    let res = app
+        .clone()
+        .oneshot(with_cookie("POST", "/api/auth/totp", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let enrollment = json_body(res).await;
+    let secret = enrollment["secret"].as_str().unwrap().to_owned();
+    assert!(enrollment["uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/channel-party:alice?"));
+    let res = app
+        .clone()
+        .oneshot(json_with_cookie(
+            "POST",
+            "/api/auth/totp/confirm",
+            &cookie,
+            json!({ "code": "000000" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let code = totp::code_at(&secret, SystemTime::now()).unwrap();
+    let res = app
+        .clone()
+        .oneshot(json_with_cookie(
+            "POST",
+            "/api/auth/totp/confirm",
+            &cookie,
+            json!({ "code": code }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let recovery = json_body(res).await["recovery_codes"].clone();
+    let res = app
+        .clone()
+        .oneshot(with_cookie("GET", "/api/auth/totp", &cookie))
+        .await
+        .unwrap();
+    assert_eq!(
+        json_body(res).await,
+        json!({ "enabled": true, "recovery_codes_left": 10 })
+    );
+
+    // Now the password earns a pending token, not a session.
+    let res = app
+        .clone()
+        .oneshot(login_req("alice", "hunter2"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get(header::SET_COOKIE).is_none());
+    let step = json_body(res).await;
+    assert_eq!(step["totp_required"], true);
+    let pending = step["pending"].as_str().unwrap();
+
+    let res = app
+        .clone()
+        .oneshot(login_totp_req(pending, "000000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let next = totp::code_at(&secret, SystemTime::now() + Duration::from_secs(30)).unwrap();
+    let res = app
+        .clone()
+        .oneshot(login_totp_req(pending, &next))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let second = session_cookie(&res);
+    assert_eq!(json_body(res).await["handle"], "alice");
+    let res = app.clone().oneshot(me_req(Some(&second))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Turning it off takes a code; then the password alone signs in again.
+    let res = app
+        .clone()
+        .oneshot(json_with_cookie(
+            "DELETE",
+            "/api/auth/totp",
+            &second,
+            json!({ "code": recovery[0] }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app
+        .clone()
+        .oneshot(login_req("alice", "hunter2"))
+        .await
+        .unwrap();
+    assert!(res.headers().get(header::SET_COOKIE).is_some());
+}
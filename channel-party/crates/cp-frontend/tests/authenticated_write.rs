@@ -2,7 +2,9 @@
 //! over the real router via `oneshot`. Proves the full gate with the real `basic` slice — a session is
 //! required (401), the kind's `Permission` must allow `Post` (basic = members-only → 403 for a
 //! non-member; deny-by-default → 403 for a kind with no `Permission`), and on success the item is created
-//! with its author stamped server-side (a client-supplied author is overwritten).
+//! with its author stamped server-side (a client-supplied author is overwritten). Also `PUT
+//! /api/items/:id` and `PATCH` (merge-patch / JSON-patch) under the same gate, with `ETag`/`If-Match`
+//! optimistic concurrency (412 when stale); another member's item takes `Manage`.
 
 use std::sync::Arc;
 
@@ -12,7 +14,7 @@ use axum::response::Response;
 use axum::Router;
 use cp_core::{auth, Core, Registry};
 use cp_frontend::{router, AppState};
-use cp_model::{ChannelId, NewChannel, TypeId, UserId, WriteCtx};
+use cp_model::{ChannelId, NewChannel, Role, TypeId, UserId, WriteCtx};
 use http_body_util::BodyExt;
 use serde_json::{json, Value};
 use tower::ServiceExt;
@@ -198,3 +200,306 @@ async fn unauthenticated_and_unauthorized_are_rejected() {
         .unwrap();
     assert_eq!(res.status(), StatusCode::FORBIDDEN);
 }
+
+fn put_item(id: &str, cookie: &str, if_match: Option<&str>, payload: Value) -> Request<Body> {
+    let mut b = Request::builder()
+        .method("PUT")
+        .uri(format!("/api/items/{id}"))
+        .header("content-type", "application/json")
+        .header(header::COOKIE, cookie);
+    if let Some(tag) = if_match {
+        b = b.header(header::IF_MATCH, tag);
+    }
+    b.body(Body::from(json!({ "payload": payload }).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn put_is_conditional_on_if_match() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let room = channel(&h.core, "basic").await;
+    h.core.store().add_member(room, alice).await.unwrap();
+    let cookie = login(&h.app, "alice").await;
+    let res = h
+        .app
+        .clone()
+        .oneshot(post_item(
+            room,
+            Some(&cookie),
+            "basic",
+            json!({ "body": "v1" }),
+        ))
+        .await
+        .unwrap();
+    let id = json_body(res).await["id"].as_str().unwrap().to_owned();
+
+    let res = h
+        .app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/items/{id}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.headers()[header::ETAG], "\"1\"");
+
+    // Matching tag: written, and the response carries the bumped tag.
+    let res = h
+        .app
+        .clone()
+        .oneshot(put_item(
+            &id,
+            &cookie,
+            Some("\"1\""),
+            json!({ "body": "v2" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::ETAG], "\"2\"");
+    assert_eq!(json_body(res).await["payload"]["body"], "v2");
+
+    // The other tab still holds rev 1: 412 with the current tag, and nothing written.
+    let res = h
+        .app
+        .clone()
+        .oneshot(put_item(
+            &id,
+            &cookie,
+            Some("\"1\""),
+            json!({ "body": "lost" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(res.headers()[header::ETAG], "\"2\"");
+    let item = h
+        .core
+        .store()
+        .get_item(id.parse().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.payload["body"], "v2");
+
+    // No If-Match: last-write-wins. A malformed one is a 400.
+    let res = h
+        .app
+        .clone()
+        .oneshot(put_item(&id, &cookie, None, json!({ "body": "v3" })))
+        .await
+        .unwrap();
+    assert_eq!(res.headers()[header::ETAG], "\"3\"");
+    let res = h
+        .app
+        .oneshot(put_item(
+            &id,
+            &cookie,
+            Some("W/\"3\""),
+            json!({ "body": "v4" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+fn patch_item(
+    id: &str,
+    cookie: &str,
+    content_type: &str,
+    if_match: Option<&str>,
+    doc: Value,
+) -> Request<Body> {
+    let mut b = Request::builder()
+        .method("PATCH")
+        .uri(format!("/api/items/{id}"))
+        .header("content-type", content_type)
+        .header(header::COOKIE, cookie);
+    if let Some(tag) = if_match {
+        b = b.header(header::IF_MATCH, tag);
+    }
+    b.body(Body::from(doc.to_string())).unwrap()
+}
+
+#[tokio::test]
+async fn patch_takes_either_format_and_restamps_the_author() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let room = channel(&h.core, "basic").await;
+    h.core.store().add_member(room, alice).await.unwrap();
+    let cookie = login(&h.app, "alice").await;
+    let res = h
+        .app
+        .clone()
+        .oneshot(post_item(
+            room,
+            Some(&cookie),
+            "basic",
+            json!({ "body": "v1" }),
+        ))
+        .await
+        .unwrap();
+    let id = json_body(res).await["id"].as_str().unwrap().to_owned();
+
+    // Merge-patch: the spoofed author is overwritten, like on create.
+    let res = h
+        .app
+        .clone()
+        .oneshot(patch_item(
+            &id,
+            &cookie,
+            "application/merge-patch+json",
+            None,
+            json!({ "body": "v2", "author": "spoofed" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::ETAG], "\"2\"");
+    let item = json_body(res).await;
+    assert_eq!(item["payload"]["body"], "v2");
+    assert_eq!(item["payload"]["author"], alice.to_string());
+
+    // JSON-patch, conditional: stale → 412; current → applied, author still stamped.
+    let ops = json!([
+        { "op": "replace", "path": "/body", "value": "v3" },
+        { "op": "replace", "path": "/author", "value": "spoofed" }
+    ]);
+    let res = h
+        .app
+        .clone()
+        .oneshot(patch_item(
+            &id,
+            &cookie,
+            "application/json-patch+json",
+            Some("\"1\""),
+            ops.clone(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    let res = h
+        .app
+        .clone()
+        .oneshot(patch_item(
+            &id,
+            &cookie,
+            "application/json-patch+json",
+            Some("\"2\""),
+            ops,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let item = json_body(res).await;
+    assert_eq!(item["payload"]["body"], "v3");
+    assert_eq!(item["payload"]["author"], alice.to_string());
+
+    // A patch that cannot apply is a 400; plain JSON is not a patch format.
+    let res = h
+        .app
+        .clone()
+        .oneshot(patch_item(
+            &id,
+            &cookie,
+            "application/json-patch+json",
+            None,
+            json!([{ "op": "remove", "path": "/nope" }]),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = h
+        .app
+        .oneshot(patch_item(
+            &id,
+            &cookie,
+            "application/json",
+            None,
+            json!({ "body": "v4" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn only_the_author_or_a_manager_edits_an_item() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let bob = user(&h.core, "bob").await;
+    let carol = user(&h.core, "carol").await;
+    let room = channel(&h.core, "basic").await;
+    for member in [alice, bob, carol] {
+        h.core.store().add_member(room, member).await.unwrap();
+    }
+    let alice_cookie = login(&h.app, "alice").await;
+    let res = h
+        .app
+        .clone()
+        .oneshot(post_item(
+            room,
+            Some(&alice_cookie),
+            "basic",
+            json!({ "body": "mine" }),
+        ))
+        .await
+        .unwrap();
+    let id = json_body(res).await["id"].as_str().unwrap().to_owned();
+
+    // Bob may post in the room, but not rewrite (and so take over) alice's message, either way.
+    let bob_cookie = login(&h.app, "bob").await;
+    let res = h
+        .app
+        .clone()
+        .oneshot(put_item(&id, &bob_cookie, None, json!({ "body": "bob's" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = h
+        .app
+        .clone()
+        .oneshot(patch_item(
+            &id,
+            &bob_cookie,
+            "application/merge-patch+json",
+            None,
+            json!({ "body": "bob's" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let item = h
+        .core
+        .store()
+        .get_item(id.parse().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.payload["body"], "mine");
+    assert_eq!(item.payload["author"], alice.to_string());
+
+    // An admin of the room may (moderation), and the edit is stamped as theirs.
+    h.core
+        .store()
+        .grant_role(room, carol, Role::Admin)
+        .await
+        .unwrap();
+    let carol_cookie = login(&h.app, "carol").await;
+    let res = h
+        .app
+        .oneshot(put_item(
+            &id,
+            &carol_cookie,
+            None,
+            json!({ "body": "moderated" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["payload"]["author"], carol.to_string());
+}
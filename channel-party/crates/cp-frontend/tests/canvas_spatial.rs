@@ -200,10 +200,11 @@ async fn reflects_move_and_delete() {
         )
         .await
         .unwrap();
+    // The projection carries the moved box's bumped `rev`, ready for a conditional edit.
     wait_for(&fx.app, fx.canvas, |boxes| {
         boxes
             .iter()
-            .any(|n| n["id"] == a.to_string() && box_x(n) > 800.0)
+            .any(|n| n["id"] == a.to_string() && box_x(n) > 800.0 && n["rev"] == 2)
     })
     .await;
     let near = view(
@@ -226,3 +227,26 @@ async fn reflects_move_and_delete() {
 
     fx.handle.shutdown().await;
 }
+
+#[tokio::test]
+async fn deleting_the_canvas_drops_its_boxes() {
+    let fx = setup().await;
+    add_box(&fx.store, fx.canvas, 10.0, 10.0, 5.0, 5.0).await;
+    add_box(&fx.store, fx.canvas, 20.0, 20.0, 5.0, 5.0).await;
+    wait_for(&fx.app, fx.canvas, |boxes| boxes.len() == 2).await;
+
+    // The boxes vanish by FK cascade; the per-descendant `Deleted` events let SpatialIndex follow.
+    fx.store.delete_channel(fx.canvas).await.unwrap();
+    for _ in 0..150 {
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM canvas_box_rtree")
+            .fetch_one(fx.store.pool())
+            .await
+            .unwrap();
+        if left == 0 {
+            fx.handle.shutdown().await;
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("canvas_box_rtree rows survived the canvas delete");
+}
@@ -0,0 +1,301 @@
+//! Channel administration over HTTP (`TODO.md` #39, `design/permissions.md`): `POST /api/channels`,
+//! `PATCH`/`DELETE /api/channels/:id`, `POST /api/channels/:id/move` and the membership routes, over
+//! the real router via `oneshot`. Each takes `Action::Manage` from the channel's kind and, for creating
+//! and moving, from the destination's — `space` and `basic` grant it to admins and owners, and the
+//! top level is an operator's. A creator owns the channel it made, and joins it through the kind's
+//! `Membership`.
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::Response;
+use cp_core::auth;
+use cp_model::{ChannelId, Role, UserId, WriteCtx};
+use serde_json::json;
+
+mod common;
+use common::{harness, json_body, login, request, user, Harness};
+
+/// `POST /api/channels`, returning the response.
+async fn create(
+    h: &Harness,
+    cookie: Option<&str>,
+    type_id: &str,
+    container: Option<ChannelId>,
+    name: &str,
+) -> Response {
+    h.send(request(
+        "POST",
+        "/api/channels",
+        cookie,
+        Some(json!({ "type_id": type_id, "container": container, "payload": { "name": name } })),
+    ))
+    .await
+}
+
+async fn created(res: Response) -> ChannelId {
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await["id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn creating_takes_manage_on_the_destination() {
+    let h = harness().await;
+    let op = user(&h.core, "op").await;
+    let alice = user(&h.core, "alice").await;
+    let _bob = user(&h.core, "bob").await;
+    auth::set_operator(h.core.pool(), "op", true).await.unwrap();
+    let op_cookie = login(&h, "op").await;
+    let alice_cookie = login(&h, "alice").await;
+    let bob_cookie = login(&h, "bob").await;
+
+    // No session → 401; the top level is an operator's → 403 for anyone else.
+    let res = create(&h, None, "space", None, "server").await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = create(&h, Some(&alice_cookie), "space", None, "server").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // The operator makes a space, owns it and, through `space`'s Membership, joins it.
+    let space = created(create(&h, Some(&op_cookie), "space", None, "server").await).await;
+    assert_eq!(h.core.store().members(space).await.unwrap(), vec![op]);
+    assert_eq!(
+        h.core.store().role_grants(space).await.unwrap(),
+        vec![(op, Role::Owner)]
+    );
+
+    // The operator adds alice. A member may not create rooms in a space; an admin may, and joins each.
+    let res = h
+        .send(request(
+            "PUT",
+            &format!("/api/channels/{space}/members/{alice}"),
+            Some(&op_cookie),
+            None,
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = create(&h, Some(&alice_cookie), "basic", Some(space), "general").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    h.core
+        .store()
+        .grant_role(space, alice, Role::Admin)
+        .await
+        .unwrap();
+    let room =
+        created(create(&h, Some(&alice_cookie), "basic", Some(space), "general").await).await;
+    assert_eq!(h.core.store().members(room).await.unwrap(), vec![alice]);
+    let room = h.core.store().get_channel(room).await.unwrap().unwrap();
+    assert_eq!(room.container, Some(space));
+
+    // Bob holds nothing in the space → 403; an unknown type → 400.
+    let res = create(&h, Some(&bob_cookie), "basic", Some(space), "mine").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = create(&h, Some(&alice_cookie), "nope", Some(space), "x").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Membership is readable like any channel read.
+    let res = h
+        .send(request(
+            "GET",
+            &format!("/api/channels/{space}/members"),
+            None,
+            None,
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut members: Vec<String> = json_body(res).await["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m.as_str().unwrap().to_owned())
+        .collect();
+    members.sort();
+    let mut expected = vec![op.to_string(), alice.to_string()];
+    expected.sort();
+    assert_eq!(members, expected);
+}
+
+#[tokio::test]
+async fn patch_and_delete_take_manage_on_the_channel() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let _bob = user(&h.core, "bob").await;
+    auth::set_operator(h.core.pool(), "alice", true)
+        .await
+        .unwrap();
+    let alice_cookie = login(&h, "alice").await;
+    let bob_cookie = login(&h, "bob").await;
+    let room = created(create(&h, Some(&alice_cookie), "basic", None, "general").await).await;
+    assert_eq!(h.core.store().members(room).await.unwrap(), vec![alice]);
+
+    let rename = |cookie: &str, if_match: Option<&str>, name: &str| {
+        let mut b = Request::builder()
+            .method("PATCH")
+            .uri(format!("/api/channels/{room}"))
+            .header(header::COOKIE, cookie)
+            .header("content-type", "application/merge-patch+json");
+        if let Some(tag) = if_match {
+            b = b.header(header::IF_MATCH, tag);
+        }
+        b.body(Body::from(json!({ "name": name }).to_string()))
+            .unwrap()
+    };
+
+    // A non-member may not rename the room; a member may, and gets the new ETag.
+    let res = h.send(rename(&bob_cookie, None, "hijacked")).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = h.send(rename(&alice_cookie, Some("\"1\""), "lobby")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::ETAG], "\"2\"");
+    assert_eq!(json_body(res).await["payload"]["name"], "lobby");
+
+    // A stale tag is a 412 carrying the current one.
+    let res = h.send(rename(&alice_cookie, Some("\"1\""), "again")).await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(res.headers()[header::ETAG], "\"2\"");
+
+    // Once private, the room is a 404 to bob even for admin routes.
+    let res = h
+        .send(
+            Request::builder()
+                .method("PATCH")
+                .uri(format!("/api/channels/{room}"))
+                .header(header::COOKIE, &alice_cookie)
+                .header("content-type", "application/json-patch+json")
+                .body(Body::from(
+                    json!([{ "op": "add", "path": "/private", "value": true }]).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let delete = |cookie: &str| {
+        request(
+            "DELETE",
+            &format!("/api/channels/{room}"),
+            Some(cookie),
+            None,
+        )
+    };
+    assert_eq!(
+        h.send(delete(&bob_cookie)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // A member trashes it; it is gone from the reads.
+    assert_eq!(
+        h.send(delete(&alice_cookie)).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    assert!(h.core.store().get_channel(room).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn moving_takes_manage_on_both_ends() {
+    let h = harness().await;
+    let _op = user(&h.core, "op").await;
+    let alice = user(&h.core, "alice").await;
+    auth::set_operator(h.core.pool(), "op", true).await.unwrap();
+    let op_cookie = login(&h, "op").await;
+    let alice_cookie = login(&h, "alice").await;
+
+    let home = created(create(&h, Some(&op_cookie), "space", None, "home").await).await;
+    let away = created(create(&h, Some(&op_cookie), "space", None, "away").await).await;
+    h.core
+        .store()
+        .grant_role(home, alice, Role::Admin)
+        .await
+        .unwrap();
+    let room = created(create(&h, Some(&alice_cookie), "basic", Some(home), "general").await).await;
+    let nested =
+        created(create(&h, Some(&alice_cookie), "basic", Some(room), "thread").await).await;
+
+    let move_to = |cookie: &str, target: ChannelId, container: Option<ChannelId>| {
+        request(
+            "POST",
+            &format!("/api/channels/{target}/move"),
+            Some(cookie),
+            Some(json!({ "container": container })),
+        )
+    };
+
+    // Alice manages the room but not `away` → 403; nor the top level.
+    let res = h.send(move_to(&alice_cookie, room, Some(away))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = h.send(move_to(&alice_cookie, room, None)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Under its own descendant → 400, however well authorized.
+    let res = h.send(move_to(&alice_cookie, room, Some(nested))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Once she is an admin of `away`, the move goes through.
+    h.core
+        .store()
+        .grant_role(away, alice, Role::Admin)
+        .await
+        .unwrap();
+    let res = h.send(move_to(&alice_cookie, room, Some(away))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["container"], away.to_string());
+
+    // Revoking it takes the grant away again.
+    h.core
+        .store()
+        .revoke_role(away, alice, Role::Admin)
+        .await
+        .unwrap();
+    let res = h.send(move_to(&alice_cookie, nested, Some(away))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn membership_changes_need_manage_and_a_real_user() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let bob = user(&h.core, "bob").await;
+    auth::set_operator(h.core.pool(), "alice", true)
+        .await
+        .unwrap();
+    let alice_cookie = login(&h, "alice").await;
+    let bob_cookie = login(&h, "bob").await;
+    let room = created(create(&h, Some(&alice_cookie), "basic", None, "general").await).await;
+
+    let member = |method: &str, cookie: &str, who: &str| {
+        request(
+            method,
+            &format!("/api/channels/{room}/members/{who}"),
+            Some(cookie),
+            None,
+        )
+    };
+
+    // Bob cannot add himself; an unknown user is a 404.
+    let res = h.send(member("PUT", &bob_cookie, &bob.to_string())).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let stranger = UserId::generate();
+    let res = h
+        .send(member("PUT", &alice_cookie, &stranger.to_string()))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Alice adds him. As a member he still may not manage the room; as an admin he may.
+    let res = h.send(member("PUT", &alice_cookie, &bob.to_string())).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = h
+        .send(member("DELETE", &bob_cookie, &alice.to_string()))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    h.core
+        .store()
+        .grant_role(room, bob, Role::Admin)
+        .await
+        .unwrap();
+    let res = h
+        .send(member("DELETE", &bob_cookie, &alice.to_string()))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(h.core.store().members(room).await.unwrap(), vec![bob]);
+}
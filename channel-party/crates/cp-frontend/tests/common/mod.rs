@@ -0,0 +1,111 @@
+//! Shared by the router suites: a tempfile store with the `space` and `basic` kinds behind the real
+//! router, password users, and request helpers. Each suite compiles its own copy and uses a subset.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::response::Response;
+use axum::Router;
+use cp_core::{auth, Core, Registry};
+use cp_frontend::{router, AppState};
+use cp_model::UserId;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+pub async fn json_body(res: Response) -> Value {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+pub struct Harness {
+    _dir: tempfile::TempDir,
+    pub app: Router,
+    pub core: Arc<Core>,
+}
+
+impl Harness {
+    pub async fn send(&self, req: Request<Body>) -> Response {
+        self.app.clone().oneshot(req).await.unwrap()
+    }
+}
+
+/// A fresh store and router; the `web_dir` is the (empty) tempdir.
+pub async fn harness() -> Harness {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let registry = Registry::builder()
+        .channel(cp_space::channel())
+        .channel(cp_basic::channel())
+        .item(cp_basic::item())
+        .build();
+    let core = Arc::new(Core::open(&url, registry.clone()).await.unwrap());
+    let app = router(AppState {
+        core: core.clone(),
+        registry,
+        web_dir: dir.path().to_path_buf(),
+    });
+    Harness {
+        _dir: dir,
+        app,
+        core,
+    }
+}
+
+/// Provision a user whose password is `pw`, returning their id.
+pub async fn user(core: &Core, handle: &str) -> UserId {
+    let store = core.store();
+    let id = auth::provision_user(store.pool(), handle).await.unwrap();
+    auth::set_password(store.pool(), handle, "pw")
+        .await
+        .unwrap();
+    id
+}
+
+/// Log in and return the `cp_session=…` cookie pair.
+pub async fn login(h: &Harness, handle: &str) -> String {
+    let res = h
+        .send(request(
+            "POST",
+            "/api/auth/login",
+            None,
+            Some(json!({ "handle": handle, "password": "pw" })),
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    cookie_of(&res)
+}
+
+pub fn request(
+    method: &str,
+    uri: &str,
+    cookie: Option<&str>,
+    body: Option<Value>,
+) -> Request<Body> {
+    let mut b = Request::builder().method(method).uri(uri);
+    if let Some(c) = cookie {
+        b = b.header(header::COOKIE, c);
+    }
+    match body {
+        Some(v) => b
+            .header("content-type", "application/json")
+            .body(Body::from(v.to_string()))
+            .unwrap(),
+        None => b.body(Body::empty()).unwrap(),
+    }
+}
+
+/// The `cp_session=…` pair from a response's `Set-Cookie`.
+pub fn cookie_of(res: &Response) -> String {
+    res.headers()
+        .get(header::SET_COOKIE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_owned()
+}
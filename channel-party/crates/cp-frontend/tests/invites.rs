@@ -0,0 +1,167 @@
+//! Invite codes and self-registration over HTTP (`TODO.md` #41, `design/auth.md`): `POST
+//! /api/auth/register` spends an invite and signs the new user in, and `/api/invites` mints, lists and
+//! withdraws codes — anything for an operator, only for channels they manage for anyone else.
+
+use axum::http::StatusCode;
+use axum::response::Response;
+use cp_core::{auth, Core};
+use cp_model::{ChannelId, NewChannel, Role, TypeId, UserId, WriteCtx};
+use serde_json::{json, Value};
+
+mod common;
+use common::{cookie_of, harness, json_body, login, request, user, Harness};
+
+async fn room(core: &Core, name: &str) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("basic"),
+            container: None,
+            payload: json!({ "name": name }),
+        })
+        .await
+        .unwrap()
+}
+
+/// `POST /api/invites` as `cookie`, returning the response.
+async fn mint(h: &Harness, cookie: &str, body: Value) -> Response {
+    h.send(request("POST", "/api/invites", Some(cookie), Some(body)))
+        .await
+}
+
+async fn register(h: &Harness, code: &str, handle: &str, password: &str) -> Response {
+    h.send(request(
+        "POST",
+        "/api/auth/register",
+        None,
+        Some(json!({ "code": code, "handle": handle, "password": password })),
+    ))
+    .await
+}
+
+#[tokio::test]
+async fn registering_spends_an_invite_and_signs_in() {
+    let h = harness().await;
+    user(&h.core, "op").await;
+    auth::set_operator(h.core.pool(), "op", true).await.unwrap();
+    let op_cookie = login(&h, "op").await;
+    let lobby = room(&h.core, "lobby").await;
+
+    // Without a session there is no minting; without a code there is no registering.
+    let res = h
+        .send(request("POST", "/api/invites", None, Some(json!({}))))
+        .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = register(&h, "not-a-code", "alice", "correct horse").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // The operator mints a one-use invite into the lobby; the code comes back once.
+    let res = mint(&h, &op_cookie, json!({ "channels": [lobby] })).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let minted = json_body(res).await;
+    let code = minted["code"].as_str().unwrap().to_owned();
+    assert_eq!(minted["invite"]["uses_left"], 1);
+
+    // Policy failures are 400s and spend nothing.
+    let res = register(&h, &code, "Alice!", "correct horse").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = register(&h, &code, "alice", "short").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = register(&h, &code, "op", "correct horse").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Registering creates alice, signs her in and joins her to the lobby.
+    let res = register(&h, &code, "alice", "correct horse").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let cookie = cookie_of(&res);
+    let alice: UserId = json_body(res).await["id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let res = h
+        .send(request("GET", "/api/auth/me", Some(&cookie), None))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["handle"], "alice");
+    assert_eq!(h.core.store().members(lobby).await.unwrap(), vec![alice]);
+
+    // The code is spent.
+    let res = register(&h, &code, "bob", "correct horse").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        json_body(res).await["error"],
+        "invalid payload: invalid invite code"
+    );
+}
+
+#[tokio::test]
+async fn channel_admins_mint_for_their_channels_only() {
+    let h = harness().await;
+    user(&h.core, "op").await;
+    let bob = user(&h.core, "bob").await;
+    user(&h.core, "carol").await;
+    auth::set_operator(h.core.pool(), "op", true).await.unwrap();
+    let op_cookie = login(&h, "op").await;
+    let bob_cookie = login(&h, "bob").await;
+    let carol_cookie = login(&h, "carol").await;
+    let lobby = room(&h.core, "lobby").await;
+
+    // Bob may not mint a bare invite, nor one into a room he does not manage.
+    let res = mint(&h, &bob_cookie, json!({})).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = mint(&h, &bob_cookie, json!({ "channels": [lobby] })).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // As the lobby's admin he may.
+    h.core
+        .store()
+        .grant_role(lobby, bob, Role::Admin)
+        .await
+        .unwrap();
+    let res = mint(
+        &h,
+        &bob_cookie,
+        json!({ "uses": 5, "expires_in_hours": 2, "channels": [lobby] }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let invite = json_body(res).await["invite"]["id"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let res = mint(&h, &op_cookie, json!({ "uses": 0 })).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    // A lifetime past the cap, or one too large to add to the clock, is a 400, not a panic.
+    for hours in [24 * 366, u64::MAX] {
+        let res = mint(&h, &op_cookie, json!({ "expires_in_hours": hours })).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+    let res = mint(&h, &op_cookie, json!({})).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+
+    // Bob lists his own; the operator lists every open invite.
+    let count = |cookie: String| {
+        let h = &h;
+        async move {
+            let res = h
+                .send(request("GET", "/api/invites", Some(&cookie), None))
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            json_body(res).await["invites"].as_array().unwrap().len()
+        }
+    };
+    assert_eq!(count(bob_cookie.clone()).await, 1);
+    assert_eq!(count(op_cookie.clone()).await, 2);
+
+    // Someone else's invite is a 404 to carol; bob withdraws his own.
+    let uri = format!("/api/invites/{invite}");
+    let res = h
+        .send(request("DELETE", &uri, Some(&carol_cookie), None))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = h
+        .send(request("DELETE", &uri, Some(&bob_cookie), None))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(count(bob_cookie).await, 0);
+}
@@ -0,0 +1,97 @@
+//! `GET /api/items/:id/revisions` over the real router via `oneshot`: an item's superseded states with
+//! the acting principal, oldest first; still readable after the item is deleted; 404 for an unknown id.
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use cp_core::{Core, Registry};
+use cp_frontend::{router, AppState};
+use cp_model::{Actor, NewChannel, NewItem, TypeId, UserId, WriteCtx};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn json_body(res: Response) -> Value {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+fn get(uri: String) -> Request<Body> {
+    Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn lists_revisions_with_actor_and_survives_delete() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let registry = Registry::builder()
+        .channel(cp_basic::channel())
+        .item(cp_basic::item())
+        .build();
+    let core = Arc::new(Core::open(&url, registry.clone()).await.unwrap());
+    let app = router(AppState {
+        core: core.clone(),
+        registry,
+        web_dir: dir.path().to_path_buf(),
+    });
+
+    let store = core.store();
+    let room = store
+        .create_channel(NewChannel {
+            type_id: TypeId::new("basic"),
+            container: None,
+            payload: json!({ "name": "room" }),
+        })
+        .await
+        .unwrap();
+    let iid = store
+        .create_item(NewItem {
+            type_id: TypeId::new("basic"),
+            container: Some(room),
+            external_key: None,
+            payload: json!({ "body": "before" }),
+        })
+        .await
+        .unwrap();
+
+    let res = app
+        .clone()
+        .oneshot(get(format!("/api/items/{iid}/revisions")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["revisions"], json!([]));
+
+    let alice = UserId::generate();
+    store
+        .acting_as(Actor::User(alice))
+        .set_item_payload(iid, json!({ "body": "after" }))
+        .await
+        .unwrap();
+    store.delete_item(iid).await.unwrap();
+
+    let res = app
+        .clone()
+        .oneshot(get(format!("/api/items/{iid}/revisions")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let revs = json_body(res).await["revisions"].clone();
+    assert_eq!(revs[0]["rev"], 1);
+    assert_eq!(revs[0]["payload"]["body"], "before");
+    assert_eq!(revs[0]["container"], room.to_string());
+    assert_eq!(
+        revs[0]["actor"],
+        json!({ "kind": "user", "id": alice.to_string() })
+    );
+    assert_eq!(revs[1]["payload"]["body"], "after");
+    assert_eq!(revs[1]["actor"], json!({ "kind": "system" }));
+
+    let res = app
+        .oneshot(get(format!("/api/items/{}/revisions", UserId::generate())))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -1,6 +1,7 @@
 //! SSE live-update slice (`TODO.md` #13): subscribe to `GET /api/events?scope=…`, then a committed
 //! write on the same core must surface as a `change` event on the stream. Drives the real Router +
-//! broadcast bus, bounded by timeouts so a wiring regression fails fast instead of hanging.
+//! broadcast bus, bounded by timeouts so a wiring regression fails fast instead of hanging. Frames carry
+//! their change-log `seq` as the SSE `id`, and `Last-Event-ID` replays what a reconnecting client missed.
 
 use std::sync::Arc;
 use std::time::Duration;
@@ -173,3 +174,87 @@ async fn scope_filters_out_other_channels() {
         "out-of-scope channel's event leaked through: {buf}"
     );
 }
+
+#[tokio::test]
+async fn last_event_id_replays_missed_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let registry = Registry::builder()
+        .channel(cp_basic::channel())
+        .item(cp_basic::item())
+        .build();
+    let core = Arc::new(Core::open(&url, registry.clone()).await.unwrap());
+    let cid = core
+        .store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("basic"),
+            container: None,
+            payload: serde_json::json!({}),
+        })
+        .await
+        .unwrap();
+    let item = |body: &str| NewItem {
+        type_id: TypeId::new("basic"),
+        container: Some(cid),
+        external_key: None,
+        payload: serde_json::json!({ "body": body }),
+    };
+    // The client saw up to the first item, then dropped; two more commit while it is away.
+    let seen = core.store().create_item(item("seen")).await.unwrap();
+    let seen_seq = cp_core::change_log::head(core.pool()).await.unwrap();
+    let missed = core.store().create_item(item("missed")).await.unwrap();
+    let also = core.store().create_item(item("also")).await.unwrap();
+
+    let app = router(AppState {
+        core: core.clone(),
+        registry,
+        web_dir: dir.path().to_path_buf(),
+    });
+    let res = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/events?scope={cid}"))
+                .header("last-event-id", seen_seq.to_string())
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let mut stream = res.into_body().into_data_stream();
+    let mut buf = String::new();
+    timeout(Duration::from_secs(5), async {
+        while let Some(chunk) = stream.next().await {
+            buf.push_str(&String::from_utf8_lossy(&chunk.unwrap()));
+            if buf.contains(&also.to_string()) {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("replay arrived");
+    assert!(!buf.contains(&seen.to_string()), "already seen: {buf}");
+    let (m, a) = (
+        buf.find(&missed.to_string()).expect("missed replayed"),
+        buf.find(&also.to_string()).unwrap(),
+    );
+    assert!(m < a, "replayed in commit order: {buf}");
+    assert!(
+        buf.contains(&format!("id: {}", seen_seq + 1)),
+        "frame: {buf}"
+    );
+
+    let bad = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/events")
+                .header("last-event-id", "yesterday")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(bad.status(), StatusCode::BAD_REQUEST);
+}
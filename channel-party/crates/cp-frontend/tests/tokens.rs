@@ -0,0 +1,206 @@
+//! Personal access tokens over HTTP (`TODO.md` #42, `design/auth.md`): a session mints, lists and
+//! revokes its user's tokens at `/api/auth/tokens`, and a request signed `Authorization: Bearer` acts as
+//! that user within the token's scopes — `read`/`post`/`manage`, anywhere or under one channel.
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use cp_core::{auth, Core};
+use cp_model::{ChannelId, NewChannel, Role, TypeId, WriteCtx};
+use serde_json::{json, Value};
+
+mod common;
+use common::{harness, json_body, login, request, user, Harness};
+
+/// A request signed with an API token instead of a cookie.
+fn bearer(method: &str, uri: &str, token: &str, body: Option<Value>) -> Request<Body> {
+    let mut req = request(method, uri, None, body);
+    req.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Bearer {token}").parse().unwrap(),
+    );
+    req
+}
+
+async fn room(core: &Core, payload: Value) -> ChannelId {
+    core.store()
+        .create_channel(NewChannel {
+            type_id: TypeId::new("basic"),
+            container: None,
+            payload,
+        })
+        .await
+        .unwrap()
+}
+
+/// `POST /api/auth/tokens` as `cookie`, returning the token's id and secret.
+async fn mint(h: &Harness, cookie: &str, body: Value) -> (String, String) {
+    let res = h
+        .send(request(
+            "POST",
+            "/api/auth/tokens",
+            Some(cookie),
+            Some(body),
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body = json_body(res).await;
+    (
+        body["token"]["id"].as_str().unwrap().to_owned(),
+        body["secret"].as_str().unwrap().to_owned(),
+    )
+}
+
+/// The URI and body of `POST /api/channels/{cid}/items` for one message.
+fn post_item(cid: ChannelId) -> (String, Option<Value>) {
+    (
+        format!("/api/channels/{cid}/items"),
+        Some(json!({ "type_id": "basic", "payload": { "text": "hi" } })),
+    )
+}
+
+#[tokio::test]
+async fn a_bearer_token_acts_within_its_scopes() {
+    let h = harness().await;
+    let alice = user(&h.core, "alice").await;
+    let cookie = login(&h, "alice").await;
+    let lobby = room(&h.core, json!({ "name": "lobby" })).await;
+    let den = room(&h.core, json!({ "name": "den", "private": true })).await;
+    for cid in [lobby, den] {
+        h.core
+            .store()
+            .grant_role(cid, alice, Role::Member)
+            .await
+            .unwrap();
+    }
+
+    let (_, reader) = mint(&h, &cookie, json!({ "name": "reader", "scopes": ["read"] })).await;
+    let (_, poster) = mint(
+        &h,
+        &cookie,
+        json!({ "name": "bot", "scopes": [format!("post:{lobby}")], "expires_in_days": 30 }),
+    )
+    .await;
+
+    // A token signs the request in as its user; a bad one is a 401 even on a public read.
+    let res = h.send(bearer("GET", "/api/auth/me", &reader, None)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["handle"], "alice");
+    let uri = format!("/api/channels/{lobby}");
+    let res = h.send(bearer("GET", &uri, "cpt_nope", None)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // `read` reads what alice can, but cannot post.
+    let res = h
+        .send(bearer(
+            "GET",
+            &format!("/api/channels/{den}"),
+            &reader,
+            None,
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let (uri, body) = post_item(lobby);
+    let res = h.send(bearer("POST", &uri, &reader, body)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // `post:<lobby>` posts there only, and sees the private den as a visitor would: not at all.
+    let (uri, body) = post_item(lobby);
+    let res = h.send(bearer("POST", &uri, &poster, body)).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let (uri, body) = post_item(den);
+    let res = h.send(bearer("POST", &uri, &poster, body)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = h
+        .send(bearer(
+            "GET",
+            &format!("/api/channels/{den}"),
+            &poster,
+            None,
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Alice's own session is not narrowed.
+    let (uri, body) = post_item(den);
+    let res = h.send(request("POST", &uri, Some(&cookie), body)).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn tokens_are_managed_from_a_session_only() {
+    let h = harness().await;
+    user(&h.core, "op").await;
+    user(&h.core, "bob").await;
+    auth::set_operator(h.core.pool(), "op", true).await.unwrap();
+    let cookie = login(&h, "op").await;
+    let bob_cookie = login(&h, "bob").await;
+
+    let res = h
+        .send(request(
+            "POST",
+            "/api/auth/tokens",
+            Some(&cookie),
+            Some(json!({ "name": "ci", "scopes": ["write"] })),
+        ))
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    // A lifetime past the cap, or one too large to add to the clock, is a 400, not a panic.
+    for days in [5 * 366, u64::MAX] {
+        let body = json!({ "name": "ci", "scopes": ["read"], "expires_in_days": days });
+        let res = h
+            .send(request(
+                "POST",
+                "/api/auth/tokens",
+                Some(&cookie),
+                Some(body),
+            ))
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+    let (id, secret) = mint(&h, &cookie, json!({ "name": "ci", "scopes": ["manage"] })).await;
+    let (_, narrow) = mint(&h, &cookie, json!({ "name": "ro", "scopes": ["read"] })).await;
+
+    // A token cannot manage tokens, and only unscoped `manage` acts as the operator.
+    let res = h
+        .send(bearer("GET", "/api/auth/tokens", &secret, None))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = h
+        .send(bearer("GET", "/api/admin/runtimes", &secret, None))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = h
+        .send(bearer("GET", "/api/admin/runtimes", &narrow, None))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    // Nor may a narrower token list or withdraw invites.
+    let res = h.send(bearer("GET", "/api/invites", &secret, None)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    for (method, uri) in [("GET", "/api/invites"), ("DELETE", "/api/invites/x")] {
+        let res = h.send(bearer(method, uri, &narrow, None)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    // The listing shows use but never the secret.
+    let res = h
+        .send(request("GET", "/api/auth/tokens", Some(&cookie), None))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let listed = json_body(res).await;
+    let tokens = listed["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0]["scopes"], json!(["manage"]));
+    assert!(tokens[0]["last_used_at"].is_string());
+    assert!(!listed.to_string().contains(&secret));
+
+    // Someone else's token is a 404; revoking signs the token out.
+    let uri = format!("/api/auth/tokens/{id}");
+    let res = h
+        .send(request("DELETE", &uri, Some(&bob_cookie), None))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = h.send(request("DELETE", &uri, Some(&cookie), None)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = h.send(bearer("GET", "/api/auth/me", &secret, None)).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
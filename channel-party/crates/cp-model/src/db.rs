@@ -0,0 +1,85 @@
+//! The database handle core hands escape-hatch kinds (§6), and the two backends it can be. Core and the
+//! kinds share one code path through sqlx's `Any` driver: a query is written once with `$1`-style
+//! placeholders (which both sqlite and Postgres accept) and only genuinely dialect-specific SQL — a
+//! kind's schema, an R-tree or FTS query — branches on [`Backend`]. See DESIGN §3/§6.
+
+use std::fmt::Display;
+
+use sqlx::any::{Any, AnyArguments};
+use sqlx::query::Query;
+use sqlx::{Arguments, Encode, Type};
+
+/// The database a pool is connected to, chosen by the `CP_DB` URL scheme (`sqlite:` or
+/// `postgres://`). Kinds branch on it for SQL one dialect lacks, and ship a migration list per backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    /// The backend a database URL names, or `None` for a scheme channel-party does not support.
+    pub fn from_url(url: &str) -> Option<Backend> {
+        let scheme = url.split(':').next()?;
+        match scheme {
+            "sqlite" => Some(Backend::Sqlite),
+            "postgres" | "postgresql" => Some(Backend::Postgres),
+            _ => None,
+        }
+    }
+
+    /// The backend `pool` is connected to. Every pool core opens has a supported scheme.
+    pub fn of(pool: &sqlx::AnyPool) -> Backend {
+        let url = pool.connect_options().database_url.to_string();
+        Backend::from_url(&url).unwrap_or(Backend::Sqlite)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Backend::Sqlite => "sqlite",
+            Backend::Postgres => "postgres",
+        }
+    }
+}
+
+/// A dynamic query for either backend: `sqlx::QueryBuilder`'s `push` / `push_bind` / `build`, except
+/// that binds are numbered (`$1`, `$2`, …) and owned. `QueryBuilder<Any>` emits `?`, which Postgres
+/// rejects.
+pub struct QueryBuilder {
+    sql: String,
+    args: AnyArguments<'static>,
+}
+
+impl QueryBuilder {
+    pub fn new(init: impl Into<String>) -> Self {
+        Self {
+            sql: init.into(),
+            args: AnyArguments::default(),
+        }
+    }
+
+    /// Append raw SQL. Never user input: that goes through [`push_bind`](Self::push_bind).
+    pub fn push(&mut self, sql: impl Display) -> &mut Self {
+        use std::fmt::Write;
+        write!(self.sql, "{sql}").expect("writing to a String cannot fail");
+        self
+    }
+
+    /// Append the next placeholder and bind `value` to it.
+    pub fn push_bind<T>(&mut self, value: T) -> &mut Self
+    where
+        T: 'static + Encode<'static, Any> + Type<Any>,
+    {
+        self.args
+            .add(value)
+            .expect("encoding into AnyArguments cannot fail");
+        let n = self.args.len();
+        self.push(format_args!("${n}"))
+    }
+
+    /// The finished query, ready to execute. The binds move into it, so build once.
+    pub fn build(&mut self) -> Query<'_, Any, AnyArguments<'_>> {
+        let args: AnyArguments<'_> = std::mem::take(&mut self.args);
+        sqlx::query_with(&self.sql, args)
+    }
+}
@@ -17,6 +17,9 @@ pub struct Channel {
     pub type_id: TypeId,
     pub container: Option<ChannelId>,
     pub payload: Json,
+    /// The optimistic-concurrency revision: 1 at creation, bumped by every overwrite. What the
+    /// conditional setters (`set_channel_payload_if`) compare against. §3.
+    pub rev: i64,
 }
 
 /// A content envelope — everything that lives in a container. `external_key` is the dedup/upsert
@@ -28,6 +31,8 @@ pub struct Item {
     pub container: Option<ChannelId>,
     pub external_key: Option<String>,
     pub payload: Json,
+    /// The optimistic-concurrency revision, as on [`Channel`]. §3.
+    pub rev: i64,
 }
 
 /// The one native principal. Not a super-type because it is not extensible: there is exactly one
@@ -47,3 +52,55 @@ pub struct UserExternalLink {
     pub user_id: UserId,
     pub item_id: ItemId,
 }
+
+/// Who made a change: a native user (the HTTP API), a runtime component (by its `name()`), the
+/// operator at the debug shell, or core itself (an unattributed write). Recorded on every envelope
+/// revision; stored as its `Display` form (`user:<ulid>`, `component:<name>`, `operator`, `system`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "id", rename_all = "kebab-case")]
+pub enum Actor {
+    System,
+    Operator,
+    User(UserId),
+    Component(String),
+}
+
+impl std::fmt::Display for Actor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Actor::System => f.write_str("system"),
+            Actor::Operator => f.write_str("operator"),
+            Actor::User(id) => write!(f, "user:{id}"),
+            Actor::Component(name) => write!(f, "component:{name}"),
+        }
+    }
+}
+
+impl std::str::FromStr for Actor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "system" => Ok(Actor::System),
+            None if s == "operator" => Ok(Actor::Operator),
+            Some(("user", id)) => id
+                .parse()
+                .map(Actor::User)
+                .map_err(|_| format!("invalid actor user id: {id}")),
+            Some(("component", name)) => Ok(Actor::Component(name.to_owned())),
+            _ => Err(format!("invalid actor: {s}")),
+        }
+    }
+}
+
+/// One superseded version of a channel or item: the `payload` and `container` it had before the
+/// change numbered `rev` (1-based, per envelope), who made that change, and when. Append-only — an
+/// update, reparent, revert, or delete records the prior state; nothing rewrites history.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Revision {
+    pub rev: i64,
+    pub payload: Json,
+    pub container: Option<ChannelId>,
+    pub actor: Actor,
+    pub recorded_at: String,
+}
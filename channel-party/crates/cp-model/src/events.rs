@@ -23,6 +23,10 @@ pub enum EnvelopeRef {
 /// component `interests` filter on. Carries no payload — a consumer that needs it does a point read. §7.
 #[derive(Clone, Debug)]
 pub struct ChangeEvent {
+    /// Position in core's durable change log, assigned in the mutation's own transaction (0 until the
+    /// write path commits it). Strictly increasing in commit order, so a consumer resumes after the
+    /// last `seq` it handled.
+    pub seq: i64,
     pub op: ChangeOp,
     pub target: EnvelopeRef,
     pub type_id: TypeId,
@@ -10,7 +10,7 @@ use crate::envelope::{Channel, Item, Json};
 use crate::ids::{TypeId, UserId};
 use crate::store::StoreCtx;
 use crate::write::WriteCtx;
-use crate::Result;
+use crate::{Error, Result};
 
 /// A kind-declared projection of searchable / sortable fields, applied transactionally on write
 /// into core's built-in index substrates. A kind that needs no indexing returns `None`. §6.
@@ -23,6 +23,9 @@ pub struct IndexEntry {
     pub sort_key: Option<String>,
     /// A coordinate for the 2D / R-tree substrate (e.g. `canvas-text-box`).
     pub coord: Option<(f64, f64)>,
+    /// A `(width, height)` spanning from `coord`, making it a rectangle rather than a point. Ignored
+    /// without `coord`.
+    pub extent: Option<(f64, f64)>,
 }
 
 /// Behavior for one `channel-type:*`. §4.
@@ -60,11 +63,27 @@ pub trait ChannelKind: Send + Sync {
         None
     }
 
-    /// Debug-shell commands this kind contributes, each flagged read/write. §8.
+    /// Debug-shell commands this kind contributes, each flagged read/write. Invoked as
+    /// `<namespace> <name> <args…>`, the namespace being the type id's ([`TypeId::namespace`]). §8.
     fn debug_commands(&self) -> Vec<DebugCommand> {
         Vec::new()
     }
 
+    /// Run one of this kind's [`debug_commands`](Self::debug_commands) with its whitespace-split
+    /// arguments, returning the text to print. The shell has already applied the write-mode gate from the
+    /// declared `DebugAccess`, so a `Read` command must not write through `cx`. §8.
+    async fn run_debug_command(
+        &self,
+        _cx: &dyn WriteCtx,
+        command: &str,
+        _args: &[String],
+    ) -> Result<String> {
+        Err(Error::Validation(format!(
+            "`{}` has no debug command `{command}`",
+            self.type_id()
+        )))
+    }
+
     /// A one-line human summary for the debug shell. §8.
     fn debug_summary(&self, _ch: &Channel) -> Option<String> {
         None
@@ -72,8 +91,9 @@ pub trait ChannelKind: Send + Sync {
 }
 
 /// Behavior for one `item-type:*` — the same shape as [`ChannelKind`] minus `contents` and
-/// `membership` (items are inert content, never containers or principals). All its methods are
-/// synchronous, so it is a plain trait. §4.
+/// `membership` (items are inert content, never containers or principals). All its methods but the
+/// debug-command hook are synchronous. §4.
+#[async_trait]
 pub trait ItemKind: Send + Sync {
     fn type_id(&self) -> &TypeId;
 
@@ -93,6 +113,25 @@ pub trait ItemKind: Send + Sync {
         payload
     }
 
+    /// Debug-shell commands this kind contributes; see [`ChannelKind::debug_commands`]. §8.
+    fn debug_commands(&self) -> Vec<DebugCommand> {
+        Vec::new()
+    }
+
+    /// Run one of this kind's [`debug_commands`](Self::debug_commands); see
+    /// [`ChannelKind::run_debug_command`]. §8.
+    async fn run_debug_command(
+        &self,
+        _cx: &dyn WriteCtx,
+        command: &str,
+        _args: &[String],
+    ) -> Result<String> {
+        Err(Error::Validation(format!(
+            "`{}` has no debug command `{command}`",
+            self.type_id()
+        )))
+    }
+
     fn debug_summary(&self, _item: &Item) -> Option<String> {
         None
     }
@@ -113,7 +152,8 @@ pub trait Membership: Send + Sync {
 /// [`SuperType`](crate::store::SuperType)), distinct from the open-ended kind set. §18.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Action {
-    /// Read a channel's contents. Defined for completeness; reads are not gated yet (§18 enforces writes).
+    /// Read a channel: its envelope, its contents, and the items in it. A channel is visible only when
+    /// it and every channel above it grant `View` (§18).
     View,
     /// Create an item (post a message) in the channel.
     Post,
@@ -134,4 +174,15 @@ pub trait Permission: Send + Sync {
         user: UserId,
         action: Action,
     ) -> Result<bool>;
+
+    /// May a visitor with no session perform `action`? Only reads are ever asked — every write needs a
+    /// session. Default: no, so a kind opts in to anonymous readers, as it opts in to everything else.
+    async fn authorize_anonymous(
+        &self,
+        _cx: &dyn StoreCtx,
+        _ch: &Channel,
+        _action: Action,
+    ) -> Result<bool> {
+        Ok(false)
+    }
 }
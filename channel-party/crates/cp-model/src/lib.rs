@@ -7,25 +7,29 @@
 //! TODO(ts-rs): the payload / query / response structs each kind defines should derive `ts-rs::TS`
 //! so a kind can emit its TS types into `web/` (DESIGN §9). Deferred; not wired in the scaffold.
 
+pub mod db;
 pub mod debug;
 pub mod envelope;
 pub mod events;
 pub mod ids;
 pub mod kind;
 pub mod migration;
+pub mod role;
 pub mod runtime;
 pub mod store;
 pub mod write;
 
+pub use db::{Backend, QueryBuilder};
 pub use debug::{DebugAccess, DebugCommand};
-pub use envelope::{Channel, Item, Json, User, UserExternalLink};
+pub use envelope::{Actor, Channel, Item, Json, Revision, User, UserExternalLink};
 pub use events::{ChangeEvent, ChangeOp, EnvelopeRef};
 pub use ids::{ChannelId, ItemId, TypeId, UserId};
 pub use kind::{Action, ChannelKind, IndexEntry, ItemKind, Membership, Permission};
 pub use migration::{Migration, Migrations};
+pub use role::{Grant, Role, RolePermission};
 pub use runtime::{Interests, RuntimeComponent, RuntimeCtx, RuntimeEvent, WriteScope};
-pub use store::{Cursor, Filter, Node, NodePage, Order, Page, StoreCtx, SuperType};
-pub use write::{NewChannel, NewItem, Upsert, WriteCtx};
+pub use store::{Cursor, Filter, Node, NodePage, Order, Page, Rect, StoreCtx, SuperType};
+pub use write::{Batch, Mutation, NewChannel, NewItem, Patch, Upsert, WriteCtx};
 
 /// Crate-wide result type. Kind capabilities and store primitives return this.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -39,6 +43,9 @@ pub enum Error {
     /// A referenced envelope does not exist.
     #[error("not found")]
     NotFound,
+    /// A conditional write named a revision the envelope has since moved past. §3.
+    #[error("revision conflict: expected rev {expected}, current rev is {current}")]
+    Conflict { expected: i64, current: i64 },
     /// Anything else, kept opaque to core.
     #[error("{0}")]
     Other(String),
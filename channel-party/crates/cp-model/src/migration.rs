@@ -3,13 +3,37 @@
 //! RuntimeComponent (writer) + `contents` (reader) — that core never learns the shape of. Part of
 //! the plugin interface, so it lives here (kinds depend only on `cp-model`). See DESIGN §6.
 
-/// One migration: a name and its SQL. Kinds typically build these with `include_str!`.
+use crate::db::Backend;
+
+/// One migration: a name and its SQL. Kinds typically build these with `include_str!`. Once shipped, a
+/// migration is immutable — the migrator records a checksum of `sql` and refuses to boot if it changes;
+/// evolve a schema by appending a new migration instead.
 #[derive(Clone, Copy, Debug)]
 pub struct Migration {
     pub name: &'static str,
     pub sql: &'static str,
 }
 
-/// A crate's set of migrations, contributed at the composition root via `.migrations(...)`. §6/§10.
+/// A crate's ordered set of migrations, contributed at the composition root via `.migrations(...)`.
+/// `owner` namespaces the set in core's `_migrations` ledger (by convention the kind's type namespace,
+/// e.g. `"canvas"`), so each crate versions independently of core and of every other crate. §6/§10.
+///
+/// `steps` is the sqlite schema and `postgres` the same schema in Postgres's dialect; the migrator runs
+/// whichever list matches the backend, and each list is versioned on its own. A set with no `postgres`
+/// steps is sqlite-only, and booting it against Postgres is refused.
 #[derive(Clone, Copy, Debug)]
-pub struct Migrations(pub &'static [Migration]);
+pub struct Migrations {
+    pub owner: &'static str,
+    pub steps: &'static [Migration],
+    pub postgres: &'static [Migration],
+}
+
+impl Migrations {
+    /// The steps to run against `backend`.
+    pub fn steps_for(&self, backend: Backend) -> &'static [Migration] {
+        match backend {
+            Backend::Sqlite => self.steps,
+            Backend::Postgres => self.postgres,
+        }
+    }
+}
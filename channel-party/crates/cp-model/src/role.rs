@@ -0,0 +1,190 @@
+//! Channel roles (§18, `design/permissions.md`): named grants a native user holds on a channel, kept in
+//! core's `channel_roles` substrate and inherited down the container chain. [`RolePermission`] is a
+//! ready-made [`Permission`] over them, for kinds whose policy is "who holds which role".
+
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+
+use crate::envelope::Channel;
+use crate::ids::{ChannelId, UserId};
+use crate::kind::{Action, Permission};
+use crate::store::StoreCtx;
+use crate::{Error, Result};
+
+/// A role on a channel. The four built-ins are ranked — an owner outranks an admin, an admin a
+/// moderator, a moderator a member — and a holder of one satisfies any requirement at or below it. A
+/// custom role is unranked: only its own holders satisfy it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    Owner,
+    Admin,
+    Moderator,
+    Member,
+    Custom(String),
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Owner => "owner",
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+            Role::Member => "member",
+            Role::Custom(name) => name,
+        }
+    }
+
+    fn rank(&self) -> Option<u8> {
+        match self {
+            Role::Owner => Some(4),
+            Role::Admin => Some(3),
+            Role::Moderator => Some(2),
+            Role::Member => Some(1),
+            Role::Custom(_) => None,
+        }
+    }
+
+    /// Whether holding `self` meets a requirement for `needed`.
+    pub fn satisfies(&self, needed: &Role) -> bool {
+        match (self.rank(), needed.rank()) {
+            (Some(held), Some(needed)) => held >= needed,
+            _ => self == needed,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the built-in names; any other non-empty name of ASCII letters, digits, `-` and `_` is a
+/// custom role.
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "owner" => Role::Owner,
+            "admin" => Role::Admin,
+            "moderator" => Role::Moderator,
+            "member" => Role::Member,
+            "" => return Err(Error::Validation("a role needs a name".to_owned())),
+            name if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                return Err(Error::Validation(format!(
+                    "role `{name}` may only contain ASCII letters, digits, `-` and `_`"
+                )))
+            }
+            name => Role::Custom(name.to_owned()),
+        })
+    }
+}
+
+/// Who a [`RolePermission`] lets take one action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Grant {
+    /// No one.
+    Nobody,
+    /// Everyone, signed in or not.
+    Anyone,
+    /// Holders of this role (or, for a built-in, a higher one) on the channel or any channel above it.
+    AtLeast(Role),
+}
+
+impl Grant {
+    /// Whether `user` (`None` = anonymous) is granted on `ch`.
+    pub async fn allows(
+        &self,
+        cx: &dyn StoreCtx,
+        ch: ChannelId,
+        user: Option<UserId>,
+    ) -> Result<bool> {
+        match (self, user) {
+            (Grant::Nobody, _) => Ok(false),
+            (Grant::Anyone, _) => Ok(true),
+            (Grant::AtLeast(_), None) => Ok(false),
+            (Grant::AtLeast(needed), Some(user)) => Ok(cx
+                .roles(ch, user)
+                .await?
+                .iter()
+                .any(|held| held.satisfies(needed))),
+        }
+    }
+}
+
+/// A ready-made [`Permission`]: one [`Grant`] per action, decided against the caller's inherited roles.
+/// The default lets anyone view, members post and admins manage; the setters adjust one action each.
+#[derive(Clone, Debug)]
+pub struct RolePermission {
+    view: Grant,
+    post: Grant,
+    manage: Grant,
+}
+
+impl Default for RolePermission {
+    fn default() -> Self {
+        Self {
+            view: Grant::Anyone,
+            post: Grant::AtLeast(Role::Member),
+            manage: Grant::AtLeast(Role::Admin),
+        }
+    }
+}
+
+impl RolePermission {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn view(mut self, grant: Grant) -> Self {
+        self.view = grant;
+        self
+    }
+
+    pub fn post(mut self, grant: Grant) -> Self {
+        self.post = grant;
+        self
+    }
+
+    pub fn manage(mut self, grant: Grant) -> Self {
+        self.manage = grant;
+        self
+    }
+
+    /// The grant for `action`.
+    pub fn grant(&self, action: Action) -> &Grant {
+        match action {
+            Action::View => &self.view,
+            Action::Post => &self.post,
+            Action::Manage => &self.manage,
+        }
+    }
+}
+
+#[async_trait]
+impl Permission for RolePermission {
+    async fn authorize(
+        &self,
+        cx: &dyn StoreCtx,
+        ch: &Channel,
+        user: UserId,
+        action: Action,
+    ) -> Result<bool> {
+        self.grant(action).allows(cx, ch.id, Some(user)).await
+    }
+
+    async fn authorize_anonymous(
+        &self,
+        cx: &dyn StoreCtx,
+        ch: &Channel,
+        action: Action,
+    ) -> Result<bool> {
+        self.grant(action).allows(cx, ch.id, None).await
+    }
+}
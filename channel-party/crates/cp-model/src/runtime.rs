@@ -7,7 +7,7 @@ use async_trait::async_trait;
 
 use crate::events::ChangeEvent;
 use crate::ids::{ChannelId, ItemId, TypeId};
-use crate::{Channel, Item, Node, Result, WriteCtx};
+use crate::{Channel, Cursor, Item, Json, Node, NodePage, Result, WriteCtx};
 
 /// What a component reacts to: a schedule, and/or the change stream filtered by envelope type. §7.
 #[derive(Clone, Debug, Default)]
@@ -59,13 +59,26 @@ pub trait RuntimeCtx: Send + Sync {
     /// stream carries only *new* changes, so a component reconstructs prior state through this. §7.
     async fn scan(&self, types: &[TypeId]) -> Result<Vec<Node>>;
 
+    /// One page of [`scan`](Self::scan): the envelopes of `types` after `cursor`, id-ordered, plus the
+    /// cursor of the next page (`None` once exhausted). Checkpoint the returned cursor and an interrupted
+    /// backfill resumes where it stopped instead of re-reading every envelope. §7.
+    async fn scan_since(&self, types: &[TypeId], cursor: Cursor) -> Result<NodePage>;
+
+    /// Persist this component's progress (any JSON), replacing the previous checkpoint. §7.
+    async fn checkpoint(&self, value: Json) -> Result<()>;
+
+    /// The last [`checkpoint`](Self::checkpoint), or `None` if there is none yet. Core drops it whenever a
+    /// reset is due, so "no checkpoint" always means "start from scratch". §7.
+    async fn last_checkpoint(&self) -> Result<Option<Json>>;
+
     /// The `Primary` write surface (envelope mutations), or `None` for a `Derived` component — which
     /// therefore *cannot* write core envelopes. This is the §7 confinement, enforced structurally.
     fn writer(&self) -> Option<&dyn WriteCtx>;
 
     /// A handle to the kind's own namespaced tables (the §6 escape hatch). Used to read/write a
-    /// type-owned index; not for touching core's `channels`/`items`.
-    fn type_owned_db(&self) -> &sqlx::SqlitePool;
+    /// type-owned index; not for touching core's `channels`/`items`. `Backend::of` tells which dialect
+    /// it speaks.
+    fn type_owned_db(&self) -> &sqlx::AnyPool;
 
     /// Whether `version()` was bumped since the last boot — the component resets (idempotently). §7.
     fn reset_requested(&self) -> bool;
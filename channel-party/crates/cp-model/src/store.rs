@@ -6,7 +6,8 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
 use crate::envelope::{Channel, Item};
-use crate::ids::{ChannelId, TypeId, UserId};
+use crate::ids::{ChannelId, ItemId, TypeId, UserId};
+use crate::role::Role;
 use crate::Result;
 
 /// Which super-type a query targets. §2/§5.
@@ -25,11 +26,24 @@ pub struct Filter {
 }
 
 /// Sort order for a page of children. ULID ids make time-ordering a plain id sort. §3/§5.
+/// The sort-key orders read the kind-declared `IndexEntry::sort_key` (§6), ties broken by id;
+/// envelopes that declare no key sort as the empty string.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Order {
     TimeAsc,
     TimeDesc,
+    SortKeyAsc,
+    SortKeyDesc,
+}
+
+/// An axis-aligned rectangle `[x0, x1] × [y0, y1]` for `within_bbox`, e.g. a viewport. §5/§6.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
 }
 
 /// An opaque pagination cursor. §5.
@@ -91,14 +105,37 @@ pub trait StoreCtx: Send + Sync {
         page: Page,
     ) -> Result<NodePage>;
 
+    /// Spatial query over the `index()` projection's `coord`/`extent`, scoped to a subtree: every node
+    /// whose point or rectangle overlaps `rect`, id-ordered and cursor-paginated. §5/§6.
+    async fn within_bbox(
+        &self,
+        scope: ChannelId,
+        rect: Rect,
+        filter: Filter,
+        page: Page,
+    ) -> Result<NodePage>;
+
+    /// Point read of a live channel by id (`None` if missing or in the trash). §5.
+    async fn get_channel(&self, id: ChannelId) -> Result<Option<Channel>>;
+
+    /// Point read of a live item by id (`None` if missing or in the trash). §5.
+    async fn get_item(&self, id: ItemId) -> Result<Option<Item>>;
+
     /// Membership-substrate read: is `user` a member of `channel`? The read companion to `WriteCtx`'s
     /// `add_member` / `remove_member`, so a `Permission` policy (e.g. "members may post") can consult
     /// it. §8/§18.
     async fn is_member(&self, channel: ChannelId, user: UserId) -> Result<bool>;
 
+    /// Role-substrate read: every [`Role`] `user` holds on `channel`, including those granted on any
+    /// channel above it. A `channel_members` row counts as a [`Role::Member`] grant on that channel only,
+    /// not inherited by the channels below it. What [`RolePermission`](crate::RolePermission) decides
+    /// with. §18.
+    async fn roles(&self, channel: ChannelId, user: UserId) -> Result<Vec<Role>>;
+
     /// The §6 escape hatch: a handle to the kind's *own* namespaced tables, for a `contents` strategy
     /// the closed primitives above can't express (e.g. `canvas`'s viewport bbox over its R-tree). Pure
     /// primitive-consumers never call it; using it to read core's `channels`/`items` is a design
-    /// violation — the primitives are the supported read path. See `design/runtime.md`.
-    fn type_owned_db(&self) -> &sqlx::SqlitePool;
+    /// violation — the primitives are the supported read path. `Backend::of` tells which dialect it
+    /// speaks. See `design/runtime.md`.
+    fn type_owned_db(&self) -> &sqlx::AnyPool;
 }
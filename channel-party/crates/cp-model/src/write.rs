@@ -8,6 +8,7 @@ use async_trait::async_trait;
 
 use crate::envelope::Json;
 use crate::ids::{ChannelId, ItemId, TypeId, UserId};
+use crate::role::Role;
 use crate::store::StoreCtx;
 use crate::Result;
 
@@ -43,6 +44,207 @@ impl<Id: Copy> Upsert<Id> {
     }
 }
 
+/// A partial payload update, applied by core to the envelope's current payload inside the write
+/// transaction — so it never races a concurrent writer — before the kind's `validate` + `index()` see
+/// the result.
+#[derive(Clone, Debug)]
+pub enum Patch {
+    /// An RFC 7396 merge-patch: objects merge recursively, `null` removes a key, anything else replaces.
+    Merge(Json),
+    /// An RFC 6902 JSON-patch: an array of `add`/`remove`/`replace`/`move`/`copy`/`test` operations,
+    /// applied all-or-nothing (a failing `test` is a validation error).
+    Json(Json),
+}
+
+/// One mutation in a [`Batch`]. Mirrors the [`WriteCtx`] methods one-for-one; creates carry the id the
+/// batch minted for them so later mutations in the same batch can reference it.
+pub enum Mutation {
+    CreateChannel {
+        id: ChannelId,
+        spec: NewChannel,
+    },
+    CreateItem {
+        id: ItemId,
+        spec: NewItem,
+    },
+    UpsertItem(NewItem),
+    SetChannelPayload(ChannelId, Json),
+    SetItemPayload(ItemId, Json),
+    /// `set_channel_payload_if`: the payload, only if the channel is still at the given `rev`.
+    SetChannelPayloadIf(ChannelId, i64, Json),
+    SetItemPayloadIf(ItemId, i64, Json),
+    /// `patch_channel_payload` / `patch_channel_payload_if` (with the expected `rev`).
+    PatchChannelPayload {
+        id: ChannelId,
+        rev: Option<i64>,
+        patch: Patch,
+    },
+    PatchItemPayload {
+        id: ItemId,
+        rev: Option<i64>,
+        patch: Patch,
+    },
+    ReparentChannel(ChannelId, Option<ChannelId>),
+    ReparentItem(ItemId, Option<ChannelId>),
+    DeleteChannel(ChannelId),
+    DeleteItem(ItemId),
+    RestoreChannel(ChannelId),
+    RestoreItem(ItemId),
+    PurgeChannel(ChannelId),
+    PurgeItem(ItemId),
+    AddMember(ChannelId, UserId),
+    RemoveMember(ChannelId, UserId),
+    GrantRole(ChannelId, UserId, Role),
+    RevokeRole(ChannelId, UserId, Role),
+}
+
+/// A unit of work: mutations that [`WriteCtx::apply`] commits in one transaction, in order — all or
+/// nothing. Each still runs the owning kind's `validate` + `index()`; the change events are published
+/// only after the whole batch commits. Creates mint their id up front (a fresh ULID, as the single-shot
+/// methods do) so e.g. "create channel + add creator as member" is one batch.
+#[derive(Default)]
+pub struct Batch {
+    mutations: Vec<Mutation>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create_channel(&mut self, spec: NewChannel) -> ChannelId {
+        let id = ChannelId::generate();
+        self.mutations.push(Mutation::CreateChannel { id, spec });
+        id
+    }
+
+    pub fn create_item(&mut self, spec: NewItem) -> ItemId {
+        let id = ItemId::generate();
+        self.mutations.push(Mutation::CreateItem { id, spec });
+        id
+    }
+
+    /// Queue an upsert. Its id is only known at commit; [`WriteCtx::apply`] returns the outcome of each
+    /// upsert, in queue order.
+    pub fn upsert_item(&mut self, spec: NewItem) -> &mut Self {
+        self.push(Mutation::UpsertItem(spec))
+    }
+
+    pub fn set_channel_payload(&mut self, id: ChannelId, payload: Json) -> &mut Self {
+        self.push(Mutation::SetChannelPayload(id, payload))
+    }
+
+    pub fn set_item_payload(&mut self, id: ItemId, payload: Json) -> &mut Self {
+        self.push(Mutation::SetItemPayload(id, payload))
+    }
+
+    pub fn set_channel_payload_if(&mut self, id: ChannelId, rev: i64, payload: Json) -> &mut Self {
+        self.push(Mutation::SetChannelPayloadIf(id, rev, payload))
+    }
+
+    pub fn set_item_payload_if(&mut self, id: ItemId, rev: i64, payload: Json) -> &mut Self {
+        self.push(Mutation::SetItemPayloadIf(id, rev, payload))
+    }
+
+    pub fn patch_channel_payload(&mut self, id: ChannelId, patch: Patch) -> &mut Self {
+        self.push(Mutation::PatchChannelPayload {
+            id,
+            rev: None,
+            patch,
+        })
+    }
+
+    pub fn patch_item_payload(&mut self, id: ItemId, patch: Patch) -> &mut Self {
+        self.push(Mutation::PatchItemPayload {
+            id,
+            rev: None,
+            patch,
+        })
+    }
+
+    pub fn patch_channel_payload_if(&mut self, id: ChannelId, rev: i64, patch: Patch) -> &mut Self {
+        self.push(Mutation::PatchChannelPayload {
+            id,
+            rev: Some(rev),
+            patch,
+        })
+    }
+
+    pub fn patch_item_payload_if(&mut self, id: ItemId, rev: i64, patch: Patch) -> &mut Self {
+        self.push(Mutation::PatchItemPayload {
+            id,
+            rev: Some(rev),
+            patch,
+        })
+    }
+
+    pub fn reparent_channel(&mut self, id: ChannelId, container: Option<ChannelId>) -> &mut Self {
+        self.push(Mutation::ReparentChannel(id, container))
+    }
+
+    pub fn reparent_item(&mut self, id: ItemId, container: Option<ChannelId>) -> &mut Self {
+        self.push(Mutation::ReparentItem(id, container))
+    }
+
+    pub fn delete_channel(&mut self, id: ChannelId) -> &mut Self {
+        self.push(Mutation::DeleteChannel(id))
+    }
+
+    pub fn delete_item(&mut self, id: ItemId) -> &mut Self {
+        self.push(Mutation::DeleteItem(id))
+    }
+
+    pub fn restore_channel(&mut self, id: ChannelId) -> &mut Self {
+        self.push(Mutation::RestoreChannel(id))
+    }
+
+    pub fn restore_item(&mut self, id: ItemId) -> &mut Self {
+        self.push(Mutation::RestoreItem(id))
+    }
+
+    pub fn purge_channel(&mut self, id: ChannelId) -> &mut Self {
+        self.push(Mutation::PurgeChannel(id))
+    }
+
+    pub fn purge_item(&mut self, id: ItemId) -> &mut Self {
+        self.push(Mutation::PurgeItem(id))
+    }
+
+    pub fn add_member(&mut self, channel: ChannelId, user: UserId) -> &mut Self {
+        self.push(Mutation::AddMember(channel, user))
+    }
+
+    pub fn remove_member(&mut self, channel: ChannelId, user: UserId) -> &mut Self {
+        self.push(Mutation::RemoveMember(channel, user))
+    }
+
+    pub fn grant_role(&mut self, channel: ChannelId, user: UserId, role: Role) -> &mut Self {
+        self.push(Mutation::GrantRole(channel, user, role))
+    }
+
+    pub fn revoke_role(&mut self, channel: ChannelId, user: UserId, role: Role) -> &mut Self {
+        self.push(Mutation::RevokeRole(channel, user, role))
+    }
+
+    /// Append a prebuilt mutation.
+    pub fn push(&mut self, mutation: Mutation) -> &mut Self {
+        self.mutations.push(mutation);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+
+    pub fn into_mutations(self) -> Vec<Mutation> {
+        self.mutations
+    }
+}
+
 /// The single write path. A superset of [`StoreCtx`] (a mutation may read first — e.g. an update
 /// looks up the target's immutable `type_id` to pick the right kind's `validate`). Every method
 /// validates via the owning kind, writes the envelope + inline index in one transaction, and emits
@@ -58,15 +260,59 @@ pub trait WriteCtx: StoreCtx {
     async fn set_channel_payload(&self, id: ChannelId, payload: Json) -> Result<()>;
     async fn set_item_payload(&self, id: ItemId, payload: Json) -> Result<()>;
 
+    /// Compare-and-set: replace the payload only if the channel is still at `rev` (its
+    /// [`Channel::rev`](crate::Channel) as last read); otherwise [`Error::Conflict`](crate::Error)
+    /// with the current revision, and nothing is written. The unconditional setters are
+    /// last-write-wins.
+    async fn set_channel_payload_if(&self, id: ChannelId, rev: i64, payload: Json) -> Result<()>;
+    async fn set_item_payload_if(&self, id: ItemId, rev: i64, payload: Json) -> Result<()>;
+
+    /// Apply a [`Patch`] to the channel's current payload and store the result — read, patch,
+    /// `validate`, `index()` and write all in one transaction. A patch that cannot apply (a bad
+    /// JSON-patch path, a failing `test`) is [`Error::Validation`](crate::Error), as is a result the
+    /// kind rejects.
+    async fn patch_channel_payload(&self, id: ChannelId, patch: Patch) -> Result<()>;
+    async fn patch_item_payload(&self, id: ItemId, patch: Patch) -> Result<()>;
+
+    /// [`patch_channel_payload`](Self::patch_channel_payload), only if the channel is still at `rev`.
+    async fn patch_channel_payload_if(&self, id: ChannelId, rev: i64, patch: Patch) -> Result<()>;
+    async fn patch_item_payload_if(&self, id: ItemId, rev: i64, patch: Patch) -> Result<()>;
+
     async fn reparent_channel(&self, id: ChannelId, container: Option<ChannelId>) -> Result<()>;
     async fn reparent_item(&self, id: ItemId, container: Option<ChannelId>) -> Result<()>;
 
+    /// Move a channel to the trash: a tombstone that hides it *and its subtree* from every read
+    /// (`children`/`descendants`/`search`/point reads) until it is restored or purged. Emits `Deleted`
+    /// for each envelope that disappears. A trashed envelope is `NotFound` to the other mutations.
     async fn delete_channel(&self, id: ChannelId) -> Result<()>;
     async fn delete_item(&self, id: ItemId) -> Result<()>;
 
+    /// Bring a trashed channel (and the subtree its tombstone hid) back; emits `Created` for each
+    /// envelope that reappears. Only a directly-trashed envelope can be restored — one hidden by a
+    /// trashed ancestor is restored with that ancestor.
+    async fn restore_channel(&self, id: ChannelId) -> Result<()>;
+    async fn restore_item(&self, id: ItemId) -> Result<()>;
+
+    /// Hard-delete a channel and its subtree, trashed or not — irreversible (the revision history
+    /// keeps the final states). Emits `Deleted` for each envelope that was still visible.
+    async fn purge_channel(&self, id: ChannelId) -> Result<()>;
+    async fn purge_item(&self, id: ItemId) -> Result<()>;
+
+    /// Commit a [`Batch`] atomically: every mutation in one transaction, or none (the first error rolls
+    /// the whole batch back and emits nothing). Returns each queued upsert's outcome, in order.
+    async fn apply(&self, batch: Batch) -> Result<Vec<Upsert<ItemId>>>;
+
     /// The generic `channel_members` substrate, used by `ChannelKind::membership()` impls. The
     /// user must already exist (members are native principals — §2); user creation is auth's job. §8.
     async fn add_member(&self, channel: ChannelId, user: UserId) -> Result<()>;
     async fn remove_member(&self, channel: ChannelId, user: UserId) -> Result<()>;
     async fn members(&self, channel: ChannelId) -> Result<Vec<UserId>>;
+
+    /// The `channel_roles` substrate (§18): grant or revoke a role on a channel. Granting one already
+    /// held, or revoking one not held, is a no-op; the user must exist. A grant reaches every channel
+    /// below this one ([`StoreCtx::roles`]).
+    async fn grant_role(&self, channel: ChannelId, user: UserId, role: Role) -> Result<()>;
+    async fn revoke_role(&self, channel: ChannelId, user: UserId, role: Role) -> Result<()>;
+    /// The roles granted on this channel itself, not inherited ones.
+    async fn role_grants(&self, channel: ChannelId) -> Result<Vec<(UserId, Role)>>;
 }
@@ -12,12 +12,14 @@ fn channel_envelope_round_trips() {
         type_id: TypeId::new("basic"),
         container: None,
         payload: serde_json::json!({ "name": "general" }),
+        rev: 3,
     };
     let json = serde_json::to_string(&ch).unwrap();
     let back: Channel = serde_json::from_str(&json).unwrap();
     assert_eq!(back.id, ch.id);
     assert_eq!(back.type_id, ch.type_id);
     assert_eq!(back.payload, ch.payload);
+    assert_eq!(back.rev, 3);
 }
 
 #[test]
@@ -28,6 +30,7 @@ fn item_envelope_round_trips() {
         container: None,
         external_key: Some("discord:12345".to_owned()),
         payload: serde_json::json!({ "username": "ferris" }),
+        rev: 1,
     };
     let json = serde_json::to_string(&item).unwrap();
     let back: Item = serde_json::from_str(&json).unwrap();
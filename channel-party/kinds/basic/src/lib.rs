@@ -4,8 +4,9 @@
 
 use async_trait::async_trait;
 use cp_model::{
-    Action, Channel, ChannelKind, Cursor, Error, Filter, IndexEntry, ItemKind, Json, Membership,
-    Order, Page, Permission, Result, StoreCtx, SuperType, TypeId, UserId, WriteCtx,
+    Action, Channel, ChannelKind, Cursor, Error, Filter, Grant, IndexEntry, ItemKind, Json,
+    Membership, Order, Page, Permission, Result, Role, StoreCtx, SuperType, TypeId, UserId,
+    WriteCtx,
 };
 use serde::Deserialize;
 
@@ -84,13 +85,14 @@ impl ChannelKind for BasicChannel {
     }
 
     fn permission(&self) -> Option<&dyn Permission> {
-        // `basic` authorizes posts by membership (see the `Permission` impl). §18.
+        // `basic` authorizes by role (see the `Permission` impl). §18.
         Some(self)
     }
 }
 
-/// `basic`'s authorization rides the same `channel_members` substrate as its membership: a member may
-/// post, contents are public, and structural admin isn't exposed over HTTP. §18.
+/// `basic`'s authorization is by role (§18), inherited down the tree, and a `channel_members` row is a
+/// `member` grant: members post, admins and owners manage the room (rename, move, delete, add or remove
+/// members), and contents are public unless the payload says `"private": true` (then members only).
 #[async_trait]
 impl Permission for BasicChannel {
     async fn authorize(
@@ -100,12 +102,31 @@ impl Permission for BasicChannel {
         user: UserId,
         action: Action,
     ) -> Result<bool> {
-        Ok(match action {
-            Action::View => true,
-            Action::Post => cx.is_member(ch.id, user).await?,
-            Action::Manage => false,
-        })
+        grant(ch, action).allows(cx, ch.id, Some(user)).await
     }
+
+    async fn authorize_anonymous(
+        &self,
+        cx: &dyn StoreCtx,
+        ch: &Channel,
+        action: Action,
+    ) -> Result<bool> {
+        grant(ch, action).allows(cx, ch.id, None).await
+    }
+}
+
+/// Who may take `action` on a `basic` channel. Only `View` depends on the channel itself.
+fn grant(ch: &Channel, action: Action) -> Grant {
+    match action {
+        Action::View if !is_private(ch) => Grant::Anyone,
+        Action::View | Action::Post => Grant::AtLeast(Role::Member),
+        Action::Manage => Grant::AtLeast(Role::Admin),
+    }
+}
+
+/// Whether a channel is members-only (`"private": true` in its payload).
+fn is_private(ch: &Channel) -> bool {
+    ch.payload.get("private").and_then(Json::as_bool) == Some(true)
 }
 
 /// A `basic` channel's membership rides the generic edge table; "add a user" is a plain edge. §8.
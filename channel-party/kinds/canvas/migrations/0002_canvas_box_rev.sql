@@ -0,0 +1,3 @@
+-- Carry the box item's envelope `rev` in the projection, so the boxes `contents` reconstructs have the
+-- revision a client needs for a conditional edit (`If-Match`). Existing rows match core's default of 1.
+ALTER TABLE canvas_box ADD COLUMN rev INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,25 @@
+-- The canvas slice's type-owned tables on Postgres: the same two tables as the sqlite migrations
+-- 0001–0002 (whose comments describe them), in one step. Postgres has no R-tree virtual table, so
+-- `canvas_box_rtree` is a plain table with a GiST index over its box, which `contents` queries with the
+-- `&&` overlap operator.
+CREATE TABLE IF NOT EXISTS canvas_box (
+    rid       BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    item_id   TEXT NOT NULL UNIQUE,
+    container TEXT NOT NULL,
+    x    DOUBLE PRECISION NOT NULL,
+    y    DOUBLE PRECISION NOT NULL,
+    w    DOUBLE PRECISION NOT NULL,
+    h    DOUBLE PRECISION NOT NULL,
+    text TEXT,
+    rev  INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS canvas_box_rtree (
+    rid  BIGINT PRIMARY KEY,              -- = canvas_box.rid
+    minX DOUBLE PRECISION NOT NULL,
+    maxX DOUBLE PRECISION NOT NULL,
+    minY DOUBLE PRECISION NOT NULL,
+    maxY DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS canvas_box_rtree_box ON canvas_box_rtree
+    USING gist (box(point(minX, minY), point(maxX, maxY)));
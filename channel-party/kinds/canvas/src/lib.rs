@@ -5,13 +5,14 @@
 
 use async_trait::async_trait;
 use cp_model::{
-    ChangeEvent, ChangeOp, Channel, ChannelKind, Cursor, EnvelopeRef, Error, Interests, Item,
-    ItemId, ItemKind, Json, Migration, Migrations, Node, NodePage, Result, RuntimeComponent,
-    RuntimeCtx, RuntimeEvent, StoreCtx, TypeId, WriteScope,
+    Action, Backend, ChangeEvent, ChangeOp, Channel, ChannelKind, Cursor, DebugAccess,
+    DebugCommand, EnvelopeRef, Error, Interests, Item, ItemId, ItemKind, Json, Migration,
+    Migrations, Node, NodePage, Patch, Permission, QueryBuilder, Result, RuntimeComponent,
+    RuntimeCtx, RuntimeEvent, StoreCtx, TypeId, UserId, WriteCtx, WriteScope,
 };
 use serde::Deserialize;
-use sqlx::sqlite::SqliteRow;
-use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use sqlx::any::AnyRow;
+use sqlx::{AnyPool, Row};
 
 /// The channel and item type strings this crate contributes. §4.
 pub const CHANNEL_TYPE: &str = "canvas";
@@ -59,6 +60,11 @@ impl ChannelKind for Canvas {
         &self.type_id
     }
 
+    fn permission(&self) -> Option<&dyn Permission> {
+        // View-only, for anyone (see the `Permission` impl). §18.
+        Some(self)
+    }
+
     async fn contents(&self, cx: &dyn StoreCtx, ch: &Channel, query: Json) -> Result<Json> {
         // DESIGN §5/§6: a viewport bbox query over this canvas's own R-tree — a strategy the closed
         // primitives can't express, so it uses the §6 escape hatch (`type_owned_db`). Reads only
@@ -77,23 +83,40 @@ impl ChannelKind for Canvas {
             });
         }
 
-        let mut qb = QueryBuilder::<Sqlite>::new(
-            "SELECT b.item_id, b.x, b.y, b.w, b.h, b.text \
+        let pool = cx.type_owned_db();
+        let mut qb = QueryBuilder::new(
+            "SELECT b.item_id, b.x, b.y, b.w, b.h, b.text, b.rev \
              FROM canvas_box_rtree r JOIN canvas_box b ON b.rid = r.rid WHERE b.container = ",
         );
         qb.push_bind(ch.id.to_string());
-        // AABB overlap of the box [minX,maxX]×[minY,maxY] with the viewport.
-        qb.push(" AND r.maxX >= ").push_bind(q.x0);
-        qb.push(" AND r.minX <= ").push_bind(q.x1);
-        qb.push(" AND r.maxY >= ").push_bind(q.y0);
-        qb.push(" AND r.minY <= ").push_bind(q.y1);
+        // AABB overlap of the box [minX,maxX]×[minY,maxY] with the viewport: R-tree range constraints
+        // on sqlite, the GiST-indexed `&&` box overlap on Postgres.
+        match Backend::of(pool) {
+            Backend::Sqlite => {
+                qb.push(" AND r.maxX >= ").push_bind(q.x0);
+                qb.push(" AND r.minX <= ").push_bind(q.x1);
+                qb.push(" AND r.maxY >= ").push_bind(q.y0);
+                qb.push(" AND r.minY <= ").push_bind(q.y1);
+            }
+            Backend::Postgres => {
+                qb.push(" AND box(point(r.minX, r.minY), point(r.maxX, r.maxY)) && box(point(")
+                    .push_bind(q.x0)
+                    .push(", ")
+                    .push_bind(q.y0)
+                    .push("), point(")
+                    .push_bind(q.x1)
+                    .push(", ")
+                    .push_bind(q.y1)
+                    .push("))");
+            }
+        }
         if let Some(cursor) = &q.cursor {
             qb.push(" AND b.item_id > ").push_bind(cursor.clone());
         }
         qb.push(" ORDER BY b.item_id ASC LIMIT ")
             .push_bind(i64::from(limit) + 1);
 
-        let rows = qb.build().fetch_all(cx.type_owned_db()).await.map_err(db)?;
+        let rows = qb.build().fetch_all(pool).await.map_err(db)?;
         let mut nodes = rows
             .iter()
             .map(|r| box_node(ch, r))
@@ -106,6 +129,72 @@ impl ChannelKind for Canvas {
         };
         to_json(NodePage { nodes, next })
     }
+
+    fn debug_commands(&self) -> Vec<DebugCommand> {
+        vec![DebugCommand {
+            name: "move-box".to_owned(),
+            access: DebugAccess::Write,
+            help: "<box-id> <x> <y>    move a text box (its size and text are kept)".to_owned(),
+        }]
+    }
+
+    async fn run_debug_command(
+        &self,
+        cx: &dyn WriteCtx,
+        command: &str,
+        args: &[String],
+    ) -> Result<String> {
+        let ("move-box", [id, x, y]) = (command, args) else {
+            return Err(Error::Validation(
+                "usage: canvas move-box <box-id> <x> <y>".to_owned(),
+            ));
+        };
+        let id: ItemId = id
+            .parse()
+            .map_err(|_| Error::Validation(format!("`{id}` is not an item id")))?;
+        let coord = |v: &str| {
+            v.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| Error::Validation(format!("`{v}` is not a coordinate")))
+        };
+        let (x, y) = (coord(x)?, coord(y)?);
+        let item = cx.get_item(id).await?.ok_or(Error::NotFound)?;
+        if item.type_id.as_str() != ITEM_TYPE {
+            return Err(Error::Validation(format!(
+                "{id} is a `{}`, not a `{ITEM_TYPE}`",
+                item.type_id
+            )));
+        }
+        // A merge-patch through the write path: revisioned, evented, and re-projected by `SpatialIndex`.
+        cx.patch_item_payload(id, Patch::Merge(serde_json::json!({ "x": x, "y": y })))
+            .await?;
+        Ok(format!("moved {id} to ({x}, {y})"))
+    }
+}
+
+/// A canvas is public to view. Boxes are placed through the shell or the runtime, not posted over
+/// HTTP, so nothing else is granted. §18.
+#[async_trait]
+impl Permission for Canvas {
+    async fn authorize(
+        &self,
+        _cx: &dyn StoreCtx,
+        _ch: &Channel,
+        _user: UserId,
+        action: Action,
+    ) -> Result<bool> {
+        Ok(action == Action::View)
+    }
+
+    async fn authorize_anonymous(
+        &self,
+        _cx: &dyn StoreCtx,
+        _ch: &Channel,
+        action: Action,
+    ) -> Result<bool> {
+        Ok(action == Action::View)
+    }
 }
 
 /// `item-type:canvas-text-box`. No `index()`: its projection lives in the kind's own R-tree (written by
@@ -121,7 +210,8 @@ impl ItemKind for CanvasTextBox {
 }
 
 /// Maintains the canvas R-tree off the change stream (DESIGN §7). `Derived`, so it structurally cannot
-/// write core envelopes. Backfills existing boxes, then applies each `canvas-text-box` change.
+/// write core envelopes. Backfills existing boxes (resumably, via its checkpoint), then applies each
+/// `canvas-text-box` change.
 struct SpatialIndex;
 
 #[async_trait]
@@ -143,14 +233,32 @@ impl RuntimeComponent for SpatialIndex {
 
     async fn run(&self, cx: &dyn RuntimeCtx) -> Result<()> {
         let pool = cx.type_owned_db();
-        // A version bump rebuilds from scratch; backfill then reconstructs every existing box.
-        if cx.reset_requested() {
+        // Backfill progress is checkpointed: `{"after": cursor}` part-way, `{"done": true}` once every
+        // existing box is in. Core drops the checkpoint on a reset, so only a fresh rebuild truncates;
+        // a restart mid-rebuild picks up after the last page, and one after it goes straight to the
+        // stream (which resumes from its own persisted offset).
+        let progress = cx.last_checkpoint().await?;
+        if progress.is_none() && cx.reset_requested() {
             clear(pool).await?;
         }
-        for node in cx.scan(&[TypeId::new(ITEM_TYPE)]).await? {
-            if let Node::Item(item) = node {
-                upsert_box(pool, &item).await?;
+        let done = progress.as_ref().and_then(|p| p.get("done")).is_some();
+        if !done {
+            let after = progress.as_ref().and_then(|p| p.get("after")?.as_str());
+            let mut cursor = Cursor(after.map(str::to_owned));
+            loop {
+                let page = cx.scan_since(&[TypeId::new(ITEM_TYPE)], cursor).await?;
+                for node in page.nodes {
+                    if let Node::Item(item) = node {
+                        upsert_box(pool, &item).await?;
+                    }
+                }
+                let Some(next) = page.next.0 else {
+                    break;
+                };
+                cx.checkpoint(serde_json::json!({ "after": next })).await?;
+                cursor = Cursor(Some(next));
             }
+            cx.checkpoint(serde_json::json!({ "done": true })).await?;
         }
         // Steady state: keep the R-tree in sync with box create/update/delete.
         while let Some(event) = cx.next_event().await {
@@ -164,7 +272,7 @@ impl RuntimeComponent for SpatialIndex {
 }
 
 /// Apply one box change to the R-tree.
-async fn apply(cx: &dyn RuntimeCtx, pool: &SqlitePool, change: &ChangeEvent) -> Result<()> {
+async fn apply(cx: &dyn RuntimeCtx, pool: &AnyPool, change: &ChangeEvent) -> Result<()> {
     let EnvelopeRef::Item(id) = change.target else {
         return Ok(());
     };
@@ -179,7 +287,7 @@ async fn apply(cx: &dyn RuntimeCtx, pool: &SqlitePool, change: &ChangeEvent) ->
 
 /// Project a box into `canvas_box` + the R-tree (delete-then-insert, since R-trees have no UPSERT), all
 /// in one transaction. Skips boxes with no container or no coordinates.
-async fn upsert_box(pool: &SqlitePool, item: &Item) -> Result<()> {
+async fn upsert_box(pool: &AnyPool, item: &Item) -> Result<()> {
     let Some(container) = item.container else {
         return Ok(());
     };
@@ -190,9 +298,11 @@ async fn upsert_box(pool: &SqlitePool, item: &Item) -> Result<()> {
 
     let mut tx = pool.begin().await.map_err(db)?;
     let rid: i64 = sqlx::query_scalar(
-        "INSERT INTO canvas_box (item_id, container, x, y, w, h, text) VALUES (?, ?, ?, ?, ?, ?, ?) \
+        "INSERT INTO canvas_box (item_id, container, x, y, w, h, text, rev) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
          ON CONFLICT(item_id) DO UPDATE SET container = excluded.container, x = excluded.x, \
-         y = excluded.y, w = excluded.w, h = excluded.h, text = excluded.text RETURNING rid",
+         y = excluded.y, w = excluded.w, h = excluded.h, text = excluded.text, rev = excluded.rev \
+         RETURNING rid",
     )
     .bind(item.id.to_string())
     .bind(container.to_string())
@@ -201,16 +311,17 @@ async fn upsert_box(pool: &SqlitePool, item: &Item) -> Result<()> {
     .bind(w)
     .bind(h)
     .bind(text)
+    .bind(item.rev)
     .fetch_one(&mut *tx)
     .await
     .map_err(db)?;
-    sqlx::query("DELETE FROM canvas_box_rtree WHERE rid = ?")
+    sqlx::query("DELETE FROM canvas_box_rtree WHERE rid = $1")
         .bind(rid)
         .execute(&mut *tx)
         .await
         .map_err(db)?;
     sqlx::query(
-        "INSERT INTO canvas_box_rtree (rid, minX, maxX, minY, maxY) VALUES (?, ?, ?, ?, ?)",
+        "INSERT INTO canvas_box_rtree (rid, minX, maxX, minY, maxY) VALUES ($1, $2, $3, $4, $5)",
     )
     .bind(rid)
     .bind(x)
@@ -225,20 +336,20 @@ async fn upsert_box(pool: &SqlitePool, item: &Item) -> Result<()> {
 }
 
 /// Remove a box from both tables (a no-op if it was never indexed).
-async fn delete_box(pool: &SqlitePool, id: ItemId) -> Result<()> {
+async fn delete_box(pool: &AnyPool, id: ItemId) -> Result<()> {
     let mut tx = pool.begin().await.map_err(db)?;
-    let rid: Option<i64> = sqlx::query_scalar("SELECT rid FROM canvas_box WHERE item_id = ?")
+    let rid: Option<i64> = sqlx::query_scalar("SELECT rid FROM canvas_box WHERE item_id = $1")
         .bind(id.to_string())
         .fetch_optional(&mut *tx)
         .await
         .map_err(db)?;
     if let Some(rid) = rid {
-        sqlx::query("DELETE FROM canvas_box_rtree WHERE rid = ?")
+        sqlx::query("DELETE FROM canvas_box_rtree WHERE rid = $1")
             .bind(rid)
             .execute(&mut *tx)
             .await
             .map_err(db)?;
-        sqlx::query("DELETE FROM canvas_box WHERE item_id = ?")
+        sqlx::query("DELETE FROM canvas_box WHERE item_id = $1")
             .bind(id.to_string())
             .execute(&mut *tx)
             .await
@@ -249,7 +360,7 @@ async fn delete_box(pool: &SqlitePool, id: ItemId) -> Result<()> {
 }
 
 /// Truncate the index (reset path).
-async fn clear(pool: &SqlitePool) -> Result<()> {
+async fn clear(pool: &AnyPool) -> Result<()> {
     sqlx::query("DELETE FROM canvas_box_rtree")
         .execute(pool)
         .await
@@ -280,7 +391,7 @@ fn box_rect(payload: &Json) -> Option<(f64, f64, f64, f64)> {
 
 /// Rebuild a box `Node::Item` from a `canvas_box` row — the envelope reconstructed from the kind's own
 /// projection, so `contents` never reads core's `items`.
-fn box_node(ch: &Channel, row: &SqliteRow) -> Result<Node> {
+fn box_node(ch: &Channel, row: &AnyRow) -> Result<Node> {
     let item_id: String = row.try_get("item_id").map_err(db)?;
     let id: ItemId = item_id
         .parse()
@@ -296,6 +407,7 @@ fn box_node(ch: &Channel, row: &SqliteRow) -> Result<Node> {
         container: Some(ch.id),
         external_key: None,
         payload: serde_json::json!({ "x": x, "y": y, "w": w, "h": h, "text": text }),
+        rev: row.try_get("rev").map_err(db)?,
     }))
 }
 
@@ -326,7 +438,20 @@ pub fn spatial_index() -> impl RuntimeComponent {
 }
 
 /// Type-owned migrations (namespaced `canvas_*`). §6.
-pub static MIGRATIONS: Migrations = Migrations(&[Migration {
-    name: "0001_canvas_init",
-    sql: include_str!("../migrations/0001_canvas_init.sql"),
-}]);
+pub static MIGRATIONS: Migrations = Migrations {
+    owner: CHANNEL_TYPE,
+    steps: &[
+        Migration {
+            name: "0001_canvas_init",
+            sql: include_str!("../migrations/0001_canvas_init.sql"),
+        },
+        Migration {
+            name: "0002_canvas_box_rev",
+            sql: include_str!("../migrations/0002_canvas_box_rev.sql"),
+        },
+    ],
+    postgres: &[Migration {
+        name: "0001_canvas_init",
+        sql: include_str!("../migrations/postgres/0001_canvas_init.sql"),
+    }],
+};
@@ -0,0 +1,6 @@
+-- The discord-compatible slice's type-owned tables on Postgres; see the sqlite migration of the same
+-- name. BLOB is BYTEA here.
+CREATE TABLE IF NOT EXISTS discord_message_embeddings (
+    item_id   TEXT PRIMARY KEY,
+    embedding BYTEA NOT NULL
+);
@@ -16,9 +16,10 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use cp_model::{
-    Channel, ChannelId, ChannelKind, Cursor, Error, Filter, Interests, ItemKind, Json, Migration,
-    Migrations, NewChannel, NewItem, Node, NodePage, Order, Page, Result, RuntimeComponent,
-    RuntimeCtx, RuntimeEvent, StoreCtx, SuperType, TypeId, WriteCtx, WriteScope,
+    Action, Batch, Channel, ChannelId, ChannelKind, Cursor, DebugAccess, DebugCommand, Error,
+    Filter, Interests, ItemKind, Json, Migration, Migrations, NewChannel, NewItem, Node, NodePage,
+    Order, Page, Permission, Result, RuntimeComponent, RuntimeCtx, RuntimeEvent, StoreCtx,
+    SuperType, TypeId, UserId, WriteCtx, WriteScope,
 };
 use serde::Deserialize;
 
@@ -33,9 +34,11 @@ const CACHED_MESSAGE: &str = "discord-compatible/cached-message";
 /// Page size when a `contents` query omits `limit`.
 const DEFAULT_LIMIT: u32 = 50;
 
-/// A channel kind in this namespace (guild / section / channel / forum). §4.
+/// A channel kind in this namespace (guild / section / channel / forum). §4. Built from a configured
+/// [`DiscordBridge`], it holds the bridge's client for the shell's `resync` command.
 struct DiscordChannel {
     type_id: TypeId,
+    bridge: Option<DiscordSync>,
 }
 
 #[async_trait]
@@ -44,6 +47,11 @@ impl ChannelKind for DiscordChannel {
         &self.type_id
     }
 
+    fn permission(&self) -> Option<&dyn Permission> {
+        // View-only, for anyone (see the `Permission` impl). §18.
+        Some(self)
+    }
+
     // §4 capability table: discord-compatible validates its payloads.
     fn validate(&self, _payload: &Json) -> Result<()> {
         Ok(())
@@ -90,6 +98,78 @@ impl ChannelKind for DiscordChannel {
         };
         serde_json::to_value(page).map_err(|e| Error::Other(e.to_string()))
     }
+
+    fn debug_commands(&self) -> Vec<DebugCommand> {
+        if self.type_id.as_str() != CHANNEL {
+            return Vec::new();
+        }
+        vec![DebugCommand {
+            name: "resync".to_owned(),
+            access: DebugAccess::Write,
+            help: "<channel-id>    re-fetch a bridged channel's recent messages now".to_owned(),
+        }]
+    }
+
+    async fn run_debug_command(
+        &self,
+        cx: &dyn WriteCtx,
+        command: &str,
+        args: &[String],
+    ) -> Result<String> {
+        let ("resync", [id]) = (command, args) else {
+            return Err(Error::Validation(
+                "usage: discord-compatible resync <channel-id>".to_owned(),
+            ));
+        };
+        let Some(bridge) = &self.bridge else {
+            return Err(Error::Validation(
+                "the Discord bridge is not configured (set CP_DISCORD_TOKEN and CP_DISCORD_GUILD)"
+                    .to_owned(),
+            ));
+        };
+        let id: ChannelId = id
+            .parse()
+            .map_err(|_| Error::Validation(format!("`{id}` is not a channel id")))?;
+        let ch = cx.get_channel(id).await?.ok_or(Error::NotFound)?;
+        if ch.type_id.as_str() != CHANNEL {
+            return Err(Error::Validation(format!(
+                "{id} is a `{}`, not a `{CHANNEL}`",
+                ch.type_id
+            )));
+        }
+        let discord_id = ch
+            .payload
+            .get("discord_id")
+            .and_then(Json::as_str)
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(|| Error::Validation(format!("{id} carries no Discord channel id")))?;
+        let n = bridge.sync_channel(cx, discord_id, id).await?;
+        Ok(format!("resynced {n} messages into {id}"))
+    }
+}
+
+/// A mirrored Discord channel is public to view; it is read-only here, since the bridge (not the HTTP
+/// API) writes it. §18.
+#[async_trait]
+impl Permission for DiscordChannel {
+    async fn authorize(
+        &self,
+        _cx: &dyn StoreCtx,
+        _ch: &Channel,
+        _user: UserId,
+        action: Action,
+    ) -> Result<bool> {
+        Ok(action == Action::View)
+    }
+
+    async fn authorize_anonymous(
+        &self,
+        _cx: &dyn StoreCtx,
+        _ch: &Channel,
+        action: Action,
+    ) -> Result<bool> {
+        Ok(action == Action::View)
+    }
 }
 
 /// The `contents` query shared by discord channel kinds — all optional. Leaf channels page their
@@ -122,8 +202,13 @@ impl ItemKind for DiscordItem {
     }
 }
 
-/// The channel kinds this namespace contributes. §10.
+/// The channel kinds this namespace contributes, with no bridge behind them (their `resync` command
+/// reports the bridge unconfigured). With a bridge, register [`DiscordBridge::channels`] instead. §10.
 pub fn channels() -> Vec<Box<dyn ChannelKind>> {
+    channel_kinds(None)
+}
+
+fn channel_kinds(bridge: Option<&DiscordBridge>) -> Vec<Box<dyn ChannelKind>> {
     [
         "discord-compatible/guild",
         "discord-compatible/section",
@@ -134,6 +219,7 @@ pub fn channels() -> Vec<Box<dyn ChannelKind>> {
     .map(|type_id| {
         Box::new(DiscordChannel {
             type_id: TypeId::new(type_id),
+            bridge: bridge.map(DiscordBridge::sync),
         }) as Box<dyn ChannelKind>
     })
     .collect()
@@ -213,6 +299,11 @@ pub struct DiscordBridge {
 }
 
 impl DiscordBridge {
+    /// The namespace's channel kinds, sharing this bridge's client for the shell's `resync`. §8/§10.
+    pub fn channels(&self) -> Vec<Box<dyn ChannelKind>> {
+        channel_kinds(Some(self))
+    }
+
     /// The Primary ingestor, sharing this bridge's client. (A future `semantic_index()` would share it
     /// too — the point of a bridge.) §7/§10.
     pub fn sync(&self) -> DiscordSync {
@@ -238,7 +329,8 @@ pub fn bridge(config: BridgeConfig) -> DiscordBridge {
 /// Initial sync then poll: ensures the guild/channel envelope structure, then ingests each channel's
 /// messages + authors behind the shared rate-limited client. Writes `Primary` envelopes (the source of
 /// truth), deduped by `external_key` (items) / carried Discord id (channels). Resets by re-fetching from
-/// Discord (idempotent upserts converge). §7. (Reactions are a later pass.)
+/// Discord (idempotent upserts converge); its checkpoint records the last sync, so a quick restart does
+/// not re-fetch. §7. (Reactions are a later pass.)
 pub struct DiscordSync {
     client: Arc<DiscordClient>,
     guild: u64,
@@ -268,36 +360,70 @@ impl RuntimeComponent for DiscordSync {
         let writer = cx
             .writer()
             .ok_or_else(|| Error::Other("discord-sync is Primary but got no writer".to_owned()))?;
-        // Initial sync == reset: both re-fetch from Discord (§7). Idempotent, so it converges.
-        self.sync_all(cx, writer).await?;
+        // The schedule's first tick fires at once, so it is the initial sync. Initial sync == reset: both
+        // re-fetch from Discord (§7), idempotently. Each sync checkpoints when it started, and a restart
+        // within one poll of that skips the initial sync — the supervisor restarts a failing component
+        // with backoff, and every restart re-fetching every channel would burn the rate limit.
+        let mut first = true;
         while let Some(event) = cx.next_event().await {
-            if let RuntimeEvent::Tick = event {
-                self.sync_all(cx, writer).await?;
+            let RuntimeEvent::Tick = event else {
+                continue;
+            };
+            if std::mem::take(&mut first) && self.synced_recently(cx).await? {
+                continue;
             }
+            let started = now_ms();
+            self.sync_all(cx, writer).await?;
+            cx.checkpoint(serde_json::json!({ "synced_at_ms": started }))
+                .await?;
         }
         Ok(())
     }
 }
 
 impl DiscordSync {
+    /// Whether the checkpointed last sync started less than one poll interval ago. No checkpoint (a
+    /// first boot, or a reset) means a sync is due.
+    async fn synced_recently(&self, cx: &dyn RuntimeCtx) -> Result<bool> {
+        let last = cx.last_checkpoint().await?;
+        let Some(at) = last.as_ref().and_then(|c| c.get("synced_at_ms")?.as_i64()) else {
+            return Ok(false);
+        };
+        let poll_ms = i64::try_from(self.poll_secs.saturating_mul(1000)).unwrap_or(i64::MAX);
+        Ok(now_ms().saturating_sub(at) < poll_ms)
+    }
+
     async fn sync_all(&self, cx: &dyn RuntimeCtx, writer: &dyn WriteCtx) -> Result<()> {
         let containers = self.ensure_structure(cx, writer).await?;
         for discord_channel in self.channels.iter() {
             let Some(&container) = containers.get(discord_channel) else {
                 continue;
             };
-            let messages = self
-                .client
-                .channel_messages(*discord_channel, 100)
-                .await
-                .map_err(Error::Other)?;
-            for message in messages {
-                self.ingest(writer, container, &message).await?;
-            }
+            self.sync_channel(writer, *discord_channel, container)
+                .await?;
         }
         Ok(())
     }
 
+    /// Fetch one Discord channel's recent messages and upsert them under `container`. Returns how many
+    /// were ingested.
+    async fn sync_channel(
+        &self,
+        writer: &dyn WriteCtx,
+        discord_channel: u64,
+        container: ChannelId,
+    ) -> Result<usize> {
+        let messages = self
+            .client
+            .channel_messages(discord_channel, 100)
+            .await
+            .map_err(Error::Other)?;
+        for message in &messages {
+            self.ingest(writer, container, message).await?;
+        }
+        Ok(messages.len())
+    }
+
     /// Ensure the `guild` envelope and a `channel` envelope per configured Discord channel exist,
     /// deduped by the Discord id each envelope carries in its payload. Channels have no `external_key`,
     /// so dedup is *derived from the source of truth* — `scan` the existing envelopes and create only the
@@ -357,14 +483,16 @@ impl DiscordSync {
 
     /// Upsert one message and its author: a `cached-user` (one per Discord user, `external_key` dedup,
     /// §3) then a `cached-message` under the mapped channel. Its author is a reference to the cached-user
-    /// by Discord id (resolvable to a native user via a `linked-users` link, §2/#19).
+    /// by Discord id (resolvable to a native user via a `linked-users` link, §2/#19). Both land in one
+    /// `Batch`, so a message is never persisted without its author (or vice versa).
     async fn ingest(
         &self,
         writer: &dyn WriteCtx,
         container: ChannelId,
         m: &FetchedMessage,
     ) -> Result<()> {
-        writer
+        let mut batch = Batch::new();
+        batch
             .upsert_item(NewItem {
                 type_id: TypeId::new(CACHED_USER),
                 container: None,
@@ -374,8 +502,6 @@ impl DiscordSync {
                     "name": m.author_name,
                 }),
             })
-            .await?;
-        writer
             .upsert_item(NewItem {
                 type_id: TypeId::new(CACHED_MESSAGE),
                 container: Some(container),
@@ -387,14 +513,29 @@ impl DiscordSync {
                     "content": m.content,
                     "timestamp_ms": m.timestamp_ms,
                 }),
-            })
-            .await?;
+            });
+        writer.apply(batch).await?;
         Ok(())
     }
 }
 
+/// Wall-clock milliseconds since the Unix epoch (the sync checkpoint's timestamp).
+fn now_ms() -> i64 {
+    let since = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    i64::try_from(since.as_millis()).unwrap_or(i64::MAX)
+}
+
 /// Type-owned migrations (namespaced `discord_*`). Core never learns their shape. §6.
-pub static MIGRATIONS: Migrations = Migrations(&[Migration {
-    name: "0001_discord_init",
-    sql: include_str!("../migrations/0001_discord_init.sql"),
-}]);
+pub static MIGRATIONS: Migrations = Migrations {
+    owner: "discord-compatible",
+    steps: &[Migration {
+        name: "0001_discord_init",
+        sql: include_str!("../migrations/0001_discord_init.sql"),
+    }],
+    postgres: &[Migration {
+        name: "0001_discord_init",
+        sql: include_str!("../migrations/postgres/0001_discord_init.sql"),
+    }],
+};
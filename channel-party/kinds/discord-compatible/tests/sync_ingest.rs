@@ -3,10 +3,12 @@
 //! `Primary` write path end to end: the component builds the guild/channel envelope tree (deduped),
 //! upserts `cached-message` + `cached-user` envelopes through `writer()`, and the channel kinds' own
 //! `contents` reads them back (guild → subtree via `descendants`, channel → message feed via `children`).
+//! Also drives the `discord-compatible resync` debug-shell command (`TODO.md` #34).
 
 use std::sync::Arc;
 use std::time::Duration;
 
+use cp_core::debug::DebugShell;
 use cp_core::{Core, Registry, Store};
 use cp_model::{Channel, Node, TypeId};
 use wiremock::matchers::{method, path_regex};
@@ -174,3 +176,102 @@ async fn ingests_structure_messages_and_serves_contents() {
 
     handle.shutdown().await;
 }
+
+#[tokio::test]
+async fn resync_command_refetches_one_channel_on_demand() {
+    let server = MockServer::start().await;
+    mock_channel(
+        &server,
+        100,
+        serde_json::json!([message(1001, 555, "alice", "hello")]),
+    )
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let config = cp_discord::BridgeConfig {
+        token: "Bot test.token".to_owned(),
+        proxy: Some(server.address().to_string()),
+        // Long enough that only the initial sync and the explicit resync ever poll.
+        poll_secs: 3600,
+        guild: 10,
+        channels: vec![100],
+    };
+    let bridge = cp_discord::bridge(config);
+    let registry = Registry::builder()
+        .channels(bridge.channels())
+        .items(cp_discord::items())
+        .migrations(cp_discord::MIGRATIONS)
+        .runtime(bridge.sync())
+        .build();
+    let core = Core::open(&url, registry.clone()).await.unwrap();
+    let store = core.store();
+    let handle = core.spawn_runtime();
+    for _ in 0..200 {
+        if count(&store, CACHED_MESSAGE).await == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    assert_eq!(
+        count(&store, CACHED_MESSAGE).await,
+        1,
+        "initial sync landed"
+    );
+    let channel = channels_of_type(&store, CHANNEL).await.pop().unwrap();
+
+    // Discord grows a message; the operator pulls it in without waiting an hour for the re-poll.
+    server.reset().await;
+    mock_channel(
+        &server,
+        100,
+        serde_json::json!([
+            message(1001, 555, "alice", "hello"),
+            message(1002, 777, "bob", "late"),
+        ]),
+    )
+    .await;
+    let mut sh = DebugShell::new(registry, store.clone());
+    let cmd = format!("discord-compatible resync {}", channel.id);
+    assert_eq!(
+        sh.eval(&cmd).await,
+        "read-only; run enable-write-mode first"
+    );
+    sh.enable_write_mode();
+    assert_eq!(
+        sh.eval(&cmd).await,
+        format!("resynced 2 messages into {}", channel.id)
+    );
+    assert_eq!(
+        count(&store, CACHED_MESSAGE).await,
+        2,
+        "the new message landed"
+    );
+
+    // Only a Discord channel envelope can be resynced.
+    let guild = channels_of_type(&store, GUILD).await.pop().unwrap();
+    assert!(sh
+        .eval(&format!("discord-compatible resync {}", guild.id))
+        .await
+        .contains("not a `discord-compatible/channel`"));
+
+    handle.shutdown().await;
+}
+
+#[tokio::test]
+async fn resync_without_a_configured_bridge_is_a_clean_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}", dir.path().join("t.db").display());
+    let registry = Registry::builder()
+        .channels(cp_discord::channels())
+        .items(cp_discord::items())
+        .migrations(cp_discord::MIGRATIONS)
+        .build();
+    let core = Core::open(&url, registry.clone()).await.unwrap();
+    let mut sh = DebugShell::new(registry, core.store());
+    sh.enable_write_mode();
+    assert!(sh
+        .eval("discord-compatible resync 0000")
+        .await
+        .contains("the Discord bridge is not configured"));
+}
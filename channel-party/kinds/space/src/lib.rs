@@ -4,7 +4,8 @@
 
 use async_trait::async_trait;
 use cp_model::{
-    Channel, ChannelKind, Cursor, Error, Filter, Json, Page, Result, StoreCtx, SuperType, TypeId,
+    Channel, ChannelKind, Cursor, Error, Filter, Grant, Json, Membership, Page, Permission, Result,
+    RolePermission, StoreCtx, SuperType, TypeId, UserId, WriteCtx,
 };
 use serde::Deserialize;
 
@@ -24,6 +25,7 @@ struct SpaceQuery {
 /// `channel-type:space`.
 struct Space {
     type_id: TypeId,
+    permission: RolePermission,
 }
 
 #[async_trait]
@@ -32,6 +34,17 @@ impl ChannelKind for Space {
         &self.type_id
     }
 
+    fn membership(&self) -> Option<&dyn Membership> {
+        // A space accepts users on core's generic `channel_members` substrate; a member of the space
+        // is a `member` of the space only, not of the channels in it (§18). §8.
+        Some(self)
+    }
+
+    fn permission(&self) -> Option<&dyn Permission> {
+        // The ready-made role policy (see [`channel`]). §18.
+        Some(&self.permission)
+    }
+
     async fn contents(&self, cx: &dyn StoreCtx, ch: &Channel, query: Json) -> Result<Json> {
         // DESIGN §5: search(scope = self, query.q, {Channel}, page) -> paginated channel matches over
         // the FTS projection (§6), then serialize the NodePage. Deviation from §5's `{Channel, [basic]}`:
@@ -62,9 +75,29 @@ impl ChannelKind for Space {
     }
 }
 
-/// The `channel-type:space` kind, for the composition root. §10.
+/// A space's membership is the generic edge table, like `basic`'s. §8.
+#[async_trait]
+impl Membership for Space {
+    async fn add_user(&self, cx: &dyn WriteCtx, ch: &Channel, user: UserId) -> Result<()> {
+        cx.add_member(ch.id, user).await
+    }
+
+    async fn remove_user(&self, cx: &dyn WriteCtx, ch: &Channel, user: UserId) -> Result<()> {
+        cx.remove_member(ch.id, user).await
+    }
+
+    async fn members(&self, cx: &dyn WriteCtx, ch: &Channel) -> Result<Vec<UserId>> {
+        cx.members(ch.id).await
+    }
+}
+
+/// The `channel-type:space` kind, for the composition root. §10. A `space` is a public directory:
+/// anyone may view it (each channel found in it is still judged by its own kind), admins manage it —
+/// create, move and remove the channels in it, and add or remove members — and nothing is posted to a
+/// space itself. §18.
 pub fn channel() -> impl ChannelKind {
     Space {
         type_id: TypeId::new("space"),
+        permission: RolePermission::new().post(Grant::Nobody),
     }
 }